@@ -18,7 +18,7 @@ export type RustFetchNativeBinding = {
   initMemoryLayout?: (args: Buffer, ro: Buffer, rw: Buffer, stackSize: number, heapZeroPadding: number) => void;
   init_memory_layout?: (args: Buffer, ro: Buffer, rw: Buffer, stackSize: number, heapZeroPadding: number) => void;
   setRegisters: (registers: Buffer) => void;
-  setGasLeft: (gas: number) => void;
+  setGasLeft: (gas: bigint) => void;
   setNextProgramCounter: (pc: number) => void;
   nextStep: () => boolean;
   getRegisters: () => Buffer;
@@ -691,7 +691,7 @@ describe("FETCH Host Function Comparison", () => {
     view.setBigUint64(7 * 8, 0x20000n, true);
     view.setBigUint64(10 * 8, 14n, true); // selector 14
     native.setRegisters(Buffer.from(regs));
-    native.setGasLeft(1000);
+    native.setGasLeft(1000n);
     native.setNextProgramCounter(0);
 
     while (native.nextStep()) {}
@@ -733,7 +733,7 @@ describe("FETCH Host Function Comparison", () => {
     view.setBigUint64(9 * 8, 512n, true); // length (system constants are 134 bytes)
     view.setBigUint64(10 * 8, 0n, true); // selector 0 = system constants
     native.setRegisters(Buffer.from(regs));
-    native.setGasLeft(1000);
+    native.setGasLeft(1000n);
     native.setNextProgramCounter(0);
 
     while (native.nextStep()) {}
@@ -760,7 +760,7 @@ describe("FETCH Host Function Comparison", () => {
     view.setBigUint64(9 * 8, 512n, true);
     view.setBigUint64(10 * 8, 0n, true);
     native.setRegisters(Buffer.from(regs));
-    native.setGasLeft(1000);
+    native.setGasLeft(1000n);
     native.setNextProgramCounter(0);
     // Do NOT call initPage: 0x20000 is not writable → write_octets faults → panic
 
@@ -320,7 +320,7 @@ describe('INFO Host Function Comparison', () => {
       getRamTypeSimpleRam: () => number
       prepareBlob: (program: Buffer) => void
       setRegisters: (registers: Buffer) => void
-      setGasLeft: (gas: number) => void
+      setGasLeft: (gas: bigint) => void
       setNextProgramCounter: (pc: number) => void
       initPage: (address: number, length: number, accessType: number) => void
       nextStep: () => boolean
@@ -340,7 +340,7 @@ describe('INFO Host Function Comparison', () => {
     program.writeBigUInt64LE(5n, 1) // INFO
     native.prepareBlob(program)
 
-    native.setGasLeft(1000)
+    native.setGasLeft(1000n)
     native.setNextProgramCounter(0)
     native.initPage(0x20000, 4096, 2)
 
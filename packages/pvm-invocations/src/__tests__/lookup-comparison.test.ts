@@ -487,7 +487,7 @@ describe('LOOKUP Host Function', () => {
       getRamTypeSimpleRam: () => number
       prepareBlob: (program: Buffer) => void
       setRegisters: (registers: Buffer) => void
-      setGasLeft: (gas: number) => void
+      setGasLeft: (gas: bigint) => void
       setNextProgramCounter: (pc: number) => void
       initPage: (address: number, length: number, accessType: number) => void
       nextStep: () => boolean
@@ -516,7 +516,7 @@ describe('LOOKUP Host Function', () => {
     view.setBigUint64(10 * 8, 0n, true)
     view.setBigUint64(11 * 8, 32n, true)
     native.setRegisters(Buffer.from(regs))
-    native.setGasLeft(1000)
+    native.setGasLeft(1000n)
     native.setNextProgramCounter(0)
     native.initPage(0x20000, 4096, 2)
 
@@ -19,6 +19,7 @@ import type {
   WorkPackage,
 } from '@pbnjam/types'
 import {
+  RustPVMExecutor,
   TypeScriptPVMExecutor,
   WasmPVMExecutor,
 } from '../pvm-executor-adapters'
@@ -31,8 +32,12 @@ import {
 export class IsAuthorizedPVM {
   private readonly serviceAccountService: IServiceAccountService
   //   private readonly configService: IConfigService
-  private readonly pvmExecutor: TypeScriptPVMExecutor | WasmPVMExecutor
+  private readonly pvmExecutor:
+    | TypeScriptPVMExecutor
+    | WasmPVMExecutor
+    | RustPVMExecutor
   private readonly useWasm: boolean
+  private readonly useRust: boolean
 
   constructor(options: {
     hostFunctionRegistry: HostFunctionRegistry
@@ -40,14 +45,22 @@ export class IsAuthorizedPVM {
     configService: IConfigService
     pvmOptions?: PVMOptions
     useWasm: boolean
+    useRust?: boolean
     traceSubfolder?: string
   }) {
     this.useWasm = options.useWasm
+    this.useRust = options.useRust ?? false
     this.serviceAccountService = options.serviceAccountService
     //     this.configService = options.configService
 
-    // Create PVM executor based on useWasm flag
-    if (options.useWasm) {
+    // Create PVM executor based on useRust / useWasm flags
+    if (this.useRust) {
+      this.pvmExecutor = new RustPVMExecutor(
+        options.configService,
+        null, // entropyService not needed for is-authorized
+        options.traceSubfolder,
+      )
+    } else if (options.useWasm) {
       // Create WASM executor - module will be loaded from pvm-assemblyscript/build/pvm.wasm
       // and instantiated lazily on first use
       this.pvmExecutor = new WasmPVMExecutor(
@@ -150,7 +163,17 @@ export class IsAuthorizedPVM {
         }
       | undefined
 
-    if (this.useWasm) {
+    if (this.useRust) {
+      // Rust executor - Cpackageauthgas and E_2(c) are applied natively
+      const [rustError, rustResult] = await (
+        this.pvmExecutor as RustPVMExecutor
+      ).executeIsAuthorizedInvocation(authCode, coreIndex, workPackage)
+      if (rustResult?.result === 'BIG') {
+        return { result: 'BIG', gasUsed: 0n }
+      }
+      error = rustError
+      marshallingResult = rustResult as typeof marshallingResult
+    } else if (this.useWasm) {
       // WASM executor - use direct is-authorized method
       const [wasmError, wasmResult] = await (
         this.pvmExecutor as WasmPVMExecutor
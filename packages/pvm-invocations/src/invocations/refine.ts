@@ -87,7 +87,7 @@ export class RefinePVM {
   /**
    * Execute refine invocation (Ψ_R)
    *
-   * Gray Paper Equation 78-89: Ψ_R: (coreindex, N, workpackage, blob, sequence{sequence{segment}}, N) → (blob ∪ workerror, sequence{segment}, gas)
+   * Gray Paper Equation 78-89: Ψ_R: (coreindex, N, workpackage, blob, sequence{sequence{segment}}, sequence{sequence{blob}}, N) → (blob ∪ workerror, sequence{segment}, gas)
    *
    * Refine Invocation Constituents (Gray Paper):
    * - coreIndex (c): Core performing the refinement
@@ -95,6 +95,7 @@ export class RefinePVM {
    * - workPackage (p): Work package containing work items
    * - authorizerTrace (r): Authorizer trace blob
    * - importSegments (ī): Import segments for all work items
+   * - extrinsics (x̄): Extrinsic data blobs for all work items
    * - exportSegmentOffset (segoff): Export segment offset
    *
    * Internal Processing (Gray Paper):
@@ -110,6 +111,8 @@ export class RefinePVM {
    * @param workPackage - Work package
    * @param authorizerTrace - Authorizer trace blob
    * @param importSegments - Import segments for all work items
+   * @param extrinsics - Extrinsic data blobs for all work items, served by FETCH selectors 3 and 4
+   *   (Rust executor only; the WASM and TypeScript executors do not take them yet)
    * @param exportSegmentOffset - Export segment offset
    * @returns Tuple of (result, exportSegments, gasUsed)
    */
@@ -119,6 +122,7 @@ export class RefinePVM {
     workPackage: WorkPackage,
     authorizerTrace: Uint8Array,
     importSegments: Uint8Array[][],
+    extrinsics: Uint8Array[][],
     exportSegmentOffset: bigint,
   ): Promise<{
     result: Uint8Array | WorkError
@@ -208,9 +212,12 @@ export class RefinePVM {
         workPackage,
         authorizerTrace,
         importSegments,
+        extrinsics,
         exportSegmentOffset,
         serviceAccount,
         lookupAnchorTimeslot,
+        workItemIndex,
+        workItem.serviceindex,
       )
       if (rustError || !rustResult) {
         logger.error('[RefinePVM] Rust refine invocation failed', {
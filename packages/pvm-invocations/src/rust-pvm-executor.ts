@@ -3,6 +3,7 @@
  *
  * Wraps the Rust native PVM (@pbnjam/pvm-rust-native) to provide the same interface
  * and return format as WasmPVMExecutor. Matches pvm-rust lib.rs NAPI bindings:
+ * accumulateInvocation (one-shot Ψ_A in native code) or, when tracing,
  * setupAccumulateInvocation (single call with encoded_accumulate_inputs), nextStep,
  * getStatus, getGasLeft, getResult, getAccumulationContext.
  *
@@ -18,16 +19,23 @@ const require = createRequire(import.meta.url)
 import {
   decodeImplicationsPair,
   encodeAccumulateInput,
+  encodeCompleteServiceAccount,
+  encodeFixedLength,
   encodeImplicationsPair,
+  encodeNatural,
   encodeRefineContext,
   encodeVariableSequence,
+  encodeWorkItemSummary,
+  encodeWorkPackage,
 } from '@pbnjam/codec'
+import { concatBytes, hexToBytes } from '@pbnjam/core'
 import { getInstructionName, writeTraceDump } from '@pbnjam/pvm'
 import type {
   AccumulateInput,
   IConfigService,
   IEntropyService,
   ImplicationsPair,
+  Safe,
   SafePromise,
   ServiceAccount,
   WorkPackage,
@@ -39,7 +47,31 @@ export type NativeBinding = {
   init: (ramType: number) => void
   reset: () => void
   setupAccumulateInvocation: (
-    gasLimit: number,
+    gasLimit: bigint,
+    program: Buffer,
+    args: Buffer,
+    context: Buffer,
+    numCores: number,
+    numValidators: number,
+    authQueueSize: number,
+    entropyAccumulator: Buffer,
+    encodedWorkItems: Buffer,
+    encodedAccumulateInputs: Buffer[] | null | undefined,
+    configPreimageExpungePeriod: number,
+    configEpochDuration: number,
+    configMaxBlockGas: number,
+    configMaxRefineGas: number,
+    configMaxTicketsPerExtrinsic: number,
+    configTicketsPerValidator: number,
+    configSlotDuration: number,
+    configRotationPeriod: number,
+    configNumEcPiecesPerSegment: number,
+    configContestDuration: number,
+    configMaxLookupAnchorage: number,
+    configEcPieceSize: number,
+  ) => boolean
+  accumulateInvocation?: (
+    gasLimit: bigint,
     program: Buffer,
     args: Buffer,
     context: Buffer,
@@ -61,10 +93,17 @@ export type NativeBinding = {
     configContestDuration: number,
     configMaxLookupAnchorage: number,
     configEcPieceSize: number,
-  ) => void
+  ) => {
+    gasConsumed: bigint
+    resultCode: number
+    output: Buffer
+    yieldHash: Buffer
+    context: Buffer
+    postState: Buffer
+  }
   nextStep: () => boolean
   getStatus: () => number
-  getGasLeft: () => number
+  getGasLeft: () => bigint
   getProgramCounter?: () => number
   getRegisters?: () => Buffer
   getLastOpcode?: () => number
@@ -81,13 +120,122 @@ export type NativeBinding = {
     authQueueSize: number,
   ) => Buffer
   setupRefineInvocation?: (
-    gasLimit: number,
+    gasLimit: bigint,
     program: Buffer,
     args: Buffer,
     refineContextEncoded: Buffer,
     configMaxRefineGas: number,
-  ) => void
+    inputs?: NativeRefineInputs,
+  ) => boolean
   getExportSegments?: () => Buffer[]
+  setExternalHostCalls?: (enabled: boolean) => void
+  setUseRecompiler?: (enabled: boolean) => boolean
+  resumeHostCall?: (gasCost?: bigint) => boolean
+  setTrace?: (enabled: boolean, path?: string) => boolean
+  takeTrace?: () => string
+  checkTrace?: (reference: string) => {
+    matched: boolean
+    steps: number
+    finished: boolean
+    step?: number
+    pc?: number
+    opcode?: string
+    mismatches: string[]
+    expected?: string
+    actual?: string
+  }
+  setProfiling?: (enabled: boolean) => void
+  getProfile?: () => NativeProfileReport | null
+  setVerifyOnLoad?: (enabled: boolean) => void
+  setBlockGas?: (enabled: boolean, costs?: number[]) => void
+  getLoadDiagnostics?: () => NativeProgramDiagnostic[]
+  snapshot?: () => Buffer
+  restore?: (blob: Buffer) => boolean
+  getDirtyPages?: () => number[]
+  clearDirtyPages?: () => void
+  memoryDiff?: (snapshot: Buffer) => NativePageDiff[] | null
+  readMemory?: (address: number, length: number) => Buffer | null
+  isAuthorizedInvocation?: (
+    program: Buffer,
+    coreIndex: number,
+    inputs: NativeIsAuthorizedInputs,
+  ) => {
+    gasConsumed: bigint
+    resultCode: number
+    output: Buffer
+    error?: string
+  }
+}
+
+/** Work package fields for FETCH selectors 7-13 (IsAuthorizedInputs in pvm-rust). */
+interface NativeIsAuthorizedInputs {
+  workPackage: Buffer
+  authConfig: Buffer
+  authToken: Buffer
+  refineContext: Buffer
+  workItemSummaries: Buffer[]
+  workItemPayloads: Buffer[]
+}
+
+/** Ψ_R host-call inputs for setupRefineInvocation (RefineInvocationInputs in pvm-rust). */
+interface NativeRefineInputs {
+  workPackage: Buffer
+  authConfig: Buffer
+  authToken: Buffer
+  workItemSummaries: Buffer[]
+  workItemPayloads: Buffer[]
+  workItemIndex: number
+  importSegments: Buffer[][]
+  extrinsics: Buffer[][]
+  authorizerTrace: Buffer
+  exportSegmentOffset: number
+  lookupAnchorTimeslot: number
+  serviceId: number
+  /** var{sequence{(serviceid, CompleteServiceAccount)}} */
+  accounts: Buffer
+}
+
+/** Execution profile from getProfile (ProfileReportOutput in pvm-rust). Lists are in ascending key order. */
+export interface NativeProfileReport {
+  instructions: number
+  gas: number
+  opcodes: { opcode: number; name: string; count: number; gas: number }[]
+  pcs: { pc: number; count: number; gas: number }[]
+  blocks: {
+    start: number
+    end: number
+    entries: number
+    instructions: number
+    gas: number
+  }[]
+  hostCalls: {
+    id: number
+    name: string
+    calls: number
+    gas: number
+    /** Outcome (OK, OOB, WHO, FULL, VALUE, PANIC, …) to count. */
+    results: Record<string, number>
+  }[]
+}
+
+/** Static verifier finding from getLoadDiagnostics (ProgramDiagnosticOutput in pvm-rust). */
+export interface NativeProgramDiagnostic {
+  /** unknown_opcode, truncated_instruction, invalid_branch_target or invalid_jump_table_entry */
+  kind: string
+  pc?: number
+  opcode?: number
+  target?: number
+  jumpTableIndex?: number
+  /** out_of_range, not_instruction_start or not_block_start */
+  reason?: string
+  message: string
+}
+
+/** A guest page written since clearDirtyPages whose contents differ from a snapshot. */
+export interface NativePageDiff {
+  pageIndex: number
+  /** Changed bytes at `offset` within the page; `old` and `new` have the same length. */
+  ranges: { offset: number; old: Buffer; new: Buffer }[]
 }
 
 /** Paths to try so the addon resolves when running the compiled fuzzer binary (./bin/fuzzer-target). */
@@ -152,6 +300,98 @@ export class RustPVMExecutor {
     this.native.init(RAM_TYPE_PVM_RAM)
   }
 
+  /**
+   * Run invocations with the native x86-64 recompiler instead of the interpreter.
+   * Returns false when the addon does not support it on this platform (the interpreter stays in use).
+   */
+  setUseRecompiler(enabled: boolean): boolean {
+    return this.native?.setUseRecompiler?.(enabled) ?? false
+  }
+
+  /**
+   * Record the trace natively (writeTraceDump line format) into `path`, or into a buffer read with takeNativeTrace.
+   * Avoids the per-step getter round-trips of the JS-side trace. Returns false when unsupported or the file cannot be created.
+   */
+  setNativeTrace(enabled: boolean, path?: string): boolean {
+    return this.native?.setTrace?.(enabled, path) ?? false
+  }
+
+  /**
+   * Native trace lines buffered since the last call (empty when tracing to a file).
+   */
+  takeNativeTrace(): string {
+    return this.native?.takeTrace?.() ?? ''
+  }
+
+  /**
+   * Count steps and gas per opcode, PC/basic block and host function natively. Enabling clears the counters.
+   */
+  setProfiling(enabled: boolean): void {
+    this.native?.setProfiling?.(enabled)
+  }
+
+  /**
+   * Profile of the current program, or null when profiling is disabled or unsupported.
+   */
+  getProfile(): NativeProfileReport | null {
+    return this.native?.getProfile?.() ?? null
+  }
+
+  /**
+   * Charge gas per basic block (cost of the whole block on entry, `costs[opcode]` per instruction) instead of
+   * 1 per instruction. Traces only match the reference with per-instruction charging.
+   */
+  setBlockGas(enabled: boolean, costs?: number[]): void {
+    this.native?.setBlockGas?.(enabled, costs)
+  }
+
+  /**
+   * Run the native static verifier whenever a program is loaded. Execution is unaffected.
+   */
+  setVerifyOnLoad(enabled: boolean): void {
+    this.native?.setVerifyOnLoad?.(enabled)
+  }
+
+  /**
+   * Verifier findings for the loaded program (empty when verification on load is off or unsupported).
+   */
+  getLoadDiagnostics(): NativeProgramDiagnostic[] {
+    return this.native?.getLoadDiagnostics?.() ?? []
+  }
+
+  /**
+   * Versioned binary snapshot of the native PVM (registers, memory, invocation context), or null when unavailable.
+   */
+  snapshot(): Uint8Array | null {
+    return this.native?.snapshot?.() ?? null
+  }
+
+  /**
+   * Restore a snapshot taken with snapshot(). Returns false for malformed or incompatible snapshots.
+   */
+  restore(blob: Uint8Array): boolean {
+    return this.native?.restore?.(Buffer.from(blob)) ?? false
+  }
+
+  /**
+   * Guest pages written since clearDirtyPages() (or since memory was set up), ascending.
+   */
+  getDirtyPages(): number[] {
+    return this.native?.getDirtyPages?.() ?? []
+  }
+
+  clearDirtyPages(): void {
+    this.native?.clearDirtyPages?.()
+  }
+
+  /**
+   * Changed byte ranges of the dirty pages against the memory in a snapshot() taken when the dirty set was
+   * cleared. Null when unavailable or the snapshot is not accepted.
+   */
+  memoryDiff(snapshot: Uint8Array): NativePageDiff[] | null {
+    return this.native?.memoryDiff?.(Buffer.from(snapshot)) ?? null
+  }
+
   /**
    * Execute accumulation invocation. Returns the same format as WasmPVMExecutor:
    * { gasConsumed, result, context } with context from getAccumulationContext.
@@ -231,9 +471,8 @@ export class RustPVMExecutor {
           })()
         : null
 
-    this.native.reset()
-    this.native.setupAccumulateInvocation(
-      Number(gasLimit),
+    const invocationArgs = [
+      gasLimit,
       Buffer.from(preimageBlob),
       Buffer.from(encodedArgs),
       Buffer.from(encodedContext),
@@ -255,13 +494,45 @@ export class RustPVMExecutor {
       this.configService.contestDuration,
       this.configService.maxLookupAnchorage,
       this.configService.ecPieceSize,
-    )
+    ] as const
+
+    const enableTrace =
+      this.traceSubfolder && process.env['ENABLE_PVM_TRACE_DUMP'] === 'true'
+    if (!enableTrace && this.native.accumulateInvocation) {
+      // Whole Ψ_A runs natively; no per-step NAPI round trips.
+      const native = this.native.accumulateInvocation(...invocationArgs)
+      const [decodeError, decodeResult] = decodeImplicationsPair(
+        new Uint8Array(native.context),
+        this.configService,
+      )
+      if (decodeError || !decodeResult) {
+        return safeError(
+          new Error(
+            `Failed to decode updated implications: ${decodeError?.message}`,
+          ),
+        )
+      }
+      let result: Uint8Array | 'PANIC' | 'OOG'
+      if (native.resultCode === RESULT_CODES.OOG) {
+        result = 'OOG'
+      } else if (native.resultCode !== RESULT_CODES.HALT) {
+        result = 'PANIC'
+      } else {
+        result = new Uint8Array(native.output)
+      }
+      return safeResult({
+        gasConsumed: native.gasConsumed,
+        result,
+        context: decodeResult.value,
+      })
+    }
+
+    this.native.reset()
+    this.native.setupAccumulateInvocation(...invocationArgs)
 
     const initialGas = gasLimit
     const maxSteps = Number(this.configService.maxBlockGas)
     let steps = 0
-    const enableTrace =
-      this.traceSubfolder && process.env['ENABLE_PVM_TRACE_DUMP'] === 'true'
     const executionLogs: Array<{
       step: number
       pc: bigint
@@ -289,11 +560,7 @@ export class RustPVMExecutor {
       const status = this.native.getStatus()
 
       if (enableTrace) {
-        const gasRaw = this.native.getGasLeft()
-        const gasAfter =
-          gasRaw >>> 0 === gasRaw
-            ? BigInt(gasRaw)
-            : BigInt(gasRaw >>> 0) + 0x1_0000_0000n
+        const gasAfter = this.native.getGasLeft()
         const pc = this.native.getProgramCounter
           ? BigInt(this.native.getProgramCounter())
           : 0n
@@ -327,7 +594,7 @@ export class RustPVMExecutor {
           hostFunctionLogs.push({
             step: steps,
             hostCallId: BigInt(hostCallId),
-            gasBefore: BigInt(gasBeforeStep >>> 0),
+            gasBefore: gasBeforeStep,
             gasAfter,
             serviceId: _serviceId,
           })
@@ -341,11 +608,7 @@ export class RustPVMExecutor {
       if (status !== 0 && status !== 4) break
     }
 
-    const finalGasRaw = this.native.getGasLeft()
-    const finalGas =
-      finalGasRaw >>> 0 === finalGasRaw
-        ? BigInt(finalGasRaw)
-        : BigInt(finalGasRaw >>> 0) + 0x1_0000_0000n
+    const finalGas = this.native.getGasLeft()
     const status = this.native.getStatus()
 
     let gasConsumed: bigint
@@ -451,17 +714,21 @@ export class RustPVMExecutor {
 
   /**
    * Execute refinement invocation (Ψ_R). Uses native setupRefineInvocation + next_step loop + getResult + getExportSegments.
+   * FETCH (selectors 2-13) and HISTORICAL_LOOKUP are served natively from the work package, segments and account passed here.
    */
   async executeRefinementInvocation(
     preimageBlob: Uint8Array,
     gasLimit: bigint,
     encodedArgs: Uint8Array,
     workPackage: WorkPackage,
-    _authorizerTrace: Uint8Array,
-    _importSegments: Uint8Array[][],
-    _exportSegmentOffset: bigint,
-    _serviceAccount: ServiceAccount,
-    _lookupAnchorTimeslot: bigint,
+    authorizerTrace: Uint8Array,
+    importSegments: Uint8Array[][],
+    extrinsics: Uint8Array[][],
+    exportSegmentOffset: bigint,
+    serviceAccount: ServiceAccount,
+    lookupAnchorTimeslot: bigint,
+    workItemIndex: bigint,
+    serviceId: bigint,
   ): SafePromise<{
     gasConsumed: bigint
     result: Uint8Array | 'PANIC' | 'OOG'
@@ -491,14 +758,40 @@ export class RustPVMExecutor {
         ),
       )
     }
+    const [inputsErr, refineInputs] = this.encodeRefineInputs(
+      workPackage,
+      workItemIndex,
+      importSegments,
+      extrinsics,
+      authorizerTrace,
+      exportSegmentOffset,
+      lookupAnchorTimeslot,
+      serviceId,
+      serviceAccount,
+    )
+    if (inputsErr || !refineInputs) {
+      return safeError(
+        new Error(
+          `Failed to encode refine inputs: ${inputsErr?.message ?? 'unknown'}`,
+        ),
+      )
+    }
     this.native.reset()
-    this.native.setupRefineInvocation(
-      Number(gasLimit),
+    const setupOk = this.native.setupRefineInvocation(
+      gasLimit,
       Buffer.from(preimageBlob),
       Buffer.from(encodedArgs),
       Buffer.from(refineContextEncoded),
       Number(this.configService.maxRefineGas),
+      refineInputs,
     )
+    if (!setupOk) {
+      return safeResult({
+        gasConsumed: 0n,
+        result: 'PANIC' as const,
+        exportSegments: [],
+      })
+    }
     const initialGas = gasLimit
     const maxSteps = Number(this.configService.maxBlockGas)
     let steps = 0
@@ -531,11 +824,7 @@ export class RustPVMExecutor {
       const status = this.native.getStatus()
 
       if (enableTrace) {
-        const gasRaw = this.native.getGasLeft()
-        const gasAfter =
-          gasRaw >>> 0 === gasRaw
-            ? BigInt(gasRaw)
-            : BigInt(gasRaw >>> 0) + 0x1_0000_0000n
+        const gasAfter = this.native.getGasLeft()
         const pc = this.native.getProgramCounter
           ? BigInt(this.native.getProgramCounter())
           : 0n
@@ -569,7 +858,7 @@ export class RustPVMExecutor {
           hostFunctionLogs.push({
             step: steps,
             hostCallId: BigInt(hostCallId),
-            gasBefore: BigInt(gasBeforeStep >>> 0),
+            gasBefore: gasBeforeStep,
             gasAfter,
           })
         }
@@ -581,11 +870,7 @@ export class RustPVMExecutor {
       }
       if (status !== 0 && status !== 4) break
     }
-    const finalGasRaw = this.native.getGasLeft()
-    const finalGas =
-      finalGasRaw >>> 0 === finalGasRaw
-        ? BigInt(finalGasRaw)
-        : BigInt(finalGasRaw >>> 0) + 0x1_0000_0000n
+    const finalGas = this.native.getGasLeft()
     const status = this.native.getStatus()
     let gasConsumed: bigint
     if (status === 5) {
@@ -657,6 +942,137 @@ export class RustPVMExecutor {
     })
   }
 
+  /**
+   * Encode the Ψ_R host-call inputs for the native module. Accounts (δ) carry only the refined service,
+   * which is what HISTORICAL_LOOKUP resolves when r7 = NONE or the service's own ID.
+   */
+  private encodeRefineInputs(
+    workPackage: WorkPackage,
+    workItemIndex: bigint,
+    importSegments: Uint8Array[][],
+    extrinsics: Uint8Array[][],
+    authorizerTrace: Uint8Array,
+    exportSegmentOffset: bigint,
+    lookupAnchorTimeslot: bigint,
+    serviceId: bigint,
+    serviceAccount: ServiceAccount,
+  ): Safe<NativeRefineInputs> {
+    const [packageErr, packageInputs] =
+      this.encodeWorkPackageInputs(workPackage)
+    if (packageErr) {
+      return safeError(packageErr)
+    }
+    const [idErr, encodedId] = encodeFixedLength(serviceId, 4n)
+    if (idErr) {
+      return safeError(idErr)
+    }
+    const [accountErr, encodedAccount] =
+      encodeCompleteServiceAccount(serviceAccount)
+    if (accountErr) {
+      return safeError(accountErr)
+    }
+    const accountPair = concatBytes([encodedId, encodedAccount])
+    const [lengthErr, encodedLength] = encodeNatural(BigInt(accountPair.length))
+    if (lengthErr) {
+      return safeError(lengthErr)
+    }
+    return safeResult({
+      workPackage: packageInputs.workPackage,
+      authConfig: packageInputs.authConfig,
+      authToken: packageInputs.authToken,
+      workItemSummaries: packageInputs.workItemSummaries,
+      workItemPayloads: packageInputs.workItemPayloads,
+      workItemIndex: Number(workItemIndex),
+      importSegments: importSegments.map((segments) =>
+        segments.map((segment) => Buffer.from(segment)),
+      ),
+      extrinsics: extrinsics.map((blobs) =>
+        blobs.map((blob) => Buffer.from(blob)),
+      ),
+      authorizerTrace: Buffer.from(authorizerTrace),
+      exportSegmentOffset: Number(exportSegmentOffset),
+      lookupAnchorTimeslot: Number(lookupAnchorTimeslot),
+      serviceId: Number(serviceId),
+      accounts: Buffer.from(concatBytes([encodedLength, accountPair])),
+    })
+  }
+
+  /** Encode the work package fields served by FETCH selectors 7-13. */
+  private encodeWorkPackageInputs(
+    workPackage: WorkPackage,
+  ): Safe<NativeIsAuthorizedInputs> {
+    const [packageErr, encodedPackage] = encodeWorkPackage(workPackage)
+    if (packageErr) {
+      return safeError(packageErr)
+    }
+    const [contextErr, encodedContext] = encodeRefineContext(
+      workPackage.context,
+    )
+    if (contextErr) {
+      return safeError(contextErr)
+    }
+    const summaries: Buffer[] = []
+    for (const item of workPackage.workItems) {
+      const [summaryErr, summary] = encodeWorkItemSummary(item)
+      if (summaryErr) {
+        return safeError(summaryErr)
+      }
+      summaries.push(Buffer.from(summary))
+    }
+    return safeResult({
+      workPackage: Buffer.from(encodedPackage),
+      authConfig: Buffer.from(hexToBytes(workPackage.authConfig)),
+      authToken: Buffer.from(hexToBytes(workPackage.authToken)),
+      refineContext: Buffer.from(encodedContext),
+      workItemSummaries: summaries,
+      workItemPayloads: workPackage.workItems.map((item) =>
+        Buffer.from(item.payload),
+      ),
+    })
+  }
+
+  /**
+   * Execute Is-Authorized invocation (Ψ_I) natively. The native module applies Cpackageauthgas,
+   * Cmaxauthcodesize and the GAS/FETCH/LOG host-call restriction.
+   */
+  async executeIsAuthorizedInvocation(
+    authCode: Uint8Array,
+    coreIndex: bigint,
+    workPackage: WorkPackage,
+  ): SafePromise<{
+    gasConsumed: bigint
+    result: Uint8Array | 'PANIC' | 'OOG' | 'BIG'
+  }> {
+    if (!this.native) {
+      return safeError(
+        new Error(
+          'Rust native module not available. Build with: cd packages/pvm-rust && bun run build',
+        ),
+      )
+    }
+    if (!this.native.isAuthorizedInvocation) {
+      return safeError(
+        new Error(
+          'Rust native module does not support is-authorized. Rebuild pvm-rust with isAuthorizedInvocation.',
+        ),
+      )
+    }
+    const [inputsErr, inputs] = this.encodeWorkPackageInputs(workPackage)
+    if (inputsErr) {
+      return safeError(inputsErr)
+    }
+    const native = this.native.isAuthorizedInvocation(
+      Buffer.from(authCode),
+      Number(coreIndex),
+      inputs,
+    )
+    const error = native.error as 'PANIC' | 'OOG' | 'BIG' | undefined
+    return safeResult({
+      gasConsumed: native.gasConsumed,
+      result: error ?? new Uint8Array(native.output),
+    })
+  }
+
   dispose(): void {
     // No persistent state to clear when using minimal NAPI binding
   }
@@ -0,0 +1,59 @@
+/**
+ * Replay a program against a reference trace (writeTraceDump format, e.g. from jamtestnet) and report the
+ * first step where the Rust PVM diverges.
+ *
+ * Usage: bun scripts/check-trace.ts <program.bin> <reference.log> [gas]
+ * Exits 0 when every reference step matches, 1 on divergence, 2 on bad arguments.
+ */
+
+import { readFileSync } from 'node:fs'
+
+interface TraceCheckResult {
+  matched: boolean
+  steps: number
+  finished: boolean
+  step?: number
+  pc?: number
+  opcode?: string
+  mismatches: string[]
+  expected?: string
+  actual?: string
+}
+
+const native = require('@pbnjam/pvm-rust-native/native') as {
+  init: (ramType: number) => void
+  getRamTypePvmRam: () => number
+  prepareBlob: (program: Buffer) => void
+  setGasLeft: (gas: bigint) => void
+  checkTrace: (reference: string) => TraceCheckResult
+}
+
+const [programPath, referencePath, gas] = process.argv.slice(2)
+if (!programPath || !referencePath) {
+  console.error(
+    'Usage: bun scripts/check-trace.ts <program.bin> <reference.log> [gas]',
+  )
+  process.exit(2)
+}
+
+native.init(native.getRamTypePvmRam())
+native.prepareBlob(readFileSync(programPath))
+if (gas !== undefined) native.setGasLeft(BigInt(gas))
+
+const result = native.checkTrace(readFileSync(referencePath, 'utf-8'))
+if (result.matched) {
+  console.log(
+    `All ${result.steps} reference steps match${result.finished ? '' : ' (program had not finished)'}`,
+  )
+  process.exit(0)
+}
+
+console.log(
+  `Divergence at step ${result.step}: ${result.mismatches.join(', ')}`,
+)
+if (result.opcode !== undefined) {
+  console.log(`  actual opcode ${result.opcode} at PC ${result.pc}`)
+}
+console.log(`  expected: ${result.expected}`)
+console.log(`  actual:   ${result.actual ?? '(program stopped)'}`)
+process.exit(1)
@@ -0,0 +1,584 @@
+//! Text assembler for test programs. Accepts the disassembler's operand syntax (`r0`…`r12`, decimal or `0x`
+//! immediates, `@<pc>` targets) plus labels and directives, and emits a deblob-format blob (`codec::encode_blob`)
+//! or a service-code preimage whose Y-format program `decode_program_from_preimage` accepts.
+//!
+//! ```text
+//! .jump_table done            ; djump index 1 -> done (entries are labels or @<pc>)
+//! .ro_data 01 02 0x0304       ; hex octets, appended in order; also .rw_data and .metadata
+//! .stack_size 4096            ; Y-format s
+//! .heap_pages 1               ; Y-format z (zero-padding pages after rw data)
+//! start:
+//!     LOAD_IMM r0, 7
+//!     BRANCH_EQ_IMM r0, 7, done
+//!     TRAP
+//! done: FALLTHROUGH
+//! ```
+//!
+//! Mnemonics are the handlers' `name()`s (case-insensitive). Immediates use the shortest encoding; branch
+//! offsets are shrunk until the layout is stable.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::codec::{encode_blob, encode_natural, DecodedBlob};
+use crate::config::OPCODE_LOAD_U8;
+use crate::disassembler::{instruction_format, InstructionFormat};
+use crate::instructions::base::{sign_extend, value_to_bytes_le};
+use crate::state_wrapper::instruction_registry;
+
+/// Error with the 1-based source line it refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Assembled code plus the data sections and sizes of the Y-format program.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssembledProgram {
+    pub code: Vec<u8>,
+    pub bitmask: Vec<u8>,
+    pub jump_table: Vec<u32>,
+    pub ro_data: Vec<u8>,
+    pub rw_data: Vec<u8>,
+    pub stack_size: u32,
+    pub heap_pages: u32,
+    pub metadata: Vec<u8>,
+}
+
+impl AssembledProgram {
+    /// Deblob format: code, bitmask and jump table (for `prepareBlob` / `runBlob`).
+    #[must_use]
+    pub fn to_blob(&self) -> Vec<u8> {
+        let max_entry = self.jump_table.iter().copied().max().unwrap_or(0);
+        let element_size = (1..4).find(|&z| max_entry < 1u32 << (8 * z)).unwrap_or(4);
+        encode_blob(&DecodedBlob {
+            code: self.code.clone(),
+            bitmask: self.bitmask.clone(),
+            jump_table: self.jump_table.clone(),
+            element_size,
+            header_size: 0,
+        })
+    }
+
+    /// Y format: E₃(|o|) ⊕ E₃(|w|) ⊕ E₂(z) ⊕ E₃(s) ⊕ o ⊕ w ⊕ E₄(|c|) ⊕ c, with c the deblob-format blob.
+    #[must_use]
+    pub fn to_program(&self) -> Vec<u8> {
+        let blob = self.to_blob();
+        let mut out = Vec::with_capacity(15 + self.ro_data.len() + self.rw_data.len() + blob.len());
+        out.extend(value_to_bytes_le(self.ro_data.len() as u64, 3));
+        out.extend(value_to_bytes_le(self.rw_data.len() as u64, 3));
+        out.extend(value_to_bytes_le(u64::from(self.heap_pages), 2));
+        out.extend(value_to_bytes_le(u64::from(self.stack_size), 3));
+        out.extend_from_slice(&self.ro_data);
+        out.extend_from_slice(&self.rw_data);
+        out.extend(value_to_bytes_le(blob.len() as u64, 4));
+        out.extend(blob);
+        out
+    }
+
+    /// Service-code preimage: encode(|m|) ⊕ m ⊕ Y-format program.
+    #[must_use]
+    pub fn to_preimage(&self) -> Vec<u8> {
+        let mut out = encode_natural(self.metadata.len() as u64);
+        out.extend_from_slice(&self.metadata);
+        out.extend(self.to_program());
+        out
+    }
+}
+
+enum Arg {
+    Register(u8),
+    Value(i64),
+    Label(String),
+}
+
+struct Statement {
+    line: usize,
+    opcode: u8,
+    format: InstructionFormat,
+    args: Vec<Arg>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Register,
+    Immediate,
+    Target,
+}
+
+fn operand_kinds(format: InstructionFormat) -> &'static [Kind] {
+    use Kind::{Immediate as I, Register as R, Target as T};
+    match format {
+        InstructionFormat::NoArguments => &[],
+        InstructionFormat::OneImmediate => &[I],
+        InstructionFormat::OneRegisterOneExtendedImmediate => &[R, I],
+        InstructionFormat::TwoImmediates => &[I, I],
+        InstructionFormat::OneOffset => &[T],
+        InstructionFormat::OneRegisterOneImmediate => &[R, I],
+        InstructionFormat::OneRegisterTwoImmediates => &[R, I, I],
+        InstructionFormat::OneRegisterOneImmediateOneOffset => &[R, I, T],
+        InstructionFormat::TwoRegisters => &[R, R],
+        InstructionFormat::TwoRegistersOneImmediate => &[R, R, I],
+        InstructionFormat::TwoRegistersOneOffset => &[R, R, T],
+        InstructionFormat::TwoRegistersTwoImmediates => &[R, R, I, I],
+        InstructionFormat::ThreeRegisters => &[R, R, R],
+    }
+}
+
+/// Decimal or `0x` hex, optionally negative; values above i64::MAX wrap (for LOAD_IMM_64).
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u64>().ok()?,
+    };
+    if negative {
+        (value <= 1 << 63).then(|| (value as i64).wrapping_neg())
+    } else {
+        Some(value as i64)
+    }
+}
+
+fn parse_target(text: &str) -> Option<Arg> {
+    if let Some(pc) = text.strip_prefix('@') {
+        return parse_number(pc)
+            .filter(|pc| u32::try_from(*pc).is_ok())
+            .map(Arg::Value);
+    }
+    let valid = text
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    valid.then(|| Arg::Label(text.to_string()))
+}
+
+fn parse_arg(kind: Kind, text: &str) -> Option<Arg> {
+    match kind {
+        Kind::Register => {
+            let index: u8 = text.strip_prefix(['r', 'R'])?.parse().ok()?;
+            (index <= 12).then_some(Arg::Register(index))
+        }
+        Kind::Immediate => parse_number(text).map(Arg::Value),
+        Kind::Target => parse_target(text),
+    }
+}
+
+/// Hex octets: whitespace/comma separated tokens, each an even number of digits with optional `0x`.
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for token in text.split([' ', '\t', ',']).filter(|t| !t.is_empty()) {
+        let hex = token.strip_prefix("0x").unwrap_or(token);
+        if hex.len() % 2 != 0 {
+            return None;
+        }
+        for i in (0..hex.len()).step_by(2) {
+            bytes.push(u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()?);
+        }
+    }
+    Some(bytes)
+}
+
+/// Shortest length (0..=4 octets) whose sign extension reproduces `value`.
+fn signed_length(value: i64) -> Option<usize> {
+    (0..=4).find(|&length| {
+        if length == 0 {
+            value == 0
+        } else {
+            sign_extend(value as u64, length as i32) as i64 == value
+        }
+    })
+}
+
+fn unsigned_length(value: i64) -> Option<usize> {
+    (0..=4).find(|&length| (value as u64) < 1u64 << (8 * length) && value >= 0)
+}
+
+/// Append `value` as `length` little-endian octets (nothing for length 0).
+fn push_le(out: &mut Vec<u8>, value: i64, length: usize) {
+    if length > 0 {
+        out.extend(value_to_bytes_le(value as u64, length));
+    }
+}
+
+/// Encode one instruction at `pc`. `values` holds register indices, immediates and absolute targets in operand
+/// order; the offset to a target is written in exactly `offset_length` octets.
+fn encode_instruction(
+    statement: &Statement,
+    values: &[i64],
+    pc: u32,
+    offset_length: usize,
+) -> Result<Vec<u8>, String> {
+    let signed = |value: i64| {
+        signed_length(value)
+            .map(|length| (value, length))
+            .ok_or_else(|| format!("immediate {value} does not fit in 4 octets"))
+    };
+    let offset = |target: i64| target - i64::from(pc);
+    let register = |index: usize| values[index] as u8;
+    let mut out = vec![statement.opcode];
+    match statement.format {
+        InstructionFormat::NoArguments => {}
+        InstructionFormat::OneImmediate => {
+            // Read back sign-extended and truncated to 32 bits, so encode the ID as its i32 value.
+            let id = u32::try_from(values[0])
+                .map(|id| i64::from(id as i32))
+                .map_err(|_| format!("host call ID {} out of range", values[0]))?;
+            let (id, length) = signed(id)?;
+            push_le(&mut out, id, length);
+        }
+        InstructionFormat::OneRegisterOneExtendedImmediate => {
+            out.push(register(0));
+            push_le(&mut out, values[1], 8);
+        }
+        InstructionFormat::TwoImmediates => {
+            // The address is read sign-extended and then truncated to 32 bits.
+            let address = u32::try_from(values[0])
+                .map(|a| i64::from(a as i32))
+                .or_else(|_| i32::try_from(values[0]).map(i64::from))
+                .map_err(|_| format!("address {} out of range", values[0]))?;
+            let (x, length_x) = signed(address)?;
+            let (y, length_y) = signed(values[1])?;
+            out.push(length_x as u8);
+            push_le(&mut out, x, length_x);
+            push_le(&mut out, y, length_y);
+        }
+        InstructionFormat::OneOffset => push_le(&mut out, offset(values[0]), offset_length),
+        InstructionFormat::OneRegisterOneImmediate => {
+            let length = if statement.opcode >= OPCODE_LOAD_U8 {
+                unsigned_length(values[1])
+                    .ok_or_else(|| format!("address {} out of range", values[1]))?
+            } else {
+                signed(values[1])?.1
+            };
+            out.push(register(0));
+            push_le(&mut out, values[1], length);
+        }
+        InstructionFormat::OneRegisterTwoImmediates => {
+            let (x, length_x) = signed(values[1])?;
+            let (y, length_y) = signed(values[2])?;
+            out.push(register(0) | (length_x as u8) << 4);
+            push_le(&mut out, x, length_x);
+            push_le(&mut out, y, length_y);
+        }
+        InstructionFormat::OneRegisterOneImmediateOneOffset => {
+            let (x, length_x) = signed(values[1])?;
+            out.push(register(0) | (length_x as u8) << 4);
+            push_le(&mut out, x, length_x);
+            push_le(&mut out, offset(values[2]), offset_length);
+        }
+        InstructionFormat::TwoRegisters => out.push(register(0) | register(1) << 4),
+        InstructionFormat::TwoRegistersOneImmediate => {
+            let (x, length_x) = signed(values[2])?;
+            out.push(register(0) | register(1) << 4);
+            push_le(&mut out, x, length_x);
+        }
+        InstructionFormat::TwoRegistersOneOffset => {
+            out.push(register(0) | register(1) << 4);
+            push_le(&mut out, offset(values[2]), offset_length);
+        }
+        InstructionFormat::TwoRegistersTwoImmediates => {
+            let (x, length_x) = signed(values[2])?;
+            let (y, length_y) = signed(values[3])?;
+            out.push(register(0) | register(1) << 4);
+            out.push(length_x as u8);
+            push_le(&mut out, x, length_x);
+            push_le(&mut out, y, length_y);
+        }
+        InstructionFormat::ThreeRegisters => {
+            out.push(register(1) | register(2) << 4);
+            out.push(register(0));
+        }
+    }
+    Ok(out)
+}
+
+fn has_target(format: InstructionFormat) -> bool {
+    operand_kinds(format).contains(&Kind::Target)
+}
+
+/// Resolve operands to numbers; labels become the PC of the instruction they precede.
+fn resolve(
+    statement: &Statement,
+    labels: &HashMap<String, usize>,
+    pcs: &[u32],
+) -> Result<Vec<i64>, String> {
+    statement
+        .args
+        .iter()
+        .map(|arg| match arg {
+            Arg::Register(index) => Ok(i64::from(*index)),
+            Arg::Value(value) => Ok(*value),
+            Arg::Label(name) => labels
+                .get(name)
+                .map(|&index| i64::from(pcs[index]))
+                .ok_or_else(|| format!("undefined label `{name}`")),
+        })
+        .collect()
+}
+
+/// Assemble `source` (see module docs for the syntax).
+pub fn assemble(source: &str) -> Result<AssembledProgram, AssembleError> {
+    let registry = instruction_registry();
+    let opcodes: HashMap<&'static str, u8> = (0..=u8::MAX)
+        .filter_map(|opcode| registry.handler(opcode).map(|h| (h.name(), opcode)))
+        .collect();
+
+    let mut program = AssembledProgram::default();
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut jump_table: Vec<(usize, Arg)> = Vec::new();
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| AssembleError { line, message };
+        let mut text = raw.split([';', '#']).next().unwrap_or("").trim();
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !matches!(parse_target(label), Some(Arg::Label(_))) {
+                return Err(error(format!("invalid label `{label}`")));
+            }
+            if labels.insert(label.to_string(), statements.len()).is_some() {
+                return Err(error(format!("duplicate label `{label}`")));
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (head, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+        if let Some(directive) = head.strip_prefix('.') {
+            let number = || {
+                parse_number(rest)
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| error(format!("expected a number, got `{rest}`")))
+            };
+            let bytes =
+                || parse_hex_bytes(rest).ok_or_else(|| error(format!("invalid hex `{rest}`")));
+            match directive {
+                "jump_table" => {
+                    for entry in rest.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                        let target = parse_target(entry)
+                            .ok_or_else(|| error(format!("invalid jump table entry `{entry}`")))?;
+                        jump_table.push((line, target));
+                    }
+                }
+                "ro_data" => program.ro_data.extend(bytes()?),
+                "rw_data" => program.rw_data.extend(bytes()?),
+                "metadata" => program.metadata.extend(bytes()?),
+                "stack_size" => program.stack_size = number()?,
+                "heap_pages" => program.heap_pages = number()?,
+                _ => return Err(error(format!("unknown directive `.{directive}`"))),
+            }
+            continue;
+        }
+        let mnemonic = head.to_ascii_uppercase();
+        let opcode = *opcodes
+            .get(mnemonic.as_str())
+            .ok_or_else(|| error(format!("unknown instruction `{head}`")))?;
+        let format = instruction_format(opcode)
+            .ok_or_else(|| error(format!("no operand format for `{mnemonic}`")))?;
+        let kinds = operand_kinds(format);
+        let texts: Vec<&str> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+        if texts.len() != kinds.len() {
+            return Err(error(format!(
+                "`{mnemonic}` takes {} operands, got {}",
+                kinds.len(),
+                texts.len()
+            )));
+        }
+        let args = kinds
+            .iter()
+            .zip(&texts)
+            .map(|(&kind, text)| {
+                parse_arg(kind, text).ok_or_else(|| error(format!("invalid operand `{text}`")))
+            })
+            .collect::<Result<_, _>>()?;
+        statements.push(Statement {
+            line,
+            opcode,
+            format,
+            args,
+        });
+    }
+
+    // Start with 4-octet offsets and shrink them; distances only decrease, so this terminates.
+    let mut offset_lengths = vec![4usize; statements.len()];
+    let (pcs, chunks) = loop {
+        let mut pcs = Vec::with_capacity(statements.len() + 1);
+        let mut pc = 0u32;
+        let mut chunks = Vec::with_capacity(statements.len());
+        for (statement, &offset_length) in statements.iter().zip(&offset_lengths) {
+            pcs.push(pc);
+            // Targets do not affect the size for a fixed offset length, so encode against this PC.
+            let placeholder: Vec<i64> = statement
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    Arg::Register(index) => i64::from(*index),
+                    Arg::Value(value) => *value,
+                    Arg::Label(_) => i64::from(pc),
+                })
+                .collect();
+            let chunk = encode_instruction(statement, &placeholder, pc, offset_length).map_err(
+                |message| AssembleError {
+                    line: statement.line,
+                    message,
+                },
+            )?;
+            pc += chunk.len() as u32;
+            chunks.push(chunk);
+        }
+        pcs.push(pc);
+        let mut changed = false;
+        for (i, statement) in statements.iter().enumerate() {
+            if !has_target(statement.format) {
+                continue;
+            }
+            let values = resolve(statement, &labels, &pcs).map_err(|message| AssembleError {
+                line: statement.line,
+                message,
+            })?;
+            let target = *values.last().unwrap_or(&0);
+            let needed = signed_length(target - i64::from(pcs[i])).ok_or(AssembleError {
+                line: statement.line,
+                message: "branch target out of range".to_string(),
+            })?;
+            if needed < offset_lengths[i] {
+                offset_lengths[i] = needed;
+                changed = true;
+            }
+        }
+        if !changed {
+            break (pcs, chunks);
+        }
+    };
+
+    for ((statement, chunk), (&pc, &offset_length)) in statements
+        .iter()
+        .zip(chunks)
+        .zip(pcs.iter().zip(&offset_lengths))
+    {
+        let bytes = if has_target(statement.format) {
+            let values = resolve(statement, &labels, &pcs).map_err(|message| AssembleError {
+                line: statement.line,
+                message,
+            })?;
+            encode_instruction(statement, &values, pc, offset_length).map_err(|message| {
+                AssembleError {
+                    line: statement.line,
+                    message,
+                }
+            })?
+        } else {
+            chunk
+        };
+        program.bitmask.push(1);
+        program
+            .bitmask
+            .resize(program.bitmask.len() + bytes.len() - 1, 0);
+        program.code.extend(bytes);
+    }
+
+    for (line, entry) in jump_table {
+        let target = match entry {
+            Arg::Label(name) => {
+                labels
+                    .get(&name)
+                    .map(|&index| pcs[index])
+                    .ok_or_else(|| AssembleError {
+                        line,
+                        message: format!("undefined label `{name}`"),
+                    })?
+            }
+            Arg::Value(pc) => pc as u32,
+            Arg::Register(_) => unreachable!("jump table entries are targets"),
+        };
+        program.jump_table.push(target);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::decode_program_from_preimage;
+    use crate::disassembler::disassemble_blob;
+
+    #[test]
+    fn assembles_labels_data_and_round_trips_through_the_disassembler() {
+        let source = "
+            .jump_table done, @0
+            .ro_data 01 02 0x0304
+            .stack_size 4096
+            .heap_pages 1
+            start: LOAD_IMM r0, 7           ; comment
+                   add_imm_64 r1, r0, -1
+                   STORE_IMM_U8 0x20010, 5
+                   BRANCH_EQ_IMM r1, 6, done
+                   JUMP start
+            done:  LOAD_IMM_64 r2, 0xffffffffffffffff
+                   ADD_64 r3, r1, r2
+                   ECALLI 356
+                   TRAP
+        ";
+        let program = assemble(source).unwrap();
+        let listing: Vec<String> = disassemble_blob(&program.to_blob())
+            .unwrap()
+            .instructions
+            .iter()
+            .map(|i| {
+                let operands: Vec<String> = i.operands.iter().map(ToString::to_string).collect();
+                format!("{} {}", i.mnemonic, operands.join(", "))
+                    .trim()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                "LOAD_IMM r0, 7",
+                "ADD_IMM_64 r1, r0, -1",
+                "STORE_IMM_U8 131088, 5",
+                "BRANCH_EQ_IMM r1, 6, @18",
+                "JUMP @0",
+                "LOAD_IMM_64 r2, 18446744073709551615",
+                "ADD_64 r3, r1, r2",
+                "ECALLI 356",
+                "TRAP",
+            ]
+        );
+        assert_eq!(program.jump_table, [18, 0]);
+
+        let decoded = decode_program_from_preimage(&program.to_preimage()).unwrap();
+        assert_eq!(decoded.ro_data, [1, 2, 3, 4]);
+        assert_eq!(
+            (decoded.stack_size, decoded.heap_zero_padding_size),
+            (4096, 1)
+        );
+        assert_eq!(decoded.code, program.to_blob());
+
+        let error = assemble("  JUMP nowhere").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: undefined label `nowhere`");
+    }
+}
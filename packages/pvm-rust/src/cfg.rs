@@ -0,0 +1,301 @@
+//! Control-flow graph: splits code into basic blocks (Gray Paper ϖ: PC 0 and every instruction after a
+//! terminating instruction), resolves static successors (branch, jump, fallthrough) and dynamic ones through the
+//! jump table, and exports the graph as DOT or JSON.
+//!
+//! `djump` (JUMP_IND, LOAD_IMM_JUMP_IND) gets an edge to every jump-table entry that is a block start, since
+//! the register operand is not known statically. Edges to targets that are not block starts are omitted (executing
+//! them panics; the verifier reports them).
+
+use std::fmt::Write;
+
+use crate::codec::decode_blob;
+use crate::config::{
+    is_termination_instruction, OPCODE_BRANCH_EQ, OPCODE_BRANCH_EQ_IMM, OPCODE_BRANCH_GE_S,
+    OPCODE_BRANCH_GT_S_IMM, OPCODE_FALLTHROUGH, OPCODE_JUMP, OPCODE_JUMP_IND, OPCODE_LOAD_IMM_JUMP,
+    OPCODE_LOAD_IMM_JUMP_IND,
+};
+use crate::disassembler::{disassemble_code, DisassembledInstruction, Operand};
+use crate::parser::PvmParser;
+use crate::types::DecodedInstruction;
+
+/// Basic-block starts of predecoded code: index `pc` is true when a block begins there.
+#[must_use]
+pub fn basic_block_starts(bitmask: &[u8], decoded: &[DecodedInstruction]) -> Vec<bool> {
+    let is_start = |pc: usize| bitmask.get(pc).copied().unwrap_or(0) == 1;
+    let mut starts = vec![false; decoded.len()];
+    if !starts.is_empty() {
+        starts[0] = is_start(0);
+    }
+    for (pc, instruction) in decoded.iter().enumerate() {
+        let next = instruction.next_pc as usize;
+        if is_start(pc) && is_termination_instruction(instruction.opcode) && next < starts.len() {
+            starts[next] = is_start(next);
+        }
+    }
+    starts
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// FALLTHROUGH, or a branch not taken.
+    Fallthrough,
+    /// Branch taken.
+    Branch,
+    /// JUMP or LOAD_IMM_JUMP.
+    Jump,
+    /// `djump` through a jump-table entry.
+    Dynamic,
+}
+
+impl EdgeKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fallthrough => "fallthrough",
+            Self::Branch => "branch",
+            Self::Jump => "jump",
+            Self::Dynamic => "dynamic",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub target: u32,
+    pub kind: EdgeKind,
+}
+
+/// Basic block `[start, end)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: u32,
+    pub end: u32,
+    pub instructions: Vec<DisassembledInstruction>,
+    pub successors: Vec<Edge>,
+}
+
+impl BasicBlock {
+    /// Instruction gas of one pass through the block (1 per instruction; host-call charges are not included).
+    #[must_use]
+    pub fn gas(&self) -> u64 {
+        self.instructions.len() as u64
+    }
+
+    /// Opcode of the last instruction when it is a terminating instruction (None when the code ends first).
+    #[must_use]
+    pub fn terminator(&self) -> Option<u8> {
+        self.instructions
+            .last()
+            .map(|instruction| instruction.opcode)
+            .filter(|&opcode| is_termination_instruction(opcode))
+    }
+}
+
+/// Blocks in PC order plus the jump table they were resolved with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+    pub jump_table: Vec<u32>,
+}
+
+impl ControlFlowGraph {
+    /// Block containing `pc`.
+    #[must_use]
+    pub fn block_at(&self, pc: u32) -> Option<&BasicBlock> {
+        let index = self.blocks.partition_point(|block| block.end <= pc);
+        self.blocks.get(index).filter(|block| block.start <= pc)
+    }
+
+    /// Graphviz rendering: one node per block listing its instructions, one edge per successor.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph cfg {\n  node [shape=box, fontname=monospace];\n");
+        for block in &self.blocks {
+            let mut label = format!("{}..{} (gas {})\\l", block.start, block.end, block.gas());
+            for instruction in &block.instructions {
+                let _ = write!(label, "{}: {}\\l", instruction.pc, instruction.text());
+            }
+            let _ = writeln!(out, "  b{} [label=\"{label}\"];", block.start);
+        }
+        for block in &self.blocks {
+            for edge in &block.successors {
+                let style = if edge.kind == EdgeKind::Dynamic {
+                    ", style=dashed"
+                } else {
+                    ""
+                };
+                let _ = writeln!(
+                    out,
+                    "  b{} -> b{} [label=\"{}\"{style}];",
+                    block.start,
+                    edge.target,
+                    edge.kind.as_str()
+                );
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// JSON rendering: `{"blocks":[{"start","end","instructions","gas","terminator","successors"}],"jumpTable"}`.
+    /// `terminator` is the mnemonic of the block's last instruction, or null when the code ends without one.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"blocks\":[");
+        for (i, block) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let terminator = match (block.terminator(), block.instructions.last()) {
+                (Some(_), Some(last)) => format!("\"{}\"", last.mnemonic),
+                _ => "null".to_string(),
+            };
+            let _ = write!(
+                out,
+                "{{\"start\":{},\"end\":{},\"instructions\":{},\"gas\":{},\"terminator\":{terminator},\"successors\":[",
+                block.start,
+                block.end,
+                block.instructions.len(),
+                block.gas()
+            );
+            for (j, edge) in block.successors.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                let _ = write!(
+                    out,
+                    "{{\"target\":{},\"kind\":\"{}\"}}",
+                    edge.target,
+                    edge.kind.as_str()
+                );
+            }
+            out.push_str("]}");
+        }
+        out.push_str("],\"jumpTable\":[");
+        for (i, target) in self.jump_table.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{target}");
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+fn static_target(instruction: &DisassembledInstruction) -> Option<u32> {
+    instruction
+        .operands
+        .iter()
+        .find_map(|operand| match operand {
+            Operand::Target(target) => Some(*target),
+            _ => None,
+        })
+}
+
+/// Build the CFG of raw code with its opcode bitmask and jump table.
+#[must_use]
+pub fn build_cfg(code: &[u8], bitmask: &[u8], jump_table: &[u32]) -> ControlFlowGraph {
+    let decoded = PvmParser::new().predecode(code, bitmask);
+    let starts = basic_block_starts(bitmask, &decoded);
+    let is_block_start = |target: u32| starts.get(target as usize).copied().unwrap_or(false);
+
+    let mut blocks: Vec<BasicBlock> = Vec::new();
+    let mut open = false;
+    for instruction in disassemble_code(code, bitmask, jump_table).instructions {
+        let pc = instruction.pc;
+        let next = (decoded[pc as usize].next_pc as usize).min(code.len()) as u32;
+        if !open {
+            blocks.push(BasicBlock {
+                start: pc,
+                end: next,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+            });
+        }
+        let block = blocks.last_mut().expect("block pushed above");
+        block.end = next;
+        open = !is_termination_instruction(instruction.opcode);
+        let mut successors = Vec::new();
+        match instruction.opcode {
+            OPCODE_FALLTHROUGH => successors.push((next, EdgeKind::Fallthrough)),
+            OPCODE_JUMP | OPCODE_LOAD_IMM_JUMP => {
+                successors.extend(static_target(&instruction).map(|t| (t, EdgeKind::Jump)));
+            }
+            OPCODE_BRANCH_EQ..=OPCODE_BRANCH_GE_S
+            | OPCODE_BRANCH_EQ_IMM..=OPCODE_BRANCH_GT_S_IMM => {
+                successors.extend(static_target(&instruction).map(|t| (t, EdgeKind::Branch)));
+                successors.push((next, EdgeKind::Fallthrough));
+            }
+            OPCODE_JUMP_IND | OPCODE_LOAD_IMM_JUMP_IND => {
+                for &target in jump_table {
+                    if !successors.iter().any(|&(t, _)| t == target) {
+                        successors.push((target, EdgeKind::Dynamic));
+                    }
+                }
+            }
+            _ => {}
+        }
+        block.successors = successors
+            .into_iter()
+            .filter(|&(target, _)| is_block_start(target))
+            .map(|(target, kind)| Edge { target, kind })
+            .collect();
+        block.instructions.push(instruction);
+    }
+
+    ControlFlowGraph {
+        blocks,
+        jump_table: jump_table.to_vec(),
+    }
+}
+
+/// Build the CFG of a deblob-format program blob; None when the blob does not decode.
+#[must_use]
+pub fn build_cfg_blob(program_blob: &[u8]) -> Option<ControlFlowGraph> {
+    let decoded = decode_blob(program_blob)?;
+    Some(build_cfg(
+        &decoded.code,
+        &decoded.bitmask,
+        &decoded.jump_table,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_blocks_and_resolves_successors() {
+        // 0: LOAD_IMM r0, 2; 3: FALLTHROUGH; 4: ADD_IMM_64 r0, r0, -1; 7: BRANCH_NE_IMM r0, 0, @4;
+        // 11: JUMP_IND r1, 0; 14: TRAP
+        let code = vec![51, 0, 2, 1, 149, 0, 0xff, 82, 0x10, 0, 0xfd, 50, 1, 0, 0];
+        let bitmask = vec![1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1];
+        let cfg = build_cfg(&code, &bitmask, &[14, 5]);
+        let blocks: Vec<_> = cfg
+            .blocks
+            .iter()
+            .map(|b| (b.start, b.end, b.gas(), b.successors.clone()))
+            .collect();
+        let edge = |target, kind| Edge { target, kind };
+        assert_eq!(
+            blocks,
+            [
+                (0, 4, 2, vec![edge(4, EdgeKind::Fallthrough)]),
+                (
+                    4,
+                    11,
+                    2,
+                    vec![edge(4, EdgeKind::Branch), edge(11, EdgeKind::Fallthrough)]
+                ),
+                (11, 14, 1, vec![edge(14, EdgeKind::Dynamic)]),
+                (14, 15, 1, vec![]),
+            ]
+        );
+        assert_eq!(cfg.block_at(9).map(|b| b.start), Some(4));
+        assert!(cfg.to_dot().contains("  b4 -> b4 [label=\"branch\"];"));
+        assert!(cfg.to_json().starts_with(
+            "{\"blocks\":[{\"start\":0,\"end\":4,\"instructions\":2,\"gas\":2,\"terminator\":\"FALLTHROUGH\",\"successors\":[{\"target\":4,\"kind\":\"fallthrough\"}]}"
+        ));
+    }
+}
@@ -281,7 +281,6 @@ pub fn encode_blob(decoded: &DecodedBlob) -> Vec<u8> {
             k[bit_index / 8] |= 1 << (bit_index % 8);
         }
     }
-    let k_len = encode_natural(k.len() as u64);
 
     concat_bytes(&[
         &len_j,
@@ -289,7 +288,6 @@ pub fn encode_blob(decoded: &DecodedBlob) -> Vec<u8> {
         &len_c,
         &jump_bytes,
         &decoded.code,
-        &k_len,
         &k,
     ])
 }
@@ -774,21 +772,12 @@ const VALIDATOR_KEY_SIZE: usize = 336;
 const AUTH_QUEUE_SIZE: usize = 80;
 const HASH_SIZE: usize = 32;
 
-/// Decode PartialState (Gray Paper partialstate).
+/// Decode ps_accounts: var{sequence{(serviceid (4 bytes), CompleteServiceAccount)}}.
+/// Also used standalone for the refine accounts dictionary (δ) passed to HISTORICAL_LOOKUP.
 #[must_use]
-pub fn decode_partial_state(
-    data: &[u8],
-    num_cores: i32,
-    num_validators: i32,
-    auth_queue_size: i32,
-) -> Option<DecodingResult<PartialState>> {
-    let num_cores = num_cores.max(0) as usize;
-    let num_validators = num_validators.max(0) as usize;
-    let auth_queue_size = auth_queue_size.max(0) as usize;
-
+pub fn decode_service_accounts(data: &[u8]) -> Option<DecodingResult<Vec<AccountEntry>>> {
     let accounts_var = decode_variable_length(data)?;
     let mut accounts_data = accounts_var.value.as_slice();
-    let mut offset = accounts_var.consumed as usize;
 
     let mut accounts = Vec::new();
     while accounts_data.len() >= 4 {
@@ -801,7 +790,28 @@ pub fn decode_partial_state(
             account: acc_result.value,
         });
     }
-    // offset already = accounts_var.consumed (natural + payload)
+    // consumed = natural length prefix + payload
+    Some(DecodingResult {
+        value: accounts,
+        consumed: accounts_var.consumed,
+    })
+}
+
+/// Decode PartialState (Gray Paper partialstate).
+#[must_use]
+pub fn decode_partial_state(
+    data: &[u8],
+    num_cores: i32,
+    num_validators: i32,
+    auth_queue_size: i32,
+) -> Option<DecodingResult<PartialState>> {
+    let num_cores = num_cores.max(0) as usize;
+    let num_validators = num_validators.max(0) as usize;
+    let auth_queue_size = auth_queue_size.max(0) as usize;
+
+    let accounts_result = decode_service_accounts(data)?;
+    let accounts = accounts_result.value;
+    let mut offset = accounts_result.consumed as usize;
 
     let rest = data.get(offset..)?;
     let stagingset_size = num_validators * VALIDATOR_KEY_SIZE;
@@ -1234,3 +1244,31 @@ pub fn get_preimage_value(account: &CompleteServiceAccount, service_id: u32, pre
     let key = create_preimage_key(service_id, preimage_hash);
     raw_get(&account.raw_csh_keyvals, &key)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_blob, encode_blob, DecodedBlob};
+
+    #[test]
+    fn encode_blob_round_trips_through_decode_blob() {
+        // 9 instructions so the bitmask spans two octets; 2-octet jump table entries.
+        let blob = DecodedBlob {
+            code: vec![51, 1, 7, 1, 1, 40, 3, 0, 0],
+            bitmask: vec![1, 0, 0, 1, 1, 1, 0, 1, 1],
+            jump_table: vec![3, 0x104],
+            element_size: 2,
+            header_size: 3,
+        };
+        let encoded = encode_blob(&blob);
+        // |j| = 2, z = 2, |c| = 9, j, c, then k with no length prefix.
+        assert_eq!(&encoded[..7], &[2, 2, 9, 3, 0, 4, 1]);
+        assert_eq!(&encoded[16..], &[0b1011_1001, 0b1]);
+
+        let decoded = decode_blob(&encoded).expect("decodes");
+        assert_eq!(decoded.code, blob.code);
+        assert_eq!(decoded.bitmask, blob.bitmask);
+        assert_eq!(decoded.jump_table, blob.jump_table);
+        assert_eq!((decoded.element_size, decoded.header_size), (2, 3));
+        assert_eq!(encode_blob(&decoded), encoded);
+    }
+}
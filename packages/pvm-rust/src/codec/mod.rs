@@ -23,6 +23,7 @@ pub use impl_::{
     decode_program_from_preimage,
     decode_provision_entry,
     decode_request_timeslots,
+    decode_service_accounts,
     decode_service_code_from_preimage,
     decode_variable_length,
     decode_variable_sequence,
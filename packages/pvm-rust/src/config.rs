@@ -4,7 +4,7 @@
 // ============================================================================
 // Gas Configuration
 // ============================================================================
-pub const DEFAULT_GAS_LIMIT: u32 = 0xFFFF_FFFF; // 2^32 - 1 (max u32)
+pub const DEFAULT_GAS_LIMIT: u64 = 0xFFFF_FFFF; // 2^32 - 1 (executor default; gas itself is 64-bit)
 pub const MIN_GAS_COST: u32 = 1;
 pub const MAX_GAS_COST: u32 = 1_000_000;
 
@@ -22,6 +22,8 @@ pub const DYNAMIC_ADDRESS_ALIGNMENT: u32 = 2;     // Gray Paper Cpvmdynaddralign
 // ============================================================================
 pub const ZONE_SIZE: u32 = 65_536;      // 64KB - Gray Paper Cpvminitzonesize
 pub const INIT_INPUT_SIZE: u32 = 16_777_216; // 16MB (2^24) - Gray Paper Cpvminitinputsize
+pub const REFINE_ENTRY_PC: u32 = 0;      // refine / is-authorized start at PC 0
+pub const ACCUMULATE_ENTRY_PC: u32 = 5;  // accumulate starts at PC 5
 
 // ============================================================================
 // Register Initialization Constants (Gray Paper equation 803-811)
@@ -281,38 +283,41 @@ impl Default for FetchSystemConstantsConfig {
 // ============================================================================
 // General Function Identifiers (Gray Paper Appendix B.7)
 // ============================================================================
-pub const FUNC_GAS: u8 = 0;
-pub const FUNC_FETCH: u8 = 1;
-pub const FUNC_LOOKUP: u8 = 2;
-pub const FUNC_READ: u8 = 3;
-pub const FUNC_WRITE: u8 = 4;
-pub const FUNC_INFO: u8 = 5;
-pub const FUNC_HISTORICAL_LOOKUP: u8 = 6;
-pub const FUNC_EXPORT: u8 = 7;
-pub const FUNC_MACHINE: u8 = 8;
-pub const FUNC_PEEK: u8 = 9;
-pub const FUNC_POKE: u8 = 10;
-pub const FUNC_PAGES: u8 = 11;
-pub const FUNC_INVOKE: u8 = 12;
-pub const FUNC_EXPUNGE: u8 = 13;
-pub const FUNC_LOG: u8 = 100;
+pub const FUNC_GAS: u32 = 0;
+pub const FUNC_FETCH: u32 = 1;
+pub const FUNC_LOOKUP: u32 = 2;
+pub const FUNC_READ: u32 = 3;
+pub const FUNC_WRITE: u32 = 4;
+pub const FUNC_INFO: u32 = 5;
+pub const FUNC_HISTORICAL_LOOKUP: u32 = 6;
+pub const FUNC_EXPORT: u32 = 7;
+pub const FUNC_MACHINE: u32 = 8;
+pub const FUNC_PEEK: u32 = 9;
+pub const FUNC_POKE: u32 = 10;
+pub const FUNC_PAGES: u32 = 11;
+pub const FUNC_INVOKE: u32 = 12;
+pub const FUNC_EXPUNGE: u32 = 13;
+pub const FUNC_LOG: u32 = 100;
+
+/// Base gas charged for every host call (g = 10), also the default for resumed external host calls.
+pub const HOST_CALL_BASE_GAS: u64 = 10;
 
 // ============================================================================
 // Accumulate Function Identifiers (Gray Paper Appendix B.7)
 // ============================================================================
-pub const FUNC_BLESS: u8 = 14;
-pub const FUNC_ASSIGN: u8 = 15;
-pub const FUNC_DESIGNATE: u8 = 16;
-pub const FUNC_CHECKPOINT: u8 = 17;
-pub const FUNC_NEW: u8 = 18;
-pub const FUNC_UPGRADE: u8 = 19;
-pub const FUNC_TRANSFER: u8 = 20;
-pub const FUNC_EJECT: u8 = 21;
-pub const FUNC_QUERY: u8 = 22;
-pub const FUNC_SOLICIT: u8 = 23;
-pub const FUNC_FORGET: u8 = 24;
-pub const FUNC_YIELD: u8 = 25;
-pub const FUNC_PROVIDE: u8 = 26;
+pub const FUNC_BLESS: u32 = 14;
+pub const FUNC_ASSIGN: u32 = 15;
+pub const FUNC_DESIGNATE: u32 = 16;
+pub const FUNC_CHECKPOINT: u32 = 17;
+pub const FUNC_NEW: u32 = 18;
+pub const FUNC_UPGRADE: u32 = 19;
+pub const FUNC_TRANSFER: u32 = 20;
+pub const FUNC_EJECT: u32 = 21;
+pub const FUNC_QUERY: u32 = 22;
+pub const FUNC_SOLICIT: u32 = 23;
+pub const FUNC_FORGET: u32 = 24;
+pub const FUNC_YIELD: u32 = 25;
+pub const FUNC_PROVIDE: u32 = 26;
 
 // ============================================================================
 // Accumulate Error Codes (Gray Paper section 31.2)
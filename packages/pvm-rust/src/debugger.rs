@@ -0,0 +1,400 @@
+//! Per-instance debugging: breakpoints on PC, opcode and host call ID, memory watchpoints, and
+//! `run_until_event_impl`, which steps the interpreter until one of them (or an exit) stops it.
+//!
+//! Watchpoints observe `Ram::read_octets` / `write_octets` from instructions and host functions through
+//! `WatchedRam`, so they also catch accesses made by host calls (e.g. FETCH writing into guest memory).
+
+use std::collections::BTreeSet;
+
+use crate::config::OPCODE_ECALLI;
+use crate::state_wrapper::{next_step_impl, PvmState, Status};
+use crate::types::{FaultCheckResult, MemoryAccessType, Ram, ReadResult, WriteResult};
+
+/// Which accesses a watchpoint reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read = 1,
+    Write = 2,
+    ReadWrite = 3,
+}
+
+impl WatchKind {
+    #[must_use]
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Read),
+            2 => Some(Self::Write),
+            3 => Some(Self::ReadWrite),
+            _ => None,
+        }
+    }
+
+    fn matches(self, is_write: bool) -> bool {
+        match self {
+            Self::Read => !is_write,
+            Self::Write => is_write,
+            Self::ReadWrite => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Watchpoint {
+    pub id: u32,
+    pub address: u32,
+    pub length: u32,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    fn overlaps(&self, address: u32, length: u32) -> bool {
+        let (start, end) = (u64::from(address), u64::from(address) + u64::from(length));
+        let watch_end = u64::from(self.address) + u64::from(self.length);
+        length > 0 && start < watch_end && u64::from(self.address) < end
+    }
+}
+
+/// First watched access seen since the last `run_until_event_impl` step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: u32,
+    pub address: u32,
+    pub length: u32,
+    pub is_write: bool,
+}
+
+/// Breakpoints and watchpoints for one instance. Kept across resets.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    pub pc_breakpoints: BTreeSet<u32>,
+    pub opcode_breakpoints: BTreeSet<u8>,
+    pub host_call_breakpoints: BTreeSet<u32>,
+    pub watchpoints: Vec<Watchpoint>,
+    next_watchpoint_id: u32,
+    hit: Option<WatchHit>,
+}
+
+impl Debugger {
+    /// Watch `[address, address + length)`; returns the ID for `remove_watchpoint`.
+    pub fn add_watchpoint(&mut self, address: u32, length: u32, kind: WatchKind) -> u32 {
+        self.next_watchpoint_id += 1;
+        let id = self.next_watchpoint_id;
+        self.watchpoints.push(Watchpoint {
+            id,
+            address,
+            length,
+            kind,
+        });
+        id
+    }
+
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        let before = self.watchpoints.len();
+        self.watchpoints.retain(|w| w.id != id);
+        self.watchpoints.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self {
+            next_watchpoint_id: self.next_watchpoint_id,
+            ..Self::default()
+        };
+    }
+
+    #[must_use]
+    pub fn has_watchpoints(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    fn observe(&mut self, address: u32, length: u32, is_write: bool) {
+        if self.hit.is_some() {
+            return;
+        }
+        if let Some(w) = self
+            .watchpoints
+            .iter()
+            .find(|w| w.kind.matches(is_write) && w.overlaps(address, length))
+        {
+            self.hit = Some(WatchHit {
+                watchpoint: w.id,
+                address,
+                length,
+                is_write,
+            });
+        }
+    }
+}
+
+/// RAM wrapper that reports reads and writes to the debugger's watchpoints and counts the bytes they move
+/// (host calls are charged by it, see `HostFunction::dynamic_gas`).
+pub struct WatchedRam<'a> {
+    pub ram: &'a mut dyn Ram,
+    pub debugger: &'a mut Debugger,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+impl<'a> WatchedRam<'a> {
+    pub fn new(ram: &'a mut dyn Ram, debugger: &'a mut Debugger) -> Self {
+        Self {
+            ram,
+            debugger,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+}
+
+impl Ram for WatchedRam<'_> {
+    fn read_octets(&mut self, address: u32, count: u32) -> ReadResult {
+        self.debugger.observe(address, count, false);
+        self.bytes_read += u64::from(count);
+        self.ram.read_octets(address, count)
+    }
+    fn write_octets(&mut self, address: u32, values: &[u8]) -> WriteResult {
+        self.debugger.observe(address, values.len() as u32, true);
+        self.bytes_written += values.len() as u64;
+        self.ram.write_octets(address, values)
+    }
+    fn current_heap_pointer(&self) -> u32 {
+        self.ram.current_heap_pointer()
+    }
+    fn set_current_heap_pointer(&mut self, value: u32) {
+        self.ram.set_current_heap_pointer(value);
+    }
+    fn allocate_pages(&mut self, start_page: u32, count: u32) {
+        self.ram.allocate_pages(start_page, count);
+    }
+    fn is_readable_with_fault(&self, address: u32, size: u32) -> FaultCheckResult {
+        self.ram.is_readable_with_fault(address, size)
+    }
+    fn initialize_memory_layout(
+        &mut self,
+        argument_data: &[u8],
+        read_only_data: &[u8],
+        read_write_data: &[u8],
+        stack_size: u32,
+        heap_zero_padding_size: u32,
+    ) {
+        self.ram.initialize_memory_layout(
+            argument_data,
+            read_only_data,
+            read_write_data,
+            stack_size,
+            heap_zero_padding_size,
+        );
+    }
+    fn is_writable_with_fault(&self, address: u32, size: u32) -> FaultCheckResult {
+        self.ram.is_writable_with_fault(address, size)
+    }
+    fn set_page_access_rights(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
+        self.ram
+            .set_page_access_rights(address, length, access_type);
+    }
+    fn init_page(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
+        self.ram.init_page(address, length, access_type);
+    }
+    fn write_octets_during_initialization(&mut self, address: u32, values: &[u8]) {
+        self.ram.write_octets_during_initialization(address, values);
+    }
+    fn get_page_dump(&self, page_index: u32) -> Vec<u8> {
+        self.ram.get_page_dump(page_index)
+    }
+    fn reset(&mut self) {
+        self.ram.reset();
+    }
+    fn last_load_address(&self) -> u32 {
+        self.ram.last_load_address()
+    }
+    fn last_load_value(&self) -> u64 {
+        self.ram.last_load_value()
+    }
+    fn last_store_address(&self) -> u32 {
+        self.ram.last_store_address()
+    }
+    fn last_store_value(&self) -> u64 {
+        self.ram.last_store_value()
+    }
+    fn clear_last_memory_op(&mut self) {
+        self.ram.clear_last_memory_op();
+    }
+    fn dirty_pages(&self) -> Vec<u32> {
+        self.ram.dirty_pages()
+    }
+    fn clear_dirty(&mut self) {
+        self.ram.clear_dirty();
+    }
+}
+
+/// Why `run_until_event_impl` stopped. Breakpoints stop before the instruction at `pc` runs; watchpoints
+/// stop after the instruction (or host call) that made the access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint {
+        pc: u32,
+    },
+    OpcodeBreakpoint {
+        pc: u32,
+        opcode: u8,
+    },
+    HostCallBreakpoint {
+        pc: u32,
+        host_call_id: u32,
+    },
+    Watchpoint {
+        pc: u32,
+        hit: WatchHit,
+    },
+    /// External host-call mode: ECALLI is waiting for `resume_host_call_impl`.
+    HostCall {
+        pc: u32,
+        host_call_id: u32,
+    },
+    Halt,
+    Panic,
+    Fault {
+        address: u32,
+    },
+    Oog,
+    /// `max_steps` instructions ran without any event.
+    StepLimit,
+}
+
+/// Host call ID of the ECALLI at `pc`, if that is what the next instruction is (same decoding as the handler).
+fn pending_host_call(state: &PvmState, pc: u32) -> Option<u32> {
+    let decoded = state.decoded.get(pc as usize)?;
+    if decoded.opcode != OPCODE_ECALLI {
+        return None;
+    }
+    Some(decoded.operands.immediate_x as u32)
+}
+
+fn breakpoint_at(state: &PvmState, pc: u32) -> Option<StopReason> {
+    let debugger = &state.debugger;
+    if debugger.pc_breakpoints.contains(&pc) {
+        return Some(StopReason::Breakpoint { pc });
+    }
+    let opcode = state.decoded.get(pc as usize)?.opcode;
+    if debugger.opcode_breakpoints.contains(&opcode) {
+        return Some(StopReason::OpcodeBreakpoint { pc, opcode });
+    }
+    let host_call_id = pending_host_call(state, pc)?;
+    debugger
+        .host_call_breakpoints
+        .contains(&host_call_id)
+        .then_some(StopReason::HostCallBreakpoint { pc, host_call_id })
+}
+
+fn exit_reason(state: &PvmState) -> StopReason {
+    match state.status {
+        Status::Halt | Status::Ok => StopReason::Halt,
+        Status::Panic => StopReason::Panic,
+        Status::Fault => StopReason::Fault {
+            address: state.exit_arg,
+        },
+        Status::Host => StopReason::HostCall {
+            pc: state.program_counter,
+            host_call_id: state.host_call_id,
+        },
+        Status::Oog => StopReason::Oog,
+    }
+}
+
+/// Step until a breakpoint, watchpoint, host call (external mode) or exit, or until `max_steps` instructions ran.
+/// A breakpoint at the starting PC does not stop, so calling this again continues past it. Returns the reason and
+/// the number of instructions executed.
+pub fn run_until_event_impl(state: &mut PvmState, max_steps: Option<u64>) -> (StopReason, u64) {
+    state.ensure_decoded();
+    let mut steps = 0u64;
+    loop {
+        if steps > 0 {
+            if let Some(reason) = breakpoint_at(state, state.program_counter) {
+                return (reason, steps);
+            }
+        }
+        if max_steps.is_some_and(|max| steps >= max) {
+            return (StopReason::StepLimit, steps);
+        }
+        let pc = state.program_counter;
+        state.debugger.hit = None;
+        let running = next_step_impl(state);
+        steps += 1;
+        if !running {
+            return (exit_reason(state), steps);
+        }
+        if let Some(hit) = state.debugger.hit.take() {
+            return (StopReason::Watchpoint { pc, hit }, steps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_wrapper::RAMType;
+
+    #[test]
+    fn stops_on_breakpoints_watchpoints_and_exit() {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state
+            .ram
+            .init_page(0x2_0000, 0x1000, MemoryAccessType::Write);
+        // LOAD_IMM r0, 7; STORE_IMM_U8 0x20010, 5; FALLTHROUGH; TRAP
+        let code = vec![51, 0, 7, 30, 3, 0x10, 0x00, 0x02, 5, 1, 0];
+        let bitmask = vec![1, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1];
+        state.load_code(code, bitmask, vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        state.debugger.pc_breakpoints.insert(3);
+        state.debugger.opcode_breakpoints.insert(0);
+        state.debugger.add_watchpoint(0x2_0010, 1, WatchKind::Write);
+
+        assert_eq!(
+            run_until_event_impl(&mut state, None),
+            (StopReason::Breakpoint { pc: 3 }, 1)
+        );
+        let (reason, _) = run_until_event_impl(&mut state, None);
+        assert_eq!(
+            reason,
+            StopReason::Watchpoint {
+                pc: 3,
+                hit: WatchHit {
+                    watchpoint: 1,
+                    address: 0x2_0010,
+                    length: 1,
+                    is_write: true
+                }
+            }
+        );
+        assert_eq!(
+            run_until_event_impl(&mut state, Some(0)),
+            (StopReason::StepLimit, 0)
+        );
+        assert_eq!(
+            run_until_event_impl(&mut state, None),
+            (StopReason::OpcodeBreakpoint { pc: 10, opcode: 0 }, 1)
+        );
+        assert_eq!(
+            run_until_event_impl(&mut state, None),
+            (StopReason::Panic, 1)
+        );
+    }
+
+    #[test]
+    fn run_with_recompiler_enabled_still_observes_watchpoints() {
+        let mut state = PvmState::new(RAMType::MmapRAM as i32);
+        state
+            .ram
+            .init_page(0x2_0000, 0x1000, MemoryAccessType::Write);
+        // STORE_IMM_U8 0x20010, 5; TRAP
+        state.load_code(vec![30, 3, 0x10, 0x00, 0x02, 5, 0], vec![1, 0, 0, 0, 0, 0, 1], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        state.use_recompiler = true;
+        state.debugger.add_watchpoint(0x2_0010, 1, WatchKind::Write);
+
+        crate::state_wrapper::run_impl(&mut state);
+        assert_eq!(state.status, Status::Panic);
+        assert!(state.debugger.hit.is_some());
+    }
+}
@@ -0,0 +1,308 @@
+//! Disassembler for program blobs: decodes each instruction's operands per Gray Paper §A.5 instruction format
+//! and renders a listing with PC, raw bytes, mnemonic (`InstructionHandler::name`) and operands.
+//!
+//! Operand syntax: registers are `r0`…`r12`, immediates are decimal (sign-extended as the instruction reads
+//! them), and branch/jump targets are absolute PCs written `@<pc>`.
+
+use std::fmt;
+
+use crate::codec::decode_blob;
+use crate::config::{
+    OPCODE_ADD_32, OPCODE_BRANCH_EQ, OPCODE_BRANCH_GE_S, OPCODE_BRANCH_GT_S_IMM, OPCODE_ECALLI,
+    OPCODE_FALLTHROUGH, OPCODE_JUMP, OPCODE_JUMP_IND, OPCODE_LOAD_IMM_64, OPCODE_LOAD_IMM_JUMP,
+    OPCODE_LOAD_IMM_JUMP_IND, OPCODE_LOAD_U8, OPCODE_MIN_U, OPCODE_MOVE_REG, OPCODE_REVERSE_BYTES,
+    OPCODE_ROT_R_32_IMM_ALT, OPCODE_STORE_IMM_IND_U64, OPCODE_STORE_IMM_IND_U8,
+    OPCODE_STORE_IMM_U64, OPCODE_STORE_IMM_U8, OPCODE_STORE_IND_U8, OPCODE_STORE_U64, OPCODE_TRAP,
+};
+use crate::instructions::base::{
+    get_immediate_value_unsigned, get_register_index, parse_branch_operands, parse_one_immediate,
+    parse_one_offset,
+    parse_one_register_and_immediate, parse_one_register_and_immediate_unsigned,
+    parse_register_and_two_immediates, parse_register_branch_operands, parse_three_registers,
+    parse_two_immediates, parse_two_registers, parse_two_registers_and_immediate,
+    parse_two_registers_and_two_immediates,
+};
+use crate::parser::PvmParser;
+use crate::state_wrapper::instruction_registry;
+
+/// Instruction formats (Gray Paper §A.5.1–A.5.13).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstructionFormat {
+    NoArguments,
+    OneImmediate,
+    OneRegisterOneExtendedImmediate,
+    TwoImmediates,
+    OneOffset,
+    OneRegisterOneImmediate,
+    OneRegisterTwoImmediates,
+    OneRegisterOneImmediateOneOffset,
+    TwoRegisters,
+    TwoRegistersOneImmediate,
+    TwoRegistersOneOffset,
+    TwoRegistersTwoImmediates,
+    ThreeRegisters,
+}
+
+/// Format of `opcode`, or None for opcodes the PVM does not define.
+#[must_use]
+pub fn instruction_format(opcode: u8) -> Option<InstructionFormat> {
+    use InstructionFormat::*;
+    Some(match opcode {
+        OPCODE_TRAP | OPCODE_FALLTHROUGH => NoArguments,
+        OPCODE_ECALLI => OneImmediate,
+        OPCODE_LOAD_IMM_64 => OneRegisterOneExtendedImmediate,
+        OPCODE_STORE_IMM_U8..=OPCODE_STORE_IMM_U64 => TwoImmediates,
+        OPCODE_JUMP => OneOffset,
+        OPCODE_JUMP_IND..=OPCODE_STORE_U64 => OneRegisterOneImmediate,
+        OPCODE_STORE_IMM_IND_U8..=OPCODE_STORE_IMM_IND_U64 => OneRegisterTwoImmediates,
+        OPCODE_LOAD_IMM_JUMP..=OPCODE_BRANCH_GT_S_IMM => OneRegisterOneImmediateOneOffset,
+        OPCODE_MOVE_REG..=OPCODE_REVERSE_BYTES => TwoRegisters,
+        OPCODE_STORE_IND_U8..=OPCODE_ROT_R_32_IMM_ALT => TwoRegistersOneImmediate,
+        OPCODE_BRANCH_EQ..=OPCODE_BRANCH_GE_S => TwoRegistersOneOffset,
+        OPCODE_LOAD_IMM_JUMP_IND => TwoRegistersTwoImmediates,
+        OPCODE_ADD_32..=OPCODE_MIN_U => ThreeRegisters,
+        _ => return None,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(u8),
+    Immediate(i64),
+    /// LOAD_IMM_64's full 64-bit immediate and the unsigned addresses of LOAD_*/STORE_*.
+    Unsigned(u64),
+    /// Absolute branch or jump target.
+    Target(u32),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Register(index) => write!(f, "r{index}"),
+            Self::Immediate(value) => write!(f, "{value}"),
+            Self::Unsigned(value) => write!(f, "{value}"),
+            Self::Target(pc) => write!(f, "@{pc}"),
+        }
+    }
+}
+
+/// Decode `operands` (Fskip octets, zero-padded past the end of code) of an instruction at `pc`.
+#[must_use]
+pub fn decode_operands(opcode: u8, operands: &[u8], pc: u32) -> Vec<Operand> {
+    use Operand::{Immediate, Register, Target, Unsigned};
+    let Some(format) = instruction_format(opcode) else {
+        return Vec::new();
+    };
+    let fskip = operands.len() as i32;
+    // Formats with a register byte read operands[0] unconditionally.
+    let padded;
+    let operands = if operands.is_empty() {
+        padded = [0u8; 1];
+        &padded[..]
+    } else {
+        operands
+    };
+    match format {
+        InstructionFormat::NoArguments => vec![],
+        // ECALLI: the host call ID is immed_X truncated to 32 bits, as in the handler.
+        InstructionFormat::OneImmediate => {
+            vec![Unsigned(u64::from(parse_one_immediate(operands, fskip) as u32))]
+        }
+        InstructionFormat::OneRegisterOneExtendedImmediate => vec![
+            Register(get_register_index(operands[0])),
+            Unsigned(get_immediate_value_unsigned(operands, 1, 8)),
+        ],
+        InstructionFormat::TwoImmediates => {
+            let (x, y) = parse_two_immediates(operands, fskip);
+            vec![Unsigned(x as u32 as u64), Immediate(y)]
+        }
+        InstructionFormat::OneOffset => vec![Target(parse_one_offset(operands, fskip, pc))],
+        InstructionFormat::OneRegisterOneImmediate if opcode >= OPCODE_LOAD_U8 => {
+            let (register_a, address) = parse_one_register_and_immediate_unsigned(operands, fskip);
+            vec![Register(register_a), Unsigned(address)]
+        }
+        InstructionFormat::OneRegisterOneImmediate => {
+            let parsed = parse_one_register_and_immediate(operands, fskip);
+            vec![Register(parsed.register_a), Immediate(parsed.immediate_x)]
+        }
+        InstructionFormat::OneRegisterTwoImmediates => {
+            let parsed = parse_register_and_two_immediates(operands, fskip);
+            vec![
+                Register(parsed.register_a),
+                Immediate(parsed.immediate_x),
+                Immediate(parsed.immediate_y),
+            ]
+        }
+        InstructionFormat::OneRegisterOneImmediateOneOffset if opcode == OPCODE_LOAD_IMM_JUMP => {
+            let parsed = parse_register_and_two_immediates(operands, fskip);
+            vec![
+                Register(parsed.register_a),
+                Immediate(parsed.immediate_x),
+                Target((i64::from(pc) + parsed.immediate_y) as u32),
+            ]
+        }
+        InstructionFormat::OneRegisterOneImmediateOneOffset => {
+            let parsed = parse_branch_operands(operands, pc);
+            vec![
+                Register(parsed.register_a),
+                Immediate(parsed.immediate_x),
+                Target(parsed.target_address),
+            ]
+        }
+        InstructionFormat::TwoRegisters => {
+            let parsed = parse_two_registers(operands);
+            vec![Register(parsed.register_d), Register(parsed.register_a)]
+        }
+        InstructionFormat::TwoRegistersOneImmediate => {
+            let parsed = parse_two_registers_and_immediate(operands, fskip);
+            vec![
+                Register(parsed.register_a),
+                Register(parsed.register_b),
+                Immediate(parsed.immediate_x),
+            ]
+        }
+        InstructionFormat::TwoRegistersOneOffset => {
+            let parsed = parse_register_branch_operands(operands, pc);
+            vec![
+                Register(parsed.register_a),
+                Register(parsed.register_b),
+                Target(parsed.target_address),
+            ]
+        }
+        InstructionFormat::TwoRegistersTwoImmediates => {
+            let parsed = parse_two_registers_and_two_immediates(operands, fskip);
+            vec![
+                Register(parsed.register_a),
+                Register(parsed.register_b),
+                Immediate(parsed.immediate_x),
+                Immediate(parsed.immediate_y),
+            ]
+        }
+        InstructionFormat::ThreeRegisters => {
+            let parsed = parse_three_registers(operands);
+            vec![
+                Register(parsed.register_d),
+                Register(parsed.register_a),
+                Register(parsed.register_b),
+            ]
+        }
+    }
+}
+
+/// One decoded instruction of a listing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembledInstruction {
+    pub pc: u32,
+    /// Opcode and operand octets as they appear in the code.
+    pub bytes: Vec<u8>,
+    pub opcode: u8,
+    /// Handler name, or `UNKNOWN_<opcode>` for undefined opcodes.
+    pub mnemonic: String,
+    pub operands: Vec<Operand>,
+}
+
+impl DisassembledInstruction {
+    /// Mnemonic and operands, e.g. `ADD_IMM_64 r1, r0, -1`.
+    #[must_use]
+    pub fn text(&self) -> String {
+        let operands: Vec<String> = self.operands.iter().map(ToString::to_string).collect();
+        if operands.is_empty() {
+            self.mnemonic.clone()
+        } else {
+            format!("{} {}", self.mnemonic, operands.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{b:02x}")).collect();
+        write!(f, "{:>6}: {:<30} {}", self.pc, bytes.join(" "), self.text())
+    }
+}
+
+/// Decoded program: instructions in PC order plus the jump table (`djump` index i + 1 targets entry i).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Disassembly {
+    pub instructions: Vec<DisassembledInstruction>,
+    pub jump_table: Vec<u32>,
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.jump_table.is_empty() {
+            writeln!(f, "; jump table")?;
+            for (i, target) in self.jump_table.iter().enumerate() {
+                writeln!(f, ";   [{i}] @{target}")?;
+            }
+        }
+        for instruction in &self.instructions {
+            writeln!(f, "{instruction}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassemble raw code with its opcode bitmask. Only positions marked in the bitmask start an instruction.
+#[must_use]
+pub fn disassemble_code(code: &[u8], bitmask: &[u8], jump_table: &[u32]) -> Disassembly {
+    let decoded = PvmParser::new().predecode(code, bitmask);
+    let registry = instruction_registry();
+    let mut instructions = Vec::new();
+    for (pc, instruction) in decoded.iter().enumerate() {
+        if bitmask.get(pc).copied().unwrap_or(1) == 0 {
+            continue;
+        }
+        let end = (instruction.next_pc as usize).min(code.len());
+        let mut operands = code[pc + 1..end].to_vec();
+        operands.resize(usize::from(instruction.fskip), 0);
+        instructions.push(DisassembledInstruction {
+            pc: pc as u32,
+            bytes: code[pc..end].to_vec(),
+            opcode: instruction.opcode,
+            mnemonic: registry.handler(instruction.opcode).map_or_else(
+                || format!("UNKNOWN_{}", instruction.opcode),
+                |handler| handler.name().to_string(),
+            ),
+            operands: decode_operands(instruction.opcode, &operands, pc as u32),
+        });
+    }
+    Disassembly {
+        instructions,
+        jump_table: jump_table.to_vec(),
+    }
+}
+
+/// Disassemble a deblob-format program blob; None when the blob does not decode.
+#[must_use]
+pub fn disassemble_blob(program_blob: &[u8]) -> Option<Disassembly> {
+    let decoded = decode_blob(program_blob)?;
+    Some(disassemble_code(
+        &decoded.code,
+        &decoded.bitmask,
+        &decoded.jump_table,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_operands_per_format() {
+        // LOAD_IMM r0, 7; ADD_IMM_64 r1, r0, -1; BRANCH_EQ_IMM r1, 6, @0; JUMP @3; TRAP
+        let code = vec![51, 0, 7, 149, 0x01, 0xff, 81, 0x11, 6, 0xfa, 40, 0xf9, 0];
+        let bitmask = vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 0, 1, 0, 1];
+        let listing = disassemble_code(&code, &bitmask, &[10]).to_string();
+        let lines: Vec<&str> = listing.lines().map(str::trim_end).collect();
+        assert_eq!(lines[0], "; jump table");
+        assert_eq!(lines[1], ";   [0] @10");
+        assert!(lines[2].starts_with("     0: 33 00 07"), "{listing}");
+        assert!(lines[2].ends_with(" LOAD_IMM r0, 7"), "{listing}");
+        assert!(lines[3].ends_with(" ADD_IMM_64 r1, r0, -1"), "{listing}");
+        assert!(lines[4].ends_with(" BRANCH_EQ_IMM r1, 6, @0"), "{listing}");
+        assert!(lines[5].ends_with(" JUMP @3"), "{listing}");
+        assert!(lines[6].ends_with(" TRAP"), "{listing}");
+        assert_eq!(lines.len(), 7);
+    }
+}
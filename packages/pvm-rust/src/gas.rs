@@ -0,0 +1,368 @@
+//! Static gas estimation: a cost per basic block under the current model (1 gas per instruction) and under a
+//! per-opcode cost table, and a worst-case bound over loop-free paths from an entry point (refine/is-authorized
+//! at PC 0, accumulate at PC 5).
+//!
+//! Each ECALLI on a path adds `HOST_CALL_BASE_GAS`; what host functions charge on top of that depends on their
+//! inputs and is not included. Loops are cut at their back edge, so a loop body counts once (`has_loops` says
+//! whether that happened); `djump` follows every jump-table entry.
+//!
+//! `GasCharging` selects how the interpreter charges: per instruction (the default, matching reference traces)
+//! or per basic block, where the whole block's cost is taken before its first instruction runs.
+
+use std::collections::HashSet;
+
+use crate::cfg::{basic_block_starts, BasicBlock, ControlFlowGraph};
+use crate::config::{
+    is_termination_instruction, ACCUMULATE_ENTRY_PC, HOST_CALL_BASE_GAS, OPCODE_DIV_U_32,
+    OPCODE_DIV_U_64, OPCODE_ECALLI, OPCODE_JUMP_IND, OPCODE_LOAD_IMM_JUMP_IND, OPCODE_LOAD_IND_U64,
+    OPCODE_LOAD_U8, OPCODE_MUL_32, OPCODE_MUL_64, OPCODE_MUL_IMM_32, OPCODE_MUL_IMM_64,
+    OPCODE_MUL_UPPER_S_S, OPCODE_MUL_UPPER_S_U, OPCODE_REM_S_32, OPCODE_REM_S_64,
+    OPCODE_STORE_IMM_IND_U64, OPCODE_STORE_IMM_IND_U8, OPCODE_STORE_IMM_U64, OPCODE_STORE_IMM_U8,
+    OPCODE_STORE_IND_U8, OPCODE_STORE_U64, REFINE_ENTRY_PC,
+};
+use crate::types::DecodedInstruction;
+
+/// Per-instruction gas cost model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GasCostModel {
+    /// 1 gas per instruction (what `execute_instruction` charges).
+    Unit,
+    /// Cost per opcode.
+    Table(Box<[u64; 256]>),
+}
+
+impl GasCostModel {
+    /// Approximate relative weights: 1 for register and control-flow instructions, 3 for multiplication, 4 for
+    /// memory accesses and 32 for division/remainder. Not the values of any specification draft; pass a table
+    /// with `from_costs` to match one.
+    #[must_use]
+    pub fn weighted() -> Self {
+        let mut costs = [1u64; 256];
+        let memory = [
+            OPCODE_STORE_IMM_U8..=OPCODE_STORE_IMM_U64,
+            OPCODE_LOAD_U8..=OPCODE_STORE_U64,
+            OPCODE_STORE_IMM_IND_U8..=OPCODE_STORE_IMM_IND_U64,
+            OPCODE_STORE_IND_U8..=OPCODE_LOAD_IND_U64,
+        ];
+        for opcode in memory.into_iter().flatten() {
+            costs[usize::from(opcode)] = 4;
+        }
+        let multiply = [
+            OPCODE_MUL_IMM_32,
+            OPCODE_MUL_IMM_64,
+            OPCODE_MUL_32,
+            OPCODE_MUL_64,
+        ];
+        for opcode in multiply
+            .into_iter()
+            .chain(OPCODE_MUL_UPPER_S_S..=OPCODE_MUL_UPPER_S_U)
+        {
+            costs[usize::from(opcode)] = 3;
+        }
+        for opcode in (OPCODE_DIV_U_32..=OPCODE_REM_S_32).chain(OPCODE_DIV_U_64..=OPCODE_REM_S_64) {
+            costs[usize::from(opcode)] = 32;
+        }
+        Self::Table(Box::new(costs))
+    }
+
+    /// Table model from `costs[opcode]`; opcodes past the end of `costs` cost 1.
+    #[must_use]
+    pub fn from_costs(costs: &[u64]) -> Self {
+        let mut table = [1u64; 256];
+        for (entry, &cost) in table.iter_mut().zip(costs) {
+            *entry = cost;
+        }
+        Self::Table(Box::new(table))
+    }
+
+    #[must_use]
+    pub fn instruction_cost(&self, opcode: u8) -> u64 {
+        match self {
+            Self::Unit => 1,
+            Self::Table(costs) => costs[usize::from(opcode)],
+        }
+    }
+
+    /// Cost of the block's instructions (host-call base gas not included).
+    #[must_use]
+    pub fn block_cost(&self, block: &BasicBlock) -> u64 {
+        block
+            .instructions
+            .iter()
+            .map(|instruction| self.instruction_cost(instruction.opcode))
+            .sum()
+    }
+}
+
+/// How the interpreter charges gas.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum GasCharging {
+    /// 1 gas before every instruction; OOG when none is left.
+    #[default]
+    Instruction,
+    /// The block's cost under the model before its first instruction; OOG when less is left, before any of the
+    /// block runs. Instructions inside a block (including after a host call) are not charged again.
+    Block(GasCostModel),
+}
+
+/// Cost charged on entering the basic block that starts at each PC (0 at every other PC).
+#[must_use]
+pub fn block_entry_costs(
+    bitmask: &[u8],
+    decoded: &[DecodedInstruction],
+    model: &GasCostModel,
+) -> Vec<u64> {
+    let starts = basic_block_starts(bitmask, decoded);
+    let mut costs = vec![0u64; decoded.len()];
+    for (start, cost) in costs.iter_mut().enumerate() {
+        if !starts[start] {
+            continue;
+        }
+        let mut pc = start;
+        while let Some(instruction) = decoded.get(pc) {
+            *cost += model.instruction_cost(instruction.opcode);
+            if is_termination_instruction(instruction.opcode) {
+                break;
+            }
+            pc = (instruction.next_pc as usize).max(pc + 1);
+        }
+    }
+    costs
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockGas {
+    pub start: u32,
+    pub end: u32,
+    pub instructions: u64,
+    pub host_calls: u64,
+    /// Under `GasCostModel::Unit`.
+    pub unit_gas: u64,
+    /// Under the model passed to `estimate_gas`.
+    pub model_gas: u64,
+}
+
+/// Most expensive loop-free path from an entry under one model. `gas` includes `HOST_CALL_BASE_GAS` per host call.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathBound {
+    pub gas: u64,
+    pub host_calls: u64,
+    /// Block starts along the path.
+    pub path: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntryEstimate {
+    pub entry: u32,
+    pub unit: PathBound,
+    pub model: PathBound,
+    /// A back edge was cut: the bound covers one iteration of each loop.
+    pub has_loops: bool,
+    /// A reachable block ends in `djump`; its successors are all jump-table entries.
+    pub dynamic_jumps: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasEstimate {
+    pub blocks: Vec<BlockGas>,
+    /// One per requested entry that is a basic-block start.
+    pub entries: Vec<EntryEstimate>,
+}
+
+/// Longest path from `entry` through the DAG left after removing `back_edges`, with `order` in DFS postorder.
+fn longest_path(
+    successors: &[Vec<usize>],
+    order: &[usize],
+    back_edges: &HashSet<(usize, usize)>,
+    cost: impl Fn(usize) -> u64,
+    entry: usize,
+) -> Vec<usize> {
+    let mut best = vec![0u64; successors.len()];
+    let mut next: Vec<Option<usize>> = vec![None; successors.len()];
+    for &v in order {
+        let tail = successors[v]
+            .iter()
+            .filter(|&&s| !back_edges.contains(&(v, s)))
+            .max_by_key(|&&s| best[s]);
+        best[v] = cost(v) + tail.map_or(0, |&s| best[s]);
+        next[v] = tail.copied();
+    }
+    let mut path = vec![entry];
+    while let Some(v) = next[*path.last().expect("path starts with entry")] {
+        path.push(v);
+    }
+    path
+}
+
+/// Cost every block of `cfg` and bound each entry in `entries`.
+#[must_use]
+pub fn estimate_gas(cfg: &ControlFlowGraph, model: &GasCostModel, entries: &[u32]) -> GasEstimate {
+    let blocks: Vec<BlockGas> = cfg
+        .blocks
+        .iter()
+        .map(|block| BlockGas {
+            start: block.start,
+            end: block.end,
+            instructions: block.instructions.len() as u64,
+            host_calls: block
+                .instructions
+                .iter()
+                .filter(|instruction| instruction.opcode == OPCODE_ECALLI)
+                .count() as u64,
+            unit_gas: GasCostModel::Unit.block_cost(block),
+            model_gas: model.block_cost(block),
+        })
+        .collect();
+    let index_of = |pc: u32| {
+        cfg.blocks
+            .binary_search_by_key(&pc, |block| block.start)
+            .ok()
+    };
+    let successors: Vec<Vec<usize>> = cfg
+        .blocks
+        .iter()
+        .map(|block| {
+            block
+                .successors
+                .iter()
+                .filter_map(|edge| index_of(edge.target))
+                .collect()
+        })
+        .collect();
+
+    let mut estimates = Vec::new();
+    for &entry_pc in entries {
+        let Some(entry) = index_of(entry_pc) else {
+            continue;
+        };
+        // Iterative DFS: postorder plus the back edges that close loops.
+        let mut state = vec![0u8; blocks.len()];
+        let mut order = Vec::new();
+        let mut back_edges = HashSet::new();
+        let mut stack = vec![(entry, 0usize)];
+        state[entry] = 1;
+        while let Some((v, i)) = stack.pop() {
+            if let Some(&s) = successors[v].get(i) {
+                stack.push((v, i + 1));
+                match state[s] {
+                    0 => {
+                        state[s] = 1;
+                        stack.push((s, 0));
+                    }
+                    1 => {
+                        back_edges.insert((v, s));
+                    }
+                    _ => {}
+                }
+            } else {
+                state[v] = 2;
+                order.push(v);
+            }
+        }
+
+        let host_gas = |v: usize| blocks[v].host_calls * HOST_CALL_BASE_GAS;
+        let bound = |path: Vec<usize>, gas: &dyn Fn(usize) -> u64| PathBound {
+            gas: path.iter().map(|&v| gas(v) + host_gas(v)).sum(),
+            host_calls: path.iter().map(|&v| blocks[v].host_calls).sum(),
+            path: path.iter().map(|&v| blocks[v].start).collect(),
+        };
+        let unit_gas = |v: usize| blocks[v].unit_gas;
+        let model_gas = |v: usize| blocks[v].model_gas;
+        let unit_path = longest_path(
+            &successors,
+            &order,
+            &back_edges,
+            |v| unit_gas(v) + host_gas(v),
+            entry,
+        );
+        let model_path = longest_path(
+            &successors,
+            &order,
+            &back_edges,
+            |v| model_gas(v) + host_gas(v),
+            entry,
+        );
+        estimates.push(EntryEstimate {
+            entry: entry_pc,
+            unit: bound(unit_path, &unit_gas),
+            model: bound(model_path, &model_gas),
+            has_loops: !back_edges.is_empty(),
+            dynamic_jumps: order.iter().any(|&v| {
+                matches!(
+                    cfg.blocks[v].terminator(),
+                    Some(OPCODE_JUMP_IND | OPCODE_LOAD_IMM_JUMP_IND)
+                )
+            }),
+        });
+    }
+    GasEstimate {
+        blocks,
+        entries: estimates,
+    }
+}
+
+/// `estimate_gas` from the refine/is-authorized (PC 0) and accumulate (PC 5) entry points.
+#[must_use]
+pub fn estimate_service_gas(cfg: &ControlFlowGraph, model: &GasCostModel) -> GasEstimate {
+    estimate_gas(cfg, model, &[REFINE_ENTRY_PC, ACCUMULATE_ENTRY_PC])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::build_cfg;
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+
+    #[test]
+    fn bounds_loop_free_paths_from_entries() {
+        // 0: JUMP @11; 2: FALLTHROUGH; 3: TRAP; 4: TRAP; 5: LOAD_U64 r0, 0x20000; 10: FALLTHROUGH;
+        // 11: ECALLI 1; 13: BRANCH_EQ_IMM r0, 0, @11 (loop); 17: DIV_U_64 r0, r0, r0; 20: TRAP
+        let code = vec![
+            40, 11, 1, 0, 0, 58, 0, 0, 0, 2, 1, 10, 1, 81, 0x10, 0, 0xfe, 203, 0, 0, 0,
+        ];
+        let bitmask = vec![
+            1, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0, 1,
+        ];
+        let cfg = build_cfg(&code, &bitmask, &[]);
+        let estimate = estimate_service_gas(&cfg, &GasCostModel::weighted());
+
+        let block = estimate.blocks.iter().find(|b| b.start == 5).unwrap();
+        assert_eq!(
+            (block.unit_gas, block.model_gas, block.host_calls),
+            (2, 5, 0)
+        );
+        let [refine, accumulate] = &estimate.entries[..] else {
+            panic!("expected both entries: {estimate:?}");
+        };
+        assert_eq!(refine.entry, 0);
+        assert_eq!(refine.unit.path, [0, 11, 17]);
+        assert_eq!((refine.unit.gas, refine.unit.host_calls), (1 + 12 + 2, 1));
+        assert!(refine.has_loops && !refine.dynamic_jumps);
+        assert_eq!(accumulate.model.path, [5, 11, 17]);
+        assert_eq!(accumulate.model.gas, 5 + 12 + 33);
+    }
+
+    #[test]
+    fn block_charging_takes_the_block_cost_on_entry() {
+        // 0: LOAD_IMM r0, 1; 3: FALLTHROUGH; 4: ADD_IMM_64 r0, r0, 1; 7: TRAP
+        let run = |charging: GasCharging| {
+            let mut state = PvmState::new(RAMType::PvmRam as i32);
+            state.gas_charging = charging;
+            state.load_code(
+                vec![51, 0, 1, 1, 149, 0, 1, 0],
+                vec![1, 0, 0, 1, 1, 0, 0, 1],
+                vec![],
+            );
+            state.status = Status::Ok;
+            state.gas_left = 3;
+            while next_step_impl(&mut state) {}
+            (state.status, state.program_counter, state.gas_left, state.registers[0])
+        };
+        assert_eq!(run(GasCharging::Instruction), (Status::Oog, 7, 0, 2));
+        // Block [4, 8) costs 2 and only 1 is left after [0, 4): nothing of it runs.
+        assert_eq!(
+            run(GasCharging::Block(GasCostModel::Unit)),
+            (Status::Oog, 4, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Nested PVM machines for refine (Gray Paper m: machine ID → (p, u, i); mirrors AS PVMGuest).
+//! Each guest owns its own code, PvmRam and PC. INVOKE runs it instruction by instruction and
+//! returns to the outer machine on the first host call, fault, panic, halt or out-of-gas.
+
+use crate::codec::decode_blob;
+use crate::config::PAGE_SIZE;
+use crate::host_functions::refine::{pages_access_to_memory_type, InvokeResult, RefineMachine};
+use crate::state_wrapper::{execute_instruction, PvmState, RAMType, Status, StepOutcome};
+use crate::types::Ram;
+
+const ZERO_PAGE: [u8; PAGE_SIZE as usize] = [0u8; PAGE_SIZE as usize];
+
+/// A guest machine created by MACHINE (8). RAM starts with every page inaccessible.
+pub struct GuestMachine {
+    state: PvmState,
+}
+
+impl GuestMachine {
+    /// Gray Paper Ω_M: deblob(p) must succeed, otherwise the caller reports HUH.
+    #[must_use]
+    pub fn new(program: &[u8], initial_pc: u64) -> Option<Self> {
+        let blob = decode_blob(program)?;
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        // Same padding as setup_*_from_preimage: trailing zero opcodes (TRAP) and a set bitmask for Fskip.
+        let code_len = blob.code.len();
+        let ext_len = code_len + 16;
+        let mut code = vec![0u8; ext_len];
+        code[..code_len].copy_from_slice(&blob.code);
+        let mut bitmask = vec![1u8; ext_len + 25];
+        bitmask[..blob.bitmask.len().min(ext_len)].copy_from_slice(&blob.bitmask);
+        state.load_code(code, bitmask, blob.jump_table);
+        // A PC beyond 2^32 saturates rather than wrapping into the code, so INVOKE panics on it.
+        state.program_counter = u32::try_from(initial_pc).unwrap_or(u32::MAX);
+        state.status = Status::Ok;
+        Some(Self { state })
+    }
+
+    /// Wrap an existing state (used when restoring a snapshot).
+    #[must_use]
+    pub(crate) fn from_state(state: PvmState) -> Self {
+        Self { state }
+    }
+
+    #[must_use]
+    pub(crate) fn state(&self) -> &PvmState {
+        &self.state
+    }
+
+    /// Copy-on-write copy of the machine (see `PvmState::fork`).
+    #[must_use]
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            state: self.state.fork()?,
+        })
+    }
+}
+
+impl RefineMachine for GuestMachine {
+    fn ram_read(&mut self, offset: u32, length: u32) -> Option<Vec<u8>> {
+        let read_result = self.state.ram.read_octets(offset, length);
+        if read_result.fault_address != 0 {
+            return None;
+        }
+        read_result.data
+    }
+
+    fn ram_write(&mut self, offset: u32, data: &[u8]) -> bool {
+        !self.state.ram.write_octets(offset, data).has_fault
+    }
+
+    fn ram_is_readable(&self, offset: u32, length: u32) -> bool {
+        self.state.ram.is_readable_with_fault(offset, length).success
+    }
+
+    fn ram_is_writable(&self, offset: u32, length: u32) -> bool {
+        self.state.ram.is_writable_with_fault(offset, length).success
+    }
+
+    /// Gray Paper Ω_Z: modes 0–2 zero the pages, modes 3–4 keep their contents.
+    fn set_page_access(&mut self, page_start: u32, page_count: u32, access: u8) {
+        if page_count == 0 {
+            return;
+        }
+        let address = page_start * PAGE_SIZE;
+        let length = page_count * PAGE_SIZE;
+        let access_type = pages_access_to_memory_type(access);
+        if access == 1 || access == 2 {
+            self.state.ram.init_page(address, length, access_type);
+            for i in 0..page_count {
+                self.state
+                    .ram
+                    .write_octets_during_initialization(address + i * PAGE_SIZE, &ZERO_PAGE);
+            }
+        } else {
+            // Mode 0: contents are unobservable until remapped by mode 1/2, which zeroes them.
+            // Modes 3/4: PAGES has already checked every page is accessible, so the pages exist.
+            self.state.ram.set_page_access_rights(address, length, access_type);
+        }
+    }
+
+    /// Gray Paper Ω_K: run Ψ with the given gas and registers until the first exit.
+    /// On a host call the PC is left after the ECALLI so the next INVOKE resumes there.
+    /// A PC past the code reads TRAP (ζ is zero-extended), so the machine panics without running.
+    fn invoke(&mut self, gas_limit: u64, registers: &[u64; 13]) -> InvokeResult {
+        let state = &mut self.state;
+        if state.program_counter as usize >= state.code.len() {
+            return InvokeResult::panic(gas_limit, *registers);
+        }
+        state.gas_left = gas_limit;
+        state.registers = *registers;
+        state.status = Status::Ok;
+        state.exit_arg = 0;
+        loop {
+            match execute_instruction(state) {
+                StepOutcome::Continue => {}
+                StepOutcome::Host { next_pc } => {
+                    state.program_counter = next_pc;
+                    return InvokeResult::host(state.host_call_id, state.gas_left, state.registers);
+                }
+                StepOutcome::Exit => break,
+            }
+        }
+        match state.status {
+            Status::Halt => InvokeResult::halt(state.gas_left, state.registers),
+            Status::Fault => InvokeResult::fault(state.exit_arg, state.gas_left, state.registers),
+            Status::Oog => InvokeResult::oog(state.gas_left, state.registers),
+            _ => InvokeResult::panic(state.gas_left, state.registers),
+        }
+    }
+
+    fn get_pc(&self) -> u64 {
+        u64::from(self.state.program_counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GuestMachine;
+    use crate::config::{REG_HUH, REG_OK, RESULT_CODE_HOST, RESULT_CODE_PANIC};
+    use crate::host_functions::refine::RefineMachine;
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+
+    #[test]
+    fn invoke_stops_on_host_call_and_resumes_after_ecalli() {
+        // deblob: |j| = 0, z = 0, |c| = 3, c = [ECALLI 5, TRAP], k = 0b101
+        let program = [0, 0, 3, 10, 5, 0, 0b101];
+        let mut machine = GuestMachine::new(&program, 0).expect("valid blob");
+
+        let first = machine.invoke(100, &[0u64; 13]);
+        assert_eq!(first.result_code, RESULT_CODE_HOST);
+        assert_eq!(first.extra, 5);
+        assert_eq!(machine.get_pc(), 2);
+
+        let second = machine.invoke(first.gas_remaining, &first.registers);
+        assert_eq!(second.result_code, RESULT_CODE_PANIC);
+        assert!(second.gas_remaining < first.gas_remaining);
+    }
+
+    #[test]
+    fn initial_pc_beyond_u32_does_not_wrap_into_the_code() {
+        // deblob: c = [FALLTHROUGH ×5, LOAD_IMM r1, 7, TRAP], k = 0b1_0011_1111
+        let program = [0, 0, 9, 1, 1, 1, 1, 1, 51, 1, 7, 0, 0b0011_1111, 0b1];
+        let mut machine = GuestMachine::new(&program, 5).expect("valid blob");
+        let result = machine.invoke(100, &[0u64; 13]);
+        assert_eq!((result.result_code, result.registers[1]), (RESULT_CODE_PANIC, 7));
+
+        let mut machine = GuestMachine::new(&program, (1 << 32) + 5).expect("valid blob");
+        let result = machine.invoke(100, &[0u64; 13]);
+        assert_eq!(result.result_code, RESULT_CODE_PANIC);
+        assert_eq!((result.registers[1], result.gas_remaining), (0, 100));
+        assert_eq!(machine.get_pc(), u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn pages_validates_full_register_values() {
+        // deblob: c = [TRAP], k = 0b1
+        let guest = GuestMachine::new(&[0, 0, 1, 0, 0b1], 0).expect("valid blob");
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.has_refine_context = true;
+        state.refine_machines.insert(0, guest);
+        // ECALLI 11 (PAGES) three times
+        state.load_code(vec![10, 11, 10, 11, 10, 11], vec![1, 0, 1, 0, 1, 0], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+
+        // Page 2^32 + 16 and mode 2^32 + 1 would pass as 16 and 1 if narrowed first.
+        let cases = [
+            (16, 1, REG_OK),
+            ((1 << 32) + 16, 1, REG_HUH),
+            (16, (1 << 32) + 1, REG_HUH),
+        ];
+        for (page, mode, expected) in cases {
+            state.registers[7] = 0;
+            state.registers[8] = page;
+            state.registers[9] = 1;
+            state.registers[10] = mode;
+            assert!(next_step_impl(&mut state));
+            assert_eq!(state.registers[7], expected, "page {page}, mode {mode}");
+        }
+    }
+}
@@ -24,7 +24,7 @@ impl AssignHostFunction {
 }
 
 impl HostFunction for AssignHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_ASSIGN
     }
     fn name(&self) -> &'static str {
@@ -54,7 +54,7 @@ impl BlessHostFunction {
 }
 
 impl HostFunction for BlessHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_BLESS
     }
     fn name(&self) -> &'static str {
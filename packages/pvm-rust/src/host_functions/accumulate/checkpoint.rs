@@ -8,7 +8,7 @@ use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctio
 pub struct CheckpointHostFunction;
 
 impl HostFunction for CheckpointHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_CHECKPOINT
     }
     fn name(&self) -> &'static str {
@@ -20,7 +20,7 @@ impl HostFunction for CheckpointHostFunction {
             **requested = true;
         }
         // Gray Paper line 753: registers'_7 = gascounter' (gas counter after deduction by executor)
-        base::set_accumulate_success(context.registers, u64::from(*context.gas_remaining));
+        base::set_accumulate_success(context.registers, *context.gas_remaining);
         HostFunctionResult::continue_execution()
     }
 }
@@ -8,7 +8,7 @@ use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctio
 pub struct DesignateHostFunction;
 
 impl HostFunction for DesignateHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_DESIGNATE
     }
     fn name(&self) -> &'static str {
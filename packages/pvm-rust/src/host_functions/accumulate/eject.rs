@@ -13,7 +13,7 @@ const HASH_LEN: u32 = 32;
 pub struct EjectHostFunction;
 
 impl HostFunction for EjectHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_EJECT
     }
     fn name(&self) -> &'static str {
@@ -37,7 +37,7 @@ fn remove_request_and_update_footprint(
 pub struct ForgetHostFunction;
 
 impl HostFunction for ForgetHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_FORGET
     }
     fn name(&self) -> &'static str {
@@ -33,9 +33,9 @@ pub use upgrade::UpgradeHostFunction;
 pub use yield_::YieldHostFunction;
 
 /// Build accumulate host function registry with all implementations.
-pub fn create_accumulate_registry() -> HashMap<u8, Box<dyn HostFunction>> {
+pub fn create_accumulate_registry() -> HashMap<u32, Box<dyn HostFunction>> {
     let mut m = HashMap::new();
-    let register = |m: &mut HashMap<u8, Box<dyn HostFunction>>, h: Box<dyn HostFunction>| {
+    let register = |m: &mut HashMap<u32, Box<dyn HostFunction>>, h: Box<dyn HostFunction>| {
         m.insert(h.function_id(), h);
     };
     register(&mut m, Box::new(BlessHostFunction));
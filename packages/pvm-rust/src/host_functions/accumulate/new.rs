@@ -53,7 +53,7 @@ impl NewHostFunction {
 }
 
 impl HostFunction for NewHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_NEW
     }
     fn name(&self) -> &'static str {
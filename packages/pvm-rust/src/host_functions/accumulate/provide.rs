@@ -15,7 +15,7 @@ const MAX_U64: u64 = u64::MAX;
 pub struct ProvideHostFunction;
 
 impl HostFunction for ProvideHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_PROVIDE
     }
     fn name(&self) -> &'static str {
@@ -13,7 +13,7 @@ const TWO_TO_32: u64 = 4_294_967_296; // 2^32
 pub struct QueryHostFunction;
 
 impl HostFunction for QueryHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_QUERY
     }
     fn name(&self) -> &'static str {
@@ -23,7 +23,7 @@ impl SolicitHostFunction {
 }
 
 impl HostFunction for SolicitHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_SOLICIT
     }
     fn name(&self) -> &'static str {
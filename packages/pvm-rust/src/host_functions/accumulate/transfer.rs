@@ -6,10 +6,11 @@
 use crate::codec::DeferredTransfer;
 use crate::config::{C_BASE_DEPOSIT, C_BYTE_DEPOSIT, C_ITEM_DEPOSIT, C_MEMO_SIZE, FUNC_TRANSFER};
 use crate::host_functions::accumulate::base::{self, codes};
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
-
-/// On success total gas = 10 + gasLimit; base 10 deducted in state_wrapper, we deduct gasLimit here.
+use crate::host_functions::base::{
+    HostCallUsage, HostFunction, HostFunctionContext, HostFunctionResult,
+};
 
+/// On success total gas = 10 + gasLimit: base 10 before the call, gasLimit via `dynamic_gas`.
 pub struct TransferHostFunction;
 
 impl TransferHostFunction {
@@ -23,7 +24,7 @@ impl TransferHostFunction {
 }
 
 impl HostFunction for TransferHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_TRANSFER
     }
     fn name(&self) -> &'static str {
@@ -147,10 +148,14 @@ impl HostFunction for TransferHostFunction {
         base::set_accumulate_success(context.registers, codes::OK);
         crate::host_log!("[host-calls] [{}] TRANSFER({}, {}, {}) <- OK", _log_service_id, dest, amount, gas_limit);
 
-        // Gray Paper: on success additional gas = l (base 10 already deducted in state_wrapper)
-        let additional_gas = gas_limit as u32;
-        *context.gas_remaining = context.gas_remaining.saturating_sub(additional_gas);
-
         HostFunctionResult::continue_execution()
     }
+    /// Gray Paper: on success additional gas = l (r9 at entry).
+    fn dynamic_gas(&self, usage: &HostCallUsage<'_>) -> u64 {
+        if usage.after[7] == codes::OK {
+            usage.before[9]
+        } else {
+            0
+        }
+    }
 }
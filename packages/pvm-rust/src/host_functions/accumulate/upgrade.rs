@@ -11,7 +11,7 @@ const CODE_HASH_LEN: u32 = 32;
 pub struct UpgradeHostFunction;
 
 impl HostFunction for UpgradeHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_UPGRADE
     }
     fn name(&self) -> &'static str {
@@ -12,7 +12,7 @@ const HASH_LEN: u32 = 32;
 pub struct YieldHostFunction;
 
 impl HostFunction for YieldHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_YIELD
     }
     fn name(&self) -> &'static str {
@@ -1,7 +1,7 @@
 //! Host function base types and trait (mirrors assembly/host-functions/general/base.ts and accumulate/base.ts).
 
 use crate::codec::{CompleteServiceAccount, DeferredTransfer, PartialState, ProvisionEntry};
-use crate::config::{FetchSystemConstantsConfig, RESULT_CODE_FAULT, RESULT_CODE_HALT, RESULT_CODE_OOG, RESULT_CODE_PANIC};
+use crate::config::{FetchSystemConstantsConfig, HOST_CALL_BASE_GAS, RESULT_CODE_FAULT, RESULT_CODE_HALT, RESULT_CODE_OOG, RESULT_CODE_PANIC};
 use crate::host_functions::refine::RefineContext;
 use crate::types::{RegisterState, Ram};
 use std::collections::HashMap;
@@ -62,7 +62,7 @@ impl HostFunctionResult {
 pub struct HostFunctionContext<'a> {
     pub registers: &'a mut RegisterState,
     pub ram: &'a mut dyn Ram,
-    pub gas_remaining: &'a mut u32,
+    pub gas_remaining: &'a mut u64,
     /// Current service ID (for LOOKUP, READ, WRITE, INFO, HISTORICAL_LOOKUP when params provided).
     pub service_id: Option<u64>,
     /// Current service account, mutable for WRITE (when params provided).
@@ -103,8 +103,8 @@ pub struct HostFunctionContext<'a> {
     pub fetch_entropy_accumulator: Option<&'a [u8]>,
     /// FETCH selector 2: authorizer trace (r); when provided.
     pub fetch_authorizer_trace: Option<&'a [u8]>,
-    /// FETCH selector 3,4: export segments [work_item][extrinsic_index]; when provided.
-    pub fetch_export_segments: Option<&'a [Vec<Vec<u8>>]>,
+    /// FETCH selector 3,4: extrinsic blobs (x̄) [work_item][extrinsic_index]; when provided.
+    pub fetch_extrinsics: Option<&'a [Vec<Vec<u8>>]>,
     /// FETCH selector 5,6: import segments [work_item][segment_index]; when provided.
     pub fetch_import_segments: Option<&'a [Vec<Vec<u8>>]>,
     /// FETCH selector 4,6: work item index (sentinel u64::MAX = not set); when provided.
@@ -129,12 +129,44 @@ pub struct HostFunctionContext<'a> {
     pub fetch_system_constants_config: Option<&'a FetchSystemConstantsConfig>,
 }
 
+/// Narrow a guest memory range taken from 64-bit registers. None when N_{offset..+length} reaches past 2^32,
+/// so callers treat it as inaccessible instead of wrapping a truncated offset or length.
+#[must_use]
+pub fn memory_range(offset: u64, length: u64) -> Option<(u32, u32)> {
+    if length == 0 {
+        return Some((offset as u32, 0));
+    }
+    let end = offset.checked_add(length)?;
+    (end <= 1u64 << 32).then_some((offset as u32, length as u32))
+}
+
 /// Trait for host function implementations (general and accumulate).
 pub trait HostFunction: Send + Sync {
-    /// Function ID (FUNC_* constant).
-    fn function_id(&self) -> u8;
+    /// Host call ID (FUNC_* constant); the full 32-bit ECALLI immediate.
+    fn function_id(&self) -> u32;
     /// Human-readable name.
     fn name(&self) -> &'static str;
     /// Execute the host function. May mutate context (registers, ram, gas).
     fn execute(&self, context: &mut HostFunctionContext<'_>) -> HostFunctionResult;
+    /// Gas charged before `execute` (Gray Paper g). The registry can override it per ID (`set_base_gas`).
+    fn base_gas(&self) -> u64 {
+        HOST_CALL_BASE_GAS
+    }
+    /// Gas charged after `execute` when execution continues (e.g. TRANSFER's gas limit l on success, or a
+    /// per-byte cost); more than the gas left is out-of-gas. None by default.
+    fn dynamic_gas(&self, _usage: &HostCallUsage<'_>) -> u64 {
+        0
+    }
+}
+
+/// What a host call did, for `HostFunction::dynamic_gas`.
+pub struct HostCallUsage<'a> {
+    /// Registers at entry.
+    pub before: &'a RegisterState,
+    /// Registers on return.
+    pub after: &'a RegisterState,
+    /// Guest memory bytes the call read (`read_octets`).
+    pub bytes_read: u64,
+    /// Guest memory bytes the call wrote (`write_octets`).
+    pub bytes_written: u64,
 }
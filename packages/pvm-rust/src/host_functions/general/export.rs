@@ -2,13 +2,13 @@
 //! r7=memory offset (p), r8=length (capped at SEGMENT_SIZE). No refineContext → PANIC.
 
 use crate::config::{FUNC_EXPORT, REG_FULL, SEGMENT_SIZE};
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+use crate::host_functions::base::{memory_range, HostFunction, HostFunctionContext, HostFunctionResult};
 
 /// EXPORT (7): with refine_context: read memory, zero-pad segment, append; r7=segoff+len or FULL. No refine → PANIC.
 pub struct ExportHostFunction;
 
 impl HostFunction for ExportHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_EXPORT
     }
     fn name(&self) -> &'static str {
@@ -19,9 +19,12 @@ impl HostFunction for ExportHostFunction {
             return HostFunctionResult::panic();
         };
 
-        let memory_offset = context.registers[7] as u32;
-        let raw_length = context.registers[8] as u32;
-        let capped_length = raw_length.min(SEGMENT_SIZE);
+        // Cap before narrowing: l = min(r8, W_G), so 2^32 + 1 must not become 1.
+        let capped_length = context.registers[8].min(u64::from(SEGMENT_SIZE));
+        let Some((memory_offset, capped_length)) = memory_range(context.registers[7], capped_length) else {
+            context.registers[7] = 0;
+            return HostFunctionResult::panic();
+        };
 
         let check = context.ram.is_readable_with_fault(memory_offset, capped_length);
         if !check.success {
@@ -1,14 +1,14 @@
 //! EXPUNGE host function (Ω_X). Gray Paper: function ID 13.
-//! r7=machine ID. No refineContext → PANIC. WHO → HALT; else r7=pc, continue.
+//! r7=machine ID. No refineContext → PANIC. Unknown machine → r7=WHO; else r7=pc. Both continue.
 
 use crate::config::FUNC_EXPUNGE;
 use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
 
-/// EXPUNGE (13): with refine_context: remove machine; r7=WHO and HALT if not found, else r7=pc and continue.
+/// EXPUNGE (13): with refine_context: remove machine; r7=WHO if not found, else r7=its pc (Gray Paper Ω_X).
 pub struct ExpungeHostFunction;
 
 impl HostFunction for ExpungeHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_EXPUNGE
     }
     fn name(&self) -> &'static str {
@@ -23,7 +23,7 @@ impl HostFunction for ExpungeHostFunction {
         match refine.remove_machine(machine_id) {
             None => {
                 context.registers[7] = crate::config::REG_WHO;
-                HostFunctionResult::halt()
+                HostFunctionResult::continue_execution()
             }
             Some(pc) => {
                 context.registers[7] = pc;
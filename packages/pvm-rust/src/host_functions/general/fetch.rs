@@ -36,7 +36,7 @@ impl FetchHostFunction {
 }
 
 impl HostFunction for FetchHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_FETCH
     }
     fn name(&self) -> &'static str {
@@ -93,34 +93,34 @@ impl FetchHostFunction {
                 .fetch_authorizer_trace
                 .map(|s| s.to_vec()),
             3 => {
-                let export = context.fetch_export_segments?;
+                let extrinsics = context.fetch_extrinsics?;
                 let work_item_idx = context.registers[11] as usize;
                 let extrinsic_idx = context.registers[12] as usize;
-                if work_item_idx >= export.len() {
+                if work_item_idx >= extrinsics.len() {
                     return None;
                 }
-                let segments = &export[work_item_idx];
-                if extrinsic_idx >= segments.len() {
+                let blobs = &extrinsics[work_item_idx];
+                if extrinsic_idx >= blobs.len() {
                     return None;
                 }
-                Some(segments[extrinsic_idx].clone())
+                Some(blobs[extrinsic_idx].clone())
             }
             4 => {
                 let work_item_index = context.fetch_work_item_index?;
                 if work_item_index == u64::MAX {
                     return None;
                 }
-                let export = context.fetch_export_segments?;
+                let extrinsics = context.fetch_extrinsics?;
                 let work_item_idx = work_item_index as usize;
-                let segment_idx = context.registers[11] as usize;
-                if work_item_idx >= export.len() {
+                let extrinsic_idx = context.registers[11] as usize;
+                if work_item_idx >= extrinsics.len() {
                     return None;
                 }
-                let segments = &export[work_item_idx];
-                if segment_idx >= segments.len() {
+                let blobs = &extrinsics[work_item_idx];
+                if extrinsic_idx >= blobs.len() {
                     return None;
                 }
-                Some(segments[segment_idx].clone())
+                Some(blobs[extrinsic_idx].clone())
             }
             5 => {
                 let import = context.fetch_import_segments?;
@@ -262,3 +262,41 @@ impl FetchHostFunction {
         buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FUNC_FETCH;
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+    use crate::types::{MemoryAccessType, Ram};
+
+    #[test]
+    fn selectors_3_and_4_fetch_extrinsics_and_5_and_6_fetch_imports() {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.has_refine_context = true;
+        state.refine_extrinsics =
+            Some(vec![vec![b"x00".to_vec()], vec![b"x10".to_vec(), b"x11".to_vec()]]);
+        state.refine_import_segments =
+            Some(vec![vec![b"i00".to_vec()], vec![b"i10".to_vec(), b"i11".to_vec()]]);
+        state.refine_work_item_index = Some(1);
+        state.ram.init_page(0x2_0000, 0x1000, MemoryAccessType::Write);
+        let id = FUNC_FETCH as u8;
+        state.load_code(vec![10, id, 10, id, 10, id, 10, id], vec![1, 0, 1, 0, 1, 0, 1, 0], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+
+        // (selector, r11, r12): 3 and 5 take the work item from r11, 4 and 6 use the current one.
+        let cases = [
+            (3, 1, 0, b"x10"),
+            (4, 1, 0, b"x11"),
+            (5, 1, 1, b"i11"),
+            (6, 0, 0, b"i10"),
+        ];
+        for (selector, r11, r12, expected) in cases {
+            state.registers[7..13].copy_from_slice(&[0x2_0000, 0, 3, selector, r11, r12]);
+            assert!(next_step_impl(&mut state));
+            assert_eq!(state.registers[7], 3, "selector {selector}");
+            let data = state.ram.read_octets(0x2_0000, 3).data;
+            assert_eq!(data.as_deref(), Some(&expected[..]), "selector {selector}");
+        }
+    }
+}
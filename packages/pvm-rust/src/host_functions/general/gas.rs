@@ -8,14 +8,14 @@ use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctio
 pub struct GasHostFunction;
 
 impl HostFunction for GasHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_GAS
     }
     fn name(&self) -> &'static str {
         "gas"
     }
     fn execute(&self, context: &mut HostFunctionContext<'_>) -> HostFunctionResult {
-        context.registers[7] = u64::from(*context.gas_remaining);
+        context.registers[7] = *context.gas_remaining;
         HostFunctionResult::continue_execution()
     }
 }
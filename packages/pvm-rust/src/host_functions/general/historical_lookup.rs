@@ -41,7 +41,7 @@ impl HistoricalLookupHostFunction {
 }
 
 impl HostFunction for HistoricalLookupHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_HISTORICAL_LOOKUP
     }
     fn name(&self) -> &'static str {
@@ -46,7 +46,7 @@ impl InfoHostFunction {
 }
 
 impl HostFunction for InfoHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_INFO
     }
     fn name(&self) -> &'static str {
@@ -2,7 +2,7 @@
 //! r7=machine ID, r8=memory offset for gas (8) + registers (104). No refineContext → PANIC.
 
 use crate::config::FUNC_INVOKE;
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+use crate::host_functions::base::{memory_range, HostFunction, HostFunctionContext, HostFunctionResult};
 
 const INVOKE_HEADER_SIZE: u32 = 8 + 104; // gas (8) + 13 registers (104)
 
@@ -21,7 +21,7 @@ fn encode_u64(value: u64) -> [u8; 8] {
 pub struct InvokeHostFunction;
 
 impl HostFunction for InvokeHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_INVOKE
     }
     fn name(&self) -> &'static str {
@@ -34,8 +34,16 @@ impl HostFunction for InvokeHostFunction {
         };
 
         let machine_id = context.registers[7];
-        let memory_offset = context.registers[8] as u32;
+        let Some((memory_offset, _)) = memory_range(context.registers[8], u64::from(INVOKE_HEADER_SIZE)) else {
+            crate::host_log!("[hostfn] invoke PANIC: header range past 2^32");
+            return HostFunctionResult::panic();
+        };
 
+        // Gray Paper Ω_K: the header is written back, so it must be writable, not just readable.
+        if !context.ram.is_writable_with_fault(memory_offset, INVOKE_HEADER_SIZE).success {
+            crate::host_log!("[hostfn] invoke PANIC: header not writable (offset={})", memory_offset);
+            return HostFunctionResult::panic();
+        }
         let read_result = context.ram.read_octets(memory_offset, INVOKE_HEADER_SIZE);
         let Some(data) = read_result.data else {
             crate::host_log!(
@@ -52,7 +60,7 @@ impl HostFunction for InvokeHostFunction {
             return HostFunctionResult::panic();
         }
 
-        let gas_limit = decode_u64(&data[0..8]) as u32;
+        let gas_limit = decode_u64(&data[0..8]);
         let mut registers = [0u64; 13];
         for (i, r) in registers.iter_mut().enumerate() {
             let start = 8 + i * 8;
@@ -70,7 +78,7 @@ impl HostFunction for InvokeHostFunction {
         let invoke_result = invoke_result.expect("with_machine ran");
 
         let mut out = vec![0u8; INVOKE_HEADER_SIZE as usize];
-        out[0..8].copy_from_slice(&encode_u64(invoke_result.gas_remaining as u64));
+        out[0..8].copy_from_slice(&encode_u64(invoke_result.gas_remaining));
         for (i, &r) in invoke_result.registers.iter().enumerate() {
             out[8 + i * 8..8 + (i + 1) * 8].copy_from_slice(&encode_u64(r));
         }
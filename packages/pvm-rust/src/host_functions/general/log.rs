@@ -9,7 +9,7 @@ use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctio
 pub struct LogHostFunction;
 
 impl HostFunction for LogHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_LOG
     }
     fn name(&self) -> &'static str {
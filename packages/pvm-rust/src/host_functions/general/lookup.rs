@@ -12,7 +12,7 @@ const MAX_U64: u64 = u64::MAX;
 pub struct LookupHostFunction;
 
 impl HostFunction for LookupHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_LOOKUP
     }
     fn name(&self) -> &'static str {
@@ -1,14 +1,15 @@
 //! MACHINE host function (Ω_M). Gray Paper: function ID 8.
 //! r7=program offset, r8=program length, r9=initial PC. No refineContext → PANIC.
 
-use crate::config::FUNC_MACHINE;
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+use crate::config::{FUNC_MACHINE, REG_HUH};
+use crate::host_functions::base::{memory_range, HostFunction, HostFunctionContext, HostFunctionResult};
 
-/// MACHINE (8): with refine_context: read program from memory, add_machine, r7=machine_id. No refine → PANIC.
+/// MACHINE (8): with refine_context: read program from memory, add_machine, r7=machine_id.
+/// Program unreadable → PANIC; program does not deblob → HUH. No refine → PANIC.
 pub struct MachineHostFunction;
 
 impl HostFunction for MachineHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_MACHINE
     }
     fn name(&self) -> &'static str {
@@ -21,28 +22,33 @@ impl HostFunction for MachineHostFunction {
         };
         // Base 10 gas already deducted in state_wrapper before host dispatch.
 
-        let program_offset = context.registers[7] as u32;
-        let program_length = context.registers[8] as u32;
+        let Some((program_offset, program_length)) = memory_range(context.registers[7], context.registers[8])
+        else {
+            crate::host_log!("[hostfn] machine PANIC: program range past 2^32");
+            return HostFunctionResult::panic();
+        };
         let initial_pc = context.registers[9];
 
         let read_result = context.ram.read_octets(program_offset, program_length);
         let Some(program_data) = read_result.data else {
             crate::host_log!(
-                "[hostfn] machine FAULT: program read returned no data (offset={}, len={})",
+                "[hostfn] machine PANIC: program read returned no data (offset={}, len={})",
                 program_offset, program_length
             );
-            return HostFunctionResult::fault();
+            return HostFunctionResult::panic();
         };
         if read_result.fault_address != 0 {
             crate::host_log!(
-                "[hostfn] machine FAULT: program read fault (offset={}, len={}, fault_address={})",
+                "[hostfn] machine PANIC: program read fault (offset={}, len={}, fault_address={})",
                 program_offset, program_length, read_result.fault_address
             );
-            return HostFunctionResult::fault();
+            return HostFunctionResult::panic();
         }
 
-        let machine_id = refine.add_machine(&program_data, initial_pc);
-        context.registers[7] = machine_id;
+        context.registers[7] = match refine.add_machine(&program_data, initial_pc) {
+            Some(machine_id) => machine_id,
+            None => REG_HUH,
+        };
         HostFunctionResult::continue_execution()
     }
 }
@@ -36,9 +36,9 @@ pub use read::ReadHostFunction;
 pub use write::WriteHostFunction;
 
 /// Build general host function registry with all implementations.
-pub fn create_general_registry() -> HashMap<u8, Box<dyn HostFunction>> {
+pub fn create_general_registry() -> HashMap<u32, Box<dyn HostFunction>> {
     let mut m = HashMap::new();
-    let register = |m: &mut HashMap<u8, Box<dyn HostFunction>>, h: Box<dyn HostFunction>| {
+    let register = |m: &mut HashMap<u32, Box<dyn HostFunction>>, h: Box<dyn HostFunction>| {
         m.insert(h.function_id(), h);
     };
     register(&mut m, Box::new(GasHostFunction));
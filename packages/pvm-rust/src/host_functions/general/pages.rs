@@ -12,7 +12,7 @@ const MAX_PAGE_INDEX: u32 = 1_048_576;
 pub struct PagesHostFunction;
 
 impl HostFunction for PagesHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_PAGES
     }
     fn name(&self) -> &'static str {
@@ -24,19 +24,21 @@ impl HostFunction for PagesHostFunction {
         };
 
         let machine_id = context.registers[7];
-        let page_start = context.registers[8] as u32;
-        let page_count = context.registers[9] as u32;
-        let access_rights = context.registers[10] as u32;
+        let page_start = context.registers[8];
+        let page_count = context.registers[9];
+        let access_rights = context.registers[10];
 
         let mut applied = None;
         let found = refine.with_machine(machine_id, &mut |machine| {
-            if page_start < MIN_PAGE_INDEX
-                || page_start.saturating_add(page_count) > MAX_PAGE_INDEX
+            // Validate the full register values; narrowing first would let 2^32 + 16 pass as 16.
+            if page_start < u64::from(MIN_PAGE_INDEX)
+                || page_start.saturating_add(page_count) >= u64::from(MAX_PAGE_INDEX)
                 || access_rights > 4
             {
                 applied = Some(false);
                 return;
             }
+            let (page_start, page_count) = (page_start as u32, page_count as u32);
             if access_rights > 2 {
                 for i in 0..page_count {
                     let page_index = page_start + i;
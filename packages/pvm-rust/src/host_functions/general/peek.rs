@@ -3,13 +3,13 @@
 //! No refineContext → set r7=WHO, return PANIC.
 
 use crate::config::{FUNC_PEEK, REG_OK, REG_OOB, REG_WHO};
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+use crate::host_functions::base::{memory_range, HostFunction, HostFunctionContext, HostFunctionResult};
 
-/// PEEK (9): with refine_context: copy from machine RAM to current RAM; WHO if no machine, OOB if source unreadable; dest unwritable → PANIC.
+/// PEEK (9): with refine_context: copy from machine RAM to current RAM; dest unwritable → PANIC; WHO if no machine, OOB if source unreadable.
 pub struct PeekHostFunction;
 
 impl HostFunction for PeekHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_PEEK
     }
     fn name(&self) -> &'static str {
@@ -23,14 +23,29 @@ impl HostFunction for PeekHostFunction {
         };
 
         let machine_id = context.registers[7];
-        let dest_offset = context.registers[8] as u32;
-        let source_offset = context.registers[9] as u32;
-        let length = context.registers[10] as u32;
+        let length = context.registers[10];
+
+        // Gray Paper Ω_P: an unwritable destination panics before the machine is looked up.
+        let Some((dest_offset, length)) = memory_range(context.registers[8], length) else {
+            crate::host_log!("[hostfn] peek PANIC: dest range past 2^32");
+            return HostFunctionResult::panic();
+        };
+        let source = memory_range(context.registers[9], u64::from(length));
+        let writable = context.ram.is_writable_with_fault(dest_offset, length);
+        if !writable.success {
+            crate::host_log!(
+                "[hostfn] peek PANIC: dest not writable (offset={}, len={}, fault_address={})",
+                dest_offset, length, writable.fault_address
+            );
+            return HostFunctionResult::panic();
+        }
 
         let mut data = None;
         let found = refine.with_machine(machine_id, &mut |machine| {
-            if machine.ram_is_readable(source_offset, length) {
-                data = machine.ram_read(source_offset, length);
+            if let Some((source_offset, length)) = source {
+                if machine.ram_is_readable(source_offset, length) {
+                    data = machine.ram_read(source_offset, length);
+                }
             }
         });
         if !found {
@@ -42,14 +57,6 @@ impl HostFunction for PeekHostFunction {
             return HostFunctionResult::continue_execution();
         };
 
-        let writable = context.ram.is_writable_with_fault(dest_offset, length);
-        if !writable.success {
-            crate::host_log!(
-                "[hostfn] peek PANIC: dest not writable (offset={}, len={}, fault_address={})",
-                dest_offset, length, writable.fault_address
-            );
-            return HostFunctionResult::panic();
-        }
         let write_result = context.ram.write_octets(dest_offset, &data);
         if write_result.has_fault {
             crate::host_log!(
@@ -3,13 +3,13 @@
 //! No refineContext → set r7=WHO, return PANIC.
 
 use crate::config::{FUNC_POKE, REG_OK, REG_OOB, REG_WHO};
-use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+use crate::host_functions::base::{memory_range, HostFunction, HostFunctionContext, HostFunctionResult};
 
 /// POKE (10): with refine_context: read from current RAM, write to machine RAM; source unreadable → PANIC; WHO if no machine, OOB if dest unwritable.
 pub struct PokeHostFunction;
 
 impl HostFunction for PokeHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_POKE
     }
     fn name(&self) -> &'static str {
@@ -23,9 +23,11 @@ impl HostFunction for PokeHostFunction {
         };
 
         let machine_id = context.registers[7];
-        let source_offset = context.registers[8] as u32;
-        let dest_offset = context.registers[9] as u32;
-        let length = context.registers[10] as u32;
+        let Some((source_offset, length)) = memory_range(context.registers[8], context.registers[10]) else {
+            crate::host_log!("[hostfn] poke PANIC: source range past 2^32");
+            return HostFunctionResult::panic();
+        };
+        let dest = memory_range(context.registers[9], u64::from(length));
 
         let read_result = context.ram.read_octets(source_offset, length);
         let Some(data) = read_result.data else {
@@ -45,9 +47,9 @@ impl HostFunction for PokeHostFunction {
 
         let mut write_ok = None;
         let found = refine.with_machine(machine_id, &mut |machine| {
-            write_ok = Some(
-                machine.ram_is_writable(dest_offset, length) && machine.ram_write(dest_offset, &data),
-            );
+            write_ok = Some(dest.is_some_and(|(dest_offset, length)| {
+                machine.ram_is_writable(dest_offset, length) && machine.ram_write(dest_offset, &data)
+            }));
         });
 
         if !found {
@@ -10,7 +10,7 @@ use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctio
 pub struct ReadHostFunction;
 
 impl HostFunction for ReadHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_READ
     }
     fn name(&self) -> &'static str {
@@ -17,7 +17,7 @@ impl WriteHostFunction {
 }
 
 impl HostFunction for WriteHostFunction {
-    fn function_id(&self) -> u8 {
+    fn function_id(&self) -> u32 {
         FUNC_WRITE
     }
     fn name(&self) -> &'static str {
@@ -11,14 +11,14 @@ pub struct InvokeResult {
     /// For HOST: host call ID; for FAULT: fault address; otherwise 0.
     pub extra: u64,
     /// Gas remaining after run (write back to memory).
-    pub gas_remaining: u32,
+    pub gas_remaining: u64,
     /// Register state after run (write back to memory).
     pub registers: [u64; 13],
 }
 
 impl InvokeResult {
     #[must_use]
-    pub const fn halt(gas_remaining: u32, registers: [u64; 13]) -> Self {
+    pub const fn halt(gas_remaining: u64, registers: [u64; 13]) -> Self {
         Self {
             result_code: RESULT_CODE_HALT,
             extra: 0,
@@ -27,7 +27,7 @@ impl InvokeResult {
         }
     }
     #[must_use]
-    pub const fn panic(gas_remaining: u32, registers: [u64; 13]) -> Self {
+    pub const fn panic(gas_remaining: u64, registers: [u64; 13]) -> Self {
         Self {
             result_code: RESULT_CODE_PANIC,
             extra: 0,
@@ -36,7 +36,7 @@ impl InvokeResult {
         }
     }
     #[must_use]
-    pub const fn fault(fault_address: u32, gas_remaining: u32, registers: [u64; 13]) -> Self {
+    pub const fn fault(fault_address: u32, gas_remaining: u64, registers: [u64; 13]) -> Self {
         Self {
             result_code: RESULT_CODE_FAULT,
             extra: fault_address as u64,
@@ -45,7 +45,7 @@ impl InvokeResult {
         }
     }
     #[must_use]
-    pub const fn oog(gas_remaining: u32, registers: [u64; 13]) -> Self {
+    pub const fn oog(gas_remaining: u64, registers: [u64; 13]) -> Self {
         Self {
             result_code: RESULT_CODE_OOG,
             extra: 0,
@@ -54,7 +54,7 @@ impl InvokeResult {
         }
     }
     #[must_use]
-    pub const fn host(host_call_id: u32, gas_remaining: u32, registers: [u64; 13]) -> Self {
+    pub const fn host(host_call_id: u32, gas_remaining: u64, registers: [u64; 13]) -> Self {
         Self {
             result_code: RESULT_CODE_HOST,
             extra: host_call_id as u64,
@@ -77,7 +77,7 @@ pub trait RefineMachine: Send + Sync {
     /// Set page access (Gray Paper Ω_Z). page_start, page_count in pages; access 0–4.
     fn set_page_access(&mut self, page_start: u32, page_count: u32, access: u8);
     /// Run machine with gas_limit and initial registers; return result (Gray Paper Ψ).
-    fn invoke(&mut self, gas_limit: u32, registers: &[u64; 13]) -> InvokeResult;
+    fn invoke(&mut self, gas_limit: u64, registers: &[u64; 13]) -> InvokeResult;
     /// Current program counter.
     fn get_pc(&self) -> u64;
 }
@@ -89,8 +89,8 @@ pub trait RefineContext: Send + Sync {
     fn segment_offset(&self) -> i64;
     /// Append a segment; returns Ok(segoff + len(export_segments)) or Err(()) when FULL.
     fn push_export_segment(&mut self, segment: Vec<u8>) -> Result<i64, ()>;
-    /// Create a new machine with program and initial PC; returns machine ID.
-    fn add_machine(&mut self, program: &[u8], initial_pc: u64) -> u64;
+    /// Create a new machine with program and initial PC; returns machine ID, or None if the program does not deblob (HUH).
+    fn add_machine(&mut self, program: &[u8], initial_pc: u64) -> Option<u64>;
     /// Run closure with mutable reference to machine; returns true if machine existed.
     fn with_machine(&mut self, machine_id: u64, f: &mut dyn FnMut(&mut dyn RefineMachine)) -> bool;
     /// Remove machine; returns Some(pc) if existed, None otherwise (WHO).
@@ -0,0 +1,218 @@
+//! Per-instance host function registry keyed by the full 32-bit host call ID, plus the host calls each
+//! invocation kind may make (Gray Paper: Ψ_I allows GAS/FETCH, Ψ_R general 0–13, Ψ_A general 0–5 and 14–26).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
+use crate::config::{FUNC_FETCH, FUNC_GAS, FUNC_LOG, HOST_CALL_BASE_GAS};
+use crate::host_functions::base::HostFunction;
+use crate::host_functions::{accumulate, general};
+
+/// Which invocation the instance is running; selects the host-call allowlist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InvocationKind {
+    /// Plain program run (test vectors, runBlob): every registered host call is allowed.
+    Generic,
+    IsAuthorized,
+    Refine,
+    Accumulate,
+}
+
+/// Host functions by ID, per-kind allowlists and the base gas schedule. Cloning is cheap (handlers are shared).
+#[derive(Clone, Default)]
+pub struct HostFunctionRegistry {
+    functions: HashMap<u32, Arc<dyn HostFunction>>,
+    allowlists: HashMap<InvocationKind, HashSet<u32>>,
+    /// Base gas overrides by ID; IDs without an entry use the handler's `base_gas()`.
+    base_gas: HashMap<u32, u64>,
+}
+
+impl HostFunctionRegistry {
+    /// Empty registry with no allowlists (every kind may call whatever is registered).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// General + accumulate host functions with the Gray Paper allowlists (LOG 100 allowed everywhere).
+    #[must_use]
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        for handler in general::create_general_registry().into_values() {
+            registry.register(handler);
+        }
+        for handler in accumulate::create_accumulate_registry().into_values() {
+            registry.register(handler);
+        }
+        registry.set_allowlist(InvocationKind::IsAuthorized, [FUNC_GAS, FUNC_FETCH, FUNC_LOG]);
+        registry.set_allowlist(InvocationKind::Refine, (0..=13).chain([FUNC_LOG]));
+        registry.set_allowlist(InvocationKind::Accumulate, (0..=5).chain(14..=26).chain([FUNC_LOG]));
+        registry
+    }
+
+    /// Process-wide default registry; instances share it until they modify their own copy.
+    #[must_use]
+    pub fn shared_default() -> Arc<Self> {
+        static DEFAULT: OnceLock<Arc<HostFunctionRegistry>> = OnceLock::new();
+        Arc::clone(DEFAULT.get_or_init(|| Arc::new(Self::with_defaults())))
+    }
+
+    /// Register a handler under its `function_id`, returning the one it replaces.
+    pub fn register(&mut self, handler: Box<dyn HostFunction>) -> Option<Arc<dyn HostFunction>> {
+        self.functions.insert(handler.function_id(), Arc::from(handler))
+    }
+
+    /// Remove the handler for `id`. ECALLI with that ID then returns WHAT.
+    pub fn remove(&mut self, id: u32) -> Option<Arc<dyn HostFunction>> {
+        self.functions.remove(&id)
+    }
+
+    #[must_use]
+    pub fn get(&self, id: u32) -> Option<&Arc<dyn HostFunction>> {
+        self.functions.get(&id)
+    }
+
+    /// Override the base gas charged before host call `id` (e.g. for a Gray Paper revision).
+    pub fn set_base_gas(&mut self, id: u32, gas: u64) {
+        self.base_gas.insert(id, gas);
+    }
+
+    /// Drop the override for `id`, falling back to the handler's `base_gas()`.
+    pub fn clear_base_gas(&mut self, id: u32) {
+        self.base_gas.remove(&id);
+    }
+
+    /// Base gas for `id`: the override if set, else the handler's `base_gas()`, else the Gray Paper default.
+    #[must_use]
+    pub fn base_gas(&self, id: u32) -> u64 {
+        self.base_gas
+            .get(&id)
+            .copied()
+            .or_else(|| self.functions.get(&id).map(|handler| handler.base_gas()))
+            .unwrap_or(HOST_CALL_BASE_GAS)
+    }
+
+    /// Replace the allowlist for `kind`.
+    pub fn set_allowlist(&mut self, kind: InvocationKind, ids: impl IntoIterator<Item = u32>) {
+        self.allowlists.insert(kind, ids.into_iter().collect());
+    }
+
+    /// Allow `id` for `kind` (no-op when `kind` has no allowlist).
+    pub fn allow(&mut self, kind: InvocationKind, id: u32) {
+        if let Some(ids) = self.allowlists.get_mut(&kind) {
+            ids.insert(id);
+        }
+    }
+
+    /// Remove the allowlist for `kind`, so any registered host call is allowed.
+    pub fn clear_allowlist(&mut self, kind: InvocationKind) {
+        self.allowlists.remove(&kind);
+    }
+
+    /// True if `kind` may make host call `id` (always true when `kind` has no allowlist).
+    #[must_use]
+    pub fn is_allowed(&self, kind: InvocationKind, id: u32) -> bool {
+        self.allowlists.get(&kind).is_none_or(|ids| ids.contains(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HostFunctionRegistry, InvocationKind};
+    use crate::codec::CompleteServiceAccount;
+    use crate::config::{FUNC_GAS, FUNC_LOG, FUNC_TRANSFER, REG_OK, REG_WHAT};
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+    use crate::types::{MemoryAccessType, Ram};
+
+    /// ECALLI `id`, then TRAP; `gas` left.
+    fn host_call_state(id: u8, gas: u64) -> PvmState {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.load_code(vec![10, id, 0], vec![1, 0, 1], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = gas;
+        state
+    }
+
+    /// Accumulating service 1 with a readable memo at 0x20000, about to TRANSFER 5 to service 2 with gas
+    /// limit `l`.
+    fn transfer_state(l: u64, gas: u64) -> PvmState {
+        let mut state = host_call_state(FUNC_TRANSFER as u8, gas);
+        state.has_accumulation_context = true;
+        state.accumulation_service_id = Some(1);
+        let account = CompleteServiceAccount {
+            balance: 1_000,
+            ..CompleteServiceAccount::default()
+        };
+        state.accumulation_accounts = Some([(1, account.clone()), (2, account)].into());
+        state.ram.init_page(0x2_0000, 0x1000, MemoryAccessType::Read);
+        state.registers[7..11].copy_from_slice(&[2, 5, l, 0x2_0000]);
+        state
+    }
+
+    #[test]
+    fn ids_are_not_truncated_to_u8() {
+        let registry = HostFunctionRegistry::with_defaults();
+        assert!(registry.get(FUNC_LOG).is_some());
+        assert!(registry.get(FUNC_LOG + 256).is_none());
+        assert!(!registry.is_allowed(InvocationKind::Refine, FUNC_LOG + 256));
+    }
+
+    #[test]
+    fn base_gas_override_applies_to_handled_and_rejected_calls() {
+        let mut state = host_call_state(FUNC_GAS as u8, 100);
+        state.host_functions_mut().set_base_gas(FUNC_GAS, 3);
+        assert!(next_step_impl(&mut state));
+        // ECALLI itself costs 1.
+        assert_eq!(state.gas_left, 96);
+
+        let mut state = host_call_state(FUNC_GAS as u8, 100);
+        let registry = state.host_functions_mut();
+        registry.set_base_gas(FUNC_GAS, 7);
+        registry.set_allowlist(InvocationKind::Generic, []);
+        assert!(next_step_impl(&mut state));
+        assert_eq!(state.registers[7], REG_WHAT);
+        assert_eq!(state.gas_left, 92);
+    }
+
+    #[test]
+    fn transfer_charges_base_plus_gas_limit() {
+        let mut state = transfer_state(50, 100);
+        assert!(next_step_impl(&mut state));
+        assert_eq!(state.registers[7], REG_OK);
+        assert_eq!(state.gas_left, 100 - 1 - 10 - 50);
+
+        // Base gas fits, the gas limit does not.
+        let mut state = transfer_state(50, 1 + 10 + 49);
+        assert!(!next_step_impl(&mut state));
+        assert_eq!(state.status, Status::Oog);
+        assert_eq!(state.gas_left, 0);
+    }
+
+    #[test]
+    fn base_gas_beyond_gas_left_is_out_of_gas() {
+        let mut state = host_call_state(FUNC_GAS as u8, 1 + 9);
+        assert!(!next_step_impl(&mut state));
+        assert_eq!(state.status, Status::Oog);
+
+        let mut state = host_call_state(FUNC_GAS as u8, 1 + 9);
+        state.host_functions_mut().set_allowlist(InvocationKind::Generic, []);
+        assert!(!next_step_impl(&mut state));
+        assert_eq!(state.status, Status::Oog);
+        assert_ne!(state.registers[7], REG_WHAT);
+    }
+
+    #[test]
+    fn unregistered_calls_are_charged_base_gas() {
+        let unregistered = 200;
+        let mut state = host_call_state(unregistered, 100);
+        assert!(state.host_functions_mut().get(u32::from(unregistered)).is_none());
+        assert!(next_step_impl(&mut state));
+        assert_eq!(state.registers[7], REG_WHAT);
+        assert_eq!(state.gas_left, 100 - 1 - 10);
+
+        let mut state = host_call_state(unregistered, 1 + 9);
+        assert!(!next_step_impl(&mut state));
+        assert_eq!(state.status, Status::Oog);
+        assert_ne!(state.registers[7], REG_WHAT);
+    }
+}
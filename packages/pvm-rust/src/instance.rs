@@ -0,0 +1,1772 @@
+//! Instance-based NAPI API. Each `PvmInstance` owns its own `PvmState`, so several PVMs can run
+//! side by side in one process without contending on a shared lock.
+//! The free functions in lib.rs are a compatibility shim over a process-wide default instance.
+
+use napi::bindgen_prelude::{BigInt, Buffer};
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::assembler::{AssembleError, AssembledProgram};
+use crate::codec::decode_service_accounts;
+use crate::config::{HOST_CALL_BASE_GAS, RESULT_CODE_HALT, RESULT_CODE_OOG, RESULT_CODE_PANIC};
+use crate::debugger::{run_until_event_impl, StopReason, WatchKind};
+use crate::gas::{GasCharging, GasCostModel, GasEstimate, PathBound};
+use crate::host_functions::HostFunctionRegistry;
+use crate::profiler::{ProfileReport, Profiler};
+use crate::recompiler::is_available as recompiler_available;
+use crate::snapshot::{restore_impl, snapshot_impl};
+use crate::state_wrapper::{
+    accumulate_setup_failure, get_accumulation_context_encoded, get_export_segments,
+    get_result_impl, init_memory_layout_impl, init_page_impl, is_authorized_code_too_big,
+    next_step_impl, prepare_blob_impl, resume_host_call_impl, run_accumulate_impl, run_blob_impl,
+    run_impl, run_is_authorized_impl, set_memory_impl, setup_accumulate_from_preimage,
+    setup_is_authorized_from_preimage, setup_refine_from_preimage, AccumulateOutcome,
+    IsAuthorizedOutcome, PvmState, RAMType, RefineInputs, SetupAccumulateParams,
+    SetupIsAuthorizedParams, SetupRefineParams, Status,
+};
+use crate::trace::TraceWriter;
+use crate::trace_check::{check_trace_impl, TraceCheck};
+use crate::types::{PageDiff, Ram};
+use crate::verifier::{Diagnostic, TargetError};
+
+/// AccumulateInvocationResult returned as object (gasConsumed, resultCode, output, yieldHash, context, postState).
+/// `context` is the updated implications pair (same encoding as getAccumulationContext); `postState` is the
+/// collapsed implications (regular on HALT, exceptional on PANIC/FAULT/OOG). `yieldHash` is empty when there is no yield.
+#[napi(object)]
+pub struct AccumulateInvocationResultOutput {
+    pub gas_consumed: BigInt,
+    pub result_code: u8,
+    pub output: Buffer,
+    pub yield_hash: Buffer,
+    pub context: Buffer,
+    pub post_state: Buffer,
+}
+
+impl From<AccumulateOutcome> for AccumulateInvocationResultOutput {
+    fn from(outcome: AccumulateOutcome) -> Self {
+        Self {
+            gas_consumed: BigInt::from(outcome.gas_consumed),
+            result_code: outcome.result_code,
+            output: outcome.output.into(),
+            yield_hash: outcome.yield_hash.unwrap_or_default().into(),
+            context: outcome.encoded_context.into(),
+            post_state: outcome.encoded_post_state.into(),
+        }
+    }
+}
+
+/// Refine inputs for setupRefineInvocation (Gray Paper Ψ_R: p, i, ī, x̄, r, ς, (p_x)_t, s, δ).
+/// Work-package fields are passed pre-encoded (encodeWorkPackage, encodeWorkItemSummary); `accounts` is
+/// var{sequence{(serviceid, CompleteServiceAccount)}}, the same encoding as ps_accounts.
+#[napi(object)]
+pub struct RefineInvocationInputs {
+    pub work_package: Buffer,
+    pub auth_config: Buffer,
+    pub auth_token: Buffer,
+    pub work_item_summaries: Vec<Buffer>,
+    pub work_item_payloads: Vec<Buffer>,
+    pub work_item_index: u32,
+    pub import_segments: Vec<Vec<Buffer>>,
+    pub extrinsics: Vec<Vec<Buffer>>,
+    pub authorizer_trace: Buffer,
+    pub export_segment_offset: u32,
+    pub lookup_anchor_timeslot: u32,
+    pub service_id: u32,
+    pub accounts: Buffer,
+}
+
+fn buffers_to_vecs(buffers: &[Buffer]) -> Vec<Vec<u8>> {
+    buffers.iter().map(|b| b.as_ref().to_vec()).collect()
+}
+
+impl RefineInvocationInputs {
+    /// None when `accounts` does not decode.
+    fn to_refine_inputs(&self) -> Option<RefineInputs> {
+        let accounts = decode_service_accounts(self.accounts.as_ref())?
+            .value
+            .into_iter()
+            .map(|entry| (u64::from(entry.service_id), entry.account))
+            .collect();
+        Some(RefineInputs {
+            work_package_encoded: self.work_package.as_ref().to_vec(),
+            auth_config: self.auth_config.as_ref().to_vec(),
+            auth_token: self.auth_token.as_ref().to_vec(),
+            work_item_summaries: buffers_to_vecs(&self.work_item_summaries),
+            work_item_payloads: buffers_to_vecs(&self.work_item_payloads),
+            work_item_index: u64::from(self.work_item_index),
+            import_segments: self
+                .import_segments
+                .iter()
+                .map(|s| buffers_to_vecs(s))
+                .collect(),
+            extrinsics: self.extrinsics.iter().map(|x| buffers_to_vecs(x)).collect(),
+            authorizer_trace: self.authorizer_trace.as_ref().to_vec(),
+            export_segment_offset: i64::from(self.export_segment_offset),
+            lookup_anchor_timeslot: u64::from(self.lookup_anchor_timeslot),
+            service_id: u64::from(self.service_id),
+            accounts,
+        })
+    }
+}
+
+/// Work package fields served by FETCH during Is-Authorized (selectors 7–13), pre-encoded like RefineInvocationInputs.
+#[napi(object)]
+pub struct IsAuthorizedInputs {
+    pub work_package: Buffer,
+    pub auth_config: Buffer,
+    pub auth_token: Buffer,
+    pub refine_context: Buffer,
+    pub work_item_summaries: Vec<Buffer>,
+    pub work_item_payloads: Vec<Buffer>,
+}
+
+/// IsAuthorizedResult returned as object (gasConsumed, resultCode, output, error).
+/// `output` is the authorizer trace on HALT. `error` is the Gray Paper work error ("BIG", "OOG", "PANIC"), absent on HALT.
+#[napi(object)]
+pub struct IsAuthorizedResultOutput {
+    pub gas_consumed: BigInt,
+    pub result_code: u8,
+    pub output: Buffer,
+    pub error: Option<String>,
+}
+
+impl From<IsAuthorizedOutcome> for IsAuthorizedResultOutput {
+    fn from(outcome: IsAuthorizedOutcome) -> Self {
+        let error = match outcome.result_code {
+            RESULT_CODE_HALT => None,
+            RESULT_CODE_OOG => Some("OOG".to_string()),
+            _ => Some("PANIC".to_string()),
+        };
+        Self {
+            gas_consumed: BigInt::from(outcome.gas_consumed),
+            result_code: outcome.result_code,
+            output: outcome.output.into(),
+            error,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct RunProgramResultOutput {
+    pub gas_consumed: BigInt,
+    pub result_code: u8,
+}
+
+/// Result of checkTrace. On divergence, `step` is the 1-based instruction line of the reference, `pc`/`opcode` are
+/// the actual values after that step, `mismatches` names the differing fields (`opcode`, `pc`, `gas`, `r0`…`r12`,
+/// `load`, `store`, or `ended` when the program stopped first) and `expected`/`actual` are the two trace lines.
+#[napi(object)]
+pub struct TraceCheckResultOutput {
+    pub matched: bool,
+    /// Reference steps compared (all of them when matched).
+    pub steps: u32,
+    /// Whether the program stopped by the end of the reference (only meaningful when matched).
+    pub finished: bool,
+    pub step: Option<u32>,
+    pub pc: Option<u32>,
+    pub opcode: Option<String>,
+    pub mismatches: Vec<String>,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl From<TraceCheck> for TraceCheckResultOutput {
+    fn from(check: TraceCheck) -> Self {
+        match check {
+            TraceCheck::Matched { steps, finished } => Self {
+                matched: true,
+                steps: steps as u32,
+                finished,
+                step: None,
+                pc: None,
+                opcode: None,
+                mismatches: vec![],
+                expected: None,
+                actual: None,
+            },
+            TraceCheck::Diverged(divergence) => Self {
+                matched: false,
+                steps: divergence.step as u32,
+                finished: false,
+                step: Some(divergence.step as u32),
+                pc: divergence.actual.as_ref().map(|entry| entry.pc),
+                opcode: divergence
+                    .actual
+                    .as_ref()
+                    .map(|entry| entry.name.to_string()),
+                expected: Some(divergence.expected.to_string()),
+                actual: divergence.actual.as_ref().map(ToString::to_string),
+                mismatches: divergence.fields,
+            },
+        }
+    }
+}
+
+/// Stop reason from runUntilEvent. `reason` is one of `breakpoint`, `opcode_breakpoint`, `host_call_breakpoint`,
+/// `watchpoint`, `host_call` (external mode, waiting for resumeHostCall), `halt`, `panic`, `fault`, `oog` or
+/// `step_limit`; `pc` is the breakpoint / faulting instruction's PC or the PC after the stop. The optional fields
+/// are set for the reasons they describe (`address` is the fault or watched-access address).
+#[napi(object)]
+pub struct StopEventOutput {
+    pub reason: String,
+    /// Instructions executed by this call.
+    pub steps: u32,
+    pub pc: u32,
+    pub opcode: Option<u8>,
+    pub host_call_id: Option<u32>,
+    pub address: Option<u32>,
+    pub length: Option<u32>,
+    pub is_write: Option<bool>,
+    pub watchpoint_id: Option<u32>,
+}
+
+impl StopEventOutput {
+    fn new(reason: StopReason, steps: u64, current_pc: u32) -> Self {
+        let mut out = Self {
+            reason: String::new(),
+            steps: steps as u32,
+            pc: current_pc,
+            opcode: None,
+            host_call_id: None,
+            address: None,
+            length: None,
+            is_write: None,
+            watchpoint_id: None,
+        };
+        let name = match reason {
+            StopReason::Breakpoint { pc } => {
+                out.pc = pc;
+                "breakpoint"
+            }
+            StopReason::OpcodeBreakpoint { pc, opcode } => {
+                out.pc = pc;
+                out.opcode = Some(opcode);
+                "opcode_breakpoint"
+            }
+            StopReason::HostCallBreakpoint { pc, host_call_id } => {
+                out.pc = pc;
+                out.host_call_id = Some(host_call_id);
+                "host_call_breakpoint"
+            }
+            StopReason::Watchpoint { pc, hit } => {
+                out.pc = pc;
+                out.address = Some(hit.address);
+                out.length = Some(hit.length);
+                out.is_write = Some(hit.is_write);
+                out.watchpoint_id = Some(hit.watchpoint);
+                "watchpoint"
+            }
+            StopReason::HostCall { pc, host_call_id } => {
+                out.pc = pc;
+                out.host_call_id = Some(host_call_id);
+                "host_call"
+            }
+            StopReason::Halt => "halt",
+            StopReason::Panic => "panic",
+            StopReason::Fault { address } => {
+                out.address = Some(address);
+                "fault"
+            }
+            StopReason::Oog => "oog",
+            StopReason::StepLimit => "step_limit",
+        };
+        out.reason = name.to_string();
+        out
+    }
+}
+
+/// Result of assemble: the deblob-format `blob` (for prepareBlob/runBlob) and the service-code preimage `program`
+/// (metadata + Y-format program with ro/rw data), or `error` ("line N: message") when the source does not assemble.
+#[napi(object)]
+pub struct AssembleResultOutput {
+    pub blob: Option<Buffer>,
+    pub program: Option<Buffer>,
+    pub error: Option<String>,
+}
+
+impl From<Result<AssembledProgram, AssembleError>> for AssembleResultOutput {
+    fn from(result: Result<AssembledProgram, AssembleError>) -> Self {
+        match result {
+            Ok(program) => Self {
+                blob: Some(program.to_blob().into()),
+                program: Some(program.to_preimage().into()),
+                error: None,
+            },
+            Err(error) => Self {
+                blob: None,
+                program: None,
+                error: Some(error.to_string()),
+            },
+        }
+    }
+}
+
+/// Execution profile returned by getProfile. Counts and gas are JS numbers; every list is in ascending key
+/// order and holds only executed entries. Gas of an ECALLI step includes its host function's charge, which
+/// `hostCalls[].gas` also reports on its own. `results` maps outcomes (`OK`, `OOB`, `WHO`, `FULL`, `VALUE` for
+/// other returns, or `PANIC`/`OOG`/… when the call ended the machine) to counts.
+#[napi(object)]
+pub struct ProfileReportOutput {
+    pub instructions: i64,
+    pub gas: i64,
+    pub opcodes: Vec<ProfileOpcodeOutput>,
+    pub pcs: Vec<ProfilePcOutput>,
+    pub blocks: Vec<ProfileBlockOutput>,
+    pub host_calls: Vec<ProfileHostCallOutput>,
+}
+
+#[napi(object)]
+pub struct ProfileOpcodeOutput {
+    pub opcode: u8,
+    pub name: String,
+    pub count: i64,
+    pub gas: i64,
+}
+
+#[napi(object)]
+pub struct ProfilePcOutput {
+    pub pc: u32,
+    pub count: i64,
+    pub gas: i64,
+}
+
+/// Basic block `[start, end)`; `entries` is how often its first instruction ran.
+#[napi(object)]
+pub struct ProfileBlockOutput {
+    pub start: u32,
+    pub end: u32,
+    pub entries: i64,
+    pub instructions: i64,
+    pub gas: i64,
+}
+
+#[napi(object)]
+pub struct ProfileHostCallOutput {
+    pub id: u32,
+    pub name: String,
+    pub calls: i64,
+    pub gas: i64,
+    pub results: HashMap<String, i64>,
+}
+
+impl From<ProfileReport> for ProfileReportOutput {
+    fn from(report: ProfileReport) -> Self {
+        Self {
+            instructions: report.instructions as i64,
+            gas: report.gas as i64,
+            opcodes: report
+                .opcodes
+                .into_iter()
+                .map(|o| ProfileOpcodeOutput {
+                    opcode: o.opcode,
+                    name: o.name,
+                    count: o.count as i64,
+                    gas: o.gas as i64,
+                })
+                .collect(),
+            pcs: report
+                .pcs
+                .into_iter()
+                .map(|p| ProfilePcOutput {
+                    pc: p.pc,
+                    count: p.count as i64,
+                    gas: p.gas as i64,
+                })
+                .collect(),
+            blocks: report
+                .blocks
+                .into_iter()
+                .map(|b| ProfileBlockOutput {
+                    start: b.start,
+                    end: b.end,
+                    entries: b.entries as i64,
+                    instructions: b.instructions as i64,
+                    gas: b.gas as i64,
+                })
+                .collect(),
+            host_calls: report
+                .host_calls
+                .into_iter()
+                .map(|h| ProfileHostCallOutput {
+                    id: h.id,
+                    name: h.name,
+                    calls: h.calls as i64,
+                    gas: h.gas as i64,
+                    results: h.results.into_iter().map(|(r, n)| (r, n as i64)).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Static verifier finding (verifyProgram / getLoadDiagnostics). `kind` is `unknown_opcode`,
+/// `truncated_instruction`, `invalid_branch_target` or `invalid_jump_table_entry`; `reason` says why a target is
+/// invalid (`out_of_range`, `not_instruction_start`, `not_block_start`); `message` is a one-line summary.
+#[napi(object)]
+pub struct ProgramDiagnosticOutput {
+    pub kind: String,
+    pub pc: Option<u32>,
+    pub opcode: Option<u8>,
+    pub target: Option<u32>,
+    pub jump_table_index: Option<u32>,
+    pub reason: Option<String>,
+    pub message: String,
+}
+
+impl From<&Diagnostic> for ProgramDiagnosticOutput {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        let mut out = Self {
+            kind: diagnostic.kind().to_string(),
+            pc: None,
+            opcode: None,
+            target: None,
+            jump_table_index: None,
+            reason: None,
+            message: diagnostic.to_string(),
+        };
+        let reason = |error: TargetError| {
+            Some(
+                match error {
+                    TargetError::OutOfRange => "out_of_range",
+                    TargetError::NotInstructionStart => "not_instruction_start",
+                    TargetError::NotBlockStart => "not_block_start",
+                }
+                .to_string(),
+            )
+        };
+        match *diagnostic {
+            Diagnostic::UnknownOpcode { pc, opcode }
+            | Diagnostic::TruncatedInstruction { pc, opcode, .. } => {
+                out.pc = Some(pc);
+                out.opcode = Some(opcode);
+            }
+            Diagnostic::InvalidBranchTarget { pc, target, error } => {
+                out.pc = Some(pc);
+                out.target = Some(target);
+                out.reason = reason(error);
+            }
+            Diagnostic::InvalidJumpTableEntry {
+                index,
+                target,
+                error,
+            } => {
+                out.jump_table_index = Some(index);
+                out.target = Some(target);
+                out.reason = reason(error);
+            }
+        }
+        out
+    }
+}
+
+/// Static gas estimate (estimateGas). `unitGas` is the current 1-per-instruction model, `modelGas` the cost table.
+#[napi(object)]
+pub struct GasEstimateOutput {
+    pub blocks: Vec<GasBlockOutput>,
+    pub entries: Vec<GasEntryOutput>,
+}
+
+#[napi(object)]
+pub struct GasBlockOutput {
+    pub start: u32,
+    pub end: u32,
+    pub instructions: i64,
+    pub host_calls: i64,
+    pub unit_gas: i64,
+    pub model_gas: i64,
+}
+
+/// Worst case over loop-free paths from `entry`; path gas includes the base gas of each host call.
+#[napi(object)]
+pub struct GasEntryOutput {
+    pub entry: u32,
+    pub unit: GasPathOutput,
+    pub model: GasPathOutput,
+    pub has_loops: bool,
+    pub dynamic_jumps: bool,
+}
+
+#[napi(object)]
+pub struct GasPathOutput {
+    pub gas: i64,
+    pub host_calls: i64,
+    pub path: Vec<u32>,
+}
+
+impl From<PathBound> for GasPathOutput {
+    fn from(bound: PathBound) -> Self {
+        Self {
+            gas: bound.gas as i64,
+            host_calls: bound.host_calls as i64,
+            path: bound.path,
+        }
+    }
+}
+
+impl From<GasEstimate> for GasEstimateOutput {
+    fn from(estimate: GasEstimate) -> Self {
+        Self {
+            blocks: estimate
+                .blocks
+                .into_iter()
+                .map(|b| GasBlockOutput {
+                    start: b.start,
+                    end: b.end,
+                    instructions: b.instructions as i64,
+                    host_calls: b.host_calls as i64,
+                    unit_gas: b.unit_gas as i64,
+                    model_gas: b.model_gas as i64,
+                })
+                .collect(),
+            entries: estimate
+                .entries
+                .into_iter()
+                .map(|e| GasEntryOutput {
+                    entry: e.entry,
+                    unit: e.unit.into(),
+                    model: e.model.into(),
+                    has_loops: e.has_loops,
+                    dynamic_jumps: e.dynamic_jumps,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Changed bytes at `offset` within a page (`old` and `new` have the same length).
+#[napi(object)]
+pub struct MemoryRangeDiffOutput {
+    pub offset: u32,
+    pub old: Buffer,
+    pub new: Buffer,
+}
+
+/// A page written since the dirty set was cleared whose contents differ from the snapshot.
+#[napi(object)]
+pub struct PageDiffOutput {
+    pub page_index: u32,
+    pub ranges: Vec<MemoryRangeDiffOutput>,
+}
+
+impl From<PageDiff> for PageDiffOutput {
+    fn from(diff: PageDiff) -> Self {
+        Self {
+            page_index: diff.page_index,
+            ranges: diff
+                .ranges
+                .into_iter()
+                .map(|r| MemoryRangeDiffOutput {
+                    offset: r.offset,
+                    old: r.old.into(),
+                    new: r.new.into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Gas crosses the NAPI boundary as BigInt (Gray Paper gas is 64-bit; JS numbers lose precision above 2^53).
+/// Negative values clamp to 0 and values above 2^64 - 1 saturate.
+pub fn gas_from_bigint(value: &BigInt) -> u64 {
+    let (negative, gas, lossless) = value.get_u64();
+    if negative {
+        0
+    } else if lossless {
+        gas
+    } else {
+        u64::MAX
+    }
+}
+
+/// Default instance behind the free-function API (init, nextStep, getRegisters, …).
+static DEFAULT_INSTANCE: Mutex<Option<PvmInstance>> = Mutex::new(None);
+
+/// Lock the default instance. `None` until `init` (or `initialize_program`) has been called.
+pub fn default_instance() -> MutexGuard<'static, Option<PvmInstance>> {
+    DEFAULT_INSTANCE.lock().expect("pvm default instance lock")
+}
+
+/// A PVM with its own registers, code, RAM and invocation context.
+#[napi]
+pub struct PvmInstance {
+    state: PvmState,
+}
+
+impl PvmInstance {
+    #[must_use]
+    pub fn with_ram_type(ram_type: i32) -> Self {
+        Self {
+            state: PvmState::new(ram_type),
+        }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> &PvmState {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut PvmState {
+        &mut self.state
+    }
+
+    /// This instance's host functions and allowlists (copy-on-write from the shared default).
+    pub fn host_functions_mut(&mut self) -> &mut HostFunctionRegistry {
+        self.state.host_functions_mut()
+    }
+}
+
+#[napi]
+impl PvmInstance {
+    /// Create an instance; ram_type defaults to PvmRam (0).
+    #[napi(constructor)]
+    pub fn new(ram_type: Option<i32>) -> Self {
+        Self::with_ram_type(ram_type.unwrap_or(RAMType::PvmRam as i32))
+    }
+
+    /// Replace this instance's state with a fresh one backed by the given RAM type.
+    #[napi]
+    pub fn init(&mut self, ram_type: i32) {
+        self.state = PvmState::new(ram_type);
+    }
+
+    #[napi]
+    pub fn reset(&mut self) {
+        self.state.reset_program_state();
+    }
+
+    #[napi]
+    pub fn reset_generic(&mut self, _program: Buffer, _registers: Buffer, _gas: BigInt) {
+        self.state.reset_program_state();
+    }
+
+    #[napi]
+    pub fn reset_generic_with_memory(
+        &mut self,
+        _program_ptr: Buffer,
+        _registers_ptr: Buffer,
+        _page_map_ptr: Buffer,
+        _chunks_ptr: Buffer,
+        _gas: BigInt,
+    ) {
+        self.state.reset_program_state();
+    }
+
+    #[napi]
+    pub fn next_step(&mut self) -> bool {
+        next_step_impl(&mut self.state)
+    }
+
+    #[napi]
+    pub fn n_steps(&mut self, steps: i32) -> bool {
+        let n = steps.max(0) as u32;
+        for _ in 0..n {
+            if !next_step_impl(&mut self.state) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// External host-call mode: ECALLI stops with status HOST (see getHostCallId) instead of dispatching to the
+    /// built-in host functions. Inspect or modify registers and memory, then call resumeHostCall.
+    #[napi]
+    pub fn set_external_host_calls(&mut self, enabled: bool) {
+        self.state.external_host_calls = enabled;
+    }
+
+    /// Continue after an externally serviced ECALLI, charging `gas_cost` (default 10). False if nothing is pending or OOG.
+    #[napi]
+    pub fn resume_host_call(&mut self, gas_cost: Option<BigInt>) -> bool {
+        let gas_cost = gas_cost
+            .as_ref()
+            .map_or(HOST_CALL_BASE_GAS, gas_from_bigint);
+        resume_host_call_impl(&mut self.state, gas_cost)
+    }
+
+    /// Run runProgram/runBlob/invocations with the x86-64 recompiler. Returns whether it is in use
+    /// (false when disabled or unsupported on this platform). nextStep/nSteps always interpret.
+    #[napi]
+    pub fn set_use_recompiler(&mut self, enabled: bool) -> bool {
+        self.state.use_recompiler = enabled && recompiler_available();
+        self.state.use_recompiler
+    }
+
+    /// Charge gas per basic block instead of per instruction: on entering a block its cost is taken at once, and
+    /// OOG is reported before any of it runs. `costs[opcode]` is the per-instruction cost (missing opcodes cost 1;
+    /// without a table every instruction costs 1). Disables the recompiler while on. Kept across resets.
+    #[napi]
+    pub fn set_block_gas(&mut self, enabled: bool, costs: Option<Vec<u32>>) {
+        self.state.gas_charging = if enabled {
+            GasCharging::Block(costs.map_or(GasCostModel::Unit, |costs| {
+                GasCostModel::from_costs(&costs.into_iter().map(u64::from).collect::<Vec<_>>())
+            }))
+        } else {
+            GasCharging::Instruction
+        };
+        self.state.block_gas.clear();
+    }
+
+    /// Stop runUntilEvent before executing the instruction at `pc`.
+    #[napi]
+    pub fn add_breakpoint(&mut self, pc: u32) {
+        self.state.debugger.pc_breakpoints.insert(pc);
+    }
+
+    #[napi]
+    pub fn remove_breakpoint(&mut self, pc: u32) -> bool {
+        self.state.debugger.pc_breakpoints.remove(&pc)
+    }
+
+    /// Stop runUntilEvent before executing any instruction with this opcode.
+    #[napi]
+    pub fn add_opcode_breakpoint(&mut self, opcode: u8) {
+        self.state.debugger.opcode_breakpoints.insert(opcode);
+    }
+
+    #[napi]
+    pub fn remove_opcode_breakpoint(&mut self, opcode: u8) -> bool {
+        self.state.debugger.opcode_breakpoints.remove(&opcode)
+    }
+
+    /// Stop runUntilEvent before an ECALLI with this host call ID is dispatched.
+    #[napi]
+    pub fn add_host_call_breakpoint(&mut self, host_call_id: u32) {
+        self.state
+            .debugger
+            .host_call_breakpoints
+            .insert(host_call_id);
+    }
+
+    #[napi]
+    pub fn remove_host_call_breakpoint(&mut self, host_call_id: u32) -> bool {
+        self.state
+            .debugger
+            .host_call_breakpoints
+            .remove(&host_call_id)
+    }
+
+    /// Stop runUntilEvent after an instruction or host call reads (kind 1), writes (2) or does either (3) within
+    /// `[address, address + length)`. Returns the watchpoint ID, or None for an unknown kind.
+    #[napi]
+    pub fn add_watchpoint(&mut self, address: u32, length: u32, kind: u8) -> Option<u32> {
+        let kind = WatchKind::from_u8(kind)?;
+        Some(self.state.debugger.add_watchpoint(address, length, kind))
+    }
+
+    #[napi]
+    pub fn remove_watchpoint(&mut self, id: u32) -> bool {
+        self.state.debugger.remove_watchpoint(id)
+    }
+
+    /// Remove all breakpoints and watchpoints.
+    #[napi]
+    pub fn clear_breakpoints(&mut self) {
+        self.state.debugger.clear();
+    }
+
+    /// Step until a breakpoint, watchpoint, host call (external mode) or exit, or `max_steps` instructions.
+    /// A breakpoint at the current PC is stepped over, so calling again continues.
+    #[napi]
+    pub fn run_until_event(&mut self, max_steps: Option<u32>) -> StopEventOutput {
+        let (reason, steps) = run_until_event_impl(&mut self.state, max_steps.map(u64::from));
+        StopEventOutput::new(reason, steps, self.state.program_counter)
+    }
+
+    /// Record every nextStep (and runProgram/runBlob/invocations, which then interpret) in the writeTraceDump
+    /// line format: into `path` when given (truncated), else into a buffer read with takeTrace. Disabling flushes
+    /// and closes the file. Returns false if the file cannot be created.
+    #[napi]
+    pub fn set_trace(&mut self, enabled: bool, path: Option<String>) -> bool {
+        if let Some(mut trace) = self.state.trace.take() {
+            trace.flush();
+        }
+        if !enabled {
+            return true;
+        }
+        let trace = match path {
+            Some(path) => match TraceWriter::file(&path) {
+                Ok(trace) => trace,
+                Err(_) => return false,
+            },
+            None => TraceWriter::buffer(),
+        };
+        self.state.trace = Some(trace);
+        true
+    }
+
+    /// Trace lines buffered since the last call (empty when tracing to a file or disabled).
+    #[napi]
+    pub fn take_trace(&mut self) -> String {
+        self.state
+            .trace
+            .as_mut()
+            .map(TraceWriter::take)
+            .unwrap_or_default()
+    }
+
+    /// Run the static verifier whenever a program is loaded (prepareBlob, runBlob, setup*Invocation); read the
+    /// findings with getLoadDiagnostics. Execution is unaffected. Kept across resets.
+    #[napi]
+    pub fn set_verify_on_load(&mut self, enabled: bool) {
+        self.state.verify_on_load = enabled;
+    }
+
+    /// Verifier findings for the loaded program (empty when none, or when verification on load is off).
+    #[napi]
+    pub fn get_load_diagnostics(&self) -> Vec<ProgramDiagnosticOutput> {
+        self.state.load_diagnostics.iter().map(Into::into).collect()
+    }
+
+    /// Count steps and gas per opcode, PC/basic block and host function (see getProfile). Enabling clears the
+    /// counters, as does loading a program; while enabled, runProgram/runBlob/invocations interpret.
+    #[napi]
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.state.profiler = enabled.then(Profiler::default);
+    }
+
+    /// Profile of the current program so far, or null when profiling is disabled.
+    #[napi]
+    pub fn get_profile(&self) -> Option<ProfileReportOutput> {
+        let profiler = self.state.profiler.as_ref()?;
+        Some(profiler.report(&self.state).into())
+    }
+
+    /// Step from the current state (after prepareBlob or a setup*Invocation) while comparing every step with a
+    /// reference trace in the writeTraceDump format, stopping at the first mismatch.
+    #[napi]
+    pub fn check_trace(&mut self, reference: String) -> TraceCheckResultOutput {
+        check_trace_impl(&mut self.state, &reference).into()
+    }
+
+    /// Versioned binary snapshot of the whole machine: registers, PC, gas, status, code, every RAM page with
+    /// its access rights, and the invocation context (accounts, implications, transfers, provisions, yield, guests).
+    #[napi]
+    pub fn snapshot(&self) -> Buffer {
+        snapshot_impl(&self.state).into()
+    }
+
+    /// Replace this instance's state with a snapshot. Host functions registered on this instance are kept.
+    /// Returns false (state unchanged) when the blob is malformed or from another snapshot version.
+    #[napi]
+    pub fn restore(&mut self, blob: Buffer) -> bool {
+        let Some(mut state) = restore_impl(blob.as_ref()) else {
+            return false;
+        };
+        state.host_functions = Arc::clone(&self.state.host_functions);
+        state.trace = self.state.trace.take();
+        state.debugger = std::mem::take(&mut self.state.debugger);
+        state.profiler = self.state.profiler.take();
+        state.verify_on_load = self.state.verify_on_load;
+        state.gas_charging = std::mem::take(&mut self.state.gas_charging);
+        self.state = state;
+        true
+    }
+
+    /// Guest pages written since the last clearDirtyPages (or since memory was set up), ascending.
+    #[napi]
+    pub fn get_dirty_pages(&self) -> Vec<u32> {
+        self.state.ram.dirty_pages()
+    }
+
+    #[napi]
+    pub fn clear_dirty_pages(&mut self) {
+        self.state.ram.clear_dirty();
+    }
+
+    /// Byte ranges of the dirty pages that differ from the memory in `snapshot` (a snapshot() blob taken when the
+    /// dirty set was cleared). Null when the blob is malformed or from another snapshot version.
+    #[napi]
+    pub fn memory_diff(&self, snapshot: Buffer) -> Option<Vec<PageDiffOutput>> {
+        let baseline = restore_impl(snapshot.as_ref())?;
+        Some(
+            self.state
+                .ram
+                .memory_diff(&baseline.ram)
+                .into_iter()
+                .map(PageDiffOutput::from)
+                .collect(),
+        )
+    }
+
+    /// New instance with a copy of this machine. RAM pages are shared copy-on-write (PvmRAM), so this is cheap
+    /// for large memories: speculative accumulation can run on the fork and drop it. Tracing is not carried over.
+    /// Null when the RAM cannot be copied (MmapRAM without address space left for another reservation).
+    #[napi]
+    pub fn fork(&self) -> Option<PvmInstance> {
+        Some(Self {
+            state: self.state.fork()?,
+        })
+    }
+
+    #[napi]
+    pub fn run_blob(&mut self, program: Buffer) {
+        run_blob_impl(&mut self.state, program.as_ref());
+    }
+
+    #[napi]
+    pub fn prepare_blob(&mut self, program: Buffer) {
+        prepare_blob_impl(&mut self.state, program.as_ref());
+    }
+
+    /// One-shot Ψ_A: set up from the preimage, run to completion and collapse the implications natively.
+    /// Takes the same arguments as setupAccumulateInvocation.
+    #[napi]
+    #[allow(clippy::too_many_arguments)]
+    pub fn accumulate_invocation(
+        &mut self,
+        gas_limit: BigInt,
+        program: Buffer,
+        args: Buffer,
+        context: Buffer,
+        num_cores: i32,
+        num_validators: i32,
+        auth_queue_size: i32,
+        entropy_accumulator: Buffer,
+        encoded_work_items: Buffer,
+        encoded_accumulate_inputs: Option<Vec<Buffer>>,
+        config_preimage_expunge_period: u32,
+        config_epoch_duration: u32,
+        config_max_block_gas: i64,
+        config_max_refine_gas: i64,
+        config_max_tickets_per_extrinsic: u16,
+        config_tickets_per_validator: u16,
+        config_slot_duration: u16,
+        config_rotation_period: u16,
+        config_num_ec_pieces_per_segment: u32,
+        config_contest_duration: u32,
+        config_max_lookup_anchorage: u32,
+        config_ec_piece_size: u32,
+    ) -> AccumulateInvocationResultOutput {
+        let gas = gas_from_bigint(&gas_limit);
+        let encoded_context = context.as_ref().to_vec();
+        self.state.reset_program_state();
+        let ready = self.setup_accumulate_invocation(
+            gas_limit,
+            program,
+            args,
+            context,
+            num_cores,
+            num_validators,
+            auth_queue_size,
+            entropy_accumulator,
+            encoded_work_items,
+            encoded_accumulate_inputs,
+            config_preimage_expunge_period,
+            config_epoch_duration,
+            config_max_block_gas,
+            config_max_refine_gas,
+            config_max_tickets_per_extrinsic,
+            config_tickets_per_validator,
+            config_slot_duration,
+            config_rotation_period,
+            config_num_ec_pieces_per_segment,
+            config_contest_duration,
+            config_max_lookup_anchorage,
+            config_ec_piece_size,
+        );
+        let outcome = if ready {
+            run_accumulate_impl(
+                &mut self.state,
+                gas,
+                num_cores,
+                num_validators,
+                auth_queue_size,
+            )
+        } else {
+            accumulate_setup_failure(&encoded_context, num_cores, num_validators, auth_queue_size)
+        };
+        outcome.into()
+    }
+
+    #[napi]
+    #[allow(clippy::too_many_arguments)]
+    pub fn setup_accumulate_invocation(
+        &mut self,
+        gas_limit: BigInt,
+        program: Buffer,
+        args: Buffer,
+        context: Buffer,
+        num_cores: i32,
+        num_validators: i32,
+        auth_queue_size: i32,
+        entropy_accumulator: Buffer,
+        encoded_work_items: Buffer,
+        encoded_accumulate_inputs: Option<Vec<Buffer>>,
+        config_preimage_expunge_period: u32,
+        config_epoch_duration: u32,
+        config_max_block_gas: i64,
+        config_max_refine_gas: i64,
+        config_max_tickets_per_extrinsic: u16,
+        config_tickets_per_validator: u16,
+        config_slot_duration: u16,
+        config_rotation_period: u16,
+        config_num_ec_pieces_per_segment: u32,
+        config_contest_duration: u32,
+        config_max_lookup_anchorage: u32,
+        config_ec_piece_size: u32,
+    ) -> bool {
+        let encoded_inputs =
+            encoded_accumulate_inputs.map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
+        let params = SetupAccumulateParams {
+            program: program.as_ref(),
+            args: args.as_ref(),
+            encoded_context: context.as_ref(),
+            gas_limit: gas_from_bigint(&gas_limit),
+            num_cores: num_cores.max(0) as u32,
+            num_validators: num_validators.max(0) as u32,
+            auth_queue_size: auth_queue_size.max(0) as u32,
+            entropy_accumulator: entropy_accumulator.as_ref(),
+            encoded_work_items: encoded_work_items.as_ref(),
+            encoded_accumulate_inputs: encoded_inputs,
+            config_preimage_expunge_period,
+            config_epoch_duration,
+            config_max_block_gas: config_max_block_gas.max(0) as u64,
+            config_max_refine_gas: config_max_refine_gas.max(0) as u64,
+            config_max_tickets_per_extrinsic,
+            config_tickets_per_validator,
+            config_slot_duration,
+            config_rotation_period,
+            config_num_ec_pieces_per_segment,
+            config_contest_duration,
+            config_max_lookup_anchorage,
+            config_ec_piece_size,
+        };
+        setup_accumulate_from_preimage(&mut self.state, params)
+    }
+
+    #[napi]
+    pub fn setup_refine_invocation(
+        &mut self,
+        gas_limit: BigInt,
+        program: Buffer,
+        args: Buffer,
+        refine_context_encoded: Buffer,
+        config_max_refine_gas: i64,
+        inputs: Option<RefineInvocationInputs>,
+    ) -> bool {
+        let inputs = match inputs
+            .as_ref()
+            .map(RefineInvocationInputs::to_refine_inputs)
+        {
+            Some(None) => {
+                self.state.status = Status::Panic;
+                self.state.result_code = RESULT_CODE_PANIC;
+                return false;
+            }
+            decoded => decoded.flatten(),
+        };
+        let params = SetupRefineParams {
+            program: program.as_ref(),
+            args: args.as_ref(),
+            refine_context_encoded: refine_context_encoded.as_ref(),
+            gas_limit: gas_from_bigint(&gas_limit),
+            config_max_refine_gas: config_max_refine_gas.max(0) as u64,
+            inputs,
+        };
+        setup_refine_from_preimage(&mut self.state, params)
+    }
+
+    /// Ψ_I setup: loads the authorizer code with Cpackageauthgas and E_2(core_index) as arguments.
+    /// Returns false when the code exceeds Cmaxauthcodesize or does not decode.
+    #[napi]
+    pub fn setup_is_authorized_invocation(
+        &mut self,
+        program: Buffer,
+        core_index: u16,
+        inputs: IsAuthorizedInputs,
+    ) -> bool {
+        let params = SetupIsAuthorizedParams {
+            program: program.as_ref(),
+            core_index,
+            work_package_encoded: inputs.work_package.as_ref(),
+            auth_config: inputs.auth_config.as_ref(),
+            auth_token: inputs.auth_token.as_ref(),
+            refine_context_encoded: inputs.refine_context.as_ref(),
+            work_item_summaries: buffers_to_vecs(&inputs.work_item_summaries),
+            work_item_payloads: buffers_to_vecs(&inputs.work_item_payloads),
+        };
+        setup_is_authorized_from_preimage(&mut self.state, params)
+    }
+
+    /// One-shot Ψ_I: set up, run to completion and return the authorizer trace.
+    #[napi]
+    pub fn is_authorized_invocation(
+        &mut self,
+        program: Buffer,
+        core_index: u16,
+        inputs: IsAuthorizedInputs,
+    ) -> IsAuthorizedResultOutput {
+        if is_authorized_code_too_big(program.as_ref()) {
+            return IsAuthorizedResultOutput {
+                gas_consumed: BigInt::from(0u64),
+                result_code: RESULT_CODE_PANIC,
+                output: Vec::new().into(),
+                error: Some("BIG".to_string()),
+            };
+        }
+        self.state.reset_program_state();
+        if !self.setup_is_authorized_invocation(program, core_index, inputs) {
+            return IsAuthorizedOutcome {
+                gas_consumed: 0,
+                result_code: RESULT_CODE_PANIC,
+                output: vec![],
+            }
+            .into();
+        }
+        run_is_authorized_impl(&mut self.state).into()
+    }
+
+    #[napi]
+    pub fn get_export_segments(&self) -> Vec<Buffer> {
+        get_export_segments(&self.state)
+            .into_iter()
+            .map(Buffer::from)
+            .collect()
+    }
+
+    #[napi]
+    pub fn set_accumulate_inputs(&mut self, inputs: Option<Vec<Buffer>>) {
+        self.state.accumulate_inputs_encoded = inputs
+            .map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect())
+            .unwrap_or_default();
+    }
+
+    #[napi]
+    pub fn set_fetch_work_package(&mut self, encoded: Option<Buffer>) {
+        self.state.work_package_encoded = encoded.map(|b| b.as_ref().to_vec());
+    }
+
+    #[napi]
+    pub fn set_fetch_auth_config(&mut self, data: Option<Buffer>) {
+        self.state.auth_config = data.map(|b| b.as_ref().to_vec());
+    }
+
+    #[napi]
+    pub fn set_fetch_auth_token(&mut self, data: Option<Buffer>) {
+        self.state.auth_token = data.map(|b| b.as_ref().to_vec());
+    }
+
+    #[napi]
+    pub fn set_fetch_refine_context(&mut self, encoded: Option<Buffer>) {
+        self.state.refine_context_encoded = encoded.map(|b| b.as_ref().to_vec());
+    }
+
+    #[napi]
+    pub fn set_fetch_work_item_summaries(&mut self, summaries: Option<Vec<Buffer>>) {
+        self.state.work_item_summaries =
+            summaries.map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
+    }
+
+    #[napi]
+    pub fn set_fetch_work_item_payloads(&mut self, payloads: Option<Vec<Buffer>>) {
+        self.state.work_item_payloads =
+            payloads.map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
+    }
+
+    #[napi]
+    pub fn run_program(&mut self) -> RunProgramResultOutput {
+        let gas_before = self.state.gas_left;
+        run_impl(&mut self.state);
+        RunProgramResultOutput {
+            gas_consumed: BigInt::from(gas_before.saturating_sub(self.state.gas_left)),
+            result_code: self.state.result_code,
+        }
+    }
+
+    #[napi]
+    pub fn get_program_counter(&self) -> u32 {
+        self.state.program_counter
+    }
+
+    #[napi]
+    pub fn set_next_program_counter(&mut self, pc: u32) {
+        self.state.program_counter = pc;
+    }
+
+    #[napi]
+    pub fn get_gas_left(&self) -> BigInt {
+        BigInt::from(self.state.gas_left)
+    }
+
+    #[napi]
+    pub fn set_gas_left(&mut self, gas: BigInt) {
+        self.state.gas_left = gas_from_bigint(&gas);
+    }
+
+    #[napi]
+    pub fn get_status(&self) -> i32 {
+        self.state.status as i32
+    }
+
+    #[napi]
+    pub fn get_exit_arg(&self) -> u32 {
+        self.state.exit_arg
+    }
+
+    #[napi]
+    pub fn get_host_call_id(&self) -> u32 {
+        self.state.host_call_id
+    }
+
+    #[napi]
+    pub fn get_result_code(&self) -> u32 {
+        u32::from(self.state.result_code)
+    }
+
+    /// Gray Paper equation 831: When HALT, result is the memory range [registers[7], registers[7]+registers[8]].
+    #[napi]
+    pub fn get_result(&mut self) -> Buffer {
+        get_result_impl(&mut self.state).into()
+    }
+
+    /// Returns the 32-byte accumulation yield hash when set (e.g. by YIELD host).
+    /// Used when get_result() is empty (r7/r8 not set) but the run halted successfully with a yield.
+    #[napi]
+    pub fn get_yield_hash(&self) -> Buffer {
+        self.state
+            .yield_hash
+            .as_ref()
+            .filter(|h| h.len() == 32)
+            .cloned()
+            .unwrap_or_default()
+            .into()
+    }
+
+    #[napi]
+    pub fn get_last_opcode(&self) -> u32 {
+        u32::from(self.state.last_opcode)
+    }
+
+    #[napi]
+    pub fn get_last_load_address(&self) -> u32 {
+        self.state.last_load_address
+    }
+
+    /// Returns last load value as BigInt so JS gets full u64 precision.
+    #[napi]
+    pub fn get_last_load_value(&self) -> BigInt {
+        BigInt::from(self.state.last_load_value)
+    }
+
+    #[napi]
+    pub fn get_last_store_address(&self) -> u32 {
+        self.state.last_store_address
+    }
+
+    /// Returns last store value as BigInt so JS gets full u64 precision.
+    #[napi]
+    pub fn get_last_store_value(&self) -> BigInt {
+        BigInt::from(self.state.last_store_value)
+    }
+
+    #[napi]
+    pub fn clear_last_memory_op(&mut self) {
+        self.state.last_load_address = 0;
+        self.state.last_load_value = 0;
+        self.state.last_store_address = 0;
+        self.state.last_store_value = 0;
+    }
+
+    /// Returns LOG host function messages since last drain and clears the queue.
+    #[napi]
+    pub fn get_and_clear_log_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.state.log_messages)
+    }
+
+    #[napi]
+    pub fn get_code(&self) -> Buffer {
+        self.state.code.clone().into()
+    }
+
+    #[napi]
+    pub fn get_bitmask(&self) -> Buffer {
+        self.state.bitmask.clone().into()
+    }
+
+    #[napi]
+    pub fn get_registers(&self) -> Buffer {
+        let mut out = vec![0u8; 13 * 8];
+        for (i, &r) in self.state.registers.iter().enumerate() {
+            let start = i * 8;
+            out[start..start + 8].copy_from_slice(&r.to_le_bytes());
+        }
+        out.into()
+    }
+
+    #[napi]
+    pub fn set_registers(&mut self, registers: Buffer) {
+        let buf = registers.as_ref();
+        for (i, chunk) in buf.chunks_exact(8).take(13).enumerate() {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(chunk);
+            self.state.registers[i] = u64::from_le_bytes(bytes);
+        }
+    }
+
+    /// Single-register get; returns BigInt to match AS (getRegister returns u64 → bigint).
+    #[napi]
+    pub fn get_register(&self, index: u8) -> BigInt {
+        if index >= 13 {
+            return BigInt::from(0u64);
+        }
+        BigInt::from(self.state.registers[index as usize])
+    }
+
+    /// Single-register set; accepts BigInt to match AS (setRegister takes u64/bigint).
+    #[napi]
+    pub fn set_register(&mut self, index: u8, value: BigInt) {
+        if index >= 13 {
+            return;
+        }
+        let (_, val, lossless) = value.get_u64();
+        if !lossless {
+            return;
+        }
+        self.state.registers[index as usize] = val;
+    }
+
+    #[napi]
+    pub fn get_page_dump(&self, page_index: i32) -> Buffer {
+        self.state.ram.get_page_dump(page_index as u32).into()
+    }
+
+    /// Read `length` bytes at `address`; null if any byte is not readable.
+    #[napi]
+    pub fn read_memory(&mut self, address: u32, length: u32) -> Option<Buffer> {
+        let read_result = self.state.ram.read_octets(address, length);
+        if read_result.fault_address != 0 {
+            return None;
+        }
+        read_result.data.map(Buffer::from)
+    }
+
+    #[napi]
+    pub fn set_memory(&mut self, address: u32, data: Buffer) {
+        set_memory_impl(&mut self.state, address, data.as_ref());
+    }
+
+    /// Full encoded implications pair (with updated accounts + yield), matching WASM getAccumulationContext.
+    /// Falls back to the 32-byte yield hash when there is no accumulation context.
+    #[napi]
+    pub fn get_accumulation_context(
+        &self,
+        num_cores: i32,
+        num_validators: i32,
+        auth_queue_size: i32,
+    ) -> Buffer {
+        if let Some(encoded) = get_accumulation_context_encoded(
+            &self.state,
+            num_cores,
+            num_validators,
+            auth_queue_size,
+        ) {
+            return encoded.into();
+        }
+        self.get_yield_hash()
+    }
+
+    #[napi]
+    pub fn has_accumulation_context(&self) -> bool {
+        self.state.has_accumulation_context
+    }
+
+    #[napi]
+    pub fn init_page(&mut self, address: u32, length: u32, access_type: u8) {
+        init_page_impl(&mut self.state, address, length, access_type);
+    }
+
+    /// Initialize memory layout (Gray Paper 770–802). Use with PvmRam after prepareBlob so heap at 0x20000 etc. exist.
+    #[napi]
+    pub fn init_memory_layout(
+        &mut self,
+        argument_data: Buffer,
+        read_only_data: Buffer,
+        read_write_data: Buffer,
+        stack_size: u32,
+        heap_zero_padding_size: u32,
+    ) {
+        init_memory_layout_impl(
+            &mut self.state,
+            argument_data.as_ref(),
+            read_only_data.as_ref(),
+            read_write_data.as_ref(),
+            stack_size,
+            heap_zero_padding_size,
+        );
+    }
+
+    /// No-op on an instance (state always exists); kept for API parity with the free function.
+    #[napi]
+    pub fn initialize_program(&mut self, _program: Buffer, _args: Buffer) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::codec::{
+        decode_implications, encode_implications_pair, Implications, ImplicationsPair,
+    };
+    use crate::config::{
+        DEFAULT_GAS_LIMIT, MAX_AUTH_CODE_SIZE, PACKAGE_AUTH_GAS, REG_WHAT, RESULT_CODE_HOST,
+    };
+    use crate::state_wrapper::RamEnum;
+
+    /// deblob: c = [LOAD_IMM r1, `value`; TRAP], k = 0b1001.
+    fn load_imm_then_trap(value: u8) -> Buffer {
+        vec![0, 0, 4, 51, 1, value, 0, 0b1001].into()
+    }
+
+    #[test]
+    fn instances_do_not_share_state() {
+        let mut a = PvmInstance::new(None);
+        let mut b = PvmInstance::new(Some(RAMType::SimpleRAM as i32));
+        a.prepare_blob(load_imm_then_trap(7));
+        b.prepare_blob(load_imm_then_trap(5));
+        a.state.gas_left = 100;
+        a.set_register(2, BigInt::from(9u64));
+        a.set_memory(0x20000, vec![1, 2, 3].into());
+        assert!(a.next_step());
+
+        // b saw none of a's registers, memory, gas or progress.
+        assert_eq!(b.get_program_counter(), 0);
+        assert_eq!(b.get_register(1).get_u64().1, 0);
+        assert_eq!(b.get_register(2).get_u64().1, 0);
+        assert_eq!(b.state.gas_left, DEFAULT_GAS_LIMIT);
+        assert!(b.get_page_dump(0x20).iter().all(|&byte| byte == 0));
+
+        // Running b leaves a where it stopped.
+        assert!(b.next_step());
+        assert!(!b.next_step());
+        assert_eq!(b.get_register(1).get_u64().1, 5);
+        assert_eq!(b.state.status, Status::Panic);
+        assert_eq!(a.state.status, Status::Ok);
+        assert_eq!(
+            (a.get_program_counter(), a.get_register(1).get_u64().1),
+            (3, 7)
+        );
+        assert_eq!(a.state.gas_left, 99);
+        assert_eq!(&a.get_page_dump(0x20)[..3], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn fork_keeps_the_ram_backend_and_copies_memory() {
+        for ram_type in [RAMType::PvmRam, RAMType::SimpleRAM, RAMType::MmapRAM] {
+            let mut original = PvmInstance::new(Some(ram_type as i32));
+            if ram_type == RAMType::MmapRAM && !matches!(original.state.ram, RamEnum::Mmap(_)) {
+                continue; // address space reservation unsupported here
+            }
+            original.set_memory(0x20000, vec![1, 2, 3].into());
+            let Some(mut fork) = original.fork() else {
+                continue; // no room for a second MmapRam reservation
+            };
+            assert_eq!(fork.state.ram_type, original.state.ram_type);
+            assert_eq!(
+                std::mem::discriminant(&fork.state.ram),
+                std::mem::discriminant(&original.state.ram)
+            );
+            fork.set_memory(0x20000, vec![9].into());
+            assert_eq!(&original.get_page_dump(0x20)[..3], &[1, 2, 3]);
+            assert_eq!(&fork.get_page_dump(0x20)[..3], &[9, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn run_program_counts_gas_from_the_budget_it_started_with() {
+        let mut instance = PvmInstance::new(None);
+        instance.prepare_blob(load_imm_then_trap(7));
+        instance.set_gas_left(BigInt::from(5_000_000_000u64));
+        let result = instance.run_program();
+        assert_eq!(result.result_code, RESULT_CODE_PANIC);
+        assert_eq!(result.gas_consumed.get_u64(), (false, 2, true));
+        assert_eq!(instance.get_gas_left().get_u64().1, 4_999_999_998);
+    }
+
+    /// Preimage of a program with two 32-byte hashes (0x11…, 0x22…) in read-only data at 0x10000 and
+    /// 0x10020, whose code is five FALLTHROUGHs (up to the accumulate entry PC) then `body`.
+    fn accumulate_preimage(body: &[&[u8]]) -> Vec<u8> {
+        let (mut code, mut bits) = (vec![1; 5], vec![1; 5]);
+        for instruction in body {
+            code.extend_from_slice(instruction);
+            bits.push(1);
+            bits.resize(code.len(), 0);
+        }
+        // deblob: |j| = 0, z = 0, |c| < 128
+        let mut blob = vec![0, 0, code.len() as u8];
+        blob.extend(&code);
+        blob.extend(
+            bits.chunks(8)
+                .map(|k| k.iter().rev().fold(0, |byte, &bit| byte << 1 | bit)),
+        );
+        // metadata length 0; |o| = 64, |w| = 0, z = 0, s = 4096
+        let mut preimage = vec![0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0];
+        preimage.extend([0x11; 32]);
+        preimage.extend([0x22; 32]);
+        preimage.extend((blob.len() as u32).to_le_bytes());
+        preimage.extend(blob);
+        preimage
+    }
+
+    /// Ψ_A of `body` for service 1.
+    fn accumulate(body: &[&[u8]], gas: u64) -> AccumulateInvocationResultOutput {
+        let service = Implications {
+            id: 1,
+            ..Implications::default()
+        };
+        let context = encode_implications_pair(
+            &ImplicationsPair {
+                regular: service.clone(),
+                exceptional: service,
+            },
+            0,
+            0,
+            0,
+        );
+        let mut instance = PvmInstance::new(None);
+        instance.accumulate_invocation(
+            BigInt::from(gas),
+            accumulate_preimage(body).into(),
+            vec![].into(),
+            context.into(),
+            0,
+            0,
+            0,
+            vec![0; 32].into(),
+            vec![].into(),
+            None,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        )
+    }
+
+    fn post_state_yield(result: &AccumulateInvocationResultOutput) -> Option<Vec<u8>> {
+        decode_implications(result.post_state.as_ref(), 0, 0, 0)
+            .expect("post state decodes")
+            .value
+            .yield_hash
+    }
+
+    #[test]
+    fn accumulate_collapses_to_regular_on_halt_and_to_exceptional_on_panic_or_oog() {
+        let (first, second) = (vec![0x11; 32], vec![0x22; 32]);
+        // LOAD_IMM r7, 0x10000 / 0x10020
+        let (hash_first, hash_second): (&[u8], &[u8]) = (&[51, 7, 0, 0, 1], &[51, 7, 32, 0, 1]);
+        // ECALLI 25 (YIELD), ECALLI 17 (CHECKPOINT), TRAP
+        let (yield_, checkpoint, trap): (&[u8], &[u8], &[u8]) = (&[10, 25], &[10, 17], &[0]);
+        // LOAD_IMM r7, 0; LOAD_IMM r8, 0; JUMP_IND r0, 0 (r0 holds the halt address)
+        let halt: [&[u8]; 3] = [&[51, 7], &[51, 8], &[50, 0]];
+        // FALLTHROUGH ends the block so the next FALLTHROUGH can be jumped back to with JUMP -1.
+        let spin: [&[u8]; 3] = [&[1], &[1], &[40, 255]];
+
+        // HALT keeps the regular dimension, including the latest yield.
+        let body = [
+            hash_first,
+            yield_,
+            hash_second,
+            yield_,
+            halt[0],
+            halt[1],
+            halt[2],
+        ];
+        let result = accumulate(&body, 1_000);
+        assert_eq!(result.result_code, RESULT_CODE_HALT);
+        assert_eq!(result.yield_hash.as_ref(), &second[..]);
+        assert_eq!(post_state_yield(&result), Some(second.clone()));
+
+        // PANIC without a checkpoint falls back to the context's exceptional dimension: no yield.
+        let result = accumulate(&[hash_first, yield_, trap], 1_000);
+        assert_eq!(result.result_code, RESULT_CODE_PANIC);
+        assert!(result.yield_hash.is_empty());
+        assert_eq!(post_state_yield(&result), None);
+
+        // CHECKPOINT copies regular into exceptional; later yields are lost on PANIC.
+        let result = accumulate(
+            &[hash_first, yield_, checkpoint, hash_second, yield_, trap],
+            1_000,
+        );
+        assert_eq!(result.result_code, RESULT_CODE_PANIC);
+        assert_eq!(result.yield_hash.as_ref(), &first[..]);
+        assert_eq!(post_state_yield(&result), Some(first.clone()));
+
+        // Same on OOG, which also consumes the whole gas limit.
+        let body = [
+            hash_first,
+            yield_,
+            checkpoint,
+            hash_second,
+            yield_,
+            spin[0],
+            spin[1],
+            spin[2],
+        ];
+        let result = accumulate(&body, 1_000);
+        assert_eq!(result.result_code, RESULT_CODE_OOG);
+        assert_eq!(result.gas_consumed.get_u64().1, 1_000);
+        assert_eq!(result.yield_hash.as_ref(), &first[..]);
+        assert_eq!(post_state_yield(&result), Some(first));
+    }
+
+    #[test]
+    fn external_host_calls_stop_and_resume_after_the_ecalli() {
+        let mut instance = PvmInstance::new(None);
+        instance.set_external_host_calls(true);
+        // deblob: c = [ECALLI 3; ECALLI 4; ECALLI 5; TRAP], k = 0b1010101
+        instance.prepare_blob(vec![0, 0, 7, 10, 3, 10, 4, 10, 5, 0, 0b101_0101].into());
+        instance.state.gas_left = 100;
+        assert!(!instance.resume_host_call(None), "nothing pending yet");
+
+        // The ECALLI itself is charged; the PC stays on it until resumed.
+        assert!(!instance.next_step());
+        assert_eq!(instance.state.status, Status::Host);
+        assert_eq!(instance.get_result_code(), u32::from(RESULT_CODE_HOST));
+        assert_eq!(
+            (instance.get_host_call_id(), instance.get_program_counter()),
+            (3, 0)
+        );
+        assert_eq!(instance.state.gas_left, 99);
+
+        assert!(instance.resume_host_call(Some(BigInt::from(20u64))));
+        assert_eq!(instance.state.status, Status::Ok);
+        assert_eq!(
+            (instance.get_program_counter(), instance.state.gas_left),
+            (2, 79)
+        );
+        assert!(!instance.resume_host_call(None), "already resumed");
+
+        // Without an explicit cost the host call base gas is charged.
+        assert!(!instance.next_step());
+        assert_eq!(instance.get_host_call_id(), 4);
+        assert!(instance.resume_host_call(None));
+        assert_eq!(instance.state.gas_left, 78 - HOST_CALL_BASE_GAS);
+        assert_eq!(instance.get_program_counter(), 4);
+
+        // A cost beyond the gas left is OOG and does not advance.
+        assert!(!instance.next_step());
+        assert_eq!(instance.get_host_call_id(), 5);
+        assert!(!instance.resume_host_call(Some(BigInt::from(1_000u64))));
+        assert_eq!(instance.state.status, Status::Oog);
+        assert_eq!(instance.get_result_code(), u32::from(RESULT_CODE_OOG));
+        assert_eq!(
+            (instance.get_program_counter(), instance.state.gas_left),
+            (4, 0)
+        );
+    }
+
+    fn is_authorized_inputs() -> IsAuthorizedInputs {
+        IsAuthorizedInputs {
+            work_package: vec![].into(),
+            auth_config: vec![].into(),
+            auth_token: vec![1, 2, 3].into(),
+            refine_context: vec![].into(),
+            work_item_summaries: vec![],
+            work_item_payloads: vec![],
+        }
+    }
+
+    #[test]
+    fn is_authorized_allows_only_gas_fetch_and_log() {
+        let source = "
+            LOAD_IMM r10, 9
+            LOAD_IMM r9, 0
+            ECALLI 1            ; FETCH p.authtoken
+            MOVE_REG r2, r7
+            ECALLI 0            ; GAS
+            MOVE_REG r3, r7
+            ECALLI 2            ; LOOKUP
+            MOVE_REG r4, r7
+            LOAD_IMM r8, 0
+            LOAD_IMM r10, 0
+            ECALLI 100          ; LOG
+            LOAD_IMM r7, 0
+            LOAD_IMM r8, 0
+            JUMP_IND r0, 0
+        ";
+        let program = assemble(source).unwrap().to_preimage();
+        let mut instance = PvmInstance::new(None);
+        // Left over from a refine run without reset: Ψ_I setup must not inherit it.
+        instance.state.has_refine_context = true;
+        instance.state.has_accumulation_context = true;
+        assert!(instance.setup_is_authorized_invocation(program.into(), 3, is_authorized_inputs()));
+        assert!(!instance.state.has_refine_context && !instance.state.has_accumulation_context);
+
+        let result = IsAuthorizedResultOutput::from(run_is_authorized_impl(&mut instance.state));
+        assert_eq!(result.result_code, RESULT_CODE_HALT);
+        assert!(result.error.is_none());
+        let registers = instance.state.registers;
+        assert_eq!(registers[2], 3);
+        assert!(registers[3] > 0 && registers[3] < u64::from(PACKAGE_AUTH_GAS));
+        assert_eq!(registers[4], REG_WHAT);
+        assert_eq!(instance.state.log_messages.len(), 1);
+    }
+
+    #[test]
+    fn is_authorized_maps_big_panic_and_oog() {
+        let mut instance = PvmInstance::new(None);
+        let too_big = vec![0u8; MAX_AUTH_CODE_SIZE as usize + 1];
+        let result =
+            instance.is_authorized_invocation(too_big.clone().into(), 0, is_authorized_inputs());
+        assert_eq!(result.error.as_deref(), Some("BIG"));
+        assert!(!instance.setup_is_authorized_invocation(
+            too_big.into(),
+            0,
+            is_authorized_inputs()
+        ));
+
+        let trap = assemble("TRAP").unwrap().to_preimage();
+        let result = instance.is_authorized_invocation(trap.into(), 0, is_authorized_inputs());
+        assert_eq!(
+            (result.result_code, result.error.as_deref()),
+            (RESULT_CODE_PANIC, Some("PANIC"))
+        );
+        assert!(result.output.is_empty());
+
+        let spin = assemble("start: FALLTHROUGH\n JUMP start")
+            .unwrap()
+            .to_preimage();
+        assert!(instance.setup_is_authorized_invocation(spin.into(), 0, is_authorized_inputs()));
+        instance.state.gas_left = 10;
+        let result = IsAuthorizedResultOutput::from(run_is_authorized_impl(&mut instance.state));
+        assert_eq!(
+            (result.result_code, result.error.as_deref()),
+            (RESULT_CODE_OOG, Some("OOG"))
+        );
+        assert_eq!(result.gas_consumed.get_u64().1, u64::from(PACKAGE_AUTH_GAS));
+    }
+
+    #[test]
+    fn refine_setup_drops_contexts_and_inputs_left_by_earlier_invocations() {
+        let trap = assemble("TRAP").unwrap().to_preimage();
+        let mut instance = PvmInstance::new(None);
+        let inputs = is_authorized_inputs();
+        assert!(instance.setup_is_authorized_invocation(trap.clone().into(), 0, inputs));
+        let state = &mut instance.state;
+        state.has_accumulation_context = true;
+        state.accumulation_service_id = Some(1);
+        state.accumulation_accounts = Some(HashMap::new());
+        state.refine_import_segments = Some(vec![vec![vec![1]]]);
+        state.refine_lookup_timeslot = Some(7);
+
+        let (gas, args, refine_context) = (BigInt::from(100u64), vec![].into(), vec![].into());
+        assert!(instance.setup_refine_invocation(gas, trap.into(), args, refine_context, 0, None));
+        let state = &instance.state;
+        assert!(state.has_refine_context);
+        assert!(!state.has_accumulation_context && !state.has_is_authorized_context);
+        assert_eq!(
+            (state.accumulation_service_id, state.refine_lookup_timeslot),
+            (None, None)
+        );
+        assert!(state.accumulation_accounts.is_none() && state.refine_import_segments.is_none());
+        assert!(state.work_package_encoded.is_none() && state.auth_token.is_none());
+    }
+}
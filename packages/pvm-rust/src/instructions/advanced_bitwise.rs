@@ -1,7 +1,7 @@
 //! AND_INV (reg_D = reg_A & !reg_B), OR_INV (reg_D = reg_A | !reg_B), XNOR (reg_D = !(reg_A ^ reg_B)).
 
 use crate::config::{OPCODE_AND_INV, OPCODE_OR_INV, OPCODE_XNOR};
-use crate::instructions::base::{parse_three_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -29,7 +29,7 @@ impl InstructionHandler for AndInvInstruction {
         "AND_INV"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         set_register(context.registers, p.register_d, a & !b);
@@ -52,7 +52,7 @@ impl InstructionHandler for OrInvInstruction {
         "OR_INV"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         set_register(context.registers, p.register_d, a | !b);
@@ -75,7 +75,7 @@ impl InstructionHandler for XnorInstruction {
         "XNOR"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         set_register(context.registers, p.register_d, !(a ^ b));
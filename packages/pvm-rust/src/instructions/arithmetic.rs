@@ -6,9 +6,7 @@ use crate::config::{
     OPCODE_MUL_32, OPCODE_MUL_IMM_32, OPCODE_MUL_IMM_64, OPCODE_REM_S_32, OPCODE_REM_U_32,
     OPCODE_SUB_32,
 };
-use crate::instructions::base::{
-    parse_three_registers, parse_two_registers_and_immediate, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -60,7 +58,7 @@ impl InstructionHandler for Sub32Instruction {
         "SUB_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let result = value_a.wrapping_sub(value_b) & 0xffff_ffff;
@@ -88,7 +86,7 @@ impl InstructionHandler for Mul32Instruction {
         "MUL_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let result = (value_a * value_b) & 0xffff_ffff;
@@ -116,7 +114,7 @@ impl InstructionHandler for DivU32Instruction {
         "DIV_U_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let result = if value_b == 0 {
@@ -148,7 +146,7 @@ impl InstructionHandler for DivS32Instruction {
         "DIV_S_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let signed_a = sign_extend(value_a, 4) as i64;
@@ -185,7 +183,7 @@ impl InstructionHandler for RemU32Instruction {
         "REM_U_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let result = if value_b == 0 {
@@ -217,7 +215,7 @@ impl InstructionHandler for RemS32Instruction {
         "REM_S_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a) & 0xffff_ffff;
         let value_b = get_register(context.registers, parsed.register_b) & 0xffff_ffff;
         let signed_a = sign_extend(value_a, 4) as i64;
@@ -257,7 +255,7 @@ impl InstructionHandler for Add32Instruction {
         "ADD_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register_as_32_signed(context.registers, parsed.register_a);
         let value_b = get_register_as_32_signed(context.registers, parsed.register_b);
         let sum = (value_a as i64).wrapping_add(value_b) as u64 & 0xffff_ffff;
@@ -286,7 +284,7 @@ impl InstructionHandler for AddImm32Instruction {
         "ADD_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register_as_32_signed(context.registers, parsed.register_b);
         let sum = reg_b.wrapping_add(parsed.immediate_x);
         set_register_32_signed(context.registers, parsed.register_a, sum);
@@ -313,7 +311,7 @@ impl InstructionHandler for MulImm32Instruction {
         "MUL_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register_as_32_signed(context.registers, parsed.register_b);
         let product = reg_b.wrapping_mul(parsed.immediate_x);
         set_register_32_signed(context.registers, parsed.register_a, product);
@@ -340,7 +338,7 @@ impl InstructionHandler for AddImm64Instruction {
         "ADD_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let sum = reg_b.wrapping_add(parsed.immediate_x as u64);
         set_register(context.registers, parsed.register_a, sum);
@@ -367,7 +365,7 @@ impl InstructionHandler for MulImm64Instruction {
         "MUL_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let product = reg_b.wrapping_mul(parsed.immediate_x as u64);
         set_register(context.registers, parsed.register_a, product);
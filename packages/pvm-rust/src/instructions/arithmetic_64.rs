@@ -5,7 +5,7 @@ use crate::config::{
     OPCODE_ADD_64, OPCODE_DIV_S_64, OPCODE_DIV_U_64, OPCODE_MUL_64, OPCODE_REM_S_64,
     OPCODE_REM_U_64, OPCODE_SUB_64,
 };
-use crate::instructions::base::{parse_three_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -40,7 +40,7 @@ impl InstructionHandler for Add64Instruction {
         "ADD_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, value_a.wrapping_add(value_b));
@@ -66,7 +66,7 @@ impl InstructionHandler for Sub64Instruction {
         "SUB_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, value_a.wrapping_sub(value_b));
@@ -92,7 +92,7 @@ impl InstructionHandler for Mul64Instruction {
         "MUL_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, value_a.wrapping_mul(value_b));
@@ -118,7 +118,7 @@ impl InstructionHandler for DivU64Instruction {
         "DIV_U_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         let result = if value_b == 0 {
@@ -149,7 +149,7 @@ impl InstructionHandler for DivS64Instruction {
         "DIV_S_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         let signed_a = to_signed64(value_a);
@@ -185,7 +185,7 @@ impl InstructionHandler for RemU64Instruction {
         "REM_U_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         let result = if value_b == 0 {
@@ -216,7 +216,7 @@ impl InstructionHandler for RemS64Instruction {
         "REM_S_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let value_b = get_register(context.registers, parsed.register_b);
         let signed_a = to_signed64(value_a);
@@ -237,6 +237,13 @@ pub fn parse_one_register_and_immediate_unsigned(
     (register_a, immediate_x)
 }
 
+/// Parse one immediate (ECALLI). Gray Paper §7.4.1: l_X = min(4, ℓ), immed_X = X_lX(E⁻¹_lX(operands[0..l_X])).
+#[must_use]
+pub fn parse_one_immediate(operands: &[u8], fskip: i32) -> i64 {
+    let length_x = fskip.clamp(0, 4);
+    get_immediate_value(operands, 0, length_x)
+}
+
 /// Parse two immediates. Gray Paper §7.4.4: l_X = min(4, operands[0] mod 8), then l_Y = min(4, max(0, ℓ-l_X-1)).
 #[must_use]
 pub fn parse_two_immediates(operands: &[u8], fskip: i32) -> (i64, i64) {
@@ -2,9 +2,7 @@
 //! AND_IMM, XOR_IMM, OR_IMM, AND, XOR, OR.
 
 use crate::config::{OPCODE_AND_IMM, OPCODE_AND, OPCODE_OR_IMM, OPCODE_OR, OPCODE_XOR_IMM, OPCODE_XOR};
-use crate::instructions::base::{
-    parse_three_registers, parse_two_registers_and_immediate, InstructionHandler,
-};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -35,7 +33,7 @@ impl InstructionHandler for AndInstruction {
         "AND"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let a = get_register(context.registers, parsed.register_a);
         let b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, a & b);
@@ -61,7 +59,7 @@ impl InstructionHandler for XorInstruction {
         "XOR"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let a = get_register(context.registers, parsed.register_a);
         let b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, a ^ b);
@@ -87,7 +85,7 @@ impl InstructionHandler for OrInstruction {
         "OR"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let a = get_register(context.registers, parsed.register_a);
         let b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, a | b);
@@ -113,7 +111,7 @@ impl InstructionHandler for AndImmInstruction {
         "AND_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = reg_b & (parsed.immediate_x as u64);
         set_register(context.registers, parsed.register_a, result);
@@ -139,7 +137,7 @@ impl InstructionHandler for XorImmInstruction {
         "XOR_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = reg_b ^ (parsed.immediate_x as u64);
         set_register(context.registers, parsed.register_a, result);
@@ -165,7 +163,7 @@ impl InstructionHandler for OrImmInstruction {
         "OR_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = reg_b | (parsed.immediate_x as u64);
         set_register(context.registers, parsed.register_a, result);
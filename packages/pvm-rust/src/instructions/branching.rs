@@ -4,10 +4,7 @@
 use crate::config::{
     OPCODE_BRANCH_EQ, OPCODE_BRANCH_EQ_IMM, OPCODE_BRANCH_GE_S, OPCODE_BRANCH_GE_S_IMM, OPCODE_BRANCH_GE_U, OPCODE_BRANCH_GE_U_IMM, OPCODE_BRANCH_GT_S_IMM, OPCODE_BRANCH_GT_U_IMM, OPCODE_BRANCH_LE_S_IMM, OPCODE_BRANCH_LE_U_IMM, OPCODE_BRANCH_LT_S, OPCODE_BRANCH_LT_S_IMM, OPCODE_BRANCH_LT_U, OPCODE_BRANCH_LT_U_IMM, OPCODE_BRANCH_NE, OPCODE_BRANCH_NE_IMM,
 };
-use crate::instructions::base::{
-    parse_branch_operands, parse_register_branch_operands, validate_branch_target,
-    InstructionHandler,
-};
+use crate::instructions::base::{validate_branch_target, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -64,10 +61,11 @@ macro_rules! branch_imm_instruction {
                 i32::from($opcode)
             }
             fn name(&self) -> &'static str {
-                stringify!($name)
+                // Mnemonic as in the TS PVMs and traces, e.g. BRANCH_EQ_IMM.
+                &stringify!($opcode)["OPCODE_".len()..]
             }
             fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-                let parsed = parse_branch_operands(context.operands, context.program_counter);
+                let parsed = context.operands;
                 let reg_val = get_register(context.registers, parsed.register_a);
                 let cond = $cond(reg_val, parsed.immediate_x);
                 do_branch_imm(context, cond, parsed.target_address)
@@ -136,11 +134,11 @@ macro_rules! branch_reg_instruction {
                 i32::from($opcode)
             }
             fn name(&self) -> &'static str {
-                stringify!($name)
+                // Mnemonic as in the TS PVMs and traces, e.g. BRANCH_EQ_IMM.
+                &stringify!($opcode)["OPCODE_".len()..]
             }
             fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-                let parsed =
-                    parse_register_branch_operands(context.operands, context.program_counter);
+                let parsed = context.operands;
                 let reg_a = get_register(context.registers, parsed.register_a);
                 let reg_b = get_register(context.registers, parsed.register_b);
                 let cond = $cond(reg_a, reg_b);
@@ -5,9 +5,7 @@ use crate::config::{
     OPCODE_SET_GT_S_IMM, OPCODE_SET_GT_U_IMM, OPCODE_SET_LT_S, OPCODE_SET_LT_S_IMM,
     OPCODE_SET_LT_U, OPCODE_SET_LT_U_IMM,
 };
-use crate::instructions::base::{
-    parse_three_registers, parse_two_registers_and_immediate, InstructionHandler,
-};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -38,7 +36,7 @@ impl InstructionHandler for SetLtUInstruction {
         "SET_LT_U"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let a = get_register(context.registers, parsed.register_a);
         let b = get_register(context.registers, parsed.register_b);
         set_register(context.registers, parsed.register_d, if a < b { 1 } else { 0 });
@@ -64,7 +62,7 @@ impl InstructionHandler for SetLtSInstruction {
         "SET_LT_S"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let a = get_register(context.registers, parsed.register_a) as i64;
         let b = get_register(context.registers, parsed.register_b) as i64;
         set_register(context.registers, parsed.register_d, if a < b { 1 } else { 0 });
@@ -91,7 +89,7 @@ impl InstructionHandler for SetLtUImmInstruction {
         "SET_LT_U_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let imm_u = parsed.immediate_x as u64;
         let result = if reg_b < imm_u { 1u64 } else { 0u64 };
@@ -119,7 +117,7 @@ impl InstructionHandler for SetLtSImmInstruction {
         "SET_LT_S_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b) as i64;
         let result = if reg_b < parsed.immediate_x { 1u64 } else { 0u64 };
         set_register(context.registers, parsed.register_a, result);
@@ -146,7 +144,7 @@ impl InstructionHandler for SetGtUImmInstruction {
         "SET_GT_U_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let imm_u = parsed.immediate_x as u64;
         let result = if reg_b > imm_u { 1u64 } else { 0u64 };
@@ -174,7 +172,7 @@ impl InstructionHandler for SetGtSImmInstruction {
         "SET_GT_S_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b) as i64;
         let result = if reg_b > parsed.immediate_x { 1u64 } else { 0u64 };
         set_register(context.registers, parsed.register_a, result);
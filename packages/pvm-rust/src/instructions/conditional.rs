@@ -2,9 +2,7 @@
 //! CMOV_IZ_IMM, CMOV_NZ_IMM, CMOV_IZ, CMOV_NZ.
 
 use crate::config::{OPCODE_CMOV_IZ, OPCODE_CMOV_IZ_IMM, OPCODE_CMOV_NZ, OPCODE_CMOV_NZ_IMM};
-use crate::instructions::base::{
-    parse_three_registers, parse_two_registers_and_immediate, InstructionHandler,
-};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -36,7 +34,7 @@ impl InstructionHandler for CmovIzImmInstruction {
         "CMOV_IZ_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = if reg_b == 0 {
             parsed.immediate_x as u64
@@ -67,7 +65,7 @@ impl InstructionHandler for CmovNzImmInstruction {
         "CMOV_NZ_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = if reg_b != 0 {
             parsed.immediate_x as u64
@@ -98,7 +96,7 @@ impl InstructionHandler for CmovIzInstruction {
         "CMOV_IZ"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = if reg_b == 0 {
             get_register(context.registers, parsed.register_a)
@@ -129,7 +127,7 @@ impl InstructionHandler for CmovNzInstruction {
         "CMOV_NZ"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_three_registers(context.operands);
+        let parsed = context.operands;
         let reg_b = get_register(context.registers, parsed.register_b);
         let result = if reg_b != 0 {
             get_register(context.registers, parsed.register_a)
@@ -4,10 +4,7 @@ use crate::config::{
     HALT_ADDRESS, OPCODE_FALLTHROUGH, OPCODE_JUMP, OPCODE_JUMP_IND, OPCODE_LOAD_IMM_JUMP,
     OPCODE_LOAD_IMM_JUMP_IND, OPCODE_TRAP, RESULT_CODE_HALT, RESULT_CODE_PANIC,
 };
-use crate::instructions::base::{
-    parse_one_offset, parse_one_register_and_immediate, parse_register_and_two_immediates,
-    parse_two_registers_and_two_immediates, validate_branch_target, InstructionHandler,
-};
+use crate::instructions::base::{validate_branch_target, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 /// TRAP (opcode 0): panic.
@@ -93,7 +90,7 @@ impl InstructionHandler for JumpInstruction {
 
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
         // Gray Paper: target = ι + signfunc(offset); use instruction PC (current program_counter).
-        let target = parse_one_offset(context.operands, context.fskip, context.program_counter);
+        let target = context.operands.target_address;
         if let Some(panic_result) =
             validate_branch_target(target, context.code, context.bitmask)
         {
@@ -133,7 +130,7 @@ impl InstructionHandler for JumpIndInstruction {
     }
 
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_one_register_and_immediate(context.operands, context.fskip);
+        let parsed = context.operands;
         let register_value = context.registers[parsed.register_a as usize];
         let a = (register_value.wrapping_add(parsed.immediate_x as u64)) & 0xffff_ffff;
 
@@ -191,9 +188,9 @@ impl InstructionHandler for LoadImmJumpInstruction {
     }
 
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_register_and_two_immediates(context.operands, context.fskip);
-        // Gray Paper: target = ι + signfunc(immed_Y); immediate_y is already sign-extended from parser
-        let target = (context.program_counter as i64).wrapping_add(parsed.immediate_y) as u32;
+        let parsed = context.operands;
+        // Gray Paper: target = ι + signfunc(immed_Y); computed from the sign-extended immed_Y at load time
+        let target = parsed.target_address;
         if let Some(panic_result) = validate_branch_target(target, context.code, context.bitmask) {
             return panic_result;
         }
@@ -230,7 +227,7 @@ impl InstructionHandler for LoadImmJumpIndInstruction {
     }
 
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers_and_two_immediates(context.operands, context.fskip);
+        let parsed = context.operands;
         // Gray Paper: read reg_B before overwriting reg_A
         let register_b_value = context.registers[parsed.register_b as usize];
         context.registers[parsed.register_a as usize] = parsed.immediate_x as u64;
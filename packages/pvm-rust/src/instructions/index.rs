@@ -2,7 +2,7 @@
 //! MOVE_REG (reg'_D = reg_A), SBRK (heap allocation).
 
 use crate::config::{align_to_page, MAX_MEMORY_ADDRESS, PAGE_SIZE, OPCODE_MOVE_REG, OPCODE_SBRK};
-use crate::instructions::base::{parse_two_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -33,7 +33,7 @@ impl InstructionHandler for MoveRegInstruction {
         "MOVE_REG"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers(context.operands);
+        let parsed = context.operands;
         let value = get_register(context.registers, parsed.register_a);
         set_register(context.registers, parsed.register_d, value);
         InstructionResult::new(InstructionResult::CONTINUE, 0)
@@ -59,7 +59,7 @@ impl InstructionHandler for SbrkInstruction {
         "SBRK"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let parsed = parse_two_registers(context.operands);
+        let parsed = context.operands;
         let value_a = get_register(context.registers, parsed.register_a);
         let current = context.ram.current_heap_pointer();
 
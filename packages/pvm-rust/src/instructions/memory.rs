@@ -10,12 +10,9 @@ use crate::config::{
     ZONE_SIZE,
 };
 use crate::instructions::base::{
-    get_register_index, parse_one_register_and_immediate,
-    parse_one_register_and_immediate_unsigned, parse_register_and_two_immediates,
-    parse_two_immediates, sign_extend, value_to_bytes_le, bytes_to_value_le,
-    InstructionHandler, RegisterAndImmediateResult,
+    sign_extend, value_to_bytes_le, bytes_to_value_le, InstructionHandler,
 };
-use crate::types::{InstructionContext, InstructionResult};
+use crate::types::{DecodedOperands, InstructionContext, InstructionResult};
 
 fn set_register(registers: &mut [u64; 13], index: u8, value: u64) {
     if (index as usize) < 13 {
@@ -45,12 +42,12 @@ impl InstructionHandler for LoadImm64Instruction {
         "LOAD_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let register_a = get_register_index(context.operands[0]);
-        let mut immediate_x: u64 = 0;
-        for i in 0..8.min(context.operands.len().saturating_sub(1)) {
-            immediate_x |= u64::from(context.operands[1 + i]) << (i * 8);
-        }
-        set_register(context.registers, register_a, immediate_x);
+        let DecodedOperands {
+            register_a,
+            immediate_x,
+            ..
+        } = context.operands;
+        set_register(context.registers, register_a, immediate_x as u64);
         InstructionResult::new(InstructionResult::CONTINUE, 0)
     }
 }
@@ -73,15 +70,12 @@ impl InstructionHandler for LoadImmInstruction {
         "LOAD_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let RegisterAndImmediateResult {
+        let DecodedOperands {
             register_a,
             immediate_x,
-        } = parse_one_register_and_immediate(context.operands, context.fskip);
-        set_register(
-            context.registers,
-            register_a,
-            immediate_x as u64,
-        );
+            ..
+        } = context.operands;
+        set_register(context.registers, register_a, immediate_x as u64);
         InstructionResult::new(InstructionResult::CONTINUE, 0)
     }
 }
@@ -104,7 +98,11 @@ impl InstructionHandler for StoreImmU8Instruction {
         "STORE_IMM_U8"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let (immediate_x, immediate_y) = parse_two_immediates(context.operands, context.fskip);
+        let DecodedOperands {
+            immediate_x,
+            immediate_y,
+            ..
+        } = context.operands;
         let value = (immediate_y as u64) & 0xff;
         let address = (immediate_x as u64) & 0xffff_ffff;
         if address < u64::from(ZONE_SIZE) {
@@ -140,7 +138,11 @@ macro_rules! store_imm_instruction {
                 stringify!($name)
             }
             fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-                let (immediate_x, immediate_y) = parse_two_immediates(context.operands, context.fskip);
+                let DecodedOperands {
+                    immediate_x,
+                    immediate_y,
+                    ..
+                } = context.operands;
                 let value = (immediate_y as u64) & $mask;
                 let address = (immediate_x as u64) & 0xffff_ffff;
                 if address < u64::from(ZONE_SIZE) {
@@ -180,8 +182,11 @@ impl InstructionHandler for LoadU8Instruction {
         "LOAD_U8"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let (register_a, immediate_x) =
-            parse_one_register_and_immediate_unsigned(context.operands, context.fskip);
+        let DecodedOperands {
+            register_a,
+            immediate_x,
+            ..
+        } = context.operands;
         let read_result = context.ram.read_octets(immediate_x as u32, 1);
         if read_result.fault_address != 0 || read_result.data.is_none() {
             return InstructionResult::new(RESULT_CODE_FAULT as i32, read_result.fault_address);
@@ -209,8 +214,11 @@ impl InstructionHandler for LoadI8Instruction {
         "LOAD_I8"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let (register_a, immediate_x) =
-            parse_one_register_and_immediate_unsigned(context.operands, context.fskip);
+        let DecodedOperands {
+            register_a,
+            immediate_x,
+            ..
+        } = context.operands;
         let read_result = context.ram.read_octets(immediate_x as u32, 1);
         if read_result.fault_address != 0 || read_result.data.is_none() {
             return InstructionResult::new(RESULT_CODE_FAULT as i32, read_result.fault_address);
@@ -224,8 +232,11 @@ impl InstructionHandler for LoadI8Instruction {
 
 // --- LOAD_U16, LOAD_I16 ---
 fn load_u16_common(context: &mut InstructionContext<'_>) -> InstructionResult {
-    let (register_a, immediate_x) =
-        parse_one_register_and_immediate_unsigned(context.operands, context.fskip);
+    let DecodedOperands {
+        register_a,
+        immediate_x,
+        ..
+    } = context.operands;
     let read_result = context.ram.read_octets(immediate_x as u32, 2);
     if read_result.fault_address != 0 || read_result.data.is_none() {
         return InstructionResult::new(RESULT_CODE_FAULT as i32, read_result.fault_address);
@@ -236,8 +247,11 @@ fn load_u16_common(context: &mut InstructionContext<'_>) -> InstructionResult {
 }
 
 fn load_i16_common(context: &mut InstructionContext<'_>) -> InstructionResult {
-    let (register_a, immediate_x) =
-        parse_one_register_and_immediate_unsigned(context.operands, context.fskip);
+    let DecodedOperands {
+        register_a,
+        immediate_x,
+        ..
+    } = context.operands;
     let read_result = context.ram.read_octets(immediate_x as u32, 2);
     if read_result.fault_address != 0 || read_result.data.is_none() {
         return InstructionResult::new(RESULT_CODE_FAULT as i32, read_result.fault_address);
@@ -308,9 +322,12 @@ impl InstructionHandler for LoadU32Instruction {
         "LOAD_U32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let (register_a, immediate_x) =
-            parse_one_register_and_immediate_unsigned(context.operands, context.fskip);
-        if immediate_x < u64::from(ZONE_SIZE) {
+        let DecodedOperands {
+            register_a,
+            immediate_x,
+            ..
+        } = context.operands;
+        if (immediate_x as u64) < u64::from(ZONE_SIZE) {
             return InstructionResult::new(RESULT_CODE_PANIC as i32, 0);
         }
         let read_result = context.ram.read_octets(immediate_x as u32, 4);
@@ -340,10 +357,11 @@ impl InstructionHandler for LoadI32Instruction {
         "LOAD_I32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let RegisterAndImmediateResult {
+        let DecodedOperands {
             register_a,
             immediate_x,
-        } = parse_one_register_and_immediate(context.operands, context.fskip);
+            ..
+        } = context.operands;
         let address = (immediate_x as u64) & 0xffff_ffff;
         if address < u64::from(ZONE_SIZE) {
             return InstructionResult::new(RESULT_CODE_PANIC as i32, 0);
@@ -377,10 +395,11 @@ impl InstructionHandler for LoadU64Instruction {
         "LOAD_U64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let RegisterAndImmediateResult {
+        let DecodedOperands {
             register_a,
             immediate_x,
-        } = parse_one_register_and_immediate(context.operands, context.fskip);
+            ..
+        } = context.operands;
         let address = (immediate_x as u64) & 0xffff_ffff;
         if address < u64::from(ZONE_SIZE) {
             return InstructionResult::new(RESULT_CODE_PANIC as i32, 0);
@@ -401,10 +420,11 @@ fn store_reg_at_address(
     size: usize,
     mask: u64,
 ) -> InstructionResult {
-    let RegisterAndImmediateResult {
+    let DecodedOperands {
         register_a,
         immediate_x,
-    } = parse_one_register_and_immediate(context.operands, context.fskip);
+        ..
+    } = context.operands;
     let value = get_register(context.registers, register_a) & mask;
     let address = (immediate_x as u64) & 0xffff_ffff;
     if address < u64::from(ZONE_SIZE) {
@@ -455,7 +475,7 @@ fn store_imm_ind_common(
     size: usize,
     mask: u64,
 ) -> InstructionResult {
-    let parsed = parse_register_and_two_immediates(context.operands, context.fskip);
+    let parsed = context.operands;
     let reg_value = get_register(context.registers, parsed.register_a);
     let address = (reg_value.wrapping_add(parsed.immediate_x as u64)) & 0xffff_ffff;
     if address < u64::from(ZONE_SIZE) {
@@ -8,8 +8,7 @@ use crate::config::{
     RESULT_CODE_PANIC, ZONE_SIZE,
 };
 use crate::instructions::base::{
-    bytes_to_value_le, parse_two_registers_and_immediate, sign_extend, value_to_bytes_le,
-    InstructionHandler,
+    bytes_to_value_le, sign_extend, value_to_bytes_le, InstructionHandler,
 };
 use crate::types::{InstructionContext, InstructionResult};
 
@@ -28,7 +27,7 @@ fn store_ind_common(
     size: usize,
     mask: u64,
 ) -> InstructionResult {
-    let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+    let parsed = context.operands;
     let reg_a_value = get_register(context.registers, parsed.register_a);
     let reg_b_value = get_register(context.registers, parsed.register_b);
     let address = (reg_b_value.wrapping_add(parsed.immediate_x as u64)) & 0xffff_ffff;
@@ -125,7 +124,7 @@ fn load_ind_common(
     size: usize,
     signed: bool,
 ) -> InstructionResult {
-    let parsed = parse_two_registers_and_immediate(context.operands, context.fskip);
+    let parsed = context.operands;
     let reg_b_value = get_register(context.registers, parsed.register_b);
     let address = (reg_b_value.wrapping_add(parsed.immediate_x as u64)) & 0xffff_ffff;
     if address < u64::from(ZONE_SIZE) {
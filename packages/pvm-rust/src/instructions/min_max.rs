@@ -1,7 +1,7 @@
 //! MIN, MIN_U, MAX, MAX_U (three-register: reg_D = min/max(reg_A, reg_B)).
 
 use crate::config::{OPCODE_MAX, OPCODE_MAX_U, OPCODE_MIN, OPCODE_MIN_U};
-use crate::instructions::base::{parse_three_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -29,7 +29,7 @@ impl InstructionHandler for MinInstruction {
         "MIN"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let sa = a as i64;
@@ -55,7 +55,7 @@ impl InstructionHandler for MinUInstruction {
         "MIN_U"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let result = if a < b { a } else { b };
@@ -79,7 +79,7 @@ impl InstructionHandler for MaxInstruction {
         "MAX"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let sa = a as i64;
@@ -105,7 +105,7 @@ impl InstructionHandler for MaxUInstruction {
         "MAX_U"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let result = if a > b { a } else { b };
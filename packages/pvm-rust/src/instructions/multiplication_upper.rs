@@ -1,7 +1,7 @@
 //! MUL_UPPER_S_S, MUL_UPPER_U_U, MUL_UPPER_S_U - upper 64 bits of 64×64 multiply.
 
 use crate::config::{OPCODE_MUL_UPPER_S_S, OPCODE_MUL_UPPER_S_U, OPCODE_MUL_UPPER_U_U};
-use crate::instructions::base::{parse_three_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -29,7 +29,7 @@ impl InstructionHandler for MulUpperSSInstruction {
         "MUL_UPPER_S_S"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let sa = a as i64;
@@ -57,7 +57,7 @@ impl InstructionHandler for MulUpperUUInstruction {
         "MUL_UPPER_U_U"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let product = (a as u128) * (b as u128);
@@ -82,7 +82,7 @@ impl InstructionHandler for MulUpperSUInstruction {
         "MUL_UPPER_S_U"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let b = get_register(context.registers, p.register_b);
         let sa = a as i64;
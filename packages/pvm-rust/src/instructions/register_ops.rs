@@ -5,7 +5,7 @@ use crate::config::{
     OPCODE_LEADING_ZERO_BITS_64, OPCODE_REVERSE_BYTES, OPCODE_SIGN_EXTEND_16, OPCODE_SIGN_EXTEND_8,
     OPCODE_TRAILING_ZERO_BITS_32, OPCODE_TRAILING_ZERO_BITS_64, OPCODE_ZERO_EXTEND_16,
 };
-use crate::instructions::base::{parse_two_registers, InstructionHandler};
+use crate::instructions::base::InstructionHandler;
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -33,7 +33,7 @@ impl InstructionHandler for CountSetBits64Instruction {
         "COUNT_SET_BITS_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let count = value.count_ones() as u64;
         set_register(context.registers, p.register_d, count);
@@ -56,7 +56,7 @@ impl InstructionHandler for CountSetBits32Instruction {
         "COUNT_SET_BITS_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let count = value.count_ones() as u64;
         set_register(context.registers, p.register_d, count);
@@ -79,7 +79,7 @@ impl InstructionHandler for LeadingZeroBits64Instruction {
         "LEADING_ZERO_BITS_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let count = if value == 0 {
             64u64
@@ -106,7 +106,7 @@ impl InstructionHandler for LeadingZeroBits32Instruction {
         "LEADING_ZERO_BITS_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff_ffff;
         // Count leading zeros in the low 32 bits only (Gray Paper: reg_A mod 2^32).
         // Using u64::leading_zeros() would count zeros in the full 64 bits (e.g. 1 → 63); use u32.
@@ -136,7 +136,7 @@ impl InstructionHandler for TrailingZeroBits64Instruction {
         "TRAILING_ZERO_BITS_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let count = if value == 0 {
             64u64
@@ -163,7 +163,7 @@ impl InstructionHandler for TrailingZeroBits32Instruction {
         "TRAILING_ZERO_BITS_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let count = if value == 0 {
             32u64
@@ -190,7 +190,7 @@ impl InstructionHandler for SignExtend8Instruction {
         "SIGN_EXTEND_8"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xff;
         let sign_bit = value & 0x80;
         let result = if sign_bit != 0 {
@@ -218,7 +218,7 @@ impl InstructionHandler for SignExtend16Instruction {
         "SIGN_EXTEND_16"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff;
         let signed = if value >= 0x8000 {
             (value as i64) - 0x10000
@@ -245,7 +245,7 @@ impl InstructionHandler for ZeroExtend16Instruction {
         "ZERO_EXTEND_16"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff;
         set_register(context.registers, p.register_d, value);
         InstructionResult::new(InstructionResult::CONTINUE, 0)
@@ -267,7 +267,7 @@ impl InstructionHandler for ReverseBytesInstruction {
         "REVERSE_BYTES"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let result = value.swap_bytes();
         set_register(context.registers, p.register_d, result);
@@ -1,42 +1,61 @@
 //! Instruction registry: map opcode → handler. Mirrors assembly/instructions/registry.ts.
+//! Opcodes are one byte, so handlers live in a 256-entry table indexed directly by opcode.
 
 use crate::instructions::base::InstructionHandler;
-use std::collections::HashMap;
 
 /// Central registry mapping opcodes to instruction handlers.
 pub struct InstructionRegistry {
-    handlers: HashMap<i32, Box<dyn InstructionHandler>>,
+    handlers: [Option<Box<dyn InstructionHandler>>; 256],
 }
 
 impl InstructionRegistry {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            handlers: HashMap::new(),
+            handlers: std::array::from_fn(|_| None),
         }
     }
 
+    /// Register a handler in its opcode slot. Handlers with an opcode outside 0–255 are ignored.
     pub fn register(&mut self, handler: Box<dyn InstructionHandler>) {
-        self.handlers.insert(handler.opcode(), handler);
+        if let Some(slot) = usize::try_from(handler.opcode())
+            .ok()
+            .and_then(|i| self.handlers.get_mut(i))
+        {
+            *slot = Some(handler);
+        }
     }
 
     #[must_use]
     pub fn get_handler(&self, opcode: i32) -> Option<&dyn InstructionHandler> {
-        self.handlers.get(&opcode).map(|b| b.as_ref())
+        let index = usize::try_from(opcode).ok()?;
+        self.handlers.get(index)?.as_deref()
+    }
+
+    /// Dispatch by code byte: a single table index, no bounds or hash lookup.
+    #[inline]
+    #[must_use]
+    pub fn handler(&self, opcode: u8) -> Option<&dyn InstructionHandler> {
+        self.handlers[opcode as usize].as_deref()
     }
 
     #[must_use]
     pub fn has_handler(&self, opcode: i32) -> bool {
-        self.handlers.contains_key(&opcode)
+        self.get_handler(opcode).is_some()
     }
 
     #[must_use]
     pub fn registered_opcodes(&self) -> Vec<i32> {
-        self.handlers.keys().copied().collect()
+        self.handlers
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.is_some())
+            .map(|(i, _)| i as i32)
+            .collect()
     }
 
     pub fn clear(&mut self) {
-        self.handlers.clear();
+        self.handlers = std::array::from_fn(|_| None);
     }
 }
 
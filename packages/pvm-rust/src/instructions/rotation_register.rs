@@ -1,7 +1,7 @@
 //! Rotate by register: ROT_L_64, ROT_L_32, ROT_R_64, ROT_R_32.
 
 use crate::config::{OPCODE_ROT_L_32, OPCODE_ROT_L_64, OPCODE_ROT_R_32, OPCODE_ROT_R_64};
-use crate::instructions::base::{parse_three_registers, sign_extend, InstructionHandler};
+use crate::instructions::base::{sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -82,7 +82,7 @@ impl InstructionHandler for RotL64Instruction {
         "ROT_L_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let amount = (get_register(context.registers, p.register_b) % 64) as u32;
         set_register(context.registers, p.register_d, rot_left_64(value, amount));
@@ -105,7 +105,7 @@ impl InstructionHandler for RotL32Instruction {
         "ROT_L_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let amount = (get_register(context.registers, p.register_b) % 32) as u32;
         set_register_32(context.registers, p.register_d, rot_left_32(value, amount));
@@ -128,7 +128,7 @@ impl InstructionHandler for RotR64Instruction {
         "ROT_R_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a);
         let amount = (get_register(context.registers, p.register_b) % 64) as u32;
         set_register(context.registers, p.register_d, rot_right_64(value, amount));
@@ -151,7 +151,7 @@ impl InstructionHandler for RotR32Instruction {
         "ROT_R_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let amount = (get_register(context.registers, p.register_b) % 32) as u32;
         set_register_32(context.registers, p.register_d, rot_right_32(value, amount));
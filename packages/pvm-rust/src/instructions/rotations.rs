@@ -3,9 +3,7 @@
 use crate::config::{
     OPCODE_ROT_R_32_IMM, OPCODE_ROT_R_32_IMM_ALT, OPCODE_ROT_R_64_IMM, OPCODE_ROT_R_64_IMM_ALT,
 };
-use crate::instructions::base::{
-    parse_two_registers_and_immediate, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -64,7 +62,7 @@ impl InstructionHandler for RotR64ImmInstruction {
         "ROT_R_64_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_b);
         let amount = (p.immediate_x as u64) % 64;
         let result = rot_right_64(value, amount as u32);
@@ -89,7 +87,7 @@ impl InstructionHandler for RotR64ImmAltInstruction {
         "ROT_R_64_IMM_ALT"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let value = p.immediate_x as u64;
         let amount = (get_register(context.registers, p.register_b) % 64) as u32;
         let result = rot_right_64(value, amount);
@@ -114,7 +112,7 @@ impl InstructionHandler for RotR32ImmInstruction {
         "ROT_R_32_IMM"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let value = get_register(context.registers, p.register_b) & 0xffff_ffff;
         let amount = (p.immediate_x as u64) % 32;
         let result = rot_right_32(value, amount as u32);
@@ -139,7 +137,7 @@ impl InstructionHandler for RotR32ImmAltInstruction {
         "ROT_R_32_IMM_ALT"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let value = (p.immediate_x as u64) & 0xffff_ffff;
         let amount = (get_register(context.registers, p.register_b) % 32) as u32;
         let result = rot_right_32(value, amount);
@@ -3,9 +3,7 @@
 use crate::config::{
     OPCODE_NEG_ADD_IMM_32, OPCODE_SHAR_R_IMM_32, OPCODE_SHLO_L_IMM_32, OPCODE_SHLO_R_IMM_32,
 };
-use crate::instructions::base::{
-    arithmetic_shift_right_32, parse_two_registers_and_immediate, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_32, sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -35,7 +33,7 @@ impl InstructionHandler for ShloLImm32Instruction {
         "SHLO_L_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b) & 0xffff_ffff;
         let shift = (p.immediate_x as u64) & 0xffff_ffff;
         let shift = shift % 32;
@@ -61,7 +59,7 @@ impl InstructionHandler for ShloRImm32Instruction {
         "SHLO_R_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b) & 0xffff_ffff;
         let shift = (p.immediate_x as u64) & 0xffff_ffff;
         let shift = (shift % 32) as u32;
@@ -91,7 +89,7 @@ impl InstructionHandler for SharRImm32Instruction {
         "SHAR_R_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b) & 0xffff_ffff;
         let shift = (p.immediate_x as u64) & 0xffff_ffff;
         let shift = (shift % 32) as u32;
@@ -117,7 +115,7 @@ impl InstructionHandler for NegAddImm32Instruction {
         "NEG_ADD_IMM_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b) & 0xffff_ffff;
         let imm = (p.immediate_x as u64) & 0xffff_ffff;
         let result = (imm.wrapping_add(0x1_0000_0000).wrapping_sub(reg_b)) & 0xffff_ffff;
@@ -1,9 +1,7 @@
 //! Shift by register (32-bit): SHLO_L_32, SHLO_R_32, SHAR_R_32.
 
 use crate::config::{OPCODE_SHAR_R_32, OPCODE_SHLO_L_32, OPCODE_SHLO_R_32};
-use crate::instructions::base::{
-    arithmetic_shift_right_32, parse_three_registers, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_32, sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -33,7 +31,7 @@ impl InstructionHandler for ShloL32Instruction {
         "SHLO_L_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let result = (a << shift) & 0xffff_ffff;
@@ -58,7 +56,7 @@ impl InstructionHandler for ShloR32Instruction {
         "SHLO_R_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let result = if shift == 0 { a } else { a >> shift };
@@ -83,7 +81,7 @@ impl InstructionHandler for SharR32Instruction {
         "SHAR_R_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a) & 0xffff_ffff;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let result = arithmetic_shift_right_32(a, shift);
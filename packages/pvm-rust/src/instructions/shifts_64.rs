@@ -3,9 +3,7 @@
 use crate::config::{
     OPCODE_NEG_ADD_IMM_64, OPCODE_SHAR_R_IMM_64, OPCODE_SHLO_L_IMM_64, OPCODE_SHLO_R_IMM_64,
 };
-use crate::instructions::base::{
-    arithmetic_shift_right_64, parse_two_registers_and_immediate, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_64, sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -41,7 +39,7 @@ impl InstructionHandler for ShloLImm64Instruction {
         "SHLO_L_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b);
         let shift = (p.immediate_x as u64) % 64;
         let result = (reg_b << shift) & 0xffff_ffff_ffff_ffff;
@@ -66,7 +64,7 @@ impl InstructionHandler for ShloRImm64Instruction {
         "SHLO_R_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b);
         let shift = (p.immediate_x as u64) % 64;
         let result = reg_b >> shift;
@@ -91,7 +89,7 @@ impl InstructionHandler for SharRImm64Instruction {
         "SHAR_R_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b);
         let shift = (p.immediate_x as u64) % 64;
         let result = arithmetic_shift_right_64(reg_b, shift as u32);
@@ -116,7 +114,7 @@ impl InstructionHandler for NegAddImm64Instruction {
         "NEG_ADD_IMM_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let reg_b = get_register(context.registers, p.register_b);
         let imm = p.immediate_x as u64;
         let result = imm.wrapping_add((!reg_b).wrapping_add(1));
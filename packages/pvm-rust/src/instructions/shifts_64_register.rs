@@ -1,9 +1,7 @@
 //! Shift by register (64-bit): SHLO_L_64, SHLO_R_64, SHAR_R_64.
 
 use crate::config::{OPCODE_SHAR_R_64, OPCODE_SHLO_L_64, OPCODE_SHLO_R_64};
-use crate::instructions::base::{
-    arithmetic_shift_right_64, parse_three_registers, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_64, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -32,7 +30,7 @@ impl InstructionHandler for ShloL64Instruction {
         "SHLO_L_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let shift = get_register(context.registers, p.register_b) % 64;
         let result = (a << shift) & 0xffff_ffff_ffff_ffff;
@@ -57,7 +55,7 @@ impl InstructionHandler for ShloR64Instruction {
         "SHLO_R_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let shift = get_register(context.registers, p.register_b) % 64;
         let result = a >> shift;
@@ -82,7 +80,7 @@ impl InstructionHandler for SharR64Instruction {
         "SHAR_R_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_three_registers(context.operands);
+        let p = context.operands;
         let a = get_register(context.registers, p.register_a);
         let shift = (get_register(context.registers, p.register_b) % 64) as u32;
         let result = arithmetic_shift_right_64(a, shift);
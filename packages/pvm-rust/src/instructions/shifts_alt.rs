@@ -3,9 +3,7 @@
 use crate::config::{
     OPCODE_SHAR_R_IMM_ALT_32, OPCODE_SHLO_L_IMM_ALT_32, OPCODE_SHLO_R_IMM_ALT_32,
 };
-use crate::instructions::base::{
-    arithmetic_shift_right_32, parse_two_registers_and_immediate, sign_extend, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_32, sign_extend, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn set_register_32(registers: &mut [u64; 13], index: u8, value: u64) {
@@ -35,7 +33,7 @@ impl InstructionHandler for ShloLImmAlt32Instruction {
         "SHLO_L_IMM_ALT_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let imm = (p.immediate_x as u64) & 0xffff_ffff;
         let result = (imm << shift) & 0xffff_ffff;
@@ -60,7 +58,7 @@ impl InstructionHandler for ShloRImmAlt32Instruction {
         "SHLO_R_IMM_ALT_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let imm = (p.immediate_x as u64) & 0xffff_ffff;
         let result = if shift == 0 { imm } else { imm >> shift };
@@ -85,7 +83,7 @@ impl InstructionHandler for SharRImmAlt32Instruction {
         "SHAR_R_IMM_ALT_32"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = (get_register(context.registers, p.register_b) % 32) as u32;
         let imm = (p.immediate_x as u64) & 0xffff_ffff;
         let result = arithmetic_shift_right_32(imm, shift);
@@ -3,9 +3,7 @@
 use crate::config::{
     OPCODE_SHAR_R_IMM_ALT_64, OPCODE_SHLO_L_IMM_ALT_64, OPCODE_SHLO_R_IMM_ALT_64,
 };
-use crate::instructions::base::{
-    arithmetic_shift_right_64, parse_two_registers_and_immediate, InstructionHandler,
-};
+use crate::instructions::base::{arithmetic_shift_right_64, InstructionHandler};
 use crate::types::{InstructionContext, InstructionResult};
 
 fn get_register(registers: &[u64; 13], index: u8) -> u64 {
@@ -34,7 +32,7 @@ impl InstructionHandler for ShloLImmAlt64Instruction {
         "SHLO_L_IMM_ALT_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = get_register(context.registers, p.register_b) % 64;
         let imm = p.immediate_x as u64;
         let result = (imm << shift) & 0xffff_ffff_ffff_ffff;
@@ -59,7 +57,7 @@ impl InstructionHandler for ShloRImmAlt64Instruction {
         "SHLO_R_IMM_ALT_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = get_register(context.registers, p.register_b) % 64;
         let imm = p.immediate_x as u64;
         let result = imm >> shift;
@@ -84,7 +82,7 @@ impl InstructionHandler for SharRImmAlt64Instruction {
         "SHAR_R_IMM_ALT_64"
     }
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let p = parse_two_registers_and_immediate(context.operands, context.fskip);
+        let p = context.operands;
         let shift = (get_register(context.registers, p.register_b) % 64) as u32;
         let imm = p.immediate_x as u64;
         let result = arithmetic_shift_right_64(imm, shift);
@@ -24,11 +24,8 @@ impl InstructionHandler for EcalliInstruction {
     }
 
     fn execute(&self, context: &mut InstructionContext<'_>) -> InstructionResult {
-        let host_call_id = context
-            .operands
-            .first()
-            .copied()
-            .unwrap_or(0) as u32;
+        // Host call ID is immed_X truncated to 32 bits (registry keys are u32).
+        let host_call_id = context.operands.immediate_x as u32;
         if let Some(ref mut out) = context.host_call_id_out {
             **out = host_call_id;
         }
@@ -41,3 +38,26 @@ impl Default for EcalliInstruction {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{FUNC_LOG, REG_WHAT};
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+
+    #[test]
+    fn host_call_id_is_the_full_immediate() {
+        // ECALLI 356 (immed_X = 0x64 0x01); TRAP
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.load_code(vec![10, 0x64, 0x01, 0], vec![1, 0, 0, 1], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        state.registers[7] = 0;
+
+        assert!(next_step_impl(&mut state));
+        assert_eq!(state.host_call_id, 356);
+        assert_ne!(state.host_call_id, FUNC_LOG);
+        assert_eq!(state.registers[7], REG_WHAT);
+        assert!(state.log_messages.is_empty());
+        assert_eq!(state.program_counter, 3);
+    }
+}
@@ -21,33 +21,52 @@ macro_rules! host_log_error {
     };
 }
 
+mod assembler;
+mod cfg;
 mod config;
 mod codec;
 mod crypto;
+mod debugger;
+mod disassembler;
+mod gas;
+mod guest;
 mod host_functions;
+mod instance;
 mod instructions;
+mod mmap;
+mod mmap_ram;
 mod mock_ram;
 mod parser;
+mod profiler;
 mod pvm;
 mod ram;
+mod recompiler;
 mod simple_ram;
+mod snapshot;
 mod state_wrapper;
+mod trace;
+mod trace_check;
 mod types;
+mod verifier;
 
 use napi::bindgen_prelude::{BigInt, *};
 use napi_derive::napi;
 
 use crate::codec::{decode_implications_pair, encode_implications_pair};
-use crate::config::DEFAULT_GAS_LIMIT;
-use crate::types::Ram;
-use state_wrapper::{
-    get_accumulation_context_encoded, get_state, init_memory_layout_impl,
-    init_page_impl, init_state, next_step_impl, prepare_blob_impl, reset_state, run_blob_impl,
-    set_memory_impl, setup_accumulate_from_preimage, setup_refine_from_preimage,
-    SetupAccumulateParams, SetupRefineParams, RAMType, Status,
+pub use crate::host_functions::base::{HostFunction, HostFunctionContext, HostFunctionResult};
+pub use crate::host_functions::{HostFunctionRegistry, InvocationKind};
+pub use crate::instance::{
+    AccumulateInvocationResultOutput, AssembleResultOutput, GasBlockOutput, GasEntryOutput,
+    GasEstimateOutput, GasPathOutput, IsAuthorizedInputs, IsAuthorizedResultOutput,
+    MemoryRangeDiffOutput, PageDiffOutput, ProfileBlockOutput, ProfileHostCallOutput,
+    ProfileOpcodeOutput, ProfilePcOutput, ProfileReportOutput, ProgramDiagnosticOutput,
+    PvmInstance, RefineInvocationInputs, RunProgramResultOutput, StopEventOutput,
+    TraceCheckResultOutput,
 };
+use instance::default_instance;
+use state_wrapper::{RAMType, Status};
 
-// --- RAMType values (caller can use 0, 1, 2 or these getters) ---
+// --- RAMType values (caller can use 0, 1, 2, 3 or these getters) ---
 #[napi]
 pub fn get_ram_type_pvm_ram() -> i32 {
     RAMType::PvmRam as i32
@@ -63,111 +82,292 @@ pub fn get_ram_type_mock_ram() -> i32 {
     RAMType::MockRAM as i32
 }
 
+#[napi]
+pub fn get_ram_type_mmap_ram() -> i32 {
+    RAMType::MmapRAM as i32
+}
+
 // --- NAPI exports mirroring assembly/index.ts ---
+// Thin compatibility shim: every free function forwards to the default `PvmInstance`.
+
+/// Run `f` against the default instance, or return `default` when `init` has not been called.
+fn with_default<R>(default: R, f: impl FnOnce(&mut PvmInstance) -> R) -> R {
+    default_instance().as_mut().map_or(default, f)
+}
 
 #[napi]
 pub fn init(ram_type: i32) {
-    init_state(ram_type);
+    *default_instance() = Some(PvmInstance::with_ram_type(ram_type));
 }
 
 #[napi]
 pub fn reset() {
-    reset_state();
+    with_default((), PvmInstance::reset);
 }
 
 #[napi]
-pub fn reset_generic(_program: Buffer, _registers: Buffer, _gas: u32) {
-    reset_state();
+pub fn reset_generic(program: Buffer, registers: Buffer, gas: BigInt) {
+    with_default((), |pvm| pvm.reset_generic(program, registers, gas));
 }
 
 #[napi]
 pub fn reset_generic_with_memory(
-    _program_ptr: Buffer,
-    _registers_ptr: Buffer,
-    _page_map_ptr: Buffer,
-    _chunks_ptr: Buffer,
-    _gas: u32,
+    program_ptr: Buffer,
+    registers_ptr: Buffer,
+    page_map_ptr: Buffer,
+    chunks_ptr: Buffer,
+    gas: BigInt,
 ) {
-    reset_state();
+    with_default((), |pvm| {
+        pvm.reset_generic_with_memory(program_ptr, registers_ptr, page_map_ptr, chunks_ptr, gas)
+    });
 }
 
 #[napi]
 pub fn next_step() -> bool {
-    let mut g = get_state();
-    g.as_mut().map_or(false, next_step_impl)
+    with_default(false, PvmInstance::next_step)
 }
 
 #[napi]
 pub fn n_steps(steps: i32) -> bool {
-    let mut g = get_state();
-    let Some(state) = g.as_mut() else {
-        return false;
+    with_default(false, |pvm| pvm.n_steps(steps))
+}
+
+#[napi]
+pub fn set_external_host_calls(enabled: bool) {
+    with_default((), |pvm| pvm.set_external_host_calls(enabled));
+}
+
+#[napi]
+pub fn resume_host_call(gas_cost: Option<BigInt>) -> bool {
+    with_default(false, |pvm| pvm.resume_host_call(gas_cost))
+}
+
+#[napi]
+pub fn set_use_recompiler(enabled: bool) -> bool {
+    with_default(false, |pvm| pvm.set_use_recompiler(enabled))
+}
+
+#[napi]
+pub fn add_breakpoint(pc: u32) {
+    with_default((), |pvm| pvm.add_breakpoint(pc));
+}
+
+#[napi]
+pub fn remove_breakpoint(pc: u32) -> bool {
+    with_default(false, |pvm| pvm.remove_breakpoint(pc))
+}
+
+#[napi]
+pub fn add_opcode_breakpoint(opcode: u8) {
+    with_default((), |pvm| pvm.add_opcode_breakpoint(opcode));
+}
+
+#[napi]
+pub fn remove_opcode_breakpoint(opcode: u8) -> bool {
+    with_default(false, |pvm| pvm.remove_opcode_breakpoint(opcode))
+}
+
+#[napi]
+pub fn add_host_call_breakpoint(host_call_id: u32) {
+    with_default((), |pvm| pvm.add_host_call_breakpoint(host_call_id));
+}
+
+#[napi]
+pub fn remove_host_call_breakpoint(host_call_id: u32) -> bool {
+    with_default(false, |pvm| pvm.remove_host_call_breakpoint(host_call_id))
+}
+
+#[napi]
+pub fn add_watchpoint(address: u32, length: u32, kind: u8) -> Option<u32> {
+    with_default(None, |pvm| pvm.add_watchpoint(address, length, kind))
+}
+
+#[napi]
+pub fn remove_watchpoint(id: u32) -> bool {
+    with_default(false, |pvm| pvm.remove_watchpoint(id))
+}
+
+#[napi]
+pub fn clear_breakpoints() {
+    with_default((), PvmInstance::clear_breakpoints);
+}
+
+#[napi]
+pub fn run_until_event(max_steps: Option<u32>) -> StopEventOutput {
+    let no_instance = StopEventOutput {
+        reason: "panic".to_string(),
+        steps: 0,
+        pc: 0,
+        opcode: None,
+        host_call_id: None,
+        address: None,
+        length: None,
+        is_write: None,
+        watchpoint_id: None,
     };
-    let n = steps.max(0) as u32;
-    for _ in 0..n {
-        if !next_step_impl(state) {
-            return false;
-        }
-    }
-    true
+    with_default(no_instance, |pvm| pvm.run_until_event(max_steps))
 }
 
 #[napi]
-pub fn run_blob(program: Buffer) {
-    run_blob_impl(program.as_ref());
+pub fn set_trace(enabled: bool, path: Option<String>) -> bool {
+    with_default(false, |pvm| pvm.set_trace(enabled, path))
 }
 
 #[napi]
-pub fn prepare_blob(program: Buffer) {
-    prepare_blob_impl(program.as_ref());
+pub fn take_trace() -> String {
+    with_default(String::new(), PvmInstance::take_trace)
+}
+
+#[napi]
+pub fn set_block_gas(enabled: bool, costs: Option<Vec<u32>>) {
+    with_default((), |pvm| pvm.set_block_gas(enabled, costs));
+}
+
+#[napi]
+pub fn set_verify_on_load(enabled: bool) {
+    with_default((), |pvm| pvm.set_verify_on_load(enabled));
+}
+
+#[napi]
+pub fn get_load_diagnostics() -> Vec<ProgramDiagnosticOutput> {
+    with_default(Vec::new(), |pvm| pvm.get_load_diagnostics())
 }
 
-/// AccumulateInvocationResult returned as object (gasConsumed, resultCode, output).
-#[napi(object)]
-pub struct AccumulateInvocationResultOutput {
-    pub gas_consumed: u32,
-    pub result_code: u8,
-    pub output: Buffer,
+#[napi]
+pub fn set_profiling(enabled: bool) {
+    with_default((), |pvm| pvm.set_profiling(enabled));
 }
 
 #[napi]
+pub fn get_profile() -> Option<ProfileReportOutput> {
+    with_default(None, |pvm| pvm.get_profile())
+}
+
+#[napi]
+pub fn check_trace(reference: String) -> TraceCheckResultOutput {
+    let no_instance = TraceCheckResultOutput {
+        matched: false,
+        steps: 0,
+        finished: false,
+        step: None,
+        pc: None,
+        opcode: None,
+        mismatches: vec![],
+        expected: None,
+        actual: None,
+    };
+    with_default(no_instance, |pvm| pvm.check_trace(reference))
+}
+
+#[napi]
+pub fn snapshot() -> Buffer {
+    with_default(Vec::new().into(), |pvm| pvm.snapshot())
+}
+
+#[napi]
+pub fn restore(blob: Buffer) -> bool {
+    with_default(false, |pvm| pvm.restore(blob))
+}
+
+#[napi]
+pub fn get_dirty_pages() -> Vec<u32> {
+    with_default(Vec::new(), |pvm| pvm.get_dirty_pages())
+}
+
+#[napi]
+pub fn clear_dirty_pages() {
+    with_default((), PvmInstance::clear_dirty_pages);
+}
+
+#[napi]
+pub fn memory_diff(snapshot: Buffer) -> Option<Vec<PageDiffOutput>> {
+    with_default(None, |pvm| pvm.memory_diff(snapshot))
+}
+
+/// Copy-on-write copy of the default instance as a new `PvmInstance`; null before `init` or when the RAM
+/// cannot be copied.
+#[napi]
+pub fn fork() -> Option<PvmInstance> {
+    with_default(None, |pvm| pvm.fork())
+}
+
+#[napi]
+pub fn run_blob(program: Buffer) {
+    with_default((), |pvm| pvm.run_blob(program));
+}
+
+#[napi]
+pub fn prepare_blob(program: Buffer) {
+    with_default((), |pvm| pvm.prepare_blob(program));
+}
+
+#[napi]
+#[allow(clippy::too_many_arguments)]
 pub fn accumulate_invocation(
-    _gas_limit: u32,
-    _program: Buffer,
-    _args: Buffer,
-    _context: Buffer,
-    _num_cores: i32,
-    _num_validators: i32,
-    _auth_queue_size: i32,
-    _entropy_accumulator: Buffer,
-    _encoded_work_items: Buffer,
-    _config_num_cores: i32,
-    _config_preimage_expunge_period: u32,
-    _config_epoch_duration: u32,
-    _config_max_block_gas: i64,
-    _config_tickets_per_validator: u16,
-    _config_slot_duration: u16,
-    _config_rotation_period: u16,
-    _config_num_validators: u16,
+    gas_limit: BigInt,
+    program: Buffer,
+    args: Buffer,
+    context: Buffer,
+    num_cores: i32,
+    num_validators: i32,
+    auth_queue_size: i32,
+    entropy_accumulator: Buffer,
+    encoded_work_items: Buffer,
+    encoded_accumulate_inputs: Option<Vec<Buffer>>,
+    config_preimage_expunge_period: u32,
+    config_epoch_duration: u32,
+    config_max_block_gas: i64,
+    config_max_refine_gas: i64,
+    config_max_tickets_per_extrinsic: u16,
+    config_tickets_per_validator: u16,
+    config_slot_duration: u16,
+    config_rotation_period: u16,
+    config_num_ec_pieces_per_segment: u32,
+    config_contest_duration: u32,
+    config_max_lookup_anchorage: u32,
+    config_ec_piece_size: u32,
 ) -> AccumulateInvocationResultOutput {
-    let g = get_state();
-    if g.is_none() {
-        return AccumulateInvocationResultOutput {
-            gas_consumed: 0,
-            result_code: 1, // PANIC
-            output: Vec::new().into(),
-        };
-    }
-    AccumulateInvocationResultOutput {
-        gas_consumed: 0,
-        result_code: 1,
+    let no_instance = AccumulateInvocationResultOutput {
+        gas_consumed: BigInt::from(0u64),
+        result_code: 1, // PANIC
         output: Vec::new().into(),
-    }
+        yield_hash: Vec::new().into(),
+        context: Vec::new().into(),
+        post_state: Vec::new().into(),
+    };
+    with_default(no_instance, |pvm| {
+        pvm.accumulate_invocation(
+            gas_limit,
+            program,
+            args,
+            context,
+            num_cores,
+            num_validators,
+            auth_queue_size,
+            entropy_accumulator,
+            encoded_work_items,
+            encoded_accumulate_inputs,
+            config_preimage_expunge_period,
+            config_epoch_duration,
+            config_max_block_gas,
+            config_max_refine_gas,
+            config_max_tickets_per_extrinsic,
+            config_tickets_per_validator,
+            config_slot_duration,
+            config_rotation_period,
+            config_num_ec_pieces_per_segment,
+            config_contest_duration,
+            config_max_lookup_anchorage,
+            config_ec_piece_size,
+        )
+    })
 }
 
 #[napi]
+#[allow(clippy::too_many_arguments)]
 pub fn setup_accumulate_invocation(
-    gas_limit: u32,
+    gas_limit: BigInt,
     program: Buffer,
     args: Buffer,
     context: Buffer,
@@ -189,365 +389,269 @@ pub fn setup_accumulate_invocation(
     config_contest_duration: u32,
     config_max_lookup_anchorage: u32,
     config_ec_piece_size: u32,
-) {
-    let encoded_inputs = encoded_accumulate_inputs
-        .map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
-    let params = SetupAccumulateParams {
-        program: program.as_ref(),
-        args: args.as_ref(),
-        encoded_context: context.as_ref(),
-        gas_limit,
-        num_cores: num_cores.max(0) as u32,
-        num_validators: num_validators.max(0) as u32,
-        auth_queue_size: auth_queue_size.max(0) as u32,
-        entropy_accumulator: entropy_accumulator.as_ref(),
-        encoded_work_items: encoded_work_items.as_ref(),
-        encoded_accumulate_inputs: encoded_inputs,
-        config_preimage_expunge_period,
-        config_epoch_duration,
-        config_max_block_gas: config_max_block_gas.max(0) as u64,
-        config_max_refine_gas: config_max_refine_gas.max(0) as u64,
-        config_max_tickets_per_extrinsic,
-        config_tickets_per_validator,
-        config_slot_duration,
-        config_rotation_period,
-        config_num_ec_pieces_per_segment,
-        config_contest_duration,
-        config_max_lookup_anchorage,
-        config_ec_piece_size,
-    };
-    setup_accumulate_from_preimage(params);
+) -> bool {
+    with_default(false, |pvm| {
+        pvm.setup_accumulate_invocation(
+            gas_limit,
+            program,
+            args,
+            context,
+            num_cores,
+            num_validators,
+            auth_queue_size,
+            entropy_accumulator,
+            encoded_work_items,
+            encoded_accumulate_inputs,
+            config_preimage_expunge_period,
+            config_epoch_duration,
+            config_max_block_gas,
+            config_max_refine_gas,
+            config_max_tickets_per_extrinsic,
+            config_tickets_per_validator,
+            config_slot_duration,
+            config_rotation_period,
+            config_num_ec_pieces_per_segment,
+            config_contest_duration,
+            config_max_lookup_anchorage,
+            config_ec_piece_size,
+        )
+    })
 }
 
 #[napi]
 pub fn setup_refine_invocation(
-    gas_limit: u32,
+    gas_limit: BigInt,
     program: Buffer,
     args: Buffer,
     refine_context_encoded: Buffer,
     config_max_refine_gas: i64,
-) {
-    let params = SetupRefineParams {
-        program: program.as_ref(),
-        args: args.as_ref(),
-        refine_context_encoded: refine_context_encoded.as_ref(),
-        gas_limit,
-        config_max_refine_gas: config_max_refine_gas.max(0) as u64,
+    inputs: Option<RefineInvocationInputs>,
+) -> bool {
+    with_default(false, |pvm| {
+        pvm.setup_refine_invocation(
+            gas_limit,
+            program,
+            args,
+            refine_context_encoded,
+            config_max_refine_gas,
+            inputs,
+        )
+    })
+}
+
+#[napi]
+pub fn setup_is_authorized_invocation(
+    program: Buffer,
+    core_index: u16,
+    inputs: IsAuthorizedInputs,
+) -> bool {
+    with_default(false, |pvm| {
+        pvm.setup_is_authorized_invocation(program, core_index, inputs)
+    })
+}
+
+#[napi]
+pub fn is_authorized_invocation(
+    program: Buffer,
+    core_index: u16,
+    inputs: IsAuthorizedInputs,
+) -> IsAuthorizedResultOutput {
+    let no_instance = IsAuthorizedResultOutput {
+        gas_consumed: BigInt::from(0u64),
+        result_code: 1,
+        output: Vec::new().into(),
+        error: Some("PANIC".to_string()),
     };
-    setup_refine_from_preimage(params);
+    with_default(no_instance, |pvm| {
+        pvm.is_authorized_invocation(program, core_index, inputs)
+    })
 }
 
 #[napi]
 pub fn get_export_segments() -> Vec<Buffer> {
-    state_wrapper::get_export_segments()
-        .into_iter()
-        .map(Buffer::from)
-        .collect()
+    with_default(vec![], |pvm| pvm.get_export_segments())
 }
 
 #[napi]
 pub fn set_accumulate_inputs(inputs: Option<Vec<Buffer>>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.accumulate_inputs_encoded = inputs
-            .map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect())
-            .unwrap_or_default();
-    }
+    with_default((), |pvm| pvm.set_accumulate_inputs(inputs));
 }
 
 #[napi]
 pub fn set_fetch_work_package(encoded: Option<Buffer>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.work_package_encoded = encoded.map(|b| b.as_ref().to_vec());
-    }
+    with_default((), |pvm| pvm.set_fetch_work_package(encoded));
 }
 
 #[napi]
 pub fn set_fetch_auth_config(data: Option<Buffer>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.auth_config = data.map(|b| b.as_ref().to_vec());
-    }
+    with_default((), |pvm| pvm.set_fetch_auth_config(data));
 }
 
 #[napi]
 pub fn set_fetch_auth_token(data: Option<Buffer>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.auth_token = data.map(|b| b.as_ref().to_vec());
-    }
+    with_default((), |pvm| pvm.set_fetch_auth_token(data));
 }
 
 #[napi]
 pub fn set_fetch_refine_context(encoded: Option<Buffer>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.refine_context_encoded = encoded.map(|b| b.as_ref().to_vec());
-    }
+    with_default((), |pvm| pvm.set_fetch_refine_context(encoded));
 }
 
 #[napi]
 pub fn set_fetch_work_item_summaries(summaries: Option<Vec<Buffer>>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.work_item_summaries = summaries
-            .map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
-    }
+    with_default((), |pvm| pvm.set_fetch_work_item_summaries(summaries));
 }
 
 #[napi]
 pub fn set_fetch_work_item_payloads(payloads: Option<Vec<Buffer>>) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.work_item_payloads = payloads
-            .map(|v| v.into_iter().map(|b| b.as_ref().to_vec()).collect());
-    }
-}
-
-#[napi(object)]
-pub struct RunProgramResultOutput {
-    pub gas_consumed: u32,
-    pub result_code: u8,
+    with_default((), |pvm| pvm.set_fetch_work_item_payloads(payloads));
 }
 
 #[napi]
 pub fn run_program() -> RunProgramResultOutput {
-    let mut g = get_state();
-    let Some(state) = g.as_mut() else {
-        return RunProgramResultOutput {
-            gas_consumed: 0,
-            result_code: 1,
-        };
+    let no_instance = RunProgramResultOutput {
+        gas_consumed: BigInt::from(0u64),
+        result_code: 1,
     };
-    while next_step_impl(state) {}
-    RunProgramResultOutput {
-        gas_consumed: DEFAULT_GAS_LIMIT.saturating_sub(state.gas_left),
-        result_code: state.result_code,
-    }
+    with_default(no_instance, PvmInstance::run_program)
 }
 
 #[napi]
 pub fn get_program_counter() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.program_counter)
+    with_default(0, |pvm| pvm.get_program_counter())
 }
 
 #[napi]
 pub fn set_next_program_counter(pc: u32) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.program_counter = pc;
-    }
+    with_default((), |pvm| pvm.set_next_program_counter(pc));
 }
 
 #[napi]
-pub fn get_gas_left() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.gas_left)
+pub fn get_gas_left() -> BigInt {
+    with_default(BigInt::from(0u64), |pvm| pvm.get_gas_left())
 }
 
 #[napi]
-pub fn set_gas_left(gas: i64) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.gas_left = gas as u32;
-    }
+pub fn set_gas_left(gas: BigInt) {
+    with_default((), |pvm| pvm.set_gas_left(gas));
 }
 
 #[napi]
 pub fn get_status() -> i32 {
-    let g = get_state();
-    g.as_ref()
-        .map_or(Status::Panic as i32, |s| s.status as i32)
+    with_default(Status::Panic as i32, |pvm| pvm.get_status())
 }
 
 #[napi]
 pub fn get_exit_arg() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.exit_arg)
+    with_default(0, |pvm| pvm.get_exit_arg())
 }
 
 #[napi]
 pub fn get_host_call_id() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.host_call_id)
+    with_default(0, |pvm| pvm.get_host_call_id())
 }
 
 #[napi]
 pub fn get_result_code() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(1, |s| s.result_code as u32)
+    with_default(1, |pvm| pvm.get_result_code())
 }
 
 /// Gray Paper equation 831: When HALT, result is the memory range [registers[7], registers[7]+registers[8]].
 #[napi]
 pub fn get_result() -> Buffer {
-    let mut g = get_state();
-    if let Some(state) = g.as_mut() {
-        let offset = state.registers.get(7).copied().unwrap_or(0) as u32;
-        let length = state.registers.get(8).copied().unwrap_or(0) as u32;
-        if length == 0 {
-            return Vec::new().into();
-        }
-        let read_result = state.ram.read_octets(offset, length);
-        if read_result.fault_address != 0 || read_result.data.is_none() {
-            return Vec::new().into();
-        }
-        return read_result.data.unwrap().into();
-    }
-    Vec::new().into()
+    with_default(Vec::new().into(), PvmInstance::get_result)
 }
 
 /// Returns the 32-byte accumulation yield hash when set (e.g. by YIELD host).
 /// Used when get_result() is empty (r7/r8 not set) but the run halted successfully with a yield.
 #[napi]
 pub fn get_yield_hash() -> Buffer {
-    let g = get_state();
-    g.as_ref()
-        .and_then(|s| s.yield_hash.as_ref())
-        .filter(|h| h.len() == 32)
-        .cloned()
-        .unwrap_or_default()
-        .into()
+    with_default(Vec::new().into(), |pvm| pvm.get_yield_hash())
 }
 
 #[napi]
 pub fn get_last_opcode() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.last_opcode as u32)
+    with_default(0, |pvm| pvm.get_last_opcode())
 }
 
 #[napi]
 pub fn get_last_load_address() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.last_load_address)
+    with_default(0, |pvm| pvm.get_last_load_address())
 }
 
 /// Returns last load value as BigInt so JS gets full u64 precision (see docs.rs/napi/struct.BigInt).
 #[napi]
 pub fn get_last_load_value() -> BigInt {
-    let g = get_state();
-    BigInt::from(g.as_ref().map_or(0, |s| s.last_load_value))
+    with_default(BigInt::from(0u64), |pvm| pvm.get_last_load_value())
 }
 
 #[napi]
 pub fn get_last_store_address() -> u32 {
-    let g = get_state();
-    g.as_ref().map_or(0, |s| s.last_store_address)
+    with_default(0, |pvm| pvm.get_last_store_address())
 }
 
 /// Returns last store value as BigInt so JS gets full u64 precision (see docs.rs/napi/struct.BigInt).
 #[napi]
 pub fn get_last_store_value() -> BigInt {
-    let g = get_state();
-    BigInt::from(g.as_ref().map_or(0, |s| s.last_store_value))
+    with_default(BigInt::from(0u64), |pvm| pvm.get_last_store_value())
 }
 
 #[napi]
 pub fn clear_last_memory_op() {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.last_load_address = 0;
-        s.last_load_value = 0;
-        s.last_store_address = 0;
-        s.last_store_value = 0;
-    }
+    with_default((), PvmInstance::clear_last_memory_op);
 }
 
 /// Returns LOG host function messages since last drain and clears the queue. Executor should call after each step and forward to console.log.
 #[napi]
 pub fn get_and_clear_log_messages() -> Vec<String> {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        std::mem::take(&mut s.log_messages)
-    } else {
-        vec![]
-    }
+    with_default(vec![], PvmInstance::get_and_clear_log_messages)
 }
 
 #[napi]
 pub fn get_code() -> Buffer {
-    let g = get_state();
-    g.as_ref()
-        .map_or(Vec::new(), |s| s.code.clone())
-        .into()
+    with_default(Vec::new().into(), |pvm| pvm.get_code())
 }
 
 #[napi]
 pub fn get_bitmask() -> Buffer {
-    let g = get_state();
-    g.as_ref()
-        .map_or(Vec::new(), |s| s.bitmask.clone())
-        .into()
+    with_default(Vec::new().into(), |pvm| pvm.get_bitmask())
 }
 
 #[napi]
 pub fn get_registers() -> Buffer {
-    let g = get_state();
-    let mut out = vec![0u8; 13 * 8];
-    if let Some(s) = g.as_ref() {
-        for (i, &r) in s.registers.iter().enumerate() {
-            let start = i * 8;
-            out[start..start + 8].copy_from_slice(&r.to_le_bytes());
-        }
-    }
-    out.into()
+    with_default(vec![0u8; 13 * 8].into(), |pvm| pvm.get_registers())
 }
 
 #[napi]
 pub fn set_registers(registers: Buffer) {
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        let buf = registers.as_ref();
-        for (i, chunk) in buf.chunks_exact(8).take(13).enumerate() {
-            let mut bytes = [0u8; 8];
-            bytes.copy_from_slice(chunk);
-            s.registers[i] = u64::from_le_bytes(bytes);
-        }
-    }
+    with_default((), |pvm| pvm.set_registers(registers));
 }
 
 /// Single-register get; returns BigInt to match AS (getRegister returns u64 → bigint). i64 would become Number and lose precision.
 #[napi]
 pub fn get_register(index: u8) -> BigInt {
-    if index >= 13 {
-        return BigInt::from(0u64);
-    }
-    let g = get_state();
-    BigInt::from(g.as_ref().map_or(0, |s| s.registers[index as usize]))
+    with_default(BigInt::from(0u64), |pvm| pvm.get_register(index))
 }
 
 /// Single-register set; accepts BigInt to match AS (setRegister takes u64/bigint). i64 would truncate values above 2^53.
 #[napi]
 pub fn set_register(index: u8, value: BigInt) {
-    if index >= 13 {
-        return;
-    }
-    let (_, val, lossless) = value.get_u64();
-    if !lossless {
-        return; // value out of u64 range; ignore or could panic
-    }
-    let mut g = get_state();
-    if let Some(s) = g.as_mut() {
-        s.registers[index as usize] = val;
-    }
+    with_default((), |pvm| pvm.set_register(index, value));
 }
 
 #[napi]
 pub fn get_page_dump(page_index: i32) -> Buffer {
-    let g = get_state();
-    g.as_ref()
-        .map_or_else(
-            || vec![0u8; 4096],
-            |s| s.ram.get_page_dump(page_index as u32),
-        )
-        .into()
+    with_default(vec![0u8; 4096].into(), |pvm| pvm.get_page_dump(page_index))
+}
+
+#[napi]
+pub fn read_memory(address: u32, length: u32) -> Option<Buffer> {
+    with_default(None, |pvm| pvm.read_memory(address, length))
 }
 
 #[napi]
 pub fn set_memory(address: u32, data: Buffer) {
-    set_memory_impl(address, data.as_ref());
+    with_default((), |pvm| pvm.set_memory(address, data));
 }
 
 #[napi]
@@ -556,30 +660,19 @@ pub fn get_accumulation_context(
     num_validators: i32,
     auth_queue_size: i32,
 ) -> Buffer {
-    // Return full encoded implications pair (with updated accounts + yield) so caller can decode
-    // and use as updated context, matching WASM getAccumulationContext.
-    if let Some(encoded) = get_accumulation_context_encoded(num_cores, num_validators, auth_queue_size) {
-        return encoded.into();
-    }
-    // Fallback: yield hash only (legacy 32-byte return)
-    let g = get_state();
-    g.as_ref()
-        .and_then(|s| s.yield_hash.as_ref())
-        .filter(|h| h.len() == 32)
-        .cloned()
-        .unwrap_or_default()
-        .into()
+    with_default(Vec::new().into(), |pvm| {
+        pvm.get_accumulation_context(num_cores, num_validators, auth_queue_size)
+    })
 }
 
 #[napi]
 pub fn has_accumulation_context() -> bool {
-    let g = get_state();
-    g.as_ref().map_or(false, |s| s.has_accumulation_context)
+    with_default(false, |pvm| pvm.has_accumulation_context())
 }
 
 #[napi]
 pub fn init_page(address: u32, length: u32, access_type: u8) {
-    init_page_impl(address, length, access_type);
+    with_default((), |pvm| pvm.init_page(address, length, access_type));
 }
 
 /// Initialize memory layout (Gray Paper 770–802) on current state's RAM. Use with PvmRam after prepareBlob so heap at 0x20000 etc. exist.
@@ -591,23 +684,77 @@ pub fn init_memory_layout(
     stack_size: u32,
     heap_zero_padding_size: u32,
 ) {
-    init_memory_layout_impl(
-        argument_data.as_ref(),
-        read_only_data.as_ref(),
-        read_write_data.as_ref(),
-        stack_size,
-        heap_zero_padding_size,
-    );
+    with_default((), |pvm| {
+        pvm.init_memory_layout(
+            argument_data,
+            read_only_data,
+            read_write_data,
+            stack_size,
+            heap_zero_padding_size,
+        )
+    });
 }
 
 #[napi]
 pub fn initialize_program(_program: Buffer, _args: Buffer) {
-    let g = get_state();
+    let mut g = default_instance();
     if g.is_none() {
-        init_state(RAMType::PvmRam as i32);
+        *g = Some(PvmInstance::with_ram_type(RAMType::PvmRam as i32));
+    }
+}
+
+// --- Disassembler / assembler / verifier ---
+
+/// Render a program blob (deblob format) as a listing: PC, bytes, mnemonic and operands per line,
+/// preceded by the jump table. Null when the blob does not decode.
+#[napi]
+pub fn disassemble(program: Buffer) -> Option<String> {
+    disassembler::disassemble_blob(program.as_ref()).map(|listing| listing.to_string())
+}
+
+/// Assemble PVM source (mnemonics, labels, `.jump_table`, `.ro_data`/`.rw_data`, …) into a program blob.
+#[napi]
+pub fn assemble(source: String) -> AssembleResultOutput {
+    assembler::assemble(&source).into()
+}
+
+/// Statically verify a program blob (deblob format) without loading it. Null when the blob does not decode.
+#[napi]
+pub fn verify_program(program: Buffer) -> Option<Vec<ProgramDiagnosticOutput>> {
+    let parsed = parser::PvmParser::new().parse_program(program.as_ref());
+    parsed.success.then(|| {
+        verifier::verify_program(&parsed)
+            .iter()
+            .map(Into::into)
+            .collect()
+    })
+}
+
+/// Control-flow graph of a program blob (deblob format) as `dot` (Graphviz) or `json`: basic blocks with their
+/// instruction gas and successors (fallthrough, branch, jump, dynamic via the jump table). Null when the blob does
+/// not decode or the format is unknown.
+#[napi]
+pub fn export_cfg(program: Buffer, format: String) -> Option<String> {
+    let graph = cfg::build_cfg_blob(program.as_ref())?;
+    match format.as_str() {
+        "dot" => Some(graph.to_dot()),
+        "json" => Some(graph.to_json()),
+        _ => None,
     }
 }
 
+/// Static gas estimate of a program blob (deblob format): cost per basic block and the worst case over loop-free
+/// paths from PC 0 (refine / is-authorized) and PC 5 (accumulate). `costs[opcode]` is the alternative cost table
+/// (missing opcodes cost 1); without it a built-in weighting is used. Null when the blob does not decode.
+#[napi]
+pub fn estimate_gas(program: Buffer, costs: Option<Vec<u32>>) -> Option<GasEstimateOutput> {
+    let graph = cfg::build_cfg_blob(program.as_ref())?;
+    let model = costs.map_or_else(gas::GasCostModel::weighted, |costs| {
+        gas::GasCostModel::from_costs(&costs.into_iter().map(u64::from).collect::<Vec<_>>())
+    });
+    Some(gas::estimate_service_gas(&graph, &model).into())
+}
+
 // --- Codec helpers for equivalence tests (TS/AS vs Rust) ---
 
 /// Encode value as little-endian fixed length (1, 2, 4, 8, 16, or 32 bytes). Matches @pbnjam/codec encodeFixedLength.
@@ -640,3 +787,38 @@ pub fn round_trip_implications(
     );
     Some(encoded.into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The only test touching the default instance, so it cannot race with another.
+    #[test]
+    fn free_functions_forward_to_the_default_instance() {
+        *default_instance() = None;
+        assert!(!next_step());
+        assert_eq!(get_status(), Status::Panic as i32);
+        assert_eq!(get_register(1).get_u64().1, 0);
+
+        init(get_ram_type_pvm_ram());
+        // deblob: c = [LOAD_IMM r1, 7; TRAP], k = 0b1001
+        prepare_blob(vec![0, 0, 4, 51, 1, 7, 0, 0b1001].into());
+        set_gas_left(BigInt::from(5_000_000_000u64));
+        assert!(next_step());
+        assert_eq!(get_register(1).get_u64().1, 7);
+        assert_eq!(get_program_counter(), 3);
+        assert!(!next_step());
+        assert_eq!(get_status(), Status::Panic as i32);
+        assert_eq!(get_gas_left().get_u64().1, 4_999_999_998);
+
+        // A separate instance leaves the default one untouched.
+        let mut other = PvmInstance::new(None);
+        other.set_register(1, BigInt::from(3u64));
+        assert_eq!(get_register(1).get_u64().1, 7);
+
+        // init replaces the default instance with a fresh one.
+        init(get_ram_type_pvm_ram());
+        assert_eq!(get_register(1).get_u64().1, 0);
+        *default_instance() = None;
+    }
+}
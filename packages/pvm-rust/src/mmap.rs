@@ -0,0 +1,195 @@
+//! Anonymous page mappings (mmap/mprotect/munmap) for memory the allocator cannot provide,
+//! e.g. executable code for the recompiler or the reserved guest address space of `MmapRam`.
+//! Declared directly against libc, which std already links.
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_void};
+
+    pub const PROT_NONE: c_int = 0;
+    pub const PROT_READ: c_int = 1;
+    pub const PROT_WRITE: c_int = 2;
+    pub const PROT_EXEC: c_int = 4;
+    pub const MAP_PRIVATE: c_int = 0x02;
+    #[cfg(target_os = "linux")]
+    pub const MAP_ANONYMOUS: c_int = 0x20;
+    #[cfg(not(target_os = "linux"))]
+    pub const MAP_ANONYMOUS: c_int = 0x1000;
+    #[cfg(target_os = "linux")]
+    pub const MAP_NORESERVE: c_int = 0x4000;
+    #[cfg(not(target_os = "linux"))]
+    pub const MAP_NORESERVE: c_int = 0x40;
+    #[cfg(target_os = "linux")]
+    pub const MADV_DONTNEED: c_int = 4;
+
+    extern "C" {
+        pub fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        pub fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+        #[cfg(target_os = "linux")]
+        pub fn madvise(addr: *mut c_void, len: usize, advice: c_int) -> c_int;
+    }
+}
+
+/// Page protection for a mapped region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protection {
+    None,
+    ReadOnly,
+    ReadWrite,
+    ReadExecute,
+}
+
+/// An owned anonymous mapping, unmapped on drop. Starts read-write and zero-filled.
+pub struct MmapRegion {
+    ptr: *mut u8,
+    len: usize,
+}
+
+// The mapping is exclusively owned; access goes through &self / &mut self like a Vec<u8>.
+unsafe impl Send for MmapRegion {}
+unsafe impl Sync for MmapRegion {}
+
+impl MmapRegion {
+    /// Map `len` bytes (rounded up by the OS to whole pages). None if mapping is unsupported or fails.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn new(len: usize) -> Option<Self> {
+        Self::map(len, sys::MAP_PRIVATE | sys::MAP_ANONYMOUS)
+    }
+
+    /// Like `new`, but without reserving swap for the whole range: large sparse regions only use memory for the
+    /// pages that are touched.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn reserve(len: usize) -> Option<Self> {
+        Self::map(
+            len,
+            sys::MAP_PRIVATE | sys::MAP_ANONYMOUS | sys::MAP_NORESERVE,
+        )
+    }
+
+    #[cfg(not(unix))]
+    #[must_use]
+    pub fn reserve(_len: usize) -> Option<Self> {
+        None
+    }
+
+    #[cfg(unix)]
+    fn map(len: usize, flags: std::ffi::c_int) -> Option<Self> {
+        if len == 0 {
+            return None;
+        }
+        // SAFETY: anonymous private mapping with no address hint; the result is checked against MAP_FAILED.
+        let ptr = unsafe {
+            sys::mmap(
+                std::ptr::null_mut(),
+                len,
+                sys::PROT_READ | sys::PROT_WRITE,
+                flags,
+                -1,
+                0,
+            )
+        };
+        if ptr.is_null() || ptr as isize == -1 {
+            return None;
+        }
+        Some(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    #[cfg(not(unix))]
+    #[must_use]
+    pub fn new(_len: usize) -> Option<Self> {
+        None
+    }
+
+    /// Change the protection of `[offset, offset + len)`; both must be page aligned. False on failure.
+    #[cfg(unix)]
+    pub fn protect(&mut self, offset: usize, len: usize, protection: Protection) -> bool {
+        if offset.checked_add(len).is_none_or(|end| end > self.len) {
+            return false;
+        }
+        let prot = match protection {
+            Protection::None => sys::PROT_NONE,
+            Protection::ReadOnly => sys::PROT_READ,
+            Protection::ReadWrite => sys::PROT_READ | sys::PROT_WRITE,
+            Protection::ReadExecute => sys::PROT_READ | sys::PROT_EXEC,
+        };
+        // SAFETY: the range lies inside this mapping (checked above).
+        unsafe { sys::mprotect(self.ptr.add(offset).cast(), len, prot) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    pub fn protect(&mut self, _offset: usize, _len: usize, _protection: Protection) -> bool {
+        false
+    }
+
+    /// Release the memory behind `[offset, offset + len)`; it reads as zeros afterwards. Both must be page
+    /// aligned. False when unsupported (the caller zeroes instead).
+    #[cfg(target_os = "linux")]
+    pub fn discard(&mut self, offset: usize, len: usize) -> bool {
+        if offset.checked_add(len).is_none_or(|end| end > self.len) {
+            return false;
+        }
+        // SAFETY: the range lies inside this private anonymous mapping (checked above).
+        unsafe { sys::madvise(self.ptr.add(offset).cast(), len, sys::MADV_DONTNEED) == 0 }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn discard(&mut self, _offset: usize, _len: usize) -> bool {
+        false
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    #[must_use]
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// The mapping as bytes. Only valid while the region is readable.
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        // SAFETY: ptr..ptr+len is a live mapping owned by self.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// The mapping as mutable bytes. Only valid while the region is writable.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: ptr..ptr+len is a live mapping exclusively owned by self.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for MmapRegion {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: ptr/len describe the mapping created in new and not yet unmapped.
+        unsafe {
+            sys::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
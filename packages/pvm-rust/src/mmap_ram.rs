@@ -0,0 +1,615 @@
+//! Contiguous RAM: the whole 4 GiB guest address space is one `MmapRegion` reserved up front and backed by
+//! the OS on first touch, with access rights in a flat per-page table. An access within one page needs a
+//! single table lookup and a slice copy; only accesses that cross a page boundary check every page.
+//!
+//! Same layout and fault semantics as `PvmRam` (faults report the first inaccessible page). As there, a page
+//! that only had its rights set with `set_page_access_rights` has no contents yet: reading it faults until
+//! it is written or initialised.
+//!
+//! The recompiler accesses memory directly through `native_view`; the page table is the only per-page state
+//! it has to check and update, so dirty pages are tracked there rather than in a separate list.
+
+use crate::config::{self, align_to_page, align_to_zone};
+use crate::mmap::MmapRegion;
+use crate::snapshot::{access_from_u8, SnapshotReader, SnapshotWriter};
+use crate::types::{FaultCheckResult, MemoryAccessType, Ram, ReadResult, WriteResult};
+
+const ADDRESS_SPACE: u64 = 1 << 32;
+const PAGE_COUNT: usize = (ADDRESS_SPACE / config::PAGE_SIZE as u64) as usize;
+/// Set in the page table once a page may hold data (it is zeroed on reset).
+const TOUCHED: u8 = 0x80;
+/// Set once a page is written; cleared by `clear_dirty`.
+pub(crate) const DIRTY: u8 = 0x40;
+/// Set once a page has contents (`PvmRam` has allocated it): initialised, allocated or written.
+pub(crate) const BACKED: u8 = 0x20;
+const ACCESS_MASK: u8 = 0x03;
+/// Page table bits of a page that may be read (`Read` or `Write`) and of one that may be written.
+pub(crate) const READABLE: u8 = ACCESS_MASK;
+pub(crate) const WRITABLE: u8 = MemoryAccessType::Write as u8;
+
+/// Raw pointers for native code: guest address `a` is at `memory + a` and its page table entry at
+/// `pages + a / PAGE_SIZE`. Valid until the `MmapRam` is dropped.
+#[derive(Clone, Copy)]
+pub struct NativeView {
+    pub memory: *mut u8,
+    pub pages: *mut u8,
+}
+
+pub struct MmapRam {
+    memory: MmapRegion,
+    /// `MemoryAccessType` per page, plus `TOUCHED`, `DIRTY` and `BACKED`.
+    pages: Box<[u8]>,
+    /// Pages with `TOUCHED` set, in the order they were first touched. Every dirty page is among them.
+    touched: Vec<u32>,
+    ro_data_address: u32,
+    argument_data_address: u32,
+    stack_address_end: u32,
+    stack_address: u32,
+    heap_start_address: u32,
+    heap_end_address: u32,
+    ro_data_address_end: u32,
+    current_heap_pointer: u32,
+    argument_data_end: u32,
+    last_load_address: u32,
+    last_load_value: u64,
+    last_store_address: u32,
+    last_store_value: u64,
+}
+
+fn access_from_bits(bits: u8) -> MemoryAccessType {
+    match bits & ACCESS_MASK {
+        1 => MemoryAccessType::Read,
+        2 => MemoryAccessType::Write,
+        _ => MemoryAccessType::None,
+    }
+}
+
+fn little_endian_prefix(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .take(8)
+        .enumerate()
+        .fold(0u64, |acc, (i, &b)| acc | (u64::from(b) << (i * 8)))
+}
+
+impl MmapRam {
+    /// Reserve the guest address space. None when the platform cannot map 4 GiB (use `PvmRam` instead).
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        let memory = MmapRegion::reserve(usize::try_from(ADDRESS_SPACE).ok()?)?;
+        Some(Self {
+            memory,
+            pages: vec![0u8; PAGE_COUNT].into_boxed_slice(),
+            touched: Vec::new(),
+            ro_data_address: config::ZONE_SIZE,
+            argument_data_address: config::ARGS_SEGMENT_START,
+            stack_address_end: config::STACK_SEGMENT_END,
+            stack_address: 0,
+            heap_start_address: 0,
+            heap_end_address: 0,
+            ro_data_address_end: 0,
+            current_heap_pointer: 0,
+            argument_data_end: 0,
+            last_load_address: 0,
+            last_load_value: 0,
+            last_store_address: 0,
+            last_store_value: 0,
+        })
+    }
+
+    /// Copy into a new reservation (touched pages only). None when another 4 GiB cannot be reserved.
+    #[must_use]
+    pub fn fork(&self) -> Option<Self> {
+        let mut ram = Self {
+            memory: MmapRegion::reserve(usize::try_from(ADDRESS_SPACE).ok()?)?,
+            pages: self.pages.clone(),
+            touched: self.touched.clone(),
+            ..*self
+        };
+        let page_size = config::PAGE_SIZE as usize;
+        for &index in &self.touched {
+            let start = index as usize * page_size;
+            ram.memory.as_mut_slice()[start..start + page_size]
+                .copy_from_slice(&self.memory.as_slice()[start..start + page_size]);
+        }
+        Some(ram)
+    }
+
+    fn access(&self, page_index: u32) -> MemoryAccessType {
+        access_from_bits(self.pages[page_index as usize])
+    }
+
+    fn touch(&mut self, page_index: u32) {
+        let entry = &mut self.pages[page_index as usize];
+        if *entry & TOUCHED == 0 {
+            *entry |= TOUCHED;
+            self.touched.push(page_index);
+        }
+    }
+
+    fn is_backed(&self, page_index: u32) -> bool {
+        self.pages[page_index as usize] & BACKED != 0
+    }
+
+    fn mark_dirty(&mut self, page_index: u32) {
+        self.touch(page_index);
+        self.pages[page_index as usize] |= DIRTY | BACKED;
+    }
+
+    fn set_access(&mut self, page_index: u32, access_type: MemoryAccessType) {
+        self.touch(page_index);
+        let entry = &mut self.pages[page_index as usize];
+        *entry = (*entry & (DIRTY | BACKED)) | TOUCHED | access_type as u8;
+    }
+
+    fn set_backed_access(&mut self, page_index: u32, access_type: MemoryAccessType) {
+        self.set_access(page_index, access_type);
+        self.pages[page_index as usize] |= BACKED;
+    }
+
+    /// Pages covering `[address, address + size)`; `size` must be non-zero and the range must not wrap.
+    fn page_range(address: u32, size: u32) -> std::ops::RangeInclusive<u32> {
+        address / config::PAGE_SIZE..=(address + (size - 1)) / config::PAGE_SIZE
+    }
+
+    fn within_one_page(address: u32, size: u32) -> bool {
+        size > 0 && address % config::PAGE_SIZE + size <= config::PAGE_SIZE
+    }
+
+    /// Pointers for the recompiler's inline loads and stores. Native code may read a page whose entry has
+    /// a `READABLE` bit and `BACKED`, write one whose entry has `WRITABLE`, and must set `DIRTY` and
+    /// `BACKED` on every page it writes.
+    pub fn native_view(&mut self) -> NativeView {
+        NativeView {
+            memory: self.memory.as_mut_ptr(),
+            pages: self.pages.as_mut_ptr(),
+        }
+    }
+
+    /// Same wire format as `PvmRam::write_snapshot`; every touched page is written, with contents if backed.
+    pub(crate) fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        for value in [
+            self.ro_data_address,
+            self.argument_data_address,
+            self.stack_address_end,
+            self.stack_address,
+            self.heap_start_address,
+            self.heap_end_address,
+            self.ro_data_address_end,
+            self.current_heap_pointer,
+            self.argument_data_end,
+            self.last_load_address,
+            self.last_store_address,
+        ] {
+            w.u32(value);
+        }
+        w.u64(self.last_load_value);
+        w.u64(self.last_store_value);
+        let mut indices = self.touched.clone();
+        indices.sort_unstable();
+        w.len(indices.len());
+        for index in indices {
+            w.u32(index);
+            w.option(Some(&self.access(index)), |w, &access| w.u8(access as u8));
+            let contents = self.is_backed(index).then(|| self.get_page_dump(index));
+            w.option(contents.as_ref(), |w, page| w.bytes(page));
+        }
+    }
+
+    pub(crate) fn read_snapshot(r: &mut SnapshotReader<'_>) -> Option<Self> {
+        let mut ram = Self::new()?;
+        for value in [
+            &mut ram.ro_data_address,
+            &mut ram.argument_data_address,
+            &mut ram.stack_address_end,
+            &mut ram.stack_address,
+            &mut ram.heap_start_address,
+            &mut ram.heap_end_address,
+            &mut ram.ro_data_address_end,
+            &mut ram.current_heap_pointer,
+            &mut ram.argument_data_end,
+            &mut ram.last_load_address,
+            &mut ram.last_store_address,
+        ] {
+            *value = r.u32()?;
+        }
+        ram.last_load_value = r.u64()?;
+        ram.last_store_value = r.u64()?;
+        for _ in 0..r.len()? {
+            let index = r.u32()?;
+            if index as usize >= PAGE_COUNT {
+                return None;
+            }
+            let access = r.option(|r| access_from_u8(r.u8()?))?;
+            ram.set_access(index, access.unwrap_or(MemoryAccessType::None));
+            if let Some(page) = r.option(SnapshotReader::bytes)? {
+                if page.len() != config::PAGE_SIZE as usize {
+                    return None;
+                }
+                ram.write_octets_during_initialization(index * config::PAGE_SIZE, &page);
+            }
+        }
+        ram.clear_dirty();
+        Some(ram)
+    }
+}
+
+impl Ram for MmapRam {
+    fn read_octets(&mut self, address: u32, count: u32) -> ReadResult {
+        if Self::within_one_page(address, count) {
+            let page_index = address / config::PAGE_SIZE;
+            if self.access(page_index) == MemoryAccessType::None || !self.is_backed(page_index) {
+                return ReadResult::new(None, page_index * config::PAGE_SIZE);
+            }
+        } else {
+            // Like `PvmRam`: rights on every page first, then the first page without contents.
+            let check = self.is_readable_with_fault(address, count);
+            if !check.success {
+                return ReadResult::new(None, check.fault_address);
+            }
+            if count > 0 {
+                if let Some(page_index) =
+                    Self::page_range(address, count).find(|&page_index| !self.is_backed(page_index))
+                {
+                    return ReadResult::new(None, page_index * config::PAGE_SIZE);
+                }
+            }
+        }
+        let start = address as usize;
+        let result = self.memory.as_slice()[start..start + count as usize].to_vec();
+        self.last_load_address = address;
+        self.last_load_value = little_endian_prefix(&result);
+        ReadResult::new(Some(result), 0)
+    }
+
+    fn write_octets(&mut self, address: u32, values: &[u8]) -> WriteResult {
+        let size = values.len() as u32;
+        let check = if Self::within_one_page(address, size) {
+            let page_index = address / config::PAGE_SIZE;
+            if self.access(page_index) == MemoryAccessType::Write {
+                self.mark_dirty(page_index);
+                FaultCheckResult::new(true, 0)
+            } else {
+                FaultCheckResult::new(false, page_index * config::PAGE_SIZE)
+            }
+        } else {
+            let check = self.is_writable_with_fault(address, size);
+            if check.success && size > 0 {
+                for page_index in Self::page_range(address, size) {
+                    self.mark_dirty(page_index);
+                }
+            }
+            check
+        };
+        if !check.success {
+            return WriteResult::new(
+                true,
+                if check.fault_address != 0 {
+                    check.fault_address
+                } else {
+                    0xFFFF_FFFF
+                },
+            );
+        }
+        let start = address as usize;
+        self.memory.as_mut_slice()[start..start + values.len()].copy_from_slice(values);
+        self.last_store_address = address;
+        self.last_store_value = little_endian_prefix(values);
+        WriteResult::new(false, 0)
+    }
+
+    fn current_heap_pointer(&self) -> u32 {
+        self.current_heap_pointer
+    }
+
+    fn set_current_heap_pointer(&mut self, value: u32) {
+        self.current_heap_pointer = value;
+    }
+
+    fn allocate_pages(&mut self, start_page: u32, count: u32) {
+        let end_page = start_page + count;
+        for page_index in start_page..end_page {
+            self.set_backed_access(page_index, MemoryAccessType::Write);
+        }
+        let end_address = end_page * config::PAGE_SIZE;
+        if end_address > self.current_heap_pointer {
+            self.current_heap_pointer = end_address;
+        }
+    }
+
+    fn is_readable_with_fault(&self, address: u32, size: u32) -> FaultCheckResult {
+        if size == 0 {
+            return FaultCheckResult::new(true, 0);
+        }
+        if address.checked_add(size).is_none() {
+            return FaultCheckResult::new(false, address);
+        }
+        for page_index in Self::page_range(address, size) {
+            if self.access(page_index) == MemoryAccessType::None {
+                return FaultCheckResult::new(false, page_index * config::PAGE_SIZE);
+            }
+        }
+        FaultCheckResult::new(true, 0)
+    }
+
+    fn initialize_memory_layout(
+        &mut self,
+        argument_data: &[u8],
+        read_only_data: &[u8],
+        read_write_data: &[u8],
+        stack_size: u32,
+        heap_zero_padding_size: u32,
+    ) {
+        let ro_len = read_only_data.len() as u32;
+        let heap_size = read_write_data.len() as u32;
+        let args_len = argument_data.len() as u32;
+
+        let heap_start = 2 * config::ZONE_SIZE + align_to_zone(ro_len);
+        let heap_end = heap_start + align_to_page(heap_size);
+        let heap_zeros_end = heap_end + heap_zero_padding_size * config::PAGE_SIZE;
+
+        let args_start = self.argument_data_address;
+        let args_end = args_start + align_to_page(args_len);
+        let args_zero_padding_end = args_end + align_to_page(args_len);
+
+        let stack_end = self.stack_address_end;
+        let stack_start = stack_end - align_to_page(stack_size);
+
+        let ro_start = self.ro_data_address;
+        let ro_end = ro_start + align_to_page(ro_len);
+
+        if !argument_data.is_empty() {
+            self.write_octets_during_initialization(args_start, argument_data);
+        }
+        if !read_only_data.is_empty() {
+            self.write_octets_during_initialization(ro_start, read_only_data);
+        }
+        if !read_write_data.is_empty() {
+            self.write_octets_during_initialization(heap_start, read_write_data);
+        }
+
+        self.argument_data_end = args_zero_padding_end;
+        self.ro_data_address_end = ro_end;
+        self.stack_address = stack_start;
+        self.heap_start_address = heap_start;
+        self.heap_end_address = heap_end;
+        self.current_heap_pointer = heap_zeros_end;
+
+        if ro_len > 0 {
+            self.init_page(ro_start, ro_end - ro_start, MemoryAccessType::Read);
+        }
+        if args_len > 0 {
+            self.init_page(
+                args_start,
+                args_zero_padding_end - args_start,
+                MemoryAccessType::Read,
+            );
+        }
+        if stack_start < stack_end {
+            self.init_page(
+                stack_start,
+                stack_end - stack_start,
+                MemoryAccessType::Write,
+            );
+        }
+        if heap_size > 0 {
+            self.init_page(heap_start, heap_end - heap_start, MemoryAccessType::Write);
+        }
+        if heap_end < heap_zeros_end {
+            self.init_page(heap_end, heap_zeros_end - heap_end, MemoryAccessType::Write);
+        }
+    }
+
+    fn is_writable_with_fault(&self, address: u32, size: u32) -> FaultCheckResult {
+        if size == 0 {
+            return FaultCheckResult::new(true, 0);
+        }
+        if address.checked_add(size).is_none() {
+            return FaultCheckResult::new(false, address);
+        }
+        for page_index in Self::page_range(address, size) {
+            if self.access(page_index) != MemoryAccessType::Write {
+                return FaultCheckResult::new(false, page_index * config::PAGE_SIZE);
+            }
+        }
+        FaultCheckResult::new(true, 0)
+    }
+
+    fn set_page_access_rights(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
+        if length == 0 || address.checked_add(length - 1).is_none() {
+            return;
+        }
+        for page_index in Self::page_range(address, length) {
+            self.set_access(page_index, access_type);
+        }
+    }
+
+    fn init_page(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
+        if length == 0 || address.checked_add(length - 1).is_none() {
+            return;
+        }
+        for page_index in Self::page_range(address, length) {
+            self.set_backed_access(page_index, access_type);
+        }
+    }
+
+    fn write_octets_during_initialization(&mut self, address: u32, values: &[u8]) {
+        let Some(len) = u32::try_from(values.len()).ok().filter(|&len| len > 0) else {
+            return;
+        };
+        if address.checked_add(len - 1).is_none() {
+            return;
+        }
+        for page_index in Self::page_range(address, len) {
+            self.mark_dirty(page_index);
+        }
+        let start = address as usize;
+        self.memory.as_mut_slice()[start..start + values.len()].copy_from_slice(values);
+    }
+
+    fn get_page_dump(&self, page_index: u32) -> Vec<u8> {
+        let start = page_index as usize * config::PAGE_SIZE as usize;
+        self.memory.as_slice()[start..start + config::PAGE_SIZE as usize].to_vec()
+    }
+
+    fn reset(&mut self) {
+        // Zero touched pages: hand runs of adjacent pages back to the OS, or clear them where that is unsupported.
+        let page_size = config::PAGE_SIZE as usize;
+        let mut touched = std::mem::take(&mut self.touched);
+        touched.sort_unstable();
+        let mut i = 0;
+        while i < touched.len() {
+            let first = touched[i];
+            let mut last = first;
+            while touched.get(i + 1) == Some(&(last + 1)) {
+                last += 1;
+                i += 1;
+            }
+            i += 1;
+            let (offset, len) = (
+                first as usize * page_size,
+                (last - first + 1) as usize * page_size,
+            );
+            if !self.memory.discard(offset, len) {
+                self.memory.as_mut_slice()[offset..offset + len].fill(0);
+            }
+            self.pages[first as usize..=last as usize].fill(0);
+        }
+        touched.clear();
+        self.touched = touched;
+        self.stack_address = 0;
+        self.heap_start_address = 0;
+        self.heap_end_address = 0;
+        self.ro_data_address_end = 0;
+        self.current_heap_pointer = 0;
+        self.argument_data_end = 0;
+        self.last_load_address = 0;
+        self.last_load_value = 0;
+        self.last_store_address = 0;
+        self.last_store_value = 0;
+    }
+
+    fn last_load_address(&self) -> u32 {
+        self.last_load_address
+    }
+
+    fn last_load_value(&self) -> u64 {
+        self.last_load_value
+    }
+
+    fn last_store_address(&self) -> u32 {
+        self.last_store_address
+    }
+
+    fn last_store_value(&self) -> u64 {
+        self.last_store_value
+    }
+
+    fn clear_last_memory_op(&mut self) {
+        self.last_load_address = 0;
+        self.last_load_value = 0;
+        self.last_store_address = 0;
+        self.last_store_value = 0;
+    }
+
+    fn dirty_pages(&self) -> Vec<u32> {
+        let mut pages: Vec<u32> = self
+            .touched
+            .iter()
+            .copied()
+            .filter(|&page_index| self.pages[page_index as usize] & DIRTY != 0)
+            .collect();
+        pages.sort_unstable();
+        pages
+    }
+
+    fn clear_dirty(&mut self) {
+        for &page_index in &self.touched {
+            self.pages[page_index as usize] &= !DIRTY;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ram::PvmRam;
+
+    #[test]
+    fn matches_pvm_ram_faults_and_contents() {
+        let Some(mut mmap) = MmapRam::new() else {
+            return; // address space reservation unsupported here
+        };
+        let mut pvm = PvmRam::new();
+        for ram in [&mut mmap as &mut dyn Ram, &mut pvm] {
+            ram.initialize_memory_layout(&[1, 2, 3], &[4; 5000], &[6; 10], 4096, 1);
+            ram.init_page(0xF000_0000, 0x2000, MemoryAccessType::Write);
+            // Rights without contents: reads fault until the page is written.
+            ram.set_page_access_rights(0xE000_0000, 0x2000, MemoryAccessType::Write);
+            ram.init_page(0xE000_2000, 0x1000, MemoryAccessType::Read);
+        }
+        let page = config::PAGE_SIZE;
+        let reads = [
+            (0x1_0000, 8),
+            (0x1_0000 + page - 4, 8), // ro data, crosses into the second ro page
+            (0x1_0000 + 2 * page - 4, 8), // crosses into an inaccessible page
+            (0x2_0000, 16),
+            (0xF000_1FFC, 8), // runs off the end of the mapped pages
+            (0xFFFF_FFFC, 8), // wraps
+            (0x100, 0),
+            (0xE000_0000, 4),      // rights set, never written
+            (0xE000_1FFC, 8),      // from a page without contents into an initialised one
+            (0xE000_0FFC, 0x1008), // two pages without contents, into an initialised one
+        ];
+        for (address, count) in reads {
+            let (a, b) = (
+                mmap.read_octets(address, count),
+                pvm.read_octets(address, count),
+            );
+            assert_eq!(
+                (a.data, a.fault_address),
+                (b.data, b.fault_address),
+                "read {address:#x}"
+            );
+        }
+        let writes = [
+            (0x1_0000, 1),
+            (0x2_0000 + 8, 4),
+            (0xF000_0FFE, 4),
+            (0xF000_1FFE, 4),
+            (0xE000_0008, 4),
+        ];
+        for (address, count) in writes {
+            let values = vec![0xAB; count];
+            let (a, b) = (
+                mmap.write_octets(address, &values),
+                pvm.write_octets(address, &values),
+            );
+            assert_eq!(
+                (a.has_fault, a.fault_address),
+                (b.has_fault, b.fault_address),
+                "write {address:#x}"
+            );
+        }
+        assert_eq!(mmap.dirty_pages(), pvm.dirty_pages());
+        // The written page now reads back; its neighbour still has no contents.
+        for (address, count) in [(0xE000_0000, 16), (0xE000_0FFC, 8)] {
+            let (a, b) = (
+                mmap.read_octets(address, count),
+                pvm.read_octets(address, count),
+            );
+            assert_eq!(
+                (a.data, a.fault_address),
+                (b.data, b.fault_address),
+                "read {address:#x} after write"
+            );
+        }
+        let high_page = 0xF000_0000 / page;
+        assert_eq!(mmap.get_page_dump(high_page), pvm.get_page_dump(high_page));
+
+        mmap.reset();
+        assert!(!mmap.is_readable_with_fault(0xF000_0000, 1).success);
+        mmap.allocate_pages(high_page, 1);
+        assert_eq!(mmap.read_octets(0xF000_0FFE, 2).data, Some(vec![0, 0]));
+    }
+}
@@ -2,10 +2,11 @@
 //! No-op implementation that satisfies the RAM interface.
 
 use crate::config;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
 use crate::types::{FaultCheckResult, MemoryAccessType, Ram, ReadResult, WriteResult};
 
 /// Mock RAM: no-op memory, always succeeds / returns zeros.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct MockRam {
     current_heap_pointer: u32,
     last_load_address: u32,
@@ -25,6 +26,24 @@ impl MockRam {
             last_store_value: 0,
         }
     }
+
+    pub(crate) fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        w.u32(self.current_heap_pointer);
+        w.u32(self.last_load_address);
+        w.u64(self.last_load_value);
+        w.u32(self.last_store_address);
+        w.u64(self.last_store_value);
+    }
+
+    pub(crate) fn read_snapshot(r: &mut SnapshotReader<'_>) -> Option<Self> {
+        Some(Self {
+            current_heap_pointer: r.u32()?,
+            last_load_address: r.u32()?,
+            last_load_value: r.u64()?,
+            last_store_address: r.u32()?,
+            last_store_value: r.u64()?,
+        })
+    }
 }
 
 impl Ram for MockRam {
@@ -105,4 +124,11 @@ impl Ram for MockRam {
         self.last_store_address = 0;
         self.last_store_value = 0;
     }
+
+    /// No contents, so nothing is ever dirty.
+    fn dirty_pages(&self) -> Vec<u32> {
+        Vec::new()
+    }
+
+    fn clear_dirty(&mut self) {}
 }
@@ -1,7 +1,17 @@
 //! Program parser (mirrors assembly/parser.ts). Decode blob and parse instructions with bitmask.
 
 use crate::codec::decode_blob;
+use crate::config::{OPCODE_LOAD_IMM_JUMP, OPCODE_LOAD_U32, OPCODE_LOAD_U8};
+use crate::disassembler::{instruction_format, InstructionFormat};
+use crate::instructions::base::{
+    get_immediate_value_unsigned, get_register_index, parse_branch_operands, parse_one_immediate,
+    parse_one_offset, parse_one_register_and_immediate, parse_one_register_and_immediate_unsigned,
+    parse_register_and_two_immediates, parse_register_branch_operands, parse_three_registers,
+    parse_two_immediates, parse_two_registers, parse_two_registers_and_immediate,
+    parse_two_registers_and_two_immediates,
+};
 use crate::pvm::PvmInstruction;
+use crate::types::{DecodedInstruction, DecodedOperands};
 
 /// Result of parsing a program blob.
 #[derive(Clone, Debug)]
@@ -66,6 +76,31 @@ impl PvmParser {
         24
     }
 
+    /// Pre-decode every code position once (opcode, Fskip, next PC and parsed operands) so execution does no
+    /// bitmask scans or operand parsing. Fskip matches calling `skip` at each PC, computed in a single backward pass.
+    #[must_use]
+    pub fn predecode(&self, code: &[u8], opcode_bitmask: &[u8]) -> Vec<DecodedInstruction> {
+        let is_boundary = |k: usize| k >= opcode_bitmask.len() || opcode_bitmask[k] == 1;
+        // First boundary at or after index k, tracked for k = i + 1 while walking i downwards.
+        let mut next_boundary = (code.len() + 1..).find(|&k| is_boundary(k)).unwrap_or(usize::MAX);
+        let mut decoded = vec![DecodedInstruction::default(); code.len()];
+        for (i, slot) in decoded.iter_mut().enumerate().rev() {
+            if is_boundary(i + 1) {
+                next_boundary = i + 1;
+            }
+            let fskip = (next_boundary - (i + 1)).min(24);
+            let next_pc = i + 1 + fskip;
+            let operands = &code[(i + 1).min(code.len())..next_pc.min(code.len())];
+            *slot = DecodedInstruction {
+                opcode: code[i],
+                fskip: fskip as u8,
+                next_pc: next_pc as u32,
+                operands: decode_operands(code[i], operands, fskip as i32, i as u32),
+            };
+        }
+        decoded
+    }
+
     /// Parse program blob: decode blob then walk code using bitmask and Fskip.
     #[must_use]
     pub fn parse_program(&self, program_blob: &[u8]) -> ParseResult {
@@ -131,8 +166,139 @@ impl PvmParser {
     }
 }
 
+/// Parse `operands` (code[pc + 1..next_pc], cut at the end of code) of the instruction at `pc` the way its handler
+/// would. Formats that read operands[0] see a zero octet when there are no operands.
+#[must_use]
+pub fn decode_operands(opcode: u8, operands: &[u8], fskip: i32, pc: u32) -> DecodedOperands {
+    let Some(format) = instruction_format(opcode) else {
+        return DecodedOperands::default();
+    };
+    let padded;
+    let operands = if operands.is_empty() {
+        padded = [0u8; 1];
+        &padded[..]
+    } else {
+        operands
+    };
+    let mut decoded = DecodedOperands::default();
+    match format {
+        InstructionFormat::NoArguments => {}
+        InstructionFormat::OneImmediate => decoded.immediate_x = parse_one_immediate(operands, fskip),
+        InstructionFormat::OneRegisterOneExtendedImmediate => {
+            decoded.register_a = get_register_index(operands[0]);
+            decoded.immediate_x = get_immediate_value_unsigned(operands, 1, 8) as i64;
+        }
+        InstructionFormat::TwoImmediates => {
+            (decoded.immediate_x, decoded.immediate_y) = parse_two_immediates(operands, fskip);
+        }
+        InstructionFormat::OneOffset => decoded.target_address = parse_one_offset(operands, fskip, pc),
+        // LOAD_U8..LOAD_U32 read their address zero-extended; the other loads, stores and LOAD_IMM sign-extend.
+        InstructionFormat::OneRegisterOneImmediate if (OPCODE_LOAD_U8..=OPCODE_LOAD_U32).contains(&opcode) => {
+            let (register_a, address) = parse_one_register_and_immediate_unsigned(operands, fskip);
+            decoded.register_a = register_a;
+            decoded.immediate_x = address as i64;
+        }
+        InstructionFormat::OneRegisterOneImmediate => {
+            let parsed = parse_one_register_and_immediate(operands, fskip);
+            decoded.register_a = parsed.register_a;
+            decoded.immediate_x = parsed.immediate_x;
+        }
+        InstructionFormat::OneRegisterTwoImmediates => {
+            let parsed = parse_register_and_two_immediates(operands, fskip);
+            decoded.register_a = parsed.register_a;
+            decoded.immediate_x = parsed.immediate_x;
+            decoded.immediate_y = parsed.immediate_y;
+        }
+        // LOAD_IMM_JUMP: l_X from the high nibble and the offset as immed_Y.
+        InstructionFormat::OneRegisterOneImmediateOneOffset if opcode == OPCODE_LOAD_IMM_JUMP => {
+            let parsed = parse_register_and_two_immediates(operands, fskip);
+            decoded.register_a = parsed.register_a;
+            decoded.immediate_x = parsed.immediate_x;
+            decoded.immediate_y = parsed.immediate_y;
+            decoded.target_address = (i64::from(pc)).wrapping_add(parsed.immediate_y) as u32;
+        }
+        InstructionFormat::OneRegisterOneImmediateOneOffset => {
+            let parsed = parse_branch_operands(operands, pc);
+            decoded.register_a = parsed.register_a;
+            decoded.immediate_x = parsed.immediate_x;
+            decoded.target_address = parsed.target_address;
+        }
+        InstructionFormat::TwoRegisters => {
+            let parsed = parse_two_registers(operands);
+            decoded.register_d = parsed.register_d;
+            decoded.register_a = parsed.register_a;
+        }
+        InstructionFormat::TwoRegistersOneImmediate => {
+            let parsed = parse_two_registers_and_immediate(operands, fskip);
+            decoded.register_a = parsed.register_a;
+            decoded.register_b = parsed.register_b;
+            decoded.immediate_x = parsed.immediate_x;
+        }
+        InstructionFormat::TwoRegistersOneOffset => {
+            let parsed = parse_register_branch_operands(operands, pc);
+            decoded.register_a = parsed.register_a;
+            decoded.register_b = parsed.register_b;
+            decoded.target_address = parsed.target_address;
+        }
+        InstructionFormat::TwoRegistersTwoImmediates => {
+            let parsed = parse_two_registers_and_two_immediates(operands, fskip);
+            decoded.register_a = parsed.register_a;
+            decoded.register_b = parsed.register_b;
+            decoded.immediate_x = parsed.immediate_x;
+            decoded.immediate_y = parsed.immediate_y;
+        }
+        InstructionFormat::ThreeRegisters => {
+            let parsed = parse_three_registers(operands);
+            decoded.register_a = parsed.register_a;
+            decoded.register_b = parsed.register_b;
+            decoded.register_d = parsed.register_d;
+        }
+    }
+    decoded
+}
+
 impl Default for PvmParser {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_operands, PvmParser};
+    use crate::config::{
+        OPCODE_BRANCH_EQ_IMM, OPCODE_ECALLI, OPCODE_JUMP, OPCODE_LOAD_IMM, OPCODE_LOAD_U8,
+    };
+
+    #[test]
+    fn predecode_matches_skip_at_every_pc() {
+        let mut bitmask = vec![1u8, 0, 0, 1, 0, 1, 1];
+        bitmask.extend(std::iter::repeat_n(0, 30));
+        bitmask.push(1);
+        let code: Vec<u8> = (0..bitmask.len() as u8 + 5).collect();
+        let parser = PvmParser::new();
+        let decoded = parser.predecode(&code, &bitmask);
+        for (pc, entry) in decoded.iter().enumerate() {
+            let fskip = parser.skip(pc as i32, &bitmask);
+            assert_eq!(i32::from(entry.fskip), fskip, "pc {pc}");
+            assert_eq!(entry.next_pc as usize, pc + 1 + fskip as usize);
+            assert_eq!(entry.opcode, code[pc]);
+        }
+    }
+
+    #[test]
+    fn decode_operands_matches_handler_parsing() {
+        let ecalli = decode_operands(OPCODE_ECALLI, &[0x64, 0x01], 2, 0);
+        assert_eq!(ecalli.immediate_x, 356);
+        assert_eq!(decode_operands(OPCODE_ECALLI, &[], 0, 0).immediate_x, 0);
+        // LOAD_U8 zero-extends its address, LOAD_IMM sign-extends its value.
+        let load = decode_operands(OPCODE_LOAD_U8, &[0x01, 0xff], 2, 0);
+        assert_eq!((load.register_a, load.immediate_x), (1, 0xff));
+        let load_imm = decode_operands(OPCODE_LOAD_IMM, &[0x01, 0xff], 2, 0);
+        assert_eq!((load_imm.register_a, load_imm.immediate_x), (1, -1));
+        assert_eq!(decode_operands(OPCODE_JUMP, &[0xfe], 1, 10).target_address, 8);
+        let branch = decode_operands(OPCODE_BRANCH_EQ_IMM, &[0x11, 5, 4], 3, 10);
+        assert_eq!((branch.register_a, branch.immediate_x), (1, 5));
+        assert_eq!(branch.target_address, 14);
+    }
+}
@@ -0,0 +1,315 @@
+//! Execution profiler: counts steps and gas per opcode, per PC (aggregated into basic blocks for the report)
+//! and per host function, with each host call's outcome (`OK`, `OOB`, `WHO`, `FULL`, … from r7, or the exit
+//! status when the call ended the machine). Recorded by `next_step_impl` like the trace, using fixed-size
+//! counters so it can stay enabled.
+//!
+//! Gas for a step is `gas_before - gas_after`, so an ECALLI's opcode and PC entries include what its host
+//! function charged; the host-call entry holds that charge without the instruction's own gas.
+
+use std::collections::BTreeMap;
+
+use crate::config::{
+    is_termination_instruction, OPCODE_ECALLI, REG_CASH, REG_CORE, REG_FULL, REG_HUH, REG_LOW,
+    REG_NONE, REG_OK, REG_OOB, REG_WHAT, REG_WHO,
+};
+use crate::state_wrapper::{instruction_registry, PvmState, Status};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counter {
+    pub count: u64,
+    pub gas: u64,
+}
+
+impl Counter {
+    fn add(&mut self, gas: u64) {
+        self.count += 1;
+        self.gas += gas;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostCallCounter {
+    pub calls: u64,
+    pub gas: u64,
+    pub results: BTreeMap<&'static str, u64>,
+}
+
+/// Per-instance profile of the loaded program. Cleared when code is loaded; the mode is kept across resets.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    opcodes: Box<[Counter; 256]>,
+    pcs: Vec<Counter>,
+    host_calls: BTreeMap<u32, HostCallCounter>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            opcodes: Box::new([Counter::default(); 256]),
+            pcs: Vec::new(),
+            host_calls: BTreeMap::new(),
+        }
+    }
+}
+
+/// Outcome of a host call as the guest sees it: r7's error code, `VALUE` for any other return, or the exit.
+fn host_call_result(state: &PvmState) -> &'static str {
+    match state.status {
+        Status::Ok => match state.registers[7] {
+            REG_OK => "OK",
+            REG_NONE => "NONE",
+            REG_WHAT => "WHAT",
+            REG_OOB => "OOB",
+            REG_WHO => "WHO",
+            REG_FULL => "FULL",
+            REG_CORE => "CORE",
+            REG_CASH => "CASH",
+            REG_LOW => "LOW",
+            REG_HUH => "HUH",
+            _ => "VALUE",
+        },
+        Status::Halt => "HALT",
+        Status::Panic => "PANIC",
+        Status::Fault => "FAULT",
+        Status::Oog => "OOG",
+        Status::Host => "EXTERNAL",
+    }
+}
+
+impl Profiler {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Account the step that just ran: `opcode` was at `pc`, `gas_before` was left before it and
+    /// `instruction_gas` of that was charged for the instruction itself (the rest of an ECALLI's cost is
+    /// the host call's).
+    pub fn record(
+        &mut self,
+        pc: u32,
+        opcode: u8,
+        gas_before: u64,
+        instruction_gas: u64,
+        state: &PvmState,
+    ) {
+        let gas = gas_before.saturating_sub(state.gas_left);
+        self.opcodes[usize::from(opcode)].add(gas);
+        let index = pc as usize;
+        if index >= self.pcs.len() {
+            self.pcs.resize(index + 1, Counter::default());
+        }
+        self.pcs[index].add(gas);
+        if opcode == OPCODE_ECALLI && state.last_opcode == OPCODE_ECALLI {
+            let host = self.host_calls.entry(state.host_call_id).or_default();
+            host.calls += 1;
+            host.gas += gas.saturating_sub(instruction_gas);
+            // External mode: the outcome is recorded when the embedder resumes.
+            if state.status != Status::Host {
+                *host.results.entry(host_call_result(state)).or_default() += 1;
+            }
+        }
+    }
+
+    /// Account an externally serviced host call on resume (`gas_cost` as passed to resume_host_call_impl).
+    pub fn record_resume(&mut self, host_call_id: u32, gas_cost: u64, state: &PvmState) {
+        let host = self.host_calls.entry(host_call_id).or_default();
+        host.gas += gas_cost;
+        *host.results.entry(host_call_result(state)).or_default() += 1;
+    }
+
+    /// Snapshot of the counters. Blocks start at PC 0 and after every terminating instruction of `state`'s code.
+    #[must_use]
+    pub fn report(&self, state: &PvmState) -> ProfileReport {
+        let registry = instruction_registry();
+        let opcodes = self
+            .opcodes
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.count > 0)
+            .map(|(opcode, c)| OpcodeProfile {
+                opcode: opcode as u8,
+                name: registry
+                    .handler(opcode as u8)
+                    .map_or_else(|| format!("UNKNOWN_{opcode}"), |h| h.name().to_string()),
+                count: c.count,
+                gas: c.gas,
+            })
+            .collect();
+        let pcs: Vec<PcProfile> = self
+            .pcs
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.count > 0)
+            .map(|(pc, c)| PcProfile {
+                pc: pc as u32,
+                count: c.count,
+                gas: c.gas,
+            })
+            .collect();
+
+        let mut blocks: Vec<BlockProfile> = Vec::new();
+        let mut block_start = 0u32;
+        let mut pc_iter = pcs.iter().peekable();
+        let mut pc = 0usize;
+        while pc < state.decoded.len() && pc_iter.peek().is_some() {
+            let decoded = state.decoded[pc];
+            let next = (decoded.next_pc as usize).max(pc + 1);
+            let ends_block =
+                is_termination_instruction(decoded.opcode) || next >= state.decoded.len();
+            if ends_block {
+                let mut block = BlockProfile {
+                    start: block_start,
+                    end: next as u32,
+                    entries: 0,
+                    instructions: 0,
+                    gas: 0,
+                };
+                while let Some(p) = pc_iter.next_if(|p| (p.pc as usize) < next) {
+                    if p.pc == block_start {
+                        block.entries = p.count;
+                    }
+                    block.instructions += p.count;
+                    block.gas += p.gas;
+                }
+                if block.instructions > 0 {
+                    blocks.push(block);
+                }
+                block_start = next as u32;
+            }
+            pc = next;
+        }
+
+        let host_calls = self
+            .host_calls
+            .iter()
+            .map(|(&id, host)| HostCallProfile {
+                id,
+                name: state
+                    .host_functions
+                    .get(id)
+                    .map_or_else(|| format!("UNKNOWN_{id}"), |h| h.name().to_uppercase()),
+                calls: host.calls,
+                gas: host.gas,
+                results: host
+                    .results
+                    .iter()
+                    .map(|(&r, &n)| (r.to_string(), n))
+                    .collect(),
+            })
+            .collect();
+
+        ProfileReport {
+            instructions: self.opcodes.iter().map(|c| c.count).sum(),
+            gas: self.opcodes.iter().map(|c| c.gas).sum(),
+            opcodes,
+            pcs,
+            blocks,
+            host_calls,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpcodeProfile {
+    pub opcode: u8,
+    pub name: String,
+    pub count: u64,
+    pub gas: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PcProfile {
+    pub pc: u32,
+    pub count: u64,
+    pub gas: u64,
+}
+
+/// Basic block `[start, end)`. `entries` is how often its first instruction ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockProfile {
+    pub start: u32,
+    pub end: u32,
+    pub entries: u64,
+    pub instructions: u64,
+    pub gas: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostCallProfile {
+    pub id: u32,
+    pub name: String,
+    pub calls: u64,
+    pub gas: u64,
+    /// Outcome name and count, e.g. `("OK", 3)`, `("OOB", 1)`.
+    pub results: Vec<(String, u64)>,
+}
+
+/// Structured profile; every list is in ascending key order and only holds executed entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileReport {
+    pub instructions: u64,
+    pub gas: u64,
+    pub opcodes: Vec<OpcodeProfile>,
+    pub pcs: Vec<PcProfile>,
+    pub blocks: Vec<BlockProfile>,
+    pub host_calls: Vec<HostCallProfile>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas::{GasCharging, GasCostModel};
+    use crate::state_wrapper::{next_step_impl, RAMType};
+
+    #[test]
+    fn counts_opcodes_blocks_and_host_calls() {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.profiler = Some(Profiler::default());
+        // 0: LOAD_IMM r0, 2; 3: FALLTHROUGH; 4: ADD_IMM_64 r0, r0, -1; 7: BRANCH_NE_IMM r0, 0, @4;
+        // 11: ECALLI 99; 13: TRAP
+        state.load_code(
+            vec![51, 0, 2, 1, 149, 0, 0xff, 82, 0x10, 0, 0xfd, 10, 99, 0],
+            vec![1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 0, 1, 0, 1],
+            vec![],
+        );
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        while next_step_impl(&mut state) {}
+
+        let report = state.profiler.as_ref().unwrap().report(&state);
+        assert_eq!((report.instructions, report.gas), (8, 18));
+        let add = report
+            .opcodes
+            .iter()
+            .find(|o| o.name == "ADD_IMM_64")
+            .unwrap();
+        assert_eq!((add.count, add.gas), (2, 2));
+        let spans: Vec<_> = report
+            .blocks
+            .iter()
+            .map(|b| (b.start, b.end, b.entries, b.instructions))
+            .collect();
+        assert_eq!(spans, [(0, 4, 1, 2), (4, 11, 2, 4), (11, 14, 1, 2)]);
+        assert_eq!(report.host_calls.len(), 1);
+        let host = &report.host_calls[0];
+        assert_eq!((host.id, host.calls, host.gas), (99, 1, 10));
+        assert_eq!(host.results, [("WHAT".to_string(), 1)]);
+    }
+
+    #[test]
+    fn host_call_gas_excludes_only_what_the_ecalli_was_charged() {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.profiler = Some(Profiler::default());
+        state.gas_charging = GasCharging::Block(GasCostModel::Unit);
+        // 0: LOAD_IMM r0, 2; 3: ECALLI 0 (GAS); 5: TRAP. The first block is charged at PC 0, so ECALLI costs
+        // nothing itself and GAS's base 10 is all host-call gas.
+        state.load_code(vec![51, 0, 2, 10, 0, 0], vec![1, 0, 0, 1, 0, 1], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        while next_step_impl(&mut state) {}
+
+        let report = state.profiler.as_ref().unwrap().report(&state);
+        let host = &report.host_calls[0];
+        assert_eq!((host.id, host.calls, host.gas), (0, 1, 10));
+    }
+}
@@ -1,8 +1,10 @@
 //! PVM RAM (mirrors assembly/ram.ts). Page-based memory with regions per Gray Paper.
 
 use crate::config::{self, align_to_page, align_to_zone};
+use crate::snapshot::{access_from_u8, SnapshotReader, SnapshotWriter};
 use crate::types::{FaultCheckResult, MemoryAccessType, Ram, ReadResult, WriteResult};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 
 /// Page map entry (address, length, writable, access type). Used for introspection.
 #[derive(Clone, Debug)]
@@ -14,6 +16,8 @@ pub struct PageMapEntry {
 }
 
 /// PVM RAM: page-based memory with regions (Gray Paper equation 770-802).
+/// Clones share page contents copy-on-write (see `fork`).
+#[derive(Clone)]
 pub struct PvmRam {
     ro_data_address: u32,
     argument_data_address: u32,
@@ -24,8 +28,11 @@ pub struct PvmRam {
     ro_data_address_end: u32,
     current_heap_pointer: u32,
     argument_data_end: u32,
-    pages: HashMap<u32, Vec<u8>>,
+    /// Shared with forks until written: `get_or_create_page` copies a shared page first.
+    pages: HashMap<u32, Arc<Vec<u8>>>,
     page_access: HashMap<u32, MemoryAccessType>,
+    /// Pages written since the last `clear_dirty`.
+    dirty: BTreeSet<u32>,
     last_load_address: u32,
     last_load_value: u64,
     last_store_address: u32,
@@ -46,6 +53,7 @@ impl Default for PvmRam {
             argument_data_end: 0,
             pages: HashMap::new(),
             page_access: HashMap::new(),
+            dirty: BTreeSet::new(),
             last_load_address: 0,
             last_load_value: 0,
             last_store_address: 0,
@@ -69,13 +77,91 @@ impl PvmRam {
     }
 
     fn get_or_create_page(&mut self, page_index: u32) -> &mut Vec<u8> {
-        self.pages
-            .entry(page_index)
-            .or_insert_with(|| vec![0u8; config::PAGE_SIZE as usize])
+        Arc::make_mut(
+            self.pages
+                .entry(page_index)
+                .or_insert_with(|| Arc::new(vec![0u8; config::PAGE_SIZE as usize])),
+        )
     }
 
     fn get_page(&self, page_index: u32) -> Option<&Vec<u8>> {
-        self.pages.get(&page_index)
+        self.pages.get(&page_index).map(Arc::as_ref)
+    }
+
+    /// Copy of this RAM that shares every page with it: O(pages) reference-count increments. The first write
+    /// to a shared page, on either side, copies that 4 KiB page only.
+    #[must_use]
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+
+    /// Layout addresses, heap pointer, then every page (ascending index) with its access right and contents.
+    pub(crate) fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        for value in [
+            self.ro_data_address,
+            self.argument_data_address,
+            self.stack_address_end,
+            self.stack_address,
+            self.heap_start_address,
+            self.heap_end_address,
+            self.ro_data_address_end,
+            self.current_heap_pointer,
+            self.argument_data_end,
+            self.last_load_address,
+            self.last_store_address,
+        ] {
+            w.u32(value);
+        }
+        w.u64(self.last_load_value);
+        w.u64(self.last_store_value);
+        let mut indices: Vec<u32> = self
+            .pages
+            .keys()
+            .chain(self.page_access.keys())
+            .copied()
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        w.len(indices.len());
+        for index in indices {
+            w.u32(index);
+            w.option(self.page_access.get(&index), |w, &access| {
+                w.u8(access as u8)
+            });
+            w.option(self.pages.get(&index), |w, page| w.bytes(page));
+        }
+    }
+
+    pub(crate) fn read_snapshot(r: &mut SnapshotReader<'_>) -> Option<Self> {
+        let mut ram = Self {
+            ro_data_address: r.u32()?,
+            argument_data_address: r.u32()?,
+            stack_address_end: r.u32()?,
+            stack_address: r.u32()?,
+            heap_start_address: r.u32()?,
+            heap_end_address: r.u32()?,
+            ro_data_address_end: r.u32()?,
+            current_heap_pointer: r.u32()?,
+            argument_data_end: r.u32()?,
+            last_load_address: r.u32()?,
+            last_store_address: r.u32()?,
+            last_load_value: r.u64()?,
+            last_store_value: r.u64()?,
+            ..Self::default()
+        };
+        for _ in 0..r.len()? {
+            let index = r.u32()?;
+            if let Some(access) = r.option(|r| access_from_u8(r.u8()?))? {
+                ram.page_access.insert(index, access);
+            }
+            if let Some(page) = r.option(SnapshotReader::bytes)? {
+                if page.len() != config::PAGE_SIZE as usize {
+                    return None;
+                }
+                ram.pages.insert(index, Arc::new(page));
+            }
+        }
+        Some(ram)
     }
 }
 
@@ -134,6 +220,7 @@ impl Ram for PvmRam {
         while current_addr < end_addr {
             let page_index = self.get_page_index(current_addr);
             let page_offset = self.get_page_offset(current_addr) as usize;
+            self.dirty.insert(page_index);
             let page = self.get_or_create_page(page_index);
             let bytes_in_page =
                 (values.len() - values_offset).min(config::PAGE_SIZE as usize - page_offset);
@@ -300,6 +387,7 @@ impl Ram for PvmRam {
         while current_addr < end_addr {
             let page_index = self.get_page_index(current_addr);
             let page_offset = self.get_page_offset(current_addr) as usize;
+            self.dirty.insert(page_index);
             let page = self.get_or_create_page(page_index);
             let bytes_in_page =
                 (values.len() - values_offset).min(config::PAGE_SIZE as usize - page_offset);
@@ -320,6 +408,7 @@ impl Ram for PvmRam {
     fn reset(&mut self) {
         self.pages.clear();
         self.page_access.clear();
+        self.dirty.clear();
         self.stack_address = 0;
         self.heap_start_address = 0;
         self.heap_end_address = 0;
@@ -354,4 +443,75 @@ impl Ram for PvmRam {
         self.last_store_address = 0;
         self.last_store_value = 0;
     }
+
+    fn dirty_pages(&self) -> Vec<u32> {
+        self.dirty.iter().copied().collect()
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simple_ram::SimpleRam;
+    use crate::types::{ChangedRange, PageDiff};
+
+    #[test]
+    fn fork_shares_pages_until_written() {
+        let mut ram = PvmRam::new();
+        ram.initialize_memory_layout(&[], &[], &[1; 2 * config::PAGE_SIZE as usize], 0, 0);
+        let heap = ram.heap_start_address;
+        let mut fork = ram.fork();
+        let first = heap / config::PAGE_SIZE;
+        assert!(Arc::ptr_eq(&ram.pages[&first], &fork.pages[&first]));
+
+        assert!(!fork.write_octets(heap, &[7]).has_fault);
+        assert_eq!(ram.read_octets(heap, 1).data, Some(vec![1]));
+        assert_eq!(fork.read_octets(heap, 1).data, Some(vec![7]));
+        assert!(!Arc::ptr_eq(&ram.pages[&first], &fork.pages[&first]));
+        let second = first + 1;
+        assert!(Arc::ptr_eq(&ram.pages[&second], &fork.pages[&second]));
+    }
+
+    #[test]
+    fn memory_diff_reports_changed_ranges_of_dirty_pages() {
+        let mut simple = SimpleRam::new();
+        let mut pvm = PvmRam::new();
+        let heap = 2 * config::ZONE_SIZE;
+        let page_index = heap / config::PAGE_SIZE;
+        for ram in [&mut simple as &mut dyn Ram, &mut pvm] {
+            ram.init_page(heap, 2 * config::PAGE_SIZE, MemoryAccessType::Write);
+            ram.write_octets_during_initialization(heap, &[1; 16]);
+            ram.clear_dirty();
+        }
+        let baseline = pvm.fork();
+        for ram in [&mut simple as &mut dyn Ram, &mut pvm] {
+            ram.write_octets(heap + 2, &[1, 9, 9, 1, 8]);
+            ram.write_octets(heap + config::PAGE_SIZE, &[0]); // same contents: dirty, but no diff
+            assert_eq!(ram.dirty_pages(), [page_index, page_index + 1]);
+            assert_eq!(
+                ram.memory_diff(&baseline),
+                [PageDiff {
+                    page_index,
+                    ranges: vec![
+                        ChangedRange {
+                            offset: 3,
+                            old: vec![1, 1],
+                            new: vec![9, 9]
+                        },
+                        ChangedRange {
+                            offset: 6,
+                            old: vec![1],
+                            new: vec![8]
+                        },
+                    ],
+                }]
+            );
+            ram.clear_dirty();
+            assert!(ram.memory_diff(&baseline).is_empty());
+        }
+    }
 }
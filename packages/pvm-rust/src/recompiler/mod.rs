@@ -0,0 +1,1553 @@
+//! x86-64 recompiler backend (selected per instance with `set_use_recompiler`, interpreter otherwise).
+//!
+//! Basic blocks (ending at `config::is_termination_instruction`) are translated on first entry into native
+//! code and cached per start PC. The 13 PVM registers live in machine registers for the whole block; gas for
+//! the whole block is checked and charged on entry. ALU operations, immediate loads and static jumps/branches
+//! are emitted inline. With an `MmapRam`, loads and stores are too: they check the page table, access the
+//! reserved address space directly and leave the block on a panic or fault at the same point as the
+//! interpreter. Division, dynamic jumps and SBRK (and memory accesses on the other RAM backends) call back
+//! into their interpreter handler. ECALLI and unknown opcodes end a block and are single-stepped by the
+//! interpreter (`next_step_impl`).
+
+mod x86;
+
+use crate::config::{
+    is_termination_instruction, OPCODE_ADD_32, OPCODE_ADD_64, OPCODE_ADD_IMM_32, OPCODE_ADD_IMM_64,
+    OPCODE_AND, OPCODE_AND_IMM, OPCODE_AND_INV, OPCODE_BRANCH_EQ, OPCODE_BRANCH_EQ_IMM,
+    OPCODE_BRANCH_GE_S, OPCODE_BRANCH_GE_S_IMM, OPCODE_BRANCH_GE_U, OPCODE_BRANCH_GE_U_IMM,
+    OPCODE_BRANCH_GT_S_IMM, OPCODE_BRANCH_GT_U_IMM, OPCODE_BRANCH_LE_S_IMM, OPCODE_BRANCH_LE_U_IMM,
+    OPCODE_BRANCH_LT_S, OPCODE_BRANCH_LT_S_IMM, OPCODE_BRANCH_LT_U, OPCODE_BRANCH_LT_U_IMM,
+    OPCODE_BRANCH_NE, OPCODE_BRANCH_NE_IMM, OPCODE_CMOV_IZ, OPCODE_CMOV_IZ_IMM, OPCODE_CMOV_NZ,
+    OPCODE_CMOV_NZ_IMM, OPCODE_COUNT_SET_BITS_32, OPCODE_COUNT_SET_BITS_64, OPCODE_ECALLI,
+    OPCODE_FALLTHROUGH, OPCODE_JUMP, OPCODE_LEADING_ZERO_BITS_32, OPCODE_LEADING_ZERO_BITS_64,
+    OPCODE_LOAD_I16, OPCODE_LOAD_I32, OPCODE_LOAD_I8, OPCODE_LOAD_IMM, OPCODE_LOAD_IMM_64,
+    OPCODE_LOAD_IMM_JUMP, OPCODE_LOAD_IND_I16, OPCODE_LOAD_IND_I32, OPCODE_LOAD_IND_I8,
+    OPCODE_LOAD_IND_U16, OPCODE_LOAD_IND_U32, OPCODE_LOAD_IND_U64, OPCODE_LOAD_IND_U8,
+    OPCODE_LOAD_U16, OPCODE_LOAD_U32, OPCODE_LOAD_U64, OPCODE_LOAD_U8, OPCODE_MAX, OPCODE_MAX_U,
+    OPCODE_MIN, OPCODE_MIN_U, OPCODE_MOVE_REG, OPCODE_MUL_32, OPCODE_MUL_64, OPCODE_MUL_IMM_32,
+    OPCODE_MUL_IMM_64, OPCODE_MUL_UPPER_S_S, OPCODE_MUL_UPPER_S_U, OPCODE_MUL_UPPER_U_U,
+    OPCODE_NEG_ADD_IMM_32, OPCODE_NEG_ADD_IMM_64, OPCODE_OR, OPCODE_OR_IMM, OPCODE_OR_INV,
+    OPCODE_REVERSE_BYTES, OPCODE_ROT_L_32, OPCODE_ROT_L_64, OPCODE_ROT_R_32, OPCODE_ROT_R_32_IMM,
+    OPCODE_ROT_R_32_IMM_ALT, OPCODE_ROT_R_64, OPCODE_ROT_R_64_IMM, OPCODE_ROT_R_64_IMM_ALT,
+    OPCODE_SET_GT_S_IMM, OPCODE_SET_GT_U_IMM, OPCODE_SET_LT_S, OPCODE_SET_LT_S_IMM,
+    OPCODE_SET_LT_U, OPCODE_SET_LT_U_IMM, OPCODE_SHAR_R_32, OPCODE_SHAR_R_64, OPCODE_SHAR_R_IMM_32,
+    OPCODE_SHAR_R_IMM_64, OPCODE_SHAR_R_IMM_ALT_32, OPCODE_SHAR_R_IMM_ALT_64, OPCODE_SHLO_L_32,
+    OPCODE_SHLO_L_64, OPCODE_SHLO_L_IMM_32, OPCODE_SHLO_L_IMM_64, OPCODE_SHLO_L_IMM_ALT_32,
+    OPCODE_SHLO_L_IMM_ALT_64, OPCODE_SHLO_R_32, OPCODE_SHLO_R_64, OPCODE_SHLO_R_IMM_32,
+    OPCODE_SHLO_R_IMM_64, OPCODE_SHLO_R_IMM_ALT_32, OPCODE_SHLO_R_IMM_ALT_64,
+    OPCODE_SIGN_EXTEND_16, OPCODE_SIGN_EXTEND_8, OPCODE_STORE_IMM_IND_U16,
+    OPCODE_STORE_IMM_IND_U32, OPCODE_STORE_IMM_IND_U64, OPCODE_STORE_IMM_IND_U8,
+    OPCODE_STORE_IMM_U16, OPCODE_STORE_IMM_U32, OPCODE_STORE_IMM_U64, OPCODE_STORE_IMM_U8,
+    OPCODE_STORE_IND_U16, OPCODE_STORE_IND_U32, OPCODE_STORE_IND_U64, OPCODE_STORE_IND_U8,
+    OPCODE_STORE_U16, OPCODE_STORE_U32, OPCODE_STORE_U64, OPCODE_STORE_U8, OPCODE_SUB_32,
+    OPCODE_SUB_64, OPCODE_TRAILING_ZERO_BITS_32, OPCODE_TRAILING_ZERO_BITS_64, OPCODE_TRAP,
+    OPCODE_XNOR, OPCODE_XOR, OPCODE_XOR_IMM, OPCODE_ZERO_EXTEND_16, PAGE_SIZE, RESULT_CODE_FAULT,
+    RESULT_CODE_PANIC, ZONE_SIZE,
+};
+use crate::instructions::base::validate_branch_target;
+use crate::mmap::{MmapRegion, Protection};
+use crate::mmap_ram::{BACKED, DIRTY, READABLE, WRITABLE};
+use crate::state_wrapper::{instruction_registry, status_from_result_code, PvmState};
+use crate::types::{
+    DecodedInstruction, DecodedOperands, InstructionContext, InstructionResult, Ram,
+};
+use std::mem::offset_of;
+use x86::{AluOp, Assembler, Cond, Label, Reg, ShiftOp, Width};
+
+/// True when native blocks can run on this target (x86-64, System V calling convention).
+#[must_use]
+pub const fn is_available() -> bool {
+    cfg!(all(target_arch = "x86_64", unix))
+}
+
+/// Helper result: carry on with the next instruction of the block.
+const EXIT_CONTINUE: u32 = 0;
+/// Block finished; continue at `next_pc`.
+const EXIT_NEXT: u32 = 1;
+/// The instruction at `next_pc` stopped execution (`result_code`, `has_fault` / `fault_address`).
+const EXIT_STOP: u32 = 2;
+/// Not enough gas for the whole block; nothing ran, the interpreter single-steps instead.
+const EXIT_NO_GAS: u32 = 3;
+
+/// Upper bound on instructions per block (keeps gas immediates and compile latency small).
+const MAX_BLOCK_INSTRUCTIONS: usize = 1024;
+/// Executable memory is allocated in chunks of this size (or larger for a single huge block).
+const CHUNK_SIZE: usize = 1 << 20;
+/// `block_at` marker for a PC whose first instruction is not translatable (ECALLI, unknown opcode, end of code).
+const NOT_COMPILABLE: u32 = u32::MAX;
+
+/// Machine registers holding PVM registers r0..r12 (rax and rcx are scratch).
+const PVM_REGS: [Reg; 13] = [
+    x86::RBX,
+    x86::RBP,
+    x86::R12,
+    x86::R13,
+    x86::R14,
+    x86::R15,
+    x86::RDX,
+    x86::RSI,
+    x86::RDI,
+    x86::R8,
+    x86::R9,
+    x86::R10,
+    x86::R11,
+];
+/// Callee-saved registers pushed by the prologue.
+const SAVED_REGS: [Reg; 6] = [x86::RBX, x86::RBP, x86::R12, x86::R13, x86::R14, x86::R15];
+
+/// State shared between Rust and a native block (pointer passed in rdi, kept at [rsp]).
+#[repr(C)]
+struct NativeContext {
+    regs: [u64; 13],
+    gas: u64,
+    state: *mut PvmState,
+    /// `NativeView` of the RAM; null unless the block was compiled with inline memory accesses.
+    memory: *mut u8,
+    pages: *mut u8,
+    next_pc: u32,
+    executed: u32,
+    result_code: u32,
+    has_fault: u32,
+    fault_address: u32,
+}
+
+const CTX_GAS: i32 = offset_of!(NativeContext, gas) as i32;
+const CTX_NEXT_PC: i32 = offset_of!(NativeContext, next_pc) as i32;
+const CTX_EXECUTED: i32 = offset_of!(NativeContext, executed) as i32;
+const CTX_RESULT_CODE: i32 = offset_of!(NativeContext, result_code) as i32;
+const CTX_HAS_FAULT: i32 = offset_of!(NativeContext, has_fault) as i32;
+const CTX_FAULT_ADDRESS: i32 = offset_of!(NativeContext, fault_address) as i32;
+const CTX_MEMORY: i32 = offset_of!(NativeContext, memory) as i32;
+const CTX_PAGES: i32 = offset_of!(NativeContext, pages) as i32;
+
+/// Stack slots of a running block: the context pointer, then the page table and memory base.
+const STACK_CTX: i32 = 0;
+const STACK_PAGES: i32 = 8;
+const STACK_MEMORY: i32 = 16;
+
+struct Block {
+    /// Address of the native entry point.
+    entry: usize,
+    /// PC of each instruction in the block, in order (its length is the gas charged on entry).
+    pcs: Vec<u32>,
+}
+
+/// Native code for one loaded program. Invalidated by `PvmState::load_code`.
+pub struct CompiledProgram {
+    code_len: usize,
+    /// Blocks access memory through a `NativeView` instead of the interpreter handlers.
+    inline_memory: bool,
+    /// Per PC: 0 = not compiled yet, NOT_COMPILABLE, otherwise index into `blocks` + 1.
+    block_at: Vec<u32>,
+    blocks: Vec<Block>,
+    chunks: Vec<MmapRegion>,
+    chunk_used: usize,
+}
+
+impl CompiledProgram {
+    fn new(code_len: usize, inline_memory: bool) -> Self {
+        Self {
+            code_len,
+            inline_memory,
+            block_at: vec![0; code_len],
+            blocks: Vec::new(),
+            chunks: Vec::new(),
+            chunk_used: 0,
+        }
+    }
+
+    /// Copy machine code into executable memory (W^X: the chunk is writable only while copying).
+    fn install(&mut self, machine_code: &[u8]) -> Option<usize> {
+        let start = (self.chunk_used + 15) & !15;
+        let fits = self
+            .chunks
+            .last()
+            .is_some_and(|chunk| start + machine_code.len() <= chunk.len());
+        let start = if fits {
+            start
+        } else {
+            self.chunks
+                .push(MmapRegion::new(CHUNK_SIZE.max(machine_code.len()))?);
+            0
+        };
+        let chunk = self.chunks.last_mut()?;
+        let len = chunk.len();
+        if !chunk.protect(0, len, Protection::ReadWrite) {
+            return None;
+        }
+        chunk.as_mut_slice()[start..start + machine_code.len()].copy_from_slice(machine_code);
+        if !chunk.protect(0, len, Protection::ReadExecute) {
+            return None;
+        }
+        self.chunk_used = start + machine_code.len();
+        Some(chunk.as_ptr() as usize + start)
+    }
+}
+
+/// Outcome of trying to run the block at the current PC.
+enum BlockRun {
+    /// Block ran to its end; PC, registers and gas are updated.
+    Ran,
+    /// An instruction in the block halted, panicked or faulted; `state.status` says which.
+    Stopped,
+    /// Nothing ran (not translatable or not enough gas for the block); single-step instead.
+    Interpret,
+}
+
+/// Run to completion with native blocks, falling back to `next_step_impl` for host calls and the rest.
+pub fn run(state: &mut PvmState) {
+    loop {
+        match run_block(state) {
+            BlockRun::Ran => {}
+            BlockRun::Stopped => return,
+            BlockRun::Interpret => {
+                if !crate::state_wrapper::next_step_impl(state) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn run_block(state: &mut PvmState) -> BlockRun {
+    if !is_available() {
+        return BlockRun::Interpret;
+    }
+    let pc = state.program_counter as usize;
+    if pc >= state.code.len() {
+        return BlockRun::Interpret;
+    }
+    state.ensure_decoded();
+    let view = state.ram.native_view();
+    if state
+        .compiled
+        .as_ref()
+        .is_none_or(|p| p.code_len != state.code.len() || p.inline_memory != view.is_some())
+    {
+        state.compiled = Some(Box::new(CompiledProgram::new(
+            state.code.len(),
+            view.is_some(),
+        )));
+    }
+    let Some(program) = state.compiled.as_mut() else {
+        return BlockRun::Interpret;
+    };
+    let index = match program.block_at[pc] {
+        NOT_COMPILABLE => return BlockRun::Interpret,
+        0 => {
+            let compiled = compile_block(
+                &state.code,
+                &state.bitmask,
+                &state.decoded,
+                pc as u32,
+                program.inline_memory,
+            );
+            let Some((machine_code, pcs)) = compiled else {
+                program.block_at[pc] = NOT_COMPILABLE;
+                return BlockRun::Interpret;
+            };
+            let Some(entry) = program.install(&machine_code) else {
+                program.block_at[pc] = NOT_COMPILABLE;
+                return BlockRun::Interpret;
+            };
+            program.blocks.push(Block { entry, pcs });
+            program.block_at[pc] = program.blocks.len() as u32;
+            program.blocks.len() - 1
+        }
+        i => i as usize - 1,
+    };
+    let entry = program.blocks[index].entry;
+    let count = program.blocks[index].pcs.len();
+
+    let mut ctx = NativeContext {
+        regs: state.registers,
+        gas: state.gas_left,
+        state: std::ptr::from_mut(state),
+        memory: view.map_or(std::ptr::null_mut(), |v| v.memory),
+        pages: view.map_or(std::ptr::null_mut(), |v| v.pages),
+        next_pc: 0,
+        executed: 0,
+        result_code: 0,
+        has_fault: 0,
+        fault_address: 0,
+    };
+    // SAFETY: `entry` points at a block emitted by compile_block into a live read-execute chunk owned by
+    // state.compiled. The block only touches ctx, the RAM through the view pointers (within the checked
+    // pages), and through interpret_instruction the state fields other than `compiled`; `state` itself is
+    // not used until the call returns.
+    let exit = unsafe {
+        let block: extern "C" fn(*mut NativeContext) -> u32 = std::mem::transmute(entry);
+        block(&mut ctx)
+    };
+    if exit == EXIT_NO_GAS {
+        return BlockRun::Interpret;
+    }
+
+    let executed = ctx.executed as usize;
+    state.registers = ctx.regs;
+    // Gas was charged for the whole block; refund what did not run after an early stop.
+    state.gas_left = ctx.gas + (count - executed) as u64;
+    state.host_call_id = 0;
+    if let Some(&last_pc) = state
+        .compiled
+        .as_ref()
+        .and_then(|p| p.blocks[index].pcs.get(executed.wrapping_sub(1)))
+    {
+        state.last_opcode = state.code[last_pc as usize];
+    }
+    state.last_load_address = state.ram.last_load_address();
+    state.last_load_value = state.ram.last_load_value();
+    state.last_store_address = state.ram.last_store_address();
+    state.last_store_value = state.ram.last_store_value();
+    state.program_counter = ctx.next_pc;
+    if exit == EXIT_STOP {
+        state.status = status_from_result_code(ctx.result_code as u8);
+        state.result_code = ctx.result_code as u8;
+        state.exit_arg = if ctx.has_fault != 0 {
+            ctx.fault_address
+        } else {
+            0
+        };
+        return BlockRun::Stopped;
+    }
+    BlockRun::Ran
+}
+
+/// Called from native code for instructions without an inline translation: runs the interpreter handler
+/// on the spilled registers. Returns EXIT_CONTINUE, or EXIT_NEXT / EXIT_STOP with ctx filled in.
+extern "C" fn interpret_instruction(ctx: *mut NativeContext, pc: u32) -> u32 {
+    // SAFETY: ctx is the context run_block passed to the block, which forwards it unchanged; its state
+    // pointer is valid and otherwise unused for the duration of the block.
+    let ctx = unsafe { &mut *ctx };
+    let state = unsafe { &mut *ctx.state };
+    let regs = &mut ctx.regs;
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let DecodedInstruction {
+            opcode,
+            next_pc,
+            operands,
+            ..
+        } = state.decoded[pc as usize];
+        let handler = instruction_registry().handler(opcode)?;
+        let mut context = InstructionContext {
+            code: &state.code,
+            bitmask: &state.bitmask,
+            registers: regs,
+            program_counter: pc,
+            gas_remaining: 0,
+            operands,
+            jump_table: &state.jump_table,
+            ram: &mut state.ram,
+            host_call_id_out: None,
+        };
+        let result = handler.execute(&mut context);
+        Some((result, context.program_counter, next_pc, opcode))
+    }));
+    let Ok(Some((result, pc_after, next_pc, opcode))) = outcome else {
+        ctx.next_pc = pc;
+        ctx.result_code = u32::from(RESULT_CODE_PANIC);
+        ctx.has_fault = 0;
+        return EXIT_STOP;
+    };
+    if result.result_code != InstructionResult::CONTINUE {
+        ctx.next_pc = pc;
+        ctx.result_code = result.result_code as u32;
+        ctx.has_fault = u32::from(result.has_fault_address);
+        ctx.fault_address = result.fault_address;
+        return EXIT_STOP;
+    }
+    // Same PC rule as execute_instruction: an unchanged PC advances to the next instruction.
+    if pc_after != pc {
+        ctx.next_pc = pc_after;
+        return EXIT_NEXT;
+    }
+    if is_termination_instruction(opcode) {
+        ctx.next_pc = next_pc;
+        return EXIT_NEXT;
+    }
+    EXIT_CONTINUE
+}
+
+/// Emits one block. Register i is in PVM_REGS[i] between instructions; the stack holds the STACK_* slots.
+struct BlockEmitter<'a> {
+    asm: Assembler,
+    epilogue: Label,
+    code: &'a [u8],
+    bitmask: &'a [u8],
+    /// Out-of-line exits of memory accesses, emitted after the block body.
+    stubs: Vec<(Label, Site, FaultExit)>,
+}
+
+impl BlockEmitter<'_> {
+    fn reg(index: u8) -> Reg {
+        PVM_REGS[index as usize]
+    }
+
+    /// rax = context pointer.
+    fn load_ctx(&mut self) {
+        self.asm.load64(x86::RAX, x86::RSP, STACK_CTX);
+    }
+
+    fn spill(&mut self) {
+        self.load_ctx();
+        for (i, &reg) in PVM_REGS.iter().enumerate() {
+            self.asm.store64(x86::RAX, (i * 8) as i32, reg);
+        }
+    }
+
+    fn reload(&mut self) {
+        self.load_ctx();
+        for (i, &reg) in PVM_REGS.iter().enumerate() {
+            self.asm.load64(reg, x86::RAX, (i * 8) as i32);
+        }
+    }
+
+    /// Leave the block at `next_pc` after `executed` instructions.
+    fn exit_next(&mut self, next_pc: u32, executed: usize) {
+        self.load_ctx();
+        self.asm.store32_imm(x86::RAX, CTX_NEXT_PC, next_pc);
+        self.asm
+            .store32_imm(x86::RAX, CTX_EXECUTED, executed as u32);
+        self.asm.mov_ri(x86::RCX, u64::from(EXIT_NEXT));
+        self.asm.jmp(self.epilogue);
+    }
+
+    /// Stop at instruction `pc` (the `executed`-th of the block) with `result_code`; with `fault` set, the
+    /// fault address is in ecx.
+    fn exit_stop(&mut self, pc: u32, executed: usize, result_code: u8, fault: bool) {
+        self.load_ctx();
+        self.asm.store32_imm(x86::RAX, CTX_NEXT_PC, pc);
+        self.asm
+            .store32_imm(x86::RAX, CTX_EXECUTED, executed as u32);
+        self.asm
+            .store32_imm(x86::RAX, CTX_RESULT_CODE, u32::from(result_code));
+        self.asm
+            .store32_imm(x86::RAX, CTX_HAS_FAULT, u32::from(fault));
+        if fault {
+            self.asm.store32(x86::RAX, CTX_FAULT_ADDRESS, x86::RCX);
+        }
+        self.asm.mov_ri(x86::RCX, u64::from(EXIT_STOP));
+        self.asm.jmp(self.epilogue);
+    }
+
+    /// Panic at instruction `pc` (the `executed`-th of the block).
+    fn exit_panic(&mut self, pc: u32, executed: usize) {
+        self.exit_stop(pc, executed, RESULT_CODE_PANIC, false);
+    }
+
+    /// Static jump: panic on an invalid target, otherwise leave the block (a jump to itself advances).
+    fn exit_jump(&mut self, at: Site, target: u32) {
+        if validate_branch_target(target, self.code, self.bitmask).is_some() {
+            self.exit_panic(at.pc, at.executed);
+        } else {
+            let next = if target == at.pc { at.next_pc } else { target };
+            self.exit_next(next, at.executed);
+        }
+    }
+
+    /// Compare `lhs` with `rhs` and leave the block at the target or the next instruction.
+    fn branch(&mut self, at: Site, cond: Cond, lhs: Reg, rhs: Reg, target: u32) {
+        let taken = self.asm.new_label();
+        self.asm.alu_rr(AluOp::Cmp, lhs, rhs, true);
+        self.asm.jcc(cond, taken);
+        self.exit_next(at.next_pc, at.executed);
+        self.asm.bind(taken);
+        self.exit_jump(at, target);
+    }
+
+    /// d = a op b on three registers; 32-bit forms sign-extend the low 32 bits of the result.
+    fn three_reg(&mut self, parsed: DecodedOperands, op: Option<AluOp>, wide: bool) {
+        let (a, b, d) = (
+            Self::reg(parsed.register_a),
+            Self::reg(parsed.register_b),
+            Self::reg(parsed.register_d),
+        );
+        self.asm.mov_rr(x86::RAX, a);
+        match op {
+            Some(op) => self.asm.alu_rr(op, x86::RAX, b, wide),
+            None => self.asm.imul_rr(x86::RAX, b, wide),
+        }
+        if !wide {
+            self.asm.movsxd(x86::RAX, x86::RAX);
+        }
+        self.asm.mov_rr(d, x86::RAX);
+    }
+
+    /// d = (a cmp b) ? 1 : 0 on three registers.
+    fn set_cond_reg(&mut self, parsed: DecodedOperands, cond: Cond) {
+        self.asm.alu_rr(
+            AluOp::Cmp,
+            Self::reg(parsed.register_a),
+            Self::reg(parsed.register_b),
+            true,
+        );
+        self.asm.set_cond(cond, x86::RAX);
+        self.asm.mov_rr(Self::reg(parsed.register_d), x86::RAX);
+    }
+
+    /// A = B op immed_X (two registers and one immediate); `cond` selects a set-if comparison instead.
+    fn reg_imm(&mut self, parsed: DecodedOperands, op: RegImmOp) {
+        self.asm.mov_rr(x86::RAX, Self::reg(parsed.register_b));
+        self.asm.mov_ri(x86::RCX, parsed.immediate_x as u64);
+        match op {
+            RegImmOp::Alu(op, wide) => {
+                self.asm.alu_rr(op, x86::RAX, x86::RCX, wide);
+                if !wide {
+                    self.asm.movsxd(x86::RAX, x86::RAX);
+                }
+            }
+            RegImmOp::Mul(wide) => {
+                self.asm.imul_rr(x86::RAX, x86::RCX, wide);
+                if !wide {
+                    self.asm.movsxd(x86::RAX, x86::RAX);
+                }
+            }
+            RegImmOp::Set(cond) => {
+                self.asm.alu_rr(AluOp::Cmp, x86::RAX, x86::RCX, true);
+                self.asm.set_cond(cond, x86::RAX);
+            }
+        }
+        self.asm.mov_rr(Self::reg(parsed.register_a), x86::RAX);
+    }
+
+    /// dst = value op count for shifts and rotates. 32-bit forms work on the low 32 bits (count mod 32) and
+    /// sign-extend the result.
+    fn shift(&mut self, op: ShiftOp, wide: bool, dst: u8, value: Operand, count: Operand) {
+        match value {
+            Operand::Reg(index) => self.asm.mov_rr(x86::RAX, Self::reg(index)),
+            Operand::Imm(imm) => self.asm.mov_ri(x86::RAX, imm as u64),
+        }
+        match count {
+            Operand::Reg(index) => {
+                self.asm.mov_rr(x86::RCX, Self::reg(index));
+                self.asm.shift_cl(op, x86::RAX, wide);
+            }
+            Operand::Imm(imm) => {
+                let mask = if wide { 63 } else { 31 };
+                self.asm.shift_ri(op, x86::RAX, imm as u8 & mask, wide);
+            }
+        }
+        if !wide {
+            self.asm.movsxd(x86::RAX, x86::RAX);
+        }
+        self.asm.mov_rr(Self::reg(dst), x86::RAX);
+    }
+
+    /// d = (a cond b) ? a : b (MIN, MAX and their unsigned forms).
+    fn select(&mut self, parsed: DecodedOperands, cond: Cond) {
+        let (a, b) = (Self::reg(parsed.register_a), Self::reg(parsed.register_b));
+        self.asm.mov_rr(x86::RAX, b);
+        self.asm.alu_rr(AluOp::Cmp, a, b, true);
+        self.asm.cmov(cond, x86::RAX, a);
+        self.asm.mov_rr(Self::reg(parsed.register_d), x86::RAX);
+    }
+
+    /// d = upper 64 bits of a * b with a signed or not and b signed only when a is (S_S, U_U, S_U).
+    fn mul_upper(&mut self, parsed: DecodedOperands, signed_a: bool, signed_b: bool) {
+        let (a, b) = (Self::reg(parsed.register_a), Self::reg(parsed.register_b));
+        self.asm.mov_rr(x86::RAX, a);
+        self.asm.mov_rr(x86::RCX, b);
+        // The product lands in rdx:rax; rdx holds a PVM register.
+        self.asm.push(x86::RDX);
+        self.asm.mul_wide(x86::RCX, signed_a && signed_b);
+        if signed_a && !signed_b {
+            // Signed times unsigned: the unsigned high half, minus b when a is negative.
+            if a == x86::RDX {
+                self.asm.load64(x86::RAX, x86::RSP, 0);
+            } else {
+                self.asm.mov_rr(x86::RAX, a);
+            }
+            self.asm.shift_ri(ShiftOp::Sar, x86::RAX, 63, true);
+            self.asm.alu_rr(AluOp::And, x86::RAX, x86::RCX, true);
+            self.asm.alu_rr(AluOp::Sub, x86::RDX, x86::RAX, true);
+        }
+        self.asm.mov_rr(x86::RAX, x86::RDX);
+        self.asm.pop(x86::RDX);
+        self.asm.mov_rr(Self::reg(parsed.register_d), x86::RAX);
+    }
+
+    /// rax = number of set bits in rax (SWAR, borrowing rdx for the masks).
+    fn count_set_bits(&mut self) {
+        let asm = &mut self.asm;
+        asm.push(x86::RDX);
+        asm.mov_rr(x86::RCX, x86::RAX);
+        asm.shift_ri(ShiftOp::Shr, x86::RCX, 1, true);
+        asm.mov_ri(x86::RDX, 0x5555_5555_5555_5555);
+        asm.alu_rr(AluOp::And, x86::RCX, x86::RDX, true);
+        asm.alu_rr(AluOp::Sub, x86::RAX, x86::RCX, true);
+        asm.mov_ri(x86::RDX, 0x3333_3333_3333_3333);
+        asm.mov_rr(x86::RCX, x86::RAX);
+        asm.shift_ri(ShiftOp::Shr, x86::RCX, 2, true);
+        asm.alu_rr(AluOp::And, x86::RCX, x86::RDX, true);
+        asm.alu_rr(AluOp::And, x86::RAX, x86::RDX, true);
+        asm.alu_rr(AluOp::Add, x86::RAX, x86::RCX, true);
+        asm.mov_rr(x86::RCX, x86::RAX);
+        asm.shift_ri(ShiftOp::Shr, x86::RCX, 4, true);
+        asm.alu_rr(AluOp::Add, x86::RAX, x86::RCX, true);
+        asm.mov_ri(x86::RDX, 0x0f0f_0f0f_0f0f_0f0f);
+        asm.alu_rr(AluOp::And, x86::RAX, x86::RDX, true);
+        asm.mov_ri(x86::RDX, 0x0101_0101_0101_0101);
+        asm.imul_rr(x86::RAX, x86::RDX, true);
+        asm.shift_ri(ShiftOp::Shr, x86::RAX, 56, true);
+        asm.pop(x86::RDX);
+    }
+
+    /// d = f(a) for the bit counting, extension and byte reversal instructions.
+    fn unary(&mut self, opcode: u8, parsed: DecodedOperands) {
+        let a = Self::reg(parsed.register_a);
+        let asm = &mut self.asm;
+        match opcode {
+            OPCODE_COUNT_SET_BITS_64 | OPCODE_COUNT_SET_BITS_32 => {
+                if opcode == OPCODE_COUNT_SET_BITS_64 {
+                    asm.mov_rr(x86::RAX, a);
+                } else {
+                    asm.mov_rr32(x86::RAX, a);
+                }
+                self.count_set_bits();
+            }
+            // bsr gives the index of the highest set bit: 63 - index = index ^ 63, and 127 ^ 63 = 64 for zero.
+            OPCODE_LEADING_ZERO_BITS_64 => {
+                asm.mov_ri(x86::RCX, 127);
+                asm.bit_scan(x86::RAX, a, true, true);
+                asm.cmov(Cond::Equal, x86::RAX, x86::RCX);
+                asm.alu_ri(AluOp::Xor, x86::RAX, 63, true);
+            }
+            OPCODE_LEADING_ZERO_BITS_32 => {
+                asm.mov_ri(x86::RCX, 63);
+                asm.bit_scan(x86::RAX, a, true, false);
+                asm.cmov(Cond::Equal, x86::RAX, x86::RCX);
+                asm.alu_ri(AluOp::Xor, x86::RAX, 31, true);
+            }
+            OPCODE_TRAILING_ZERO_BITS_64 | OPCODE_TRAILING_ZERO_BITS_32 => {
+                let wide = opcode == OPCODE_TRAILING_ZERO_BITS_64;
+                asm.mov_ri(x86::RCX, if wide { 64 } else { 32 });
+                asm.bit_scan(x86::RAX, a, false, wide);
+                asm.cmov(Cond::Equal, x86::RAX, x86::RCX);
+            }
+            OPCODE_SIGN_EXTEND_8 => asm.extend(x86::RAX, a, Width::Byte, true),
+            OPCODE_SIGN_EXTEND_16 => asm.extend(x86::RAX, a, Width::Word, true),
+            OPCODE_ZERO_EXTEND_16 => asm.extend(x86::RAX, a, Width::Word, false),
+            _ => {
+                asm.mov_rr(x86::RAX, a);
+                asm.bswap(x86::RAX);
+            }
+        }
+        self.asm.mov_rr(Self::reg(parsed.register_d), x86::RAX);
+    }
+
+    /// rcx = address of the page table entry for byte `offset` of the access at guest address rax.
+    fn page_entry(&mut self, offset: u32) {
+        self.asm.mov_rr(x86::RCX, x86::RAX);
+        if offset > 0 {
+            self.asm.alu_ri(AluOp::Add, x86::RCX, offset as i32, true);
+        }
+        self.asm.shift_ri(
+            ShiftOp::Shr,
+            x86::RCX,
+            PAGE_SIZE.trailing_zeros() as u8,
+            true,
+        );
+        self.asm.alu_rm(AluOp::Add, x86::RCX, x86::RSP, STACK_PAGES);
+    }
+
+    fn stub(&mut self, at: Site, exit: FaultExit) -> Label {
+        let label = self.asm.new_label();
+        self.stubs.push((label, at, exit));
+        label
+    }
+
+    /// Inline load or store with the checks of `MmapRam::read_octets` / `write_octets`, in the same order:
+    /// zone (panic), end of the address space, then the first and last page (fault).
+    fn memory(&mut self, at: Site, op: MemoryOp) {
+        match op.base {
+            Some(base) => {
+                self.asm.mov_rr(x86::RAX, Self::reg(base));
+                if let Ok(offset) = i32::try_from(op.offset) {
+                    self.asm.alu_ri(AluOp::Add, x86::RAX, offset, true);
+                } else {
+                    self.asm.mov_ri(x86::RCX, op.offset as u64);
+                    self.asm.alu_rr(AluOp::Add, x86::RAX, x86::RCX, true);
+                }
+                self.asm.mov_rr32(x86::RAX, x86::RAX);
+            }
+            None => self.asm.mov_ri(x86::RAX, u64::from(op.offset as u32)),
+        }
+        if op.zone_check {
+            self.asm
+                .alu_ri(AluOp::Cmp, x86::RAX, ZONE_SIZE as i32, false);
+            let stub = self.stub(at, FaultExit::Panic);
+            self.asm.jcc(Cond::Below, stub);
+        }
+        let last = op.width as u32 - 1;
+        let offsets: &[u32] = if last > 0 { &[0, last] } else { &[0] };
+        if last > 0 {
+            self.asm
+                .alu_ri(AluOp::Cmp, x86::RAX, (u32::MAX - last) as i32, false);
+            let stub = self.stub(at, FaultExit::Wrap);
+            self.asm.jcc(Cond::Above, stub);
+        }
+        let write = !matches!(op.access, Access::Load(..));
+        // Reads check the rights of both pages before their contents, as `read_octets` does.
+        let checks: &[u8] = if write {
+            &[WRITABLE]
+        } else {
+            &[READABLE, BACKED]
+        };
+        for &bits in checks {
+            for &offset in offsets {
+                self.page_entry(offset);
+                self.asm.test_mem8_imm(x86::RCX, 0, bits);
+                let stub = self.stub(at, FaultExit::Page(offset));
+                self.asm.jcc(Cond::Equal, stub);
+            }
+        }
+        if write {
+            for &offset in offsets {
+                self.page_entry(offset);
+                self.asm.or_mem8_imm(x86::RCX, 0, DIRTY | BACKED);
+            }
+        }
+        self.asm
+            .alu_rm(AluOp::Add, x86::RAX, x86::RSP, STACK_MEMORY);
+        match op.access {
+            Access::Load(dst, signed) => {
+                self.asm.load(Self::reg(dst), x86::RAX, 0, op.width, signed);
+            }
+            Access::StoreReg(src) => self.asm.store(x86::RAX, 0, Self::reg(src), op.width),
+            Access::StoreImm(value) => {
+                self.asm.mov_ri(x86::RCX, value as u64);
+                self.asm.store(x86::RAX, 0, x86::RCX, op.width);
+            }
+        }
+    }
+
+    /// Emit the exits collected by `memory`; rax still holds the guest address there.
+    fn emit_stubs(&mut self) {
+        for (label, at, exit) in std::mem::take(&mut self.stubs) {
+            self.asm.bind(label);
+            match exit {
+                FaultExit::Panic => self.exit_panic(at.pc, at.executed),
+                FaultExit::Wrap => {
+                    self.asm.mov_rr(x86::RCX, x86::RAX);
+                    self.exit_stop(at.pc, at.executed, RESULT_CODE_FAULT, true);
+                }
+                FaultExit::Page(offset) => {
+                    self.asm.mov_rr(x86::RCX, x86::RAX);
+                    if offset > 0 {
+                        self.asm.alu_ri(AluOp::Add, x86::RCX, offset as i32, true);
+                    }
+                    self.asm
+                        .alu_ri(AluOp::And, x86::RCX, -(PAGE_SIZE as i32), true);
+                    self.exit_stop(at.pc, at.executed, RESULT_CODE_FAULT, true);
+                }
+            }
+        }
+    }
+
+    /// Run the instruction through its interpreter handler; leave the block if it stops or jumps.
+    fn call_interpreter(&mut self, pc: u32, executed: usize) {
+        self.spill();
+        self.asm
+            .store32_imm(x86::RAX, CTX_EXECUTED, executed as u32);
+        self.asm.mov_rr(x86::RDI, x86::RAX);
+        self.asm.mov_ri(x86::RSI, u64::from(pc));
+        self.asm
+            .mov_ri(x86::RAX, interpret_instruction as *const () as usize as u64);
+        self.asm.call_reg(x86::RAX);
+        self.asm.mov_rr(x86::RCX, x86::RAX);
+        self.reload();
+        self.asm.test(x86::RCX, x86::RCX, false);
+        self.asm.jcc(Cond::NotEqual, self.epilogue);
+    }
+}
+
+/// Position of the instruction being translated: its PC, the next PC and how many instructions have run.
+#[derive(Clone, Copy)]
+struct Site {
+    pc: u32,
+    next_pc: u32,
+    executed: usize,
+}
+
+#[derive(Clone, Copy)]
+enum RegImmOp {
+    Alu(AluOp, bool),
+    Mul(bool),
+    Set(Cond),
+}
+
+/// Value or shift count of a shift or rotate.
+#[derive(Clone, Copy)]
+enum Operand {
+    Reg(u8),
+    Imm(i64),
+}
+
+/// How a memory access leaves the block when its checks fail.
+#[derive(Clone, Copy)]
+enum FaultExit {
+    /// Address below the first zone.
+    Panic,
+    /// The access runs past the end of the address space: fault at its address.
+    Wrap,
+    /// The page holding byte `offset` of the access is inaccessible: fault at the page start.
+    Page(u32),
+}
+
+/// Register written by a load, or the value a store writes.
+#[derive(Clone, Copy)]
+enum Access {
+    Load(u8, bool),
+    StoreReg(u8),
+    StoreImm(i64),
+}
+
+/// A load or store at `reg(base) + offset` (mod 2^32), or at `offset` without a base register.
+#[derive(Clone, Copy)]
+struct MemoryOp {
+    base: Option<u8>,
+    offset: i64,
+    width: Width,
+    access: Access,
+    /// Addresses below ZONE_SIZE panic (LOAD_U8..LOAD_I16 only fault there, as in the interpreter).
+    zone_check: bool,
+}
+
+/// The memory access of a load or store instruction; None for every other opcode.
+fn memory_op(opcode: u8, parsed: DecodedOperands) -> Option<MemoryOp> {
+    let DecodedOperands {
+        register_a,
+        register_b,
+        immediate_x,
+        immediate_y,
+        ..
+    } = parsed;
+    let direct = |width, access, zone_check| MemoryOp {
+        base: None,
+        offset: immediate_x,
+        width,
+        access,
+        zone_check,
+    };
+    let indirect = |base, width, access| MemoryOp {
+        base: Some(base),
+        offset: immediate_x,
+        width,
+        access,
+        zone_check: true,
+    };
+    let load = |signed| Access::Load(register_a, signed);
+    let store = Access::StoreReg(register_a);
+    let store_imm = Access::StoreImm(immediate_y);
+    Some(match opcode {
+        OPCODE_LOAD_U8 => direct(Width::Byte, load(false), false),
+        OPCODE_LOAD_I8 => direct(Width::Byte, load(true), false),
+        OPCODE_LOAD_U16 => direct(Width::Word, load(false), false),
+        OPCODE_LOAD_I16 => direct(Width::Word, load(true), false),
+        OPCODE_LOAD_U32 => direct(Width::Dword, load(false), true),
+        OPCODE_LOAD_I32 => direct(Width::Dword, load(true), true),
+        OPCODE_LOAD_U64 => direct(Width::Qword, load(false), true),
+        OPCODE_STORE_U8 => direct(Width::Byte, store, true),
+        OPCODE_STORE_U16 => direct(Width::Word, store, true),
+        OPCODE_STORE_U32 => direct(Width::Dword, store, true),
+        OPCODE_STORE_U64 => direct(Width::Qword, store, true),
+        OPCODE_STORE_IMM_U8 => direct(Width::Byte, store_imm, true),
+        OPCODE_STORE_IMM_U16 => direct(Width::Word, store_imm, true),
+        OPCODE_STORE_IMM_U32 => direct(Width::Dword, store_imm, true),
+        OPCODE_STORE_IMM_U64 => direct(Width::Qword, store_imm, true),
+        OPCODE_LOAD_IND_U8 => indirect(register_b, Width::Byte, load(false)),
+        OPCODE_LOAD_IND_I8 => indirect(register_b, Width::Byte, load(true)),
+        OPCODE_LOAD_IND_U16 => indirect(register_b, Width::Word, load(false)),
+        OPCODE_LOAD_IND_I16 => indirect(register_b, Width::Word, load(true)),
+        OPCODE_LOAD_IND_U32 => indirect(register_b, Width::Dword, load(false)),
+        OPCODE_LOAD_IND_I32 => indirect(register_b, Width::Dword, load(true)),
+        OPCODE_LOAD_IND_U64 => indirect(register_b, Width::Qword, load(false)),
+        OPCODE_STORE_IND_U8 => indirect(register_b, Width::Byte, store),
+        OPCODE_STORE_IND_U16 => indirect(register_b, Width::Word, store),
+        OPCODE_STORE_IND_U32 => indirect(register_b, Width::Dword, store),
+        OPCODE_STORE_IND_U64 => indirect(register_b, Width::Qword, store),
+        OPCODE_STORE_IMM_IND_U8 => indirect(register_a, Width::Byte, store_imm),
+        OPCODE_STORE_IMM_IND_U16 => indirect(register_a, Width::Word, store_imm),
+        OPCODE_STORE_IMM_IND_U32 => indirect(register_a, Width::Dword, store_imm),
+        OPCODE_STORE_IMM_IND_U64 => indirect(register_a, Width::Qword, store_imm),
+        _ => return None,
+    })
+}
+
+/// Translate the block starting at `start_pc`. None when its first instruction must be interpreted.
+fn compile_block(
+    code: &[u8],
+    bitmask: &[u8],
+    decoded: &[DecodedInstruction],
+    start_pc: u32,
+    inline_memory: bool,
+) -> Option<(Vec<u8>, Vec<u32>)> {
+    let registry = instruction_registry();
+    let mut pcs = Vec::new();
+    let mut pc = start_pc as usize;
+    while pc < code.len() && pcs.len() < MAX_BLOCK_INSTRUCTIONS {
+        let opcode = decoded[pc].opcode;
+        if opcode == OPCODE_ECALLI || registry.handler(opcode).is_none() {
+            break;
+        }
+        pcs.push(pc as u32);
+        if is_termination_instruction(opcode) {
+            break;
+        }
+        pc = decoded[pc].next_pc as usize;
+    }
+    let &last_pc = pcs.last()?;
+
+    let mut asm = Assembler::new();
+    let enter = asm.new_label();
+    let epilogue = asm.new_label();
+
+    // Gas for the whole block up front; too little and nothing runs.
+    asm.cmp_mem64_imm(x86::RDI, CTX_GAS, pcs.len() as i32);
+    asm.jcc(Cond::AboveOrEqual, enter);
+    asm.mov_ri(x86::RAX, u64::from(EXIT_NO_GAS));
+    asm.ret();
+    asm.bind(enter);
+    asm.sub_mem64_imm(x86::RDI, CTX_GAS, pcs.len() as i32);
+    for &reg in &SAVED_REGS {
+        asm.push(reg);
+    }
+    // The STACK_* slots; nine pushes in all keep rsp 16-byte aligned for calls into Rust.
+    asm.load64(x86::RAX, x86::RDI, CTX_MEMORY);
+    asm.push(x86::RAX);
+    asm.load64(x86::RAX, x86::RDI, CTX_PAGES);
+    asm.push(x86::RAX);
+    asm.push(x86::RDI);
+    asm.mov_rr(x86::RAX, x86::RDI);
+    for (i, &reg) in PVM_REGS.iter().enumerate() {
+        asm.load64(reg, x86::RAX, (i * 8) as i32);
+    }
+
+    let mut emitter = BlockEmitter {
+        asm,
+        epilogue,
+        code,
+        bitmask,
+        stubs: Vec::new(),
+    };
+    for (k, &pc) in pcs.iter().enumerate() {
+        let DecodedInstruction {
+            opcode,
+            next_pc,
+            operands: parsed,
+            ..
+        } = decoded[pc as usize];
+        let executed = k + 1;
+        let at = Site {
+            pc,
+            next_pc,
+            executed,
+        };
+        if let Some(op) = memory_op(opcode, parsed).filter(|_| inline_memory) {
+            emitter.memory(at, op);
+            continue;
+        }
+        match opcode {
+            OPCODE_TRAP => emitter.exit_panic(pc, executed),
+            OPCODE_FALLTHROUGH => emitter.exit_next(next_pc, executed),
+            OPCODE_JUMP => emitter.exit_jump(at, parsed.target_address),
+            OPCODE_LOAD_IMM_JUMP => {
+                let target = parsed.target_address;
+                if validate_branch_target(target, code, bitmask).is_some() {
+                    emitter.exit_panic(pc, executed);
+                } else {
+                    emitter.asm.mov_ri(
+                        BlockEmitter::reg(parsed.register_a),
+                        parsed.immediate_x as u64,
+                    );
+                    emitter.exit_next(if target == pc { next_pc } else { target }, executed);
+                }
+            }
+            OPCODE_BRANCH_EQ_IMM..=OPCODE_BRANCH_GT_S_IMM => {
+                let cond = match opcode {
+                    OPCODE_BRANCH_EQ_IMM => Cond::Equal,
+                    OPCODE_BRANCH_NE_IMM => Cond::NotEqual,
+                    OPCODE_BRANCH_LT_U_IMM => Cond::Below,
+                    OPCODE_BRANCH_LE_U_IMM => Cond::BelowOrEqual,
+                    OPCODE_BRANCH_GE_U_IMM => Cond::AboveOrEqual,
+                    OPCODE_BRANCH_GT_U_IMM => Cond::Above,
+                    OPCODE_BRANCH_LT_S_IMM => Cond::Less,
+                    OPCODE_BRANCH_LE_S_IMM => Cond::LessOrEqual,
+                    OPCODE_BRANCH_GE_S_IMM => Cond::GreaterOrEqual,
+                    _ => Cond::Greater,
+                };
+                emitter.asm.mov_ri(x86::RCX, parsed.immediate_x as u64);
+                let lhs = BlockEmitter::reg(parsed.register_a);
+                emitter.branch(at, cond, lhs, x86::RCX, parsed.target_address);
+            }
+            OPCODE_BRANCH_EQ..=OPCODE_BRANCH_GE_S => {
+                let cond = match opcode {
+                    OPCODE_BRANCH_EQ => Cond::Equal,
+                    OPCODE_BRANCH_NE => Cond::NotEqual,
+                    OPCODE_BRANCH_LT_U => Cond::Below,
+                    OPCODE_BRANCH_LT_S => Cond::Less,
+                    OPCODE_BRANCH_GE_U => Cond::AboveOrEqual,
+                    _ => Cond::GreaterOrEqual,
+                };
+                let lhs = BlockEmitter::reg(parsed.register_a);
+                let rhs = BlockEmitter::reg(parsed.register_b);
+                emitter.branch(at, cond, lhs, rhs, parsed.target_address);
+            }
+            OPCODE_LOAD_IMM | OPCODE_LOAD_IMM_64 => {
+                emitter.asm.mov_ri(
+                    BlockEmitter::reg(parsed.register_a),
+                    parsed.immediate_x as u64,
+                );
+            }
+            OPCODE_MOVE_REG => {
+                emitter.asm.mov_rr(
+                    BlockEmitter::reg(parsed.register_d),
+                    BlockEmitter::reg(parsed.register_a),
+                );
+            }
+            OPCODE_ADD_64 => emitter.three_reg(parsed, Some(AluOp::Add), true),
+            OPCODE_SUB_64 => emitter.three_reg(parsed, Some(AluOp::Sub), true),
+            OPCODE_MUL_64 => emitter.three_reg(parsed, None, true),
+            OPCODE_AND => emitter.three_reg(parsed, Some(AluOp::And), true),
+            OPCODE_XOR => emitter.three_reg(parsed, Some(AluOp::Xor), true),
+            OPCODE_OR => emitter.three_reg(parsed, Some(AluOp::Or), true),
+            OPCODE_ADD_32 => emitter.three_reg(parsed, Some(AluOp::Add), false),
+            OPCODE_SUB_32 => emitter.three_reg(parsed, Some(AluOp::Sub), false),
+            OPCODE_MUL_32 => emitter.three_reg(parsed, None, false),
+            OPCODE_SET_LT_U => emitter.set_cond_reg(parsed, Cond::Below),
+            OPCODE_SET_LT_S => emitter.set_cond_reg(parsed, Cond::Less),
+            OPCODE_ADD_IMM_64 | OPCODE_AND_IMM | OPCODE_XOR_IMM | OPCODE_OR_IMM
+            | OPCODE_ADD_IMM_32 | OPCODE_MUL_IMM_32 | OPCODE_MUL_IMM_64 | OPCODE_SET_LT_U_IMM
+            | OPCODE_SET_LT_S_IMM | OPCODE_SET_GT_U_IMM | OPCODE_SET_GT_S_IMM => {
+                let op = match opcode {
+                    OPCODE_ADD_IMM_64 => RegImmOp::Alu(AluOp::Add, true),
+                    OPCODE_AND_IMM => RegImmOp::Alu(AluOp::And, true),
+                    OPCODE_XOR_IMM => RegImmOp::Alu(AluOp::Xor, true),
+                    OPCODE_OR_IMM => RegImmOp::Alu(AluOp::Or, true),
+                    OPCODE_ADD_IMM_32 => RegImmOp::Alu(AluOp::Add, false),
+                    OPCODE_MUL_IMM_32 => RegImmOp::Mul(false),
+                    OPCODE_MUL_IMM_64 => RegImmOp::Mul(true),
+                    OPCODE_SET_LT_U_IMM => RegImmOp::Set(Cond::Below),
+                    OPCODE_SET_LT_S_IMM => RegImmOp::Set(Cond::Less),
+                    OPCODE_SET_GT_U_IMM => RegImmOp::Set(Cond::Above),
+                    _ => RegImmOp::Set(Cond::Greater),
+                };
+                emitter.reg_imm(parsed, op);
+            }
+            OPCODE_NEG_ADD_IMM_32 | OPCODE_NEG_ADD_IMM_64 => {
+                emitter.asm.mov_ri(x86::RAX, parsed.immediate_x as u64);
+                let b = BlockEmitter::reg(parsed.register_b);
+                emitter.asm.alu_rr(AluOp::Sub, x86::RAX, b, true);
+                if opcode == OPCODE_NEG_ADD_IMM_32 {
+                    emitter.asm.movsxd(x86::RAX, x86::RAX);
+                }
+                emitter
+                    .asm
+                    .mov_rr(BlockEmitter::reg(parsed.register_a), x86::RAX);
+            }
+            OPCODE_SHLO_L_32..=OPCODE_SHAR_R_32
+            | OPCODE_SHLO_L_64..=OPCODE_SHAR_R_64
+            | OPCODE_ROT_L_64..=OPCODE_ROT_R_32 => {
+                let (op, wide) = match opcode {
+                    OPCODE_SHLO_L_32 => (ShiftOp::Shl, false),
+                    OPCODE_SHLO_R_32 => (ShiftOp::Shr, false),
+                    OPCODE_SHAR_R_32 => (ShiftOp::Sar, false),
+                    OPCODE_SHLO_L_64 => (ShiftOp::Shl, true),
+                    OPCODE_SHLO_R_64 => (ShiftOp::Shr, true),
+                    OPCODE_SHAR_R_64 => (ShiftOp::Sar, true),
+                    OPCODE_ROT_L_64 => (ShiftOp::Rol, true),
+                    OPCODE_ROT_L_32 => (ShiftOp::Rol, false),
+                    OPCODE_ROT_R_64 => (ShiftOp::Ror, true),
+                    _ => (ShiftOp::Ror, false),
+                };
+                let value = Operand::Reg(parsed.register_a);
+                let count = Operand::Reg(parsed.register_b);
+                emitter.shift(op, wide, parsed.register_d, value, count);
+            }
+            OPCODE_SHLO_L_IMM_32 | OPCODE_SHLO_R_IMM_32 | OPCODE_SHAR_R_IMM_32
+            | OPCODE_SHLO_L_IMM_64 | OPCODE_SHLO_R_IMM_64 | OPCODE_SHAR_R_IMM_64
+            | OPCODE_ROT_R_64_IMM | OPCODE_ROT_R_32_IMM => {
+                let (op, wide) = match opcode {
+                    OPCODE_SHLO_L_IMM_32 => (ShiftOp::Shl, false),
+                    OPCODE_SHLO_R_IMM_32 => (ShiftOp::Shr, false),
+                    OPCODE_SHAR_R_IMM_32 => (ShiftOp::Sar, false),
+                    OPCODE_SHLO_L_IMM_64 => (ShiftOp::Shl, true),
+                    OPCODE_SHLO_R_IMM_64 => (ShiftOp::Shr, true),
+                    OPCODE_SHAR_R_IMM_64 => (ShiftOp::Sar, true),
+                    OPCODE_ROT_R_64_IMM => (ShiftOp::Ror, true),
+                    _ => (ShiftOp::Ror, false),
+                };
+                let value = Operand::Reg(parsed.register_b);
+                let count = Operand::Imm(parsed.immediate_x);
+                emitter.shift(op, wide, parsed.register_a, value, count);
+            }
+            OPCODE_SHLO_L_IMM_ALT_32
+            | OPCODE_SHLO_R_IMM_ALT_32
+            | OPCODE_SHAR_R_IMM_ALT_32
+            | OPCODE_SHLO_L_IMM_ALT_64
+            | OPCODE_SHLO_R_IMM_ALT_64
+            | OPCODE_SHAR_R_IMM_ALT_64
+            | OPCODE_ROT_R_64_IMM_ALT
+            | OPCODE_ROT_R_32_IMM_ALT => {
+                let (op, wide) = match opcode {
+                    OPCODE_SHLO_L_IMM_ALT_32 => (ShiftOp::Shl, false),
+                    OPCODE_SHLO_R_IMM_ALT_32 => (ShiftOp::Shr, false),
+                    OPCODE_SHAR_R_IMM_ALT_32 => (ShiftOp::Sar, false),
+                    OPCODE_SHLO_L_IMM_ALT_64 => (ShiftOp::Shl, true),
+                    OPCODE_SHLO_R_IMM_ALT_64 => (ShiftOp::Shr, true),
+                    OPCODE_SHAR_R_IMM_ALT_64 => (ShiftOp::Sar, true),
+                    OPCODE_ROT_R_64_IMM_ALT => (ShiftOp::Ror, true),
+                    _ => (ShiftOp::Ror, false),
+                };
+                let value = Operand::Imm(parsed.immediate_x);
+                let count = Operand::Reg(parsed.register_b);
+                emitter.shift(op, wide, parsed.register_a, value, count);
+            }
+            OPCODE_CMOV_IZ | OPCODE_CMOV_NZ => {
+                let cond = if opcode == OPCODE_CMOV_IZ {
+                    Cond::Equal
+                } else {
+                    Cond::NotEqual
+                };
+                let b = BlockEmitter::reg(parsed.register_b);
+                emitter.asm.test(b, b, true);
+                emitter.asm.cmov(
+                    cond,
+                    BlockEmitter::reg(parsed.register_d),
+                    BlockEmitter::reg(parsed.register_a),
+                );
+            }
+            OPCODE_CMOV_IZ_IMM | OPCODE_CMOV_NZ_IMM => {
+                let cond = if opcode == OPCODE_CMOV_IZ_IMM {
+                    Cond::Equal
+                } else {
+                    Cond::NotEqual
+                };
+                emitter.asm.mov_ri(x86::RCX, parsed.immediate_x as u64);
+                let b = BlockEmitter::reg(parsed.register_b);
+                emitter.asm.test(b, b, true);
+                emitter
+                    .asm
+                    .cmov(cond, BlockEmitter::reg(parsed.register_a), x86::RCX);
+            }
+            OPCODE_AND_INV | OPCODE_OR_INV => {
+                let op = if opcode == OPCODE_AND_INV {
+                    AluOp::And
+                } else {
+                    AluOp::Or
+                };
+                emitter
+                    .asm
+                    .mov_rr(x86::RAX, BlockEmitter::reg(parsed.register_b));
+                emitter.asm.not(x86::RAX);
+                let a = BlockEmitter::reg(parsed.register_a);
+                emitter.asm.alu_rr(op, x86::RAX, a, true);
+                emitter
+                    .asm
+                    .mov_rr(BlockEmitter::reg(parsed.register_d), x86::RAX);
+            }
+            OPCODE_XNOR => {
+                emitter.three_reg(parsed, Some(AluOp::Xor), true);
+                emitter.asm.not(BlockEmitter::reg(parsed.register_d));
+            }
+            OPCODE_MAX => emitter.select(parsed, Cond::Greater),
+            OPCODE_MAX_U => emitter.select(parsed, Cond::Above),
+            OPCODE_MIN => emitter.select(parsed, Cond::Less),
+            OPCODE_MIN_U => emitter.select(parsed, Cond::Below),
+            OPCODE_MUL_UPPER_S_S => emitter.mul_upper(parsed, true, true),
+            OPCODE_MUL_UPPER_U_U => emitter.mul_upper(parsed, false, false),
+            OPCODE_MUL_UPPER_S_U => emitter.mul_upper(parsed, true, false),
+            OPCODE_COUNT_SET_BITS_64..=OPCODE_REVERSE_BYTES => emitter.unary(opcode, parsed),
+            _ => {
+                emitter.call_interpreter(pc, executed);
+                if is_termination_instruction(opcode) {
+                    // The handler returned EXIT_NEXT or EXIT_STOP; this point is not reached.
+                    emitter.exit_next(next_pc, executed);
+                }
+            }
+        }
+    }
+    // Block ended without a terminator (ECALLI or an untranslatable opcode follows, or the size cap).
+    if !is_termination_instruction(decoded[last_pc as usize].opcode) {
+        emitter.exit_next(decoded[last_pc as usize].next_pc, pcs.len());
+    }
+    emitter.emit_stubs();
+
+    let mut asm = emitter.asm;
+    asm.bind(epilogue);
+    // Exit code in ecx; registers go back to the context.
+    asm.load64(x86::RAX, x86::RSP, STACK_CTX);
+    for (i, &reg) in PVM_REGS.iter().enumerate() {
+        asm.store64(x86::RAX, (i * 8) as i32, reg);
+    }
+    asm.mov_rr(x86::RAX, x86::RCX);
+    asm.add_rsp(24);
+    for &reg in SAVED_REGS.iter().rev() {
+        asm.pop(reg);
+    }
+    asm.ret();
+    Some((asm.finish(), pcs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::config::{
+        OPCODE_ADD_32, OPCODE_ADD_64, OPCODE_ADD_IMM_32, OPCODE_ADD_IMM_64, OPCODE_AND,
+        OPCODE_AND_IMM, OPCODE_AND_INV, OPCODE_BRANCH_EQ, OPCODE_BRANCH_GE_S,
+        OPCODE_BRANCH_GE_S_IMM, OPCODE_BRANCH_LT_U_IMM, OPCODE_BRANCH_NE_IMM, OPCODE_CMOV_IZ,
+        OPCODE_CMOV_IZ_IMM, OPCODE_CMOV_NZ, OPCODE_CMOV_NZ_IMM, OPCODE_COUNT_SET_BITS_32,
+        OPCODE_COUNT_SET_BITS_64, OPCODE_DIV_U_64, OPCODE_FALLTHROUGH, OPCODE_JUMP,
+        OPCODE_LEADING_ZERO_BITS_32, OPCODE_LEADING_ZERO_BITS_64, OPCODE_LOAD_IMM,
+        OPCODE_LOAD_IMM_64, OPCODE_LOAD_IND_U8, OPCODE_LOAD_U64, OPCODE_LOAD_U8, OPCODE_MAX,
+        OPCODE_MAX_U, OPCODE_MIN, OPCODE_MIN_U, OPCODE_MOVE_REG, OPCODE_MUL_32, OPCODE_MUL_64,
+        OPCODE_MUL_IMM_32, OPCODE_MUL_UPPER_S_S, OPCODE_MUL_UPPER_S_U, OPCODE_MUL_UPPER_U_U,
+        OPCODE_NEG_ADD_IMM_32, OPCODE_NEG_ADD_IMM_64, OPCODE_OR, OPCODE_OR_INV,
+        OPCODE_REVERSE_BYTES, OPCODE_ROT_L_32, OPCODE_ROT_L_64, OPCODE_ROT_R_32,
+        OPCODE_ROT_R_32_IMM, OPCODE_ROT_R_32_IMM_ALT, OPCODE_ROT_R_64, OPCODE_ROT_R_64_IMM,
+        OPCODE_ROT_R_64_IMM_ALT, OPCODE_SET_GT_S_IMM, OPCODE_SET_LT_S, OPCODE_SET_LT_U_IMM,
+        OPCODE_SHAR_R_32, OPCODE_SHAR_R_64, OPCODE_SHAR_R_IMM_32, OPCODE_SHAR_R_IMM_ALT_64,
+        OPCODE_SHLO_L_32, OPCODE_SHLO_L_64, OPCODE_SHLO_L_IMM_64, OPCODE_SHLO_L_IMM_ALT_32,
+        OPCODE_SHLO_R_32, OPCODE_SHLO_R_64, OPCODE_SHLO_R_IMM_32, OPCODE_SHLO_R_IMM_ALT_64,
+        OPCODE_SIGN_EXTEND_16, OPCODE_SIGN_EXTEND_8, OPCODE_STORE_IMM_IND_U16,
+        OPCODE_STORE_IMM_IND_U64, OPCODE_STORE_IMM_U32, OPCODE_STORE_IMM_U8, OPCODE_STORE_IND_U16,
+        OPCODE_STORE_IND_U64, OPCODE_STORE_U16, OPCODE_STORE_U64, OPCODE_SUB_32, OPCODE_SUB_64,
+        OPCODE_TRAILING_ZERO_BITS_32, OPCODE_TRAILING_ZERO_BITS_64, OPCODE_TRAP, OPCODE_XNOR,
+        OPCODE_XOR, OPCODE_ZERO_EXTEND_16,
+    };
+    use crate::state_wrapper::{next_step_impl, PvmState, RAMType, Status};
+    use crate::types::{MemoryAccessType, Ram};
+
+    /// xorshift64*: deterministic, dependency-free randomness for the differential test.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    /// Guest address near the mapped pages (see `prepared_state`), page boundaries and the end of the
+    /// address space included.
+    fn random_address(rng: &mut Rng) -> u32 {
+        match rng.below(4) {
+            0 => 0x10000 + rng.below(0x3000) as u32,
+            1 => 0x10ff8 + rng.below(0x10) as u32,
+            2 => 0xffff_f000 + rng.below(0x1000) as u32,
+            _ => rng.below(0x20000) as u32,
+        }
+    }
+
+    /// Random load or store, in every operand form.
+    fn random_memory_access(rng: &mut Rng, regs: u8) -> Vec<u8> {
+        let address = random_address(rng).to_le_bytes();
+        let value = (rng.next() as u16).to_le_bytes();
+        match rng.below(6) {
+            0 => {
+                let opcode = [OPCODE_LOAD_U8, OPCODE_LOAD_U64][rng.below(2) as usize];
+                let opcode = opcode + rng.below(7) as u8;
+                let mut v = vec![opcode, regs & 0xf];
+                v.extend_from_slice(&address);
+                v
+            }
+            1 => {
+                let opcode = [OPCODE_STORE_U16, OPCODE_STORE_U64][rng.below(2) as usize];
+                let mut v = vec![opcode - rng.below(2) as u8, regs & 0xf];
+                v.extend_from_slice(&address);
+                v
+            }
+            2 => {
+                let opcode = [OPCODE_STORE_IMM_U8, OPCODE_STORE_IMM_U32][rng.below(2) as usize];
+                let mut v = vec![opcode + rng.below(2) as u8, 4];
+                v.extend_from_slice(&address);
+                v.extend_from_slice(&value);
+                v
+            }
+            3 => {
+                // Offset from a register near the mapped pages (small, or wrapping from a large one).
+                let mut v = vec![OPCODE_LOAD_IND_U8 + rng.below(7) as u8, regs];
+                v.extend_from_slice(
+                    &(rng.below(0x40) as u16).to_le_bytes()[..rng.below(3) as usize],
+                );
+                v
+            }
+            4 => {
+                let opcode = [OPCODE_STORE_IND_U16, OPCODE_STORE_IND_U64][rng.below(2) as usize];
+                vec![opcode - rng.below(2) as u8, regs, rng.below(0x40) as u8]
+            }
+            _ => {
+                let opcode =
+                    [OPCODE_STORE_IMM_IND_U16, OPCODE_STORE_IMM_IND_U64][rng.below(2) as usize];
+                let mut v = vec![opcode - rng.below(2) as u8, (regs & 0xf) | 0x10];
+                v.push(rng.below(0x40) as u8);
+                v.extend_from_slice(&value);
+                v
+            }
+        }
+    }
+
+    /// Random program mixing inline-translated and interpreter-handled instructions, with branches
+    /// to instruction starts (valid or not) and memory accesses around the mapped pages.
+    fn random_program(rng: &mut Rng) -> (Vec<u8>, Vec<u8>) {
+        let three_reg = [
+            OPCODE_ADD_64,
+            OPCODE_SUB_64,
+            OPCODE_MUL_64,
+            OPCODE_AND,
+            OPCODE_XOR,
+            OPCODE_OR,
+            OPCODE_ADD_32,
+            OPCODE_SUB_32,
+            OPCODE_MUL_32,
+            OPCODE_SET_LT_S,
+            OPCODE_DIV_U_64,
+            OPCODE_SHLO_L_64,
+            OPCODE_SHLO_R_64,
+            OPCODE_SHAR_R_64,
+            OPCODE_SHLO_L_32,
+            OPCODE_SHLO_R_32,
+            OPCODE_SHAR_R_32,
+            OPCODE_ROT_L_64,
+            OPCODE_ROT_L_32,
+            OPCODE_ROT_R_64,
+            OPCODE_ROT_R_32,
+            OPCODE_MUL_UPPER_S_S,
+            OPCODE_MUL_UPPER_U_U,
+            OPCODE_MUL_UPPER_S_U,
+            OPCODE_CMOV_IZ,
+            OPCODE_CMOV_NZ,
+            OPCODE_AND_INV,
+            OPCODE_OR_INV,
+            OPCODE_XNOR,
+            OPCODE_MAX,
+            OPCODE_MAX_U,
+            OPCODE_MIN,
+            OPCODE_MIN_U,
+        ];
+        let reg_imm = [
+            OPCODE_ADD_IMM_64,
+            OPCODE_AND_IMM,
+            OPCODE_ADD_IMM_32,
+            OPCODE_MUL_IMM_32,
+            OPCODE_SET_LT_U_IMM,
+            OPCODE_SET_GT_S_IMM,
+            OPCODE_SHLO_L_IMM_64,
+            OPCODE_SHLO_R_IMM_32,
+            OPCODE_SHAR_R_IMM_32,
+            OPCODE_SHLO_L_IMM_ALT_32,
+            OPCODE_SHLO_R_IMM_ALT_64,
+            OPCODE_SHAR_R_IMM_ALT_64,
+            OPCODE_ROT_R_64_IMM,
+            OPCODE_ROT_R_32_IMM,
+            OPCODE_ROT_R_64_IMM_ALT,
+            OPCODE_ROT_R_32_IMM_ALT,
+            OPCODE_NEG_ADD_IMM_32,
+            OPCODE_NEG_ADD_IMM_64,
+            OPCODE_CMOV_IZ_IMM,
+            OPCODE_CMOV_NZ_IMM,
+        ];
+        let two_reg = [
+            OPCODE_COUNT_SET_BITS_64,
+            OPCODE_COUNT_SET_BITS_32,
+            OPCODE_LEADING_ZERO_BITS_64,
+            OPCODE_LEADING_ZERO_BITS_32,
+            OPCODE_TRAILING_ZERO_BITS_64,
+            OPCODE_TRAILING_ZERO_BITS_32,
+            OPCODE_SIGN_EXTEND_8,
+            OPCODE_SIGN_EXTEND_16,
+            OPCODE_ZERO_EXTEND_16,
+            OPCODE_REVERSE_BYTES,
+        ];
+        let count = 8 + rng.below(40) as usize;
+        let mut starts = Vec::new();
+        let mut instructions: Vec<Vec<u8>> = Vec::new();
+        let mut pc = 0u32;
+        for _ in 0..count {
+            starts.push(pc);
+            let regs = (rng.below(13) as u8) | ((rng.below(13) as u8) << 4);
+            let imm = (rng.next() as u32).to_le_bytes();
+            let instruction = match rng.below(14) {
+                0 | 1 => {
+                    let opcode = three_reg[rng.below(three_reg.len() as u64) as usize];
+                    vec![opcode, regs, rng.below(13) as u8]
+                }
+                2 | 3 => {
+                    let len = rng.below(5) as usize;
+                    let opcode = reg_imm[rng.below(reg_imm.len() as u64) as usize];
+                    let mut v = vec![opcode, regs];
+                    v.extend_from_slice(&imm[..len]);
+                    v
+                }
+                4 => {
+                    let mut v = vec![OPCODE_LOAD_IMM, rng.below(13) as u8];
+                    v.extend_from_slice(&imm[..rng.below(5) as usize]);
+                    v
+                }
+                5 => {
+                    let mut v = vec![OPCODE_LOAD_IMM_64, rng.below(13) as u8];
+                    v.extend_from_slice(&rng.next().to_le_bytes());
+                    v
+                }
+                6 => vec![OPCODE_MOVE_REG, regs],
+                // Branch placeholders: offset patched below once all instruction starts are known.
+                7 => vec![
+                    [
+                        OPCODE_BRANCH_NE_IMM,
+                        OPCODE_BRANCH_LT_U_IMM,
+                        OPCODE_BRANCH_GE_S_IMM,
+                    ][rng.below(3) as usize],
+                    0x10 | (rng.below(13) as u8),
+                    rng.below(4) as u8,
+                    0,
+                    0,
+                ],
+                8 => vec![
+                    [OPCODE_BRANCH_EQ, OPCODE_BRANCH_GE_S][rng.below(2) as usize],
+                    regs,
+                    0,
+                    0,
+                ],
+                9 => vec![
+                    [OPCODE_JUMP, OPCODE_FALLTHROUGH, OPCODE_TRAP][rng.below(3) as usize],
+                    0,
+                    0,
+                ],
+                10 => vec![two_reg[rng.below(two_reg.len() as u64) as usize], regs],
+                _ => random_memory_access(rng, regs),
+            };
+            pc += instruction.len() as u32;
+            instructions.push(instruction);
+        }
+        let mut code = Vec::new();
+        let mut bitmask = Vec::new();
+        for (i, mut instruction) in instructions.into_iter().enumerate() {
+            let target = starts[rng.below(starts.len() as u64) as usize] as i64 - starts[i] as i64;
+            let offset = (target as i16).to_le_bytes();
+            match instruction[0] {
+                OPCODE_BRANCH_NE_IMM | OPCODE_BRANCH_LT_U_IMM | OPCODE_BRANCH_GE_S_IMM => {
+                    instruction[3..5].copy_from_slice(&offset);
+                }
+                OPCODE_BRANCH_EQ | OPCODE_BRANCH_GE_S | OPCODE_JUMP => {
+                    let at = instruction.len() - 2;
+                    instruction[at..].copy_from_slice(&offset);
+                }
+                _ => {}
+            }
+            bitmask.push(1);
+            bitmask.extend(std::iter::repeat_n(0, instruction.len() - 1));
+            code.extend(instruction);
+        }
+        (code, bitmask)
+    }
+
+    fn prepared_state(
+        ram_type: RAMType,
+        code: &[u8],
+        bitmask: &[u8],
+        registers: [u64; 13],
+        gas: u64,
+    ) -> PvmState {
+        let mut state = PvmState::new(ram_type as i32);
+        let ext_len = code.len() + 16;
+        let mut extended_code = code.to_vec();
+        extended_code.resize(ext_len, 0);
+        let mut extended_bitmask = bitmask.to_vec();
+        extended_bitmask.resize(ext_len + 25, 1);
+        state.load_code(extended_code, extended_bitmask, vec![]);
+        state.reset_program_state();
+        state.ram.init_page(0x10000, 4096, MemoryAccessType::Write);
+        state.ram.init_page(0x11000, 4096, MemoryAccessType::Read);
+        // SimpleRAM is one flat vector and cannot map the last page.
+        if ram_type == RAMType::MmapRAM {
+            state
+                .ram
+                .init_page(0xffff_f000, 4096, MemoryAccessType::Write);
+            // Writable without contents: loads fault until a store lands there.
+            state
+                .ram
+                .set_page_access_rights(0x12000, 4096, MemoryAccessType::Write);
+        }
+        state.ram.clear_dirty();
+        state.registers = registers;
+        state.gas_left = gas;
+        state
+    }
+
+    #[test]
+    fn recompiler_matches_interpreter_on_random_programs() {
+        if !super::is_available() {
+            return;
+        }
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for round in 0..800 {
+            let (code, bitmask) = random_program(&mut rng);
+            let mut registers = [0u64; 13];
+            for r in &mut registers {
+                *r = match rng.below(4) {
+                    0 => rng.below(16),
+                    1 => u64::from(random_address(&mut rng)),
+                    2 => {
+                        u64::from(random_address(&mut rng)).wrapping_sub(0x20) | (rng.next() << 32)
+                    }
+                    _ => rng.next(),
+                };
+            }
+            let gas = rng.below(300);
+            // MmapRAM accesses memory inline; SimpleRAM goes through the interpreter handlers.
+            let ram_type = if round % 2 == 0 {
+                RAMType::MmapRAM
+            } else {
+                RAMType::SimpleRAM
+            };
+
+            let mut interpreted = prepared_state(ram_type, &code, &bitmask, registers, gas);
+            while next_step_impl(&mut interpreted) {}
+            let mut recompiled = prepared_state(ram_type, &code, &bitmask, registers, gas);
+            run(&mut recompiled);
+
+            assert_eq!(
+                recompiled.registers, interpreted.registers,
+                "registers, code {code:?}"
+            );
+            assert_eq!(
+                recompiled.program_counter, interpreted.program_counter,
+                "pc, code {code:?}"
+            );
+            assert_eq!(
+                recompiled.gas_left, interpreted.gas_left,
+                "gas, code {code:?}"
+            );
+            assert_eq!(
+                recompiled.status, interpreted.status,
+                "status, code {code:?}"
+            );
+            assert_eq!(
+                recompiled.exit_arg, interpreted.exit_arg,
+                "exit arg, code {code:?}"
+            );
+            assert_eq!(
+                recompiled.last_opcode, interpreted.last_opcode,
+                "last opcode, code {code:?}"
+            );
+            for page in [0x10, 0x11, 0x12, 0xfffff] {
+                assert_eq!(
+                    recompiled.ram.get_page_dump(page),
+                    interpreted.ram.get_page_dump(page),
+                    "page {page:#x}, code {code:?}"
+                );
+            }
+            assert_eq!(
+                recompiled.ram.dirty_pages(),
+                interpreted.ram.dirty_pages(),
+                "dirty pages, code {code:?}"
+            );
+            assert_ne!(interpreted.status, Status::Ok);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Minimal x86-64 encoder: just the instruction forms the block translator emits.
+//! Jumps use rel32 displacements to labels inside one buffer, so emitted blocks are position independent.
+
+/// General-purpose register, numbered as in the ModRM/REX encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reg(pub u8);
+
+pub const RAX: Reg = Reg(0);
+pub const RCX: Reg = Reg(1);
+pub const RDX: Reg = Reg(2);
+pub const RBX: Reg = Reg(3);
+pub const RSP: Reg = Reg(4);
+pub const RBP: Reg = Reg(5);
+pub const RSI: Reg = Reg(6);
+pub const RDI: Reg = Reg(7);
+pub const R8: Reg = Reg(8);
+pub const R9: Reg = Reg(9);
+pub const R10: Reg = Reg(10);
+pub const R11: Reg = Reg(11);
+pub const R12: Reg = Reg(12);
+pub const R13: Reg = Reg(13);
+pub const R14: Reg = Reg(14);
+pub const R15: Reg = Reg(15);
+
+impl Reg {
+    const fn low(self) -> u8 {
+        self.0 & 7
+    }
+    const fn ext(self) -> u8 {
+        (self.0 >> 3) & 1
+    }
+}
+
+/// Condition codes (low nibble of Jcc / SETcc).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cond {
+    Below = 0x2,
+    AboveOrEqual = 0x3,
+    Equal = 0x4,
+    NotEqual = 0x5,
+    BelowOrEqual = 0x6,
+    Above = 0x7,
+    Less = 0xc,
+    GreaterOrEqual = 0xd,
+    LessOrEqual = 0xe,
+    Greater = 0xf,
+}
+
+/// Two-operand ALU instructions sharing the `op r/m, r` encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AluOp {
+    Add = 0x01,
+    Or = 0x09,
+    And = 0x21,
+    Sub = 0x29,
+    Xor = 0x31,
+    Cmp = 0x39,
+}
+
+/// Shifts and rotates sharing the `D3 /digit` (count in cl) and `C1 /digit ib` encodings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShiftOp {
+    Rol = 0,
+    Ror = 1,
+    Shl = 4,
+    Shr = 5,
+    Sar = 7,
+}
+
+/// Size of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Width {
+    Byte = 1,
+    Word = 2,
+    Dword = 4,
+    Qword = 8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// Code buffer with forward/backward label resolution.
+#[derive(Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    labels: Vec<Option<usize>>,
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    pub fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.code.len());
+    }
+
+    /// Resolve all jumps and return the machine code. Panics if a used label was never bound.
+    #[must_use]
+    pub fn finish(mut self) -> Vec<u8> {
+        for &(at, label) in &self.fixups {
+            let target = self.labels[label.0].expect("label bound before finish");
+            let rel = target as i64 - (at as i64 + 4);
+            self.code[at..at + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+        }
+        self.code
+    }
+
+    fn byte(&mut self, b: u8) {
+        self.code.push(b);
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.code.extend_from_slice(b);
+    }
+
+    /// REX prefix; omitted when no bit is set and `force` is false.
+    fn rex(&mut self, w: bool, r: u8, b: u8, force: bool) {
+        let rex = 0x40 | (u8::from(w) << 3) | (r << 2) | b;
+        if rex != 0x40 || force {
+            self.byte(rex);
+        }
+    }
+
+    fn modrm(&mut self, md: u8, reg: u8, rm: u8) {
+        self.byte((md << 6) | ((reg & 7) << 3) | (rm & 7));
+    }
+
+    /// ModRM (+SIB) for [base + disp32].
+    fn mem(&mut self, reg: u8, base: Reg, disp: i32) {
+        self.modrm(0b10, reg, base.low());
+        if base.low() == RSP.low() {
+            self.byte(0x24);
+        }
+        self.bytes(&disp.to_le_bytes());
+    }
+
+    /// mov dst, src (64-bit).
+    pub fn mov_rr(&mut self, dst: Reg, src: Reg) {
+        self.rex(true, src.ext(), dst.ext(), false);
+        self.byte(0x89);
+        self.modrm(0b11, src.low(), dst.low());
+    }
+
+    /// mov dst, imm (shortest encoding for the 64-bit value).
+    pub fn mov_ri(&mut self, dst: Reg, imm: u64) {
+        if imm <= u64::from(u32::MAX) {
+            // mov r32, imm32 zero-extends.
+            self.rex(false, 0, dst.ext(), false);
+            self.byte(0xb8 + dst.low());
+            self.bytes(&(imm as u32).to_le_bytes());
+        } else if i32::try_from(imm as i64).is_ok() {
+            self.rex(true, 0, dst.ext(), false);
+            self.byte(0xc7);
+            self.modrm(0b11, 0, dst.low());
+            self.bytes(&(imm as i64 as i32).to_le_bytes());
+        } else {
+            self.rex(true, 0, dst.ext(), false);
+            self.byte(0xb8 + dst.low());
+            self.bytes(&imm.to_le_bytes());
+        }
+    }
+
+    /// op dst, src with 64-bit (`wide`) or 32-bit operands. 32-bit results zero-extend into dst.
+    pub fn alu_rr(&mut self, op: AluOp, dst: Reg, src: Reg, wide: bool) {
+        self.rex(wide, src.ext(), dst.ext(), false);
+        self.byte(op as u8);
+        self.modrm(0b11, src.low(), dst.low());
+    }
+
+    /// mov dst32, src32: copies the low 32 bits and zeroes the rest.
+    pub fn mov_rr32(&mut self, dst: Reg, src: Reg) {
+        self.rex(false, src.ext(), dst.ext(), false);
+        self.byte(0x89);
+        self.modrm(0b11, src.low(), dst.low());
+    }
+
+    /// op dst, imm32 (sign-extended) with 64-bit or 32-bit operands.
+    pub fn alu_ri(&mut self, op: AluOp, dst: Reg, imm: i32, wide: bool) {
+        self.rex(wide, 0, dst.ext(), false);
+        self.byte(0x81);
+        // The `op r/m, r` opcodes are 8 * digit + 1.
+        self.modrm(0b11, op as u8 >> 3, dst.low());
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    /// op dst, qword [base + disp].
+    pub fn alu_rm(&mut self, op: AluOp, dst: Reg, base: Reg, disp: i32) {
+        self.rex(true, dst.ext(), base.ext(), false);
+        self.byte(op as u8 + 2);
+        self.mem(dst.low(), base, disp);
+    }
+
+    /// Shift or rotate dst by cl (64-bit or 32-bit).
+    pub fn shift_cl(&mut self, op: ShiftOp, dst: Reg, wide: bool) {
+        self.rex(wide, 0, dst.ext(), false);
+        self.byte(0xd3);
+        self.modrm(0b11, op as u8, dst.low());
+    }
+
+    /// Shift or rotate dst by imm8 (64-bit or 32-bit).
+    pub fn shift_ri(&mut self, op: ShiftOp, dst: Reg, imm: u8, wide: bool) {
+        self.rex(wide, 0, dst.ext(), false);
+        self.byte(0xc1);
+        self.modrm(0b11, op as u8, dst.low());
+        self.byte(imm);
+    }
+
+    /// not dst (64-bit).
+    pub fn not(&mut self, dst: Reg) {
+        self.rex(true, 0, dst.ext(), false);
+        self.byte(0xf7);
+        self.modrm(0b11, 2, dst.low());
+    }
+
+    /// mul / imul src: rdx:rax = rax * src, unsigned or signed (64-bit).
+    pub fn mul_wide(&mut self, src: Reg, signed: bool) {
+        self.rex(true, 0, src.ext(), false);
+        self.byte(0xf7);
+        self.modrm(0b11, if signed { 5 } else { 4 }, src.low());
+    }
+
+    /// bsf / bsr dst, src: index of the lowest / highest set bit. Sets ZF (dst unspecified) when src is zero.
+    pub fn bit_scan(&mut self, dst: Reg, src: Reg, reverse: bool, wide: bool) {
+        self.rex(wide, dst.ext(), src.ext(), false);
+        self.bytes(&[0x0f, if reverse { 0xbd } else { 0xbc }]);
+        self.modrm(0b11, dst.low(), src.low());
+    }
+
+    /// cmovcc dst, src (64-bit).
+    pub fn cmov(&mut self, cond: Cond, dst: Reg, src: Reg) {
+        self.rex(true, dst.ext(), src.ext(), false);
+        self.bytes(&[0x0f, 0x40 + cond as u8]);
+        self.modrm(0b11, dst.low(), src.low());
+    }
+
+    /// bswap dst (64-bit).
+    pub fn bswap(&mut self, dst: Reg) {
+        self.rex(true, 0, dst.ext(), false);
+        self.bytes(&[0x0f, 0xc8 + dst.low()]);
+    }
+
+    /// movsx / movzx dst, src8 or src16: extend the low byte or word of src to 64 bits.
+    pub fn extend(&mut self, dst: Reg, src: Reg, width: Width, signed: bool) {
+        let op = match (width, signed) {
+            (Width::Byte, false) => 0xb6,
+            (Width::Byte, true) => 0xbe,
+            (_, false) => 0xb7,
+            (_, true) => 0xbf,
+        };
+        // REX.W also selects sil/dil/spl/bpl for byte sources.
+        self.rex(true, dst.ext(), src.ext(), false);
+        self.bytes(&[0x0f, op]);
+        self.modrm(0b11, dst.low(), src.low());
+    }
+
+    /// imul dst, src (64-bit or 32-bit).
+    pub fn imul_rr(&mut self, dst: Reg, src: Reg, wide: bool) {
+        self.rex(wide, dst.ext(), src.ext(), false);
+        self.bytes(&[0x0f, 0xaf]);
+        self.modrm(0b11, dst.low(), src.low());
+    }
+
+    /// movsxd dst, src32: sign-extend the low 32 bits.
+    pub fn movsxd(&mut self, dst: Reg, src: Reg) {
+        self.rex(true, dst.ext(), src.ext(), false);
+        self.byte(0x63);
+        self.modrm(0b11, dst.low(), src.low());
+    }
+
+    /// setcc dst8 then movzx dst32, dst8: dst = condition ? 1 : 0.
+    pub fn set_cond(&mut self, cond: Cond, dst: Reg) {
+        // REX forces the sil/dil/spl/bpl byte registers instead of ah/ch/dh/bh.
+        self.rex(false, 0, dst.ext(), dst.0 >= 4);
+        self.bytes(&[0x0f, 0x90 + cond as u8]);
+        self.modrm(0b11, 0, dst.low());
+        self.rex(false, dst.ext(), dst.ext(), dst.0 >= 4);
+        self.bytes(&[0x0f, 0xb6]);
+        self.modrm(0b11, dst.low(), dst.low());
+    }
+
+    /// mov dst, qword [base + disp].
+    pub fn load64(&mut self, dst: Reg, base: Reg, disp: i32) {
+        self.rex(true, dst.ext(), base.ext(), false);
+        self.byte(0x8b);
+        self.mem(dst.low(), base, disp);
+    }
+
+    /// Load `width` bytes at [base + disp] into dst, sign- or zero-extended to 64 bits.
+    pub fn load(&mut self, dst: Reg, base: Reg, disp: i32, width: Width, signed: bool) {
+        match (width, signed) {
+            (Width::Byte | Width::Word, _) => {
+                self.rex(true, dst.ext(), base.ext(), false);
+                let op = if width == Width::Byte { 0xb6 } else { 0xb7 };
+                self.bytes(&[0x0f, if signed { op + 8 } else { op }]);
+            }
+            (Width::Dword, true) => {
+                self.rex(true, dst.ext(), base.ext(), false);
+                self.byte(0x63);
+            }
+            // mov r32 zero-extends.
+            (Width::Dword, false) => {
+                self.rex(false, dst.ext(), base.ext(), false);
+                self.byte(0x8b);
+            }
+            (Width::Qword, _) => {
+                self.rex(true, dst.ext(), base.ext(), false);
+                self.byte(0x8b);
+            }
+        }
+        self.mem(dst.low(), base, disp);
+    }
+
+    /// Store the low `width` bytes of src at [base + disp].
+    pub fn store(&mut self, base: Reg, disp: i32, src: Reg, width: Width) {
+        if width == Width::Word {
+            self.byte(0x66);
+        }
+        // REX selects sil/dil/spl/bpl instead of dh/bh/ah/ch for byte stores.
+        let byte_reg = width == Width::Byte && (4..8).contains(&src.0);
+        self.rex(width == Width::Qword, src.ext(), base.ext(), byte_reg);
+        self.byte(if width == Width::Byte { 0x88 } else { 0x89 });
+        self.mem(src.low(), base, disp);
+    }
+
+    /// mov dword [base + disp], src32.
+    pub fn store32(&mut self, base: Reg, disp: i32, src: Reg) {
+        self.store(base, disp, src, Width::Dword);
+    }
+
+    /// test byte [base + disp], imm8.
+    pub fn test_mem8_imm(&mut self, base: Reg, disp: i32, imm: u8) {
+        self.rex(false, 0, base.ext(), false);
+        self.byte(0xf6);
+        self.mem(0, base, disp);
+        self.byte(imm);
+    }
+
+    /// or byte [base + disp], imm8.
+    pub fn or_mem8_imm(&mut self, base: Reg, disp: i32, imm: u8) {
+        self.rex(false, 0, base.ext(), false);
+        self.byte(0x80);
+        self.mem(1, base, disp);
+        self.byte(imm);
+    }
+
+    /// mov qword [base + disp], src.
+    pub fn store64(&mut self, base: Reg, disp: i32, src: Reg) {
+        self.rex(true, src.ext(), base.ext(), false);
+        self.byte(0x89);
+        self.mem(src.low(), base, disp);
+    }
+
+    /// mov dword [base + disp], imm32.
+    pub fn store32_imm(&mut self, base: Reg, disp: i32, imm: u32) {
+        self.rex(false, 0, base.ext(), false);
+        self.byte(0xc7);
+        self.mem(0, base, disp);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    /// cmp qword [base + disp], imm32 (sign-extended).
+    pub fn cmp_mem64_imm(&mut self, base: Reg, disp: i32, imm: i32) {
+        self.rex(true, 0, base.ext(), false);
+        self.byte(0x81);
+        self.mem(7, base, disp);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    /// sub qword [base + disp], imm32 (sign-extended).
+    pub fn sub_mem64_imm(&mut self, base: Reg, disp: i32, imm: i32) {
+        self.rex(true, 0, base.ext(), false);
+        self.byte(0x81);
+        self.mem(5, base, disp);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    /// test a, b (64-bit or 32-bit).
+    pub fn test(&mut self, a: Reg, b: Reg, wide: bool) {
+        self.rex(wide, b.ext(), a.ext(), false);
+        self.byte(0x85);
+        self.modrm(0b11, b.low(), a.low());
+    }
+
+    pub fn push(&mut self, reg: Reg) {
+        self.rex(false, 0, reg.ext(), false);
+        self.byte(0x50 + reg.low());
+    }
+
+    pub fn pop(&mut self, reg: Reg) {
+        self.rex(false, 0, reg.ext(), false);
+        self.byte(0x58 + reg.low());
+    }
+
+    /// add rsp, imm8.
+    pub fn add_rsp(&mut self, imm: i8) {
+        self.bytes(&[0x48, 0x83, 0xc4, imm as u8]);
+    }
+
+    /// call reg.
+    pub fn call_reg(&mut self, reg: Reg) {
+        self.rex(false, 0, reg.ext(), false);
+        self.byte(0xff);
+        self.modrm(0b11, 2, reg.low());
+    }
+
+    pub fn ret(&mut self) {
+        self.byte(0xc3);
+    }
+
+    pub fn jmp(&mut self, label: Label) {
+        self.byte(0xe9);
+        self.fixups.push((self.code.len(), label));
+        self.bytes(&[0; 4]);
+    }
+
+    pub fn jcc(&mut self, cond: Cond, label: Label) {
+        self.bytes(&[0x0f, 0x80 + cond as u8]);
+        self.fixups.push((self.code.len(), label));
+        self.bytes(&[0; 4]);
+    }
+}
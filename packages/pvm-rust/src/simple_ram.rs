@@ -2,14 +2,17 @@
 //! Flat memory with per-page access rights for runBlob and test vectors.
 
 use crate::config;
+use crate::snapshot::{access_from_u8, SnapshotReader, SnapshotWriter};
 use crate::types::{FaultCheckResult, MemoryAccessType, Ram, ReadResult, WriteResult};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 /// Simple RAM: single contiguous memory with page access rights.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct SimpleRam {
     memory: Vec<u8>,
     page_access: HashMap<u32, MemoryAccessType>,
+    /// Pages written since the last `clear_dirty`.
+    dirty: BTreeSet<u32>,
     current_heap_pointer: u32,
     last_load_address: u32,
     last_load_value: u64,
@@ -36,6 +39,48 @@ impl SimpleRam {
         self.memory.resize(aligned as usize, 0);
     }
 
+    /// Flat memory, then page access rights by ascending page index.
+    pub(crate) fn write_snapshot(&self, w: &mut SnapshotWriter) {
+        w.bytes(&self.memory);
+        w.u32(self.current_heap_pointer);
+        w.u32(self.last_load_address);
+        w.u64(self.last_load_value);
+        w.u32(self.last_store_address);
+        w.u64(self.last_store_value);
+        let mut access: Vec<(&u32, &MemoryAccessType)> = self.page_access.iter().collect();
+        access.sort_unstable_by_key(|(index, _)| **index);
+        w.len(access.len());
+        for (&index, &access_type) in access {
+            w.u32(index);
+            w.u8(access_type as u8);
+        }
+    }
+
+    pub(crate) fn read_snapshot(r: &mut SnapshotReader<'_>) -> Option<Self> {
+        let mut ram = Self {
+            memory: r.bytes()?,
+            current_heap_pointer: r.u32()?,
+            last_load_address: r.u32()?,
+            last_load_value: r.u64()?,
+            last_store_address: r.u32()?,
+            last_store_value: r.u64()?,
+            page_access: HashMap::new(),
+            dirty: BTreeSet::new(),
+        };
+        for _ in 0..r.len()? {
+            let index = r.u32()?;
+            ram.page_access.insert(index, access_from_u8(r.u8()?)?);
+        }
+        Some(ram)
+    }
+
+    /// Record a write of `length` (> 0) octets at `address`.
+    fn mark_dirty(&mut self, address: u32, length: u32) {
+        let first = self.get_page_index(address);
+        let last = self.get_page_index(address.saturating_add(length - 1));
+        self.dirty.extend(first..=last);
+    }
+
     fn get_page_access(&self, page_index: u32) -> MemoryAccessType {
         *self.page_access.get(&page_index).unwrap_or(&MemoryAccessType::None)
     }
@@ -102,6 +147,7 @@ impl Ram for SimpleRam {
             );
         }
         self.ensure_memory_size(address + size);
+        self.mark_dirty(address, size);
         let start = address as usize;
         let end = start + values.len();
         if end <= self.memory.len() {
@@ -200,6 +246,7 @@ impl Ram for SimpleRam {
             return;
         }
         self.ensure_memory_size(address + values.len() as u32);
+        self.mark_dirty(address, values.len() as u32);
         let start = address as usize;
         let end = start + values.len();
         if end <= self.memory.len() {
@@ -224,6 +271,7 @@ impl Ram for SimpleRam {
     fn reset(&mut self) {
         self.memory.clear();
         self.page_access.clear();
+        self.dirty.clear();
         self.current_heap_pointer = 0;
         self.last_load_address = 0;
         self.last_load_value = 0;
@@ -253,4 +301,12 @@ impl Ram for SimpleRam {
         self.last_store_address = 0;
         self.last_store_value = 0;
     }
+
+    fn dirty_pages(&self) -> Vec<u32> {
+        self.dirty.iter().copied().collect()
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty.clear();
+    }
 }
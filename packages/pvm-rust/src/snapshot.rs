@@ -0,0 +1,652 @@
+//! Versioned binary snapshots of a whole `PvmState`: registers, PC, gas, status, code, RAM pages with
+//! their access rights, and the accumulate / refine / is-authorized invocation context (including
+//! nested refine machines). `restore_impl(snapshot_impl(s))` resumes exactly where `s` stopped.
+//!
+//! Layout: magic `PVMS`, u32 version, then the fields in the order written by `write_state`.
+//! Integers are little-endian; byte strings and sequences are prefixed with a u32 length.
+//! The pre-decoded instruction stream and recompiled blocks are rebuilt on restore, and the host-function
+//! registry is not captured (it holds code, not state).
+
+use crate::codec::{
+    decode_complete_service_account, encode_complete_service_account, AccountEntry,
+    AlwaysAccerEntry, DeferredTransfer, Implications, PartialState, ProvisionEntry,
+};
+use crate::config::FetchSystemConstantsConfig;
+use crate::guest::GuestMachine;
+use crate::mmap_ram::MmapRam;
+use crate::mock_ram::MockRam;
+use crate::ram::PvmRam;
+use crate::recompiler;
+use crate::simple_ram::SimpleRam;
+use crate::state_wrapper::{PvmState, RamEnum, Status};
+use crate::types::MemoryAccessType;
+use std::collections::{BTreeMap, HashMap};
+
+const MAGIC: &[u8; 4] = b"PVMS";
+/// Bump whenever the layout changes; restore rejects other versions.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const RAM_PVM: u8 = 0;
+const RAM_SIMPLE: u8 = 1;
+const RAM_MOCK: u8 = 2;
+const RAM_MMAP: u8 = 3;
+
+/// Append-only encoder for the snapshot layout.
+#[derive(Default)]
+pub struct SnapshotWriter {
+    out: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    pub fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(u8::from(value));
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.len(data.len());
+        self.out.extend_from_slice(data);
+    }
+
+    pub fn byte_list(&mut self, items: &[Vec<u8>]) {
+        self.len(items.len());
+        for item in items {
+            self.bytes(item);
+        }
+    }
+
+    /// Tag byte (0 = None, 1 = Some) followed by the value.
+    pub fn option<T>(&mut self, value: Option<&T>, write: impl FnOnce(&mut Self, &T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+
+    #[must_use]
+    pub fn finish(self) -> Vec<u8> {
+        self.out
+    }
+}
+
+/// Decoder for the snapshot layout; every read returns None once the input is exhausted or malformed.
+pub struct SnapshotReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let slice = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(slice)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// A sequence length, bounded by the remaining input so corrupt lengths cannot trigger huge allocations.
+    pub fn len(&mut self) -> Option<usize> {
+        let len = self.u32()? as usize;
+        (len <= self.data.len() - self.pos).then_some(len)
+    }
+
+    pub fn bytes(&mut self) -> Option<Vec<u8>> {
+        let len = self.len()?;
+        Some(self.take(len)?.to_vec())
+    }
+
+    pub fn byte_list(&mut self) -> Option<Vec<Vec<u8>>> {
+        (0..self.len()?).map(|_| self.bytes()).collect()
+    }
+
+    pub fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        if self.bool()? {
+            read(self).map(Some)
+        } else {
+            Some(None)
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+}
+
+pub(crate) fn access_from_u8(value: u8) -> Option<MemoryAccessType> {
+    match value {
+        0 => Some(MemoryAccessType::None),
+        1 => Some(MemoryAccessType::Read),
+        2 => Some(MemoryAccessType::Write),
+        _ => None,
+    }
+}
+
+fn status_from_u32(value: u32) -> Option<Status> {
+    Some(match value {
+        0 => Status::Ok,
+        1 => Status::Halt,
+        2 => Status::Panic,
+        3 => Status::Fault,
+        4 => Status::Host,
+        5 => Status::Oog,
+        _ => return None,
+    })
+}
+
+/// Serialize the full machine state (see module docs for what is and is not included).
+#[must_use]
+pub fn snapshot_impl(state: &PvmState) -> Vec<u8> {
+    let mut w = SnapshotWriter::default();
+    w.out.extend_from_slice(MAGIC);
+    w.u32(SNAPSHOT_VERSION);
+    write_state(&mut w, state);
+    w.finish()
+}
+
+/// Rebuild a state from `snapshot_impl` output. None for a foreign version or malformed/truncated input.
+#[must_use]
+pub fn restore_impl(blob: &[u8]) -> Option<PvmState> {
+    let mut r = SnapshotReader::new(blob);
+    if r.take(4)? != MAGIC || r.u32()? != SNAPSHOT_VERSION {
+        return None;
+    }
+    let state = read_state(&mut r)?;
+    r.is_empty().then_some(state)
+}
+
+fn write_state(w: &mut SnapshotWriter, s: &PvmState) {
+    w.u32(s.ram_type as u32);
+    w.u32(s.program_counter);
+    w.u64(s.gas_left);
+    w.u32(s.status as u32);
+    w.u32(s.exit_arg);
+    w.u8(s.result_code);
+    for register in s.registers {
+        w.u64(register);
+    }
+    w.bytes(&s.code);
+    w.bytes(&s.bitmask);
+    w.len(s.jump_table.len());
+    for &target in &s.jump_table {
+        w.u32(target);
+    }
+    match &s.ram {
+        RamEnum::Pvm(ram) => {
+            w.u8(RAM_PVM);
+            ram.write_snapshot(w);
+        }
+        RamEnum::Simple(ram) => {
+            w.u8(RAM_SIMPLE);
+            ram.write_snapshot(w);
+        }
+        RamEnum::Mock(ram) => {
+            w.u8(RAM_MOCK);
+            ram.write_snapshot(w);
+        }
+        RamEnum::Mmap(ram) => {
+            w.u8(RAM_MMAP);
+            ram.write_snapshot(w);
+        }
+    }
+    w.u32(s.last_load_address);
+    w.u64(s.last_load_value);
+    w.u32(s.last_store_address);
+    w.u64(s.last_store_value);
+    w.u8(s.last_opcode);
+    w.u32(s.host_call_id);
+    w.bool(s.checkpoint_requested);
+    w.option(s.yield_hash.as_ref(), |w, v| w.bytes(v));
+    w.len(s.log_messages.len());
+    for message in &s.log_messages {
+        w.bytes(message.as_bytes());
+    }
+    w.bool(s.external_host_calls);
+    w.option(s.host_resume_pc.as_ref(), |w, &v| w.u32(v));
+    w.bool(s.use_recompiler);
+
+    // FETCH inputs.
+    w.byte_list(&s.accumulate_inputs_encoded);
+    w.option(s.work_package_encoded.as_ref(), |w, v| w.bytes(v));
+    w.option(s.auth_config.as_ref(), |w, v| w.bytes(v));
+    w.option(s.auth_token.as_ref(), |w, v| w.bytes(v));
+    w.option(s.refine_context_encoded.as_ref(), |w, v| w.bytes(v));
+    w.option(s.work_item_summaries.as_ref(), |w, v| w.byte_list(v));
+    w.option(s.work_item_payloads.as_ref(), |w, v| w.byte_list(v));
+    w.option(s.entropy_accumulator.as_ref(), |w, v| w.bytes(v));
+
+    // Accumulation context.
+    w.bool(s.has_accumulation_context);
+    w.u32(s.accumulation_num_cores);
+    w.u32(s.accumulation_num_validators);
+    w.u32(s.accumulation_auth_queue_size);
+    w.option(s.timeslot.as_ref(), |w, &v| w.u64(v));
+    w.option(s.accumulation_fetch_config.as_ref(), write_fetch_config);
+    w.option(s.accumulation_service_id.as_ref(), |w, &v| w.u64(v));
+    w.option(s.accumulation_accounts.as_ref(), |w, accounts| {
+        let mut ids: Vec<&u64> = accounts.keys().collect();
+        ids.sort_unstable();
+        w.len(ids.len());
+        for id in ids {
+            w.u64(*id);
+            w.bytes(&encode_complete_service_account(&accounts[id]));
+        }
+    });
+    w.option(
+        s.accumulation_implications_regular.as_ref(),
+        write_implications,
+    );
+    w.option(
+        s.accumulation_implications_exceptional.as_ref(),
+        write_implications,
+    );
+    write_xfers(w, &s.accumulation_pending_xfers);
+    write_partial_state(w, &s.accumulation_regular_state);
+    w.u32(s.accumulation_nextfreeid);
+    write_provisions(w, &s.accumulation_provisions);
+
+    // Refine context.
+    w.bool(s.has_refine_context);
+    w.byte_list(&s.refine_export_segments);
+    w.u64(s.refine_segment_offset as u64);
+    w.len(s.refine_machines.len());
+    for (id, machine) in &s.refine_machines {
+        w.u64(*id);
+        write_state(w, machine.state());
+    }
+    w.option(s.refine_authorizer_trace.as_ref(), |w, v| w.bytes(v));
+    w.option(s.refine_work_item_index.as_ref(), |w, &v| w.u64(v));
+    w.option(s.refine_import_segments.as_ref(), write_nested_lists);
+    w.option(s.refine_extrinsics.as_ref(), write_nested_lists);
+    w.option(s.refine_lookup_timeslot.as_ref(), |w, &v| w.u64(v));
+
+    w.bool(s.has_is_authorized_context);
+}
+
+fn read_state(r: &mut SnapshotReader<'_>) -> Option<PvmState> {
+    let ram_type = r.u32()? as i32;
+    let program_counter = r.u32()?;
+    let gas_left = r.u64()?;
+    let status = status_from_u32(r.u32()?)?;
+    let exit_arg = r.u32()?;
+    let result_code = r.u8()?;
+    let mut registers = [0u64; 13];
+    for register in &mut registers {
+        *register = r.u64()?;
+    }
+    let code = r.bytes()?;
+    let bitmask = r.bytes()?;
+    let jump_table = (0..r.len()?).map(|_| r.u32()).collect::<Option<Vec<_>>>()?;
+    let ram = match r.u8()? {
+        RAM_PVM => RamEnum::Pvm(PvmRam::read_snapshot(r)?),
+        RAM_SIMPLE => RamEnum::Simple(SimpleRam::read_snapshot(r)?),
+        RAM_MOCK => RamEnum::Mock(MockRam::read_snapshot(r)?),
+        RAM_MMAP => RamEnum::Mmap(MmapRam::read_snapshot(r)?),
+        _ => return None,
+    };
+
+    let mut s = PvmState {
+        ram_type,
+        program_counter,
+        gas_left,
+        status,
+        exit_arg,
+        result_code,
+        registers,
+        ram,
+        ..PvmState::default()
+    };
+    s.load_code(code, bitmask, jump_table);
+    s.last_load_address = r.u32()?;
+    s.last_load_value = r.u64()?;
+    s.last_store_address = r.u32()?;
+    s.last_store_value = r.u64()?;
+    s.last_opcode = r.u8()?;
+    s.host_call_id = r.u32()?;
+    s.checkpoint_requested = r.bool()?;
+    s.yield_hash = r.option(SnapshotReader::bytes)?;
+    s.log_messages = (0..r.len()?)
+        .map(|_| String::from_utf8(r.bytes()?).ok())
+        .collect::<Option<_>>()?;
+    s.external_host_calls = r.bool()?;
+    s.host_resume_pc = r.option(SnapshotReader::u32)?;
+    s.use_recompiler = r.bool()? && recompiler::is_available();
+
+    s.accumulate_inputs_encoded = r.byte_list()?;
+    s.work_package_encoded = r.option(SnapshotReader::bytes)?;
+    s.auth_config = r.option(SnapshotReader::bytes)?;
+    s.auth_token = r.option(SnapshotReader::bytes)?;
+    s.refine_context_encoded = r.option(SnapshotReader::bytes)?;
+    s.work_item_summaries = r.option(SnapshotReader::byte_list)?;
+    s.work_item_payloads = r.option(SnapshotReader::byte_list)?;
+    s.entropy_accumulator = r.option(SnapshotReader::bytes)?;
+
+    s.has_accumulation_context = r.bool()?;
+    s.accumulation_num_cores = r.u32()?;
+    s.accumulation_num_validators = r.u32()?;
+    s.accumulation_auth_queue_size = r.u32()?;
+    s.timeslot = r.option(SnapshotReader::u64)?;
+    s.accumulation_fetch_config = r.option(read_fetch_config)?;
+    s.accumulation_service_id = r.option(SnapshotReader::u64)?;
+    s.accumulation_accounts = r.option(|r| {
+        (0..r.len()?)
+            .map(|_| Some((r.u64()?, read_account(r)?)))
+            .collect::<Option<HashMap<_, _>>>()
+    })?;
+    s.accumulation_implications_regular = r.option(read_implications)?;
+    s.accumulation_implications_exceptional = r.option(read_implications)?;
+    s.accumulation_pending_xfers = read_xfers(r)?;
+    s.accumulation_regular_state = read_partial_state(r)?;
+    s.accumulation_nextfreeid = r.u32()?;
+    s.accumulation_provisions = read_provisions(r)?;
+
+    s.has_refine_context = r.bool()?;
+    s.refine_export_segments = r.byte_list()?;
+    s.refine_segment_offset = r.u64()? as i64;
+    s.refine_machines = (0..r.len()?)
+        .map(|_| Some((r.u64()?, GuestMachine::from_state(read_state(r)?))))
+        .collect::<Option<BTreeMap<_, _>>>()?;
+    s.refine_authorizer_trace = r.option(SnapshotReader::bytes)?;
+    s.refine_work_item_index = r.option(SnapshotReader::u64)?;
+    s.refine_import_segments = r.option(read_nested_lists)?;
+    s.refine_extrinsics = r.option(read_nested_lists)?;
+    s.refine_lookup_timeslot = r.option(SnapshotReader::u64)?;
+
+    s.has_is_authorized_context = r.bool()?;
+    Some(s)
+}
+
+fn write_nested_lists(w: &mut SnapshotWriter, lists: &Vec<Vec<Vec<u8>>>) {
+    w.len(lists.len());
+    for list in lists {
+        w.byte_list(list);
+    }
+}
+
+fn read_nested_lists(r: &mut SnapshotReader<'_>) -> Option<Vec<Vec<Vec<u8>>>> {
+    (0..r.len()?).map(|_| r.byte_list()).collect()
+}
+
+fn write_fetch_config(w: &mut SnapshotWriter, c: &FetchSystemConstantsConfig) {
+    w.u32(c.num_cores);
+    w.u32(c.preimage_expunge_period);
+    w.u32(c.epoch_duration);
+    w.u64(c.max_refine_gas);
+    w.u64(c.max_block_gas);
+    w.u32(c.max_tickets_per_extrinsic);
+    w.u32(c.max_lookup_anchorage);
+    w.u32(c.tickets_per_validator);
+    w.u32(c.slot_duration);
+    w.u32(c.rotation_period);
+    w.u32(c.num_validators);
+    w.u32(c.ec_piece_size);
+    w.u32(c.num_ec_pieces_per_segment);
+    w.u32(c.contest_duration);
+}
+
+fn read_fetch_config(r: &mut SnapshotReader<'_>) -> Option<FetchSystemConstantsConfig> {
+    Some(FetchSystemConstantsConfig {
+        num_cores: r.u32()?,
+        preimage_expunge_period: r.u32()?,
+        epoch_duration: r.u32()?,
+        max_refine_gas: r.u64()?,
+        max_block_gas: r.u64()?,
+        max_tickets_per_extrinsic: r.u32()?,
+        max_lookup_anchorage: r.u32()?,
+        tickets_per_validator: r.u32()?,
+        slot_duration: r.u32()?,
+        rotation_period: r.u32()?,
+        num_validators: r.u32()?,
+        ec_piece_size: r.u32()?,
+        num_ec_pieces_per_segment: r.u32()?,
+        contest_duration: r.u32()?,
+    })
+}
+
+/// Accounts reuse the Gray Paper CompleteServiceAccount codec, wrapped in a length prefix.
+fn read_account(r: &mut SnapshotReader<'_>) -> Option<crate::codec::CompleteServiceAccount> {
+    let data = r.bytes()?;
+    let decoded = decode_complete_service_account(&data)?;
+    (decoded.consumed as usize == data.len()).then_some(decoded.value)
+}
+
+fn write_partial_state(w: &mut SnapshotWriter, ps: &PartialState) {
+    w.len(ps.accounts.len());
+    for entry in &ps.accounts {
+        w.u32(entry.service_id);
+        w.bytes(&encode_complete_service_account(&entry.account));
+    }
+    w.byte_list(&ps.stagingset);
+    write_nested_lists(w, &ps.authqueue);
+    w.u32(ps.manager);
+    w.len(ps.assigners.len());
+    for &assigner in &ps.assigners {
+        w.u32(assigner);
+    }
+    w.u32(ps.delegator);
+    w.u32(ps.registrar);
+    w.len(ps.alwaysaccers.len());
+    for entry in &ps.alwaysaccers {
+        w.u32(entry.service_id);
+        w.u64(entry.gas);
+    }
+}
+
+fn read_partial_state(r: &mut SnapshotReader<'_>) -> Option<PartialState> {
+    let accounts = (0..r.len()?)
+        .map(|_| {
+            Some(AccountEntry {
+                service_id: r.u32()?,
+                account: read_account(r)?,
+            })
+        })
+        .collect::<Option<_>>()?;
+    Some(PartialState {
+        accounts,
+        stagingset: r.byte_list()?,
+        authqueue: read_nested_lists(r)?,
+        manager: r.u32()?,
+        assigners: (0..r.len()?).map(|_| r.u32()).collect::<Option<_>>()?,
+        delegator: r.u32()?,
+        registrar: r.u32()?,
+        alwaysaccers: (0..r.len()?)
+            .map(|_| {
+                Some(AlwaysAccerEntry {
+                    service_id: r.u32()?,
+                    gas: r.u64()?,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn write_xfers(w: &mut SnapshotWriter, xfers: &[DeferredTransfer]) {
+    w.len(xfers.len());
+    for xfer in xfers {
+        w.u32(xfer.source);
+        w.u32(xfer.dest);
+        w.u64(xfer.amount);
+        w.bytes(&xfer.memo);
+        w.u64(xfer.gas_limit);
+    }
+}
+
+fn read_xfers(r: &mut SnapshotReader<'_>) -> Option<Vec<DeferredTransfer>> {
+    (0..r.len()?)
+        .map(|_| {
+            Some(DeferredTransfer {
+                source: r.u32()?,
+                dest: r.u32()?,
+                amount: r.u64()?,
+                memo: r.bytes()?,
+                gas_limit: r.u64()?,
+            })
+        })
+        .collect()
+}
+
+fn write_provisions(w: &mut SnapshotWriter, provisions: &[ProvisionEntry]) {
+    w.len(provisions.len());
+    for provision in provisions {
+        w.u32(provision.service_id);
+        w.bytes(&provision.blob);
+    }
+}
+
+fn read_provisions(r: &mut SnapshotReader<'_>) -> Option<Vec<ProvisionEntry>> {
+    (0..r.len()?)
+        .map(|_| {
+            Some(ProvisionEntry {
+                service_id: r.u32()?,
+                blob: r.bytes()?,
+            })
+        })
+        .collect()
+}
+
+fn write_implications(w: &mut SnapshotWriter, im: &Implications) {
+    w.u32(im.id);
+    write_partial_state(w, &im.state);
+    w.u32(im.nextfreeid);
+    write_xfers(w, &im.xfers);
+    w.option(im.yield_hash.as_ref(), |w, v| w.bytes(v));
+    write_provisions(w, &im.provisions);
+}
+
+fn read_implications(r: &mut SnapshotReader<'_>) -> Option<Implications> {
+    Some(Implications {
+        id: r.u32()?,
+        state: read_partial_state(r)?,
+        nextfreeid: r.u32()?,
+        xfers: read_xfers(r)?,
+        yield_hash: r.option(SnapshotReader::bytes)?,
+        provisions: read_provisions(r)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::CompleteServiceAccount;
+    use crate::state_wrapper::{next_step_impl, RAMType};
+    use crate::types::Ram;
+
+    /// LOAD_IMM r0, 7; ADD_IMM_64 r0, r0, 1 (×3); TRAP.
+    fn program() -> (Vec<u8>, Vec<u8>) {
+        let code = vec![51, 0, 7, 149, 0, 1, 149, 0, 1, 149, 0, 1, 0];
+        let bitmask = vec![1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1];
+        (code, bitmask)
+    }
+
+    fn sample_state() -> PvmState {
+        let mut s = PvmState::new(RAMType::PvmRam as i32);
+        let (code, bitmask) = program();
+        s.load_code(code, bitmask, vec![0, 3]);
+        s.status = Status::Ok;
+        s.gas_left = 100;
+        s.registers[5] = u64::MAX;
+        s.ram.init_page(0x2_0000, 0x2000, MemoryAccessType::Write);
+        s.ram.write_octets_during_initialization(0x2_0010, b"hello");
+        s.ram.init_page(0x3_0000, 0x1000, MemoryAccessType::Read);
+        s.has_accumulation_context = true;
+        s.accumulation_fetch_config = Some(FetchSystemConstantsConfig::default());
+        s.accumulation_accounts = Some(HashMap::from([(
+            7,
+            CompleteServiceAccount {
+                balance: 42,
+                raw_csh_keyvals: vec![(vec![1, 2], vec![3])],
+                ..CompleteServiceAccount::default()
+            },
+        )]));
+        s.accumulation_pending_xfers.push(DeferredTransfer {
+            source: 7,
+            dest: 8,
+            amount: 5,
+            memo: vec![9; 128],
+            gas_limit: 11,
+        });
+        s.accumulation_provisions.push(ProvisionEntry {
+            service_id: 8,
+            blob: vec![1, 2, 3],
+        });
+        s.accumulation_implications_regular = Some(Implications {
+            id: 7,
+            yield_hash: Some(vec![4; 32]),
+            ..Implications::default()
+        });
+        s.refine_machines.insert(
+            3,
+            GuestMachine::from_state(PvmState::new(RAMType::PvmRam as i32)),
+        );
+        s
+    }
+
+    #[test]
+    fn snapshot_round_trips_and_resumes_identically() {
+        let mut original = sample_state();
+        next_step_impl(&mut original);
+        let blob = snapshot_impl(&original);
+
+        let mut restored = restore_impl(&blob).expect("restore");
+        assert_eq!(snapshot_impl(&restored), blob);
+        assert!(!restored.ram.is_writable_with_fault(0x3_0000, 1).success);
+        let mut probe = restore_impl(&blob).expect("restore");
+        assert_eq!(
+            probe.ram.read_octets(0x2_0010, 5).data.as_deref(),
+            Some(&b"hello"[..])
+        );
+
+        while next_step_impl(&mut original) {}
+        while next_step_impl(&mut restored) {}
+        assert_eq!(snapshot_impl(&restored), snapshot_impl(&original));
+        assert_eq!(restored.registers[0], 10);
+    }
+
+    #[test]
+    fn restore_rejects_bad_input() {
+        let blob = snapshot_impl(&sample_state());
+        assert!(restore_impl(&blob[..blob.len() - 1]).is_none());
+        let mut other_version = blob.clone();
+        other_version[4] ^= 0xff;
+        assert!(restore_impl(&other_version).is_none());
+        assert!(restore_impl(b"nope").is_none());
+    }
+}
@@ -1,30 +1,41 @@
-//! Singleton PVM state wrapper (mirrors PVMWasmWrapper in assembly/wasm-wrapper.ts).
-//! Holds global state and drives step/run_blob.
+//! PVM state wrapper (mirrors PVMWasmWrapper in assembly/wasm-wrapper.ts).
+//! Holds per-instance state and drives step/run_blob; owned by `PvmInstance` (see instance.rs).
 
 use crate::codec::{
     decode_blob, decode_accumulate_args, decode_implications_pair, decode_program_from_preimage,
     decode_service_code_from_preimage,
-    encode_implications_pair,
+    encode_implications, encode_implications_pair,
     AccountEntry, CompleteServiceAccount, DeferredTransfer, Implications, ImplicationsPair,
     PartialState, ProvisionEntry,
 };
 use crate::config::{
-    FetchSystemConstantsConfig, ARGS_SEGMENT_START, DEFAULT_GAS_LIMIT, HALT_ADDRESS,
+    FetchSystemConstantsConfig, ACCUMULATE_ENTRY_PC, ARGS_SEGMENT_START, DEFAULT_GAS_LIMIT,
+    HALT_ADDRESS, MAX_AUTH_CODE_SIZE, PACKAGE_AUTH_GAS, REFINE_ENTRY_PC, REG_WHAT,
     RESULT_CODE_FAULT, RESULT_CODE_HALT, RESULT_CODE_HOST, RESULT_CODE_OOG, RESULT_CODE_PANIC,
     STACK_SEGMENT_END,
 };
-use crate::host_functions::base::HostFunctionContext;
-use crate::host_functions::get_host_function;
-use crate::host_functions::refine::RefineContext;
+use crate::debugger::{Debugger, WatchedRam};
+use crate::gas::{block_entry_costs, GasCharging};
+use crate::guest::GuestMachine;
+use crate::host_functions::base::{HostCallUsage, HostFunctionContext};
+use crate::host_functions::{HostFunctionRegistry, InvocationKind};
+use crate::host_functions::refine::{RefineContext, RefineMachine};
 use crate::instructions::registry::InstructionRegistry;
 use crate::instructions::registry_instructions::register_all_instructions;
+use crate::mmap_ram::{MmapRam, NativeView};
 use crate::mock_ram::MockRam;
 use crate::parser::PvmParser;
+use crate::profiler::Profiler;
+use crate::recompiler::{self, CompiledProgram};
 use crate::ram::PvmRam;
 use crate::simple_ram::SimpleRam;
-use crate::types::{InstructionContext, InstructionResult, MemoryAccessType, Ram, RegisterState};
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::trace::TraceWriter;
+use crate::types::{
+    DecodedInstruction, InstructionContext, InstructionResult, MemoryAccessType, Ram, RegisterState,
+};
+use crate::verifier::{verify_code, Diagnostic};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 /// Execution status (mirrors Status enum in wasm-wrapper.ts).
 #[repr(i32)]
@@ -45,13 +56,38 @@ pub enum RAMType {
     PvmRam = 0,
     SimpleRAM = 1,
     MockRAM = 2,
+    /// Reserved 4 GiB address space (see mmap_ram.rs); PvmRam where it cannot be mapped.
+    MmapRAM = 3,
 }
 
-/// RAM backend enum for state: one of the three implementations.
+/// RAM backend enum for state: one of the four implementations.
 pub enum RamEnum {
     Pvm(PvmRam),
     Simple(SimpleRam),
     Mock(MockRam),
+    Mmap(MmapRam),
+}
+
+impl RamEnum {
+    /// Copy for `PvmState::fork`: `PvmRam` shares its pages copy-on-write, the other backends are copied.
+    /// None when an `MmapRam` cannot reserve another address space.
+    #[must_use]
+    pub fn fork(&self) -> Option<Self> {
+        Some(match self {
+            RamEnum::Pvm(r) => RamEnum::Pvm(r.fork()),
+            RamEnum::Simple(r) => RamEnum::Simple(r.clone()),
+            RamEnum::Mock(r) => RamEnum::Mock(r.clone()),
+            RamEnum::Mmap(r) => RamEnum::Mmap(r.fork()?),
+        })
+    }
+
+    /// Direct memory access for native code; only `MmapRam` has a flat address space to offer.
+    pub fn native_view(&mut self) -> Option<NativeView> {
+        match self {
+            RamEnum::Mmap(r) => Some(r.native_view()),
+            _ => None,
+        }
+    }
 }
 
 impl Ram for RamEnum {
@@ -60,6 +96,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.read_octets(address, count),
             RamEnum::Simple(r) => r.read_octets(address, count),
             RamEnum::Mock(r) => r.read_octets(address, count),
+            RamEnum::Mmap(r) => r.read_octets(address, count),
         }
     }
     fn write_octets(&mut self, address: u32, values: &[u8]) -> crate::types::WriteResult {
@@ -67,6 +104,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.write_octets(address, values),
             RamEnum::Simple(r) => r.write_octets(address, values),
             RamEnum::Mock(r) => r.write_octets(address, values),
+            RamEnum::Mmap(r) => r.write_octets(address, values),
         }
     }
     fn current_heap_pointer(&self) -> u32 {
@@ -74,6 +112,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.current_heap_pointer(),
             RamEnum::Simple(r) => r.current_heap_pointer(),
             RamEnum::Mock(r) => r.current_heap_pointer(),
+            RamEnum::Mmap(r) => r.current_heap_pointer(),
         }
     }
     fn set_current_heap_pointer(&mut self, value: u32) {
@@ -81,6 +120,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.set_current_heap_pointer(value),
             RamEnum::Simple(r) => r.set_current_heap_pointer(value),
             RamEnum::Mock(r) => r.set_current_heap_pointer(value),
+            RamEnum::Mmap(r) => r.set_current_heap_pointer(value),
         }
     }
     fn allocate_pages(&mut self, start_page: u32, count: u32) {
@@ -88,6 +128,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.allocate_pages(start_page, count),
             RamEnum::Simple(r) => r.allocate_pages(start_page, count),
             RamEnum::Mock(r) => r.allocate_pages(start_page, count),
+            RamEnum::Mmap(r) => r.allocate_pages(start_page, count),
         }
     }
     fn is_readable_with_fault(&self, address: u32, size: u32) -> crate::types::FaultCheckResult {
@@ -95,6 +136,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.is_readable_with_fault(address, size),
             RamEnum::Simple(r) => r.is_readable_with_fault(address, size),
             RamEnum::Mock(r) => r.is_readable_with_fault(address, size),
+            RamEnum::Mmap(r) => r.is_readable_with_fault(address, size),
         }
     }
     fn initialize_memory_layout(
@@ -109,6 +151,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.initialize_memory_layout(argument_data, read_only_data, read_write_data, stack_size, heap_zero_padding_size),
             RamEnum::Simple(r) => r.initialize_memory_layout(argument_data, read_only_data, read_write_data, stack_size, heap_zero_padding_size),
             RamEnum::Mock(r) => r.initialize_memory_layout(argument_data, read_only_data, read_write_data, stack_size, heap_zero_padding_size),
+            RamEnum::Mmap(r) => r.initialize_memory_layout(argument_data, read_only_data, read_write_data, stack_size, heap_zero_padding_size),
         }
     }
     fn is_writable_with_fault(&self, address: u32, size: u32) -> crate::types::FaultCheckResult {
@@ -116,6 +159,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.is_writable_with_fault(address, size),
             RamEnum::Simple(r) => r.is_writable_with_fault(address, size),
             RamEnum::Mock(r) => r.is_writable_with_fault(address, size),
+            RamEnum::Mmap(r) => r.is_writable_with_fault(address, size),
         }
     }
     fn set_page_access_rights(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
@@ -123,6 +167,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.set_page_access_rights(address, length, access_type),
             RamEnum::Simple(r) => r.set_page_access_rights(address, length, access_type),
             RamEnum::Mock(r) => r.set_page_access_rights(address, length, access_type),
+            RamEnum::Mmap(r) => r.set_page_access_rights(address, length, access_type),
         }
     }
     fn init_page(&mut self, address: u32, length: u32, access_type: MemoryAccessType) {
@@ -130,6 +175,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.init_page(address, length, access_type),
             RamEnum::Simple(r) => r.init_page(address, length, access_type),
             RamEnum::Mock(r) => r.init_page(address, length, access_type),
+            RamEnum::Mmap(r) => r.init_page(address, length, access_type),
         }
     }
     fn write_octets_during_initialization(&mut self, address: u32, values: &[u8]) {
@@ -137,6 +183,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.write_octets_during_initialization(address, values),
             RamEnum::Simple(r) => r.write_octets_during_initialization(address, values),
             RamEnum::Mock(r) => r.write_octets_during_initialization(address, values),
+            RamEnum::Mmap(r) => r.write_octets_during_initialization(address, values),
         }
     }
     fn get_page_dump(&self, page_index: u32) -> Vec<u8> {
@@ -144,6 +191,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.get_page_dump(page_index),
             RamEnum::Simple(r) => r.get_page_dump(page_index),
             RamEnum::Mock(r) => r.get_page_dump(page_index),
+            RamEnum::Mmap(r) => r.get_page_dump(page_index),
         }
     }
     fn reset(&mut self) {
@@ -151,6 +199,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.reset(),
             RamEnum::Simple(r) => r.reset(),
             RamEnum::Mock(r) => r.reset(),
+            RamEnum::Mmap(r) => r.reset(),
         }
     }
     fn last_load_address(&self) -> u32 {
@@ -158,6 +207,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.last_load_address(),
             RamEnum::Simple(r) => r.last_load_address(),
             RamEnum::Mock(r) => r.last_load_address(),
+            RamEnum::Mmap(r) => r.last_load_address(),
         }
     }
     fn last_load_value(&self) -> u64 {
@@ -165,6 +215,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.last_load_value(),
             RamEnum::Simple(r) => r.last_load_value(),
             RamEnum::Mock(r) => r.last_load_value(),
+            RamEnum::Mmap(r) => r.last_load_value(),
         }
     }
     fn last_store_address(&self) -> u32 {
@@ -172,6 +223,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.last_store_address(),
             RamEnum::Simple(r) => r.last_store_address(),
             RamEnum::Mock(r) => r.last_store_address(),
+            RamEnum::Mmap(r) => r.last_store_address(),
         }
     }
     fn last_store_value(&self) -> u64 {
@@ -179,6 +231,7 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.last_store_value(),
             RamEnum::Simple(r) => r.last_store_value(),
             RamEnum::Mock(r) => r.last_store_value(),
+            RamEnum::Mmap(r) => r.last_store_value(),
         }
     }
     fn clear_last_memory_op(&mut self) {
@@ -186,11 +239,29 @@ impl Ram for RamEnum {
             RamEnum::Pvm(r) => r.clear_last_memory_op(),
             RamEnum::Simple(r) => r.clear_last_memory_op(),
             RamEnum::Mock(r) => r.clear_last_memory_op(),
+            RamEnum::Mmap(r) => r.clear_last_memory_op(),
+        }
+    }
+    fn dirty_pages(&self) -> Vec<u32> {
+        match self {
+            RamEnum::Pvm(r) => r.dirty_pages(),
+            RamEnum::Simple(r) => r.dirty_pages(),
+            RamEnum::Mock(r) => r.dirty_pages(),
+            RamEnum::Mmap(r) => r.dirty_pages(),
+        }
+    }
+    fn clear_dirty(&mut self) {
+        match self {
+            RamEnum::Pvm(r) => r.clear_dirty(),
+            RamEnum::Simple(r) => r.clear_dirty(),
+            RamEnum::Mock(r) => r.clear_dirty(),
+            RamEnum::Mmap(r) => r.clear_dirty(),
         }
     }
 }
 
-fn get_registry() -> &'static InstructionRegistry {
+/// Handlers for every opcode (256-entry dispatch table), shared by all instances.
+pub(crate) fn instruction_registry() -> &'static InstructionRegistry {
     use std::sync::OnceLock;
     static REGISTRY: OnceLock<InstructionRegistry> = OnceLock::new();
     REGISTRY.get_or_init(|| {
@@ -200,17 +271,19 @@ fn get_registry() -> &'static InstructionRegistry {
     })
 }
 
-/// Global PVM state for NAPI.
+/// PVM state for one instance (registers, code, RAM, invocation context).
 pub struct PvmState {
     pub ram_type: i32,
     pub program_counter: u32,
-    pub gas_left: u32,
+    pub gas_left: u64,
     pub status: Status,
     pub exit_arg: u32,
     pub result_code: u8,
     pub registers: RegisterState,
     pub code: Vec<u8>,
     pub bitmask: Vec<u8>,
+    /// Per-PC opcode, Fskip and next PC for `code`; built by `load_code` (rebuilt lazily if `code` changes length).
+    pub decoded: Vec<DecodedInstruction>,
     pub jump_table: Vec<u32>,
     pub ram: RamEnum,
     pub last_load_address: u32,
@@ -254,9 +327,9 @@ pub struct PvmState {
     pub timeslot: Option<u64>,
     /// Config for FETCH selector 0 (system constants) and FORGET/EJECT expunge. Set from setup.
     pub accumulation_fetch_config: Option<FetchSystemConstantsConfig>,
-    /// Service ID from implications (im_id). Set from decode_implications_pair(context).regular.id for INFO/LOOKUP/etc.
+    /// Service ID from implications (im_id). Set from decode_implications_pair(context).regular.id for INFO/LOOKUP/etc.; in refine, w.serviceindex from RefineInputs.
     pub accumulation_service_id: Option<u64>,
-    /// Accounts map from implications (im_state.ps_accounts). Set from decode_implications_pair(context) for INFO/LOOKUP/etc.
+    /// Accounts map from implications (im_state.ps_accounts). Set from decode_implications_pair(context) for INFO/LOOKUP/etc.; in refine, δ from RefineInputs.
     pub accumulation_accounts: Option<HashMap<u64, CompleteServiceAccount>>,
     /// Full decoded regular implications at setup (for re-encoding with updated accounts/yield).
     pub accumulation_implications_regular: Option<Implications>,
@@ -276,10 +349,221 @@ pub struct PvmState {
     pub refine_export_segments: Vec<Vec<u8>>,
     /// Current segment offset for refine (segoff). Updated by push_export_segment.
     pub refine_segment_offset: i64,
+    /// Nested machines created by MACHINE (8) during refine, keyed by machine ID (Gray Paper m).
+    pub refine_machines: BTreeMap<u64, GuestMachine>,
+    /// FETCH selector 2: authorizer trace (r). Set from setup_refine_from_preimage inputs.
+    pub refine_authorizer_trace: Option<Vec<u8>>,
+    /// FETCH selectors 4,6: index of the work item being refined (i).
+    pub refine_work_item_index: Option<u64>,
+    /// FETCH selectors 5,6: import segments per work item (ī).
+    pub refine_import_segments: Option<Vec<Vec<Vec<u8>>>>,
+    /// FETCH selectors 3,4: extrinsic blobs per work item (x̄).
+    pub refine_extrinsics: Option<Vec<Vec<Vec<u8>>>>,
+    /// HISTORICAL_LOOKUP: lookup-anchor timeslot ((p_x)_t).
+    pub refine_lookup_timeslot: Option<u64>,
+    /// True when running an Is-Authorized invocation (setup_is_authorized_from_preimage). Restricts host calls to GAS, FETCH and LOG.
+    pub has_is_authorized_context: bool,
+    /// External host-call mode: ECALLI stops with Status::Host instead of dispatching; the embedder resumes
+    /// with resume_host_call_impl. Kept across resets (it is a mode, not program state).
+    pub external_host_calls: bool,
+    /// PC after the pending ECALLI while stopped in external host-call mode.
+    pub host_resume_pc: Option<u32>,
+    /// Host functions ECALLI dispatches to, and the per-invocation-kind allowlists. Shared with other
+    /// instances until modified through `host_functions_mut`; kept across resets.
+    pub host_functions: Arc<HostFunctionRegistry>,
+    /// Run with the x86-64 recompiler instead of the interpreter (see recompiler/mod.rs). Kept across resets.
+    pub use_recompiler: bool,
+    /// Native blocks for the loaded code; dropped by `load_code`.
+    pub compiled: Option<Box<CompiledProgram>>,
+    /// Per-step trace recorder (see trace.rs); while set, `run_impl` steps the interpreter. Kept across resets.
+    pub trace: Option<TraceWriter>,
+    /// Breakpoints and memory watchpoints for `run_until_event_impl` (see debugger.rs). Kept across resets.
+    pub debugger: Debugger,
+    /// Execution profile (see profiler.rs); while set, `run_impl` steps the interpreter. Cleared by `load_code`,
+    /// kept across resets.
+    pub profiler: Option<Profiler>,
+    /// Run the static verifier (see verifier.rs) when a program is loaded. Kept across resets.
+    pub verify_on_load: bool,
+    /// Verifier findings for the loaded program; empty when `verify_on_load` is off.
+    pub load_diagnostics: Vec<Diagnostic>,
+    /// Per-instruction (default) or per-block gas charging (see gas.rs). Kept across resets.
+    pub gas_charging: GasCharging,
+    /// Block mode: cost charged on entering the block at each PC; built lazily for `code`, dropped by `load_code`.
+    pub block_gas: Vec<u64>,
 }
 
 impl PvmState {
-    fn reset_program_state(&mut self) {
+    /// Create a fresh state backed by the given RAM type (0 = PvmRam, 1 = SimpleRAM, 2 = MockRAM, 3 = MmapRAM).
+    #[must_use]
+    pub fn new(ram_type: i32) -> Self {
+        let ram = match ram_type {
+            x if x == RAMType::SimpleRAM as i32 => RamEnum::Simple(SimpleRam::new()),
+            x if x == RAMType::MockRAM as i32 => RamEnum::Mock(MockRam::new()),
+            x if x == RAMType::MmapRAM as i32 => {
+                MmapRam::new().map_or_else(|| RamEnum::Pvm(PvmRam::new()), RamEnum::Mmap)
+            }
+            _ => RamEnum::Pvm(PvmRam::new()),
+        };
+        Self {
+            ram_type,
+            ram,
+            ..Self::default()
+        }
+    }
+
+    /// Independent copy for speculative execution. `PvmRam` pages (also those of nested machines) are shared
+    /// copy-on-write, so the fork costs O(pages) plus the invocation context; each side's first write to a page
+    /// copies that page. The trace recorder is not carried over, and recompiled blocks are rebuilt on demand.
+    /// None when the RAM cannot be copied (an `MmapRam` without room for another reservation).
+    #[must_use]
+    pub fn fork(&self) -> Option<Self> {
+        Some(Self {
+            ram_type: self.ram_type,
+            program_counter: self.program_counter,
+            gas_left: self.gas_left,
+            status: self.status,
+            exit_arg: self.exit_arg,
+            result_code: self.result_code,
+            registers: self.registers,
+            code: self.code.clone(),
+            bitmask: self.bitmask.clone(),
+            decoded: self.decoded.clone(),
+            jump_table: self.jump_table.clone(),
+            ram: self.ram.fork()?,
+            last_load_address: self.last_load_address,
+            last_load_value: self.last_load_value,
+            last_store_address: self.last_store_address,
+            last_store_value: self.last_store_value,
+            last_opcode: self.last_opcode,
+            has_accumulation_context: self.has_accumulation_context,
+            yield_hash: self.yield_hash.clone(),
+            host_call_id: self.host_call_id,
+            checkpoint_requested: self.checkpoint_requested,
+            accumulate_inputs_encoded: self.accumulate_inputs_encoded.clone(),
+            work_package_encoded: self.work_package_encoded.clone(),
+            auth_config: self.auth_config.clone(),
+            auth_token: self.auth_token.clone(),
+            refine_context_encoded: self.refine_context_encoded.clone(),
+            work_item_summaries: self.work_item_summaries.clone(),
+            work_item_payloads: self.work_item_payloads.clone(),
+            log_messages: self.log_messages.clone(),
+            entropy_accumulator: self.entropy_accumulator.clone(),
+            accumulation_num_cores: self.accumulation_num_cores,
+            accumulation_num_validators: self.accumulation_num_validators,
+            accumulation_auth_queue_size: self.accumulation_auth_queue_size,
+            timeslot: self.timeslot,
+            accumulation_fetch_config: self.accumulation_fetch_config.clone(),
+            accumulation_service_id: self.accumulation_service_id,
+            accumulation_accounts: self.accumulation_accounts.clone(),
+            accumulation_implications_regular: self.accumulation_implications_regular.clone(),
+            accumulation_implications_exceptional: self
+                .accumulation_implications_exceptional
+                .clone(),
+            accumulation_pending_xfers: self.accumulation_pending_xfers.clone(),
+            accumulation_regular_state: self.accumulation_regular_state.clone(),
+            accumulation_nextfreeid: self.accumulation_nextfreeid,
+            accumulation_provisions: self.accumulation_provisions.clone(),
+            has_refine_context: self.has_refine_context,
+            refine_export_segments: self.refine_export_segments.clone(),
+            refine_segment_offset: self.refine_segment_offset,
+            refine_machines: self
+                .refine_machines
+                .iter()
+                .map(|(&id, machine)| Some((id, machine.fork()?)))
+                .collect::<Option<_>>()?,
+            refine_authorizer_trace: self.refine_authorizer_trace.clone(),
+            refine_work_item_index: self.refine_work_item_index,
+            refine_import_segments: self.refine_import_segments.clone(),
+            refine_extrinsics: self.refine_extrinsics.clone(),
+            refine_lookup_timeslot: self.refine_lookup_timeslot,
+            has_is_authorized_context: self.has_is_authorized_context,
+            external_host_calls: self.external_host_calls,
+            host_resume_pc: self.host_resume_pc,
+            host_functions: Arc::clone(&self.host_functions),
+            use_recompiler: self.use_recompiler,
+            compiled: None,
+            trace: None,
+            debugger: self.debugger.clone(),
+            profiler: self.profiler.clone(),
+            verify_on_load: self.verify_on_load,
+            load_diagnostics: self.load_diagnostics.clone(),
+            gas_charging: self.gas_charging.clone(),
+            block_gas: self.block_gas.clone(),
+        })
+    }
+
+    /// Invocation kind selecting the host-call allowlist (refine, then is-authorized, then accumulate).
+    #[must_use]
+    pub fn invocation_kind(&self) -> InvocationKind {
+        if self.has_refine_context {
+            InvocationKind::Refine
+        } else if self.has_is_authorized_context {
+            InvocationKind::IsAuthorized
+        } else if self.has_accumulation_context {
+            InvocationKind::Accumulate
+        } else {
+            InvocationKind::Generic
+        }
+    }
+
+    /// Register, override or remove host functions, or change allowlists, for this instance only.
+    pub fn host_functions_mut(&mut self) -> &mut HostFunctionRegistry {
+        Arc::make_mut(&mut self.host_functions)
+    }
+
+    /// Load code, bitmask and jump table, and pre-decode the instruction stream once for execution.
+    pub fn load_code(&mut self, code: Vec<u8>, bitmask: Vec<u8>, jump_table: Vec<u32>) {
+        self.decoded = PvmParser::new().predecode(&code, &bitmask);
+        self.code = code;
+        self.bitmask = bitmask;
+        self.jump_table = jump_table;
+        self.compiled = None;
+        if let Some(trace) = &mut self.trace {
+            trace.restart();
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.clear();
+        }
+        self.load_diagnostics.clear();
+        self.block_gas.clear();
+    }
+
+    /// Verify the first `code_length` octets of the loaded code (the rest is execution padding) when
+    /// `verify_on_load` is set.
+    pub fn verify_loaded_code(&mut self, code_length: usize) {
+        if self.verify_on_load {
+            let length = code_length.min(self.code.len()).min(self.bitmask.len());
+            self.load_diagnostics = verify_code(
+                &self.code[..length],
+                &self.bitmask[..length],
+                &self.jump_table,
+            );
+        }
+    }
+
+    /// Rebuild `decoded` if `code` was replaced without `load_code`.
+    pub fn ensure_decoded(&mut self) {
+        if self.decoded.len() != self.code.len() {
+            self.decoded = PvmParser::new().predecode(&self.code, &self.bitmask);
+        }
+    }
+
+    /// Gas to take before the instruction at `pc`: 1, or in block mode the block's cost at its first instruction
+    /// and 0 inside it.
+    fn gas_charge(&mut self, pc: u32) -> u64 {
+        let GasCharging::Block(model) = &self.gas_charging else {
+            return 1;
+        };
+        if self.block_gas.len() != self.code.len() {
+            if self.decoded.len() != self.code.len() {
+                self.decoded = PvmParser::new().predecode(&self.code, &self.bitmask);
+            }
+            self.block_gas = block_entry_costs(&self.bitmask, &self.decoded, model);
+        }
+        self.block_gas[pc as usize]
+    }
+
+    pub fn reset_program_state(&mut self) {
         self.program_counter = 0;
         self.gas_left = DEFAULT_GAS_LIMIT;
         self.status = Status::Ok;
@@ -289,9 +573,6 @@ impl PvmState {
         self.ram.reset();
         self.last_opcode = 0;
         self.host_call_id = 0;
-        self.yield_hash = None;
-        self.checkpoint_requested = false;
-        self.accumulate_inputs_encoded.clear();
         self.work_package_encoded = None;
         self.auth_config = None;
         self.auth_token = None;
@@ -300,10 +581,23 @@ impl PvmState {
         self.work_item_payloads = None;
         self.log_messages.clear();
         self.entropy_accumulator = None;
+        self.timeslot = None;
+        self.clear_accumulation_context();
+        self.clear_refine_context();
+        self.has_is_authorized_context = false;
+        self.host_resume_pc = None;
+    }
+
+    /// Drop the accumulation context (Ψ_A flag, implications and service state). Also clears the service ID,
+    /// accounts and FETCH constants, which refine sets through the same fields.
+    pub fn clear_accumulation_context(&mut self) {
+        self.has_accumulation_context = false;
+        self.yield_hash = None;
+        self.checkpoint_requested = false;
+        self.accumulate_inputs_encoded.clear();
         self.accumulation_num_cores = 0;
         self.accumulation_num_validators = 0;
         self.accumulation_auth_queue_size = 0;
-        self.timeslot = None;
         self.accumulation_fetch_config = None;
         self.accumulation_service_id = None;
         self.accumulation_accounts = None;
@@ -312,9 +606,19 @@ impl PvmState {
         self.accumulation_pending_xfers.clear();
         self.accumulation_regular_state = PartialState::default();
         self.accumulation_provisions.clear();
+    }
+
+    /// Drop the refine context (Ψ_R flag, exports, nested machines and refine FETCH inputs).
+    pub fn clear_refine_context(&mut self) {
         self.has_refine_context = false;
         self.refine_export_segments.clear();
         self.refine_segment_offset = 0;
+        self.refine_machines.clear();
+        self.refine_authorizer_trace = None;
+        self.refine_work_item_index = None;
+        self.refine_import_segments = None;
+        self.refine_extrinsics = None;
+        self.refine_lookup_timeslot = None;
     }
 }
 
@@ -330,6 +634,7 @@ impl Default for PvmState {
             registers: [0u64; 13],
             code: vec![],
             bitmask: vec![],
+            decoded: vec![],
             jump_table: vec![],
             ram: RamEnum::Mock(MockRam::new()),
             last_load_address: 0,
@@ -366,16 +671,29 @@ impl Default for PvmState {
             has_refine_context: false,
             refine_export_segments: vec![],
             refine_segment_offset: 0,
+            refine_machines: BTreeMap::new(),
+            refine_authorizer_trace: None,
+            refine_work_item_index: None,
+            refine_import_segments: None,
+            refine_extrinsics: None,
+            refine_lookup_timeslot: None,
+            has_is_authorized_context: false,
+            external_host_calls: false,
+            host_resume_pc: None,
+            host_functions: HostFunctionRegistry::shared_default(),
+            use_recompiler: false,
+            compiled: None,
+            trace: None,
+            debugger: Debugger::default(),
+            profiler: None,
+            verify_on_load: false,
+            load_diagnostics: vec![],
+            gas_charging: GasCharging::Instruction,
+            block_gas: vec![],
         }
     }
 }
 
-static STATE: Mutex<Option<PvmState>> = Mutex::new(None);
-
-pub fn get_state() -> std::sync::MutexGuard<'static, Option<PvmState>> {
-    STATE.lock().expect("pvm state lock")
-}
-
 /// Build current regular implications from state (merge accounts, bless, assign, xfers, etc.).
 /// Used for CHECKPOINT snapshot (imY' = imX) and for get_accumulation_context_encoded.
 /// Accounts are sorted by service_id so the encoded context matches TypeScript (codec sorts before encode).
@@ -407,19 +725,17 @@ fn build_current_regular_implications(state: &PvmState) -> Option<Implications>
 /// Build updated implications pair from current state (accounts + yield) and return encoded buffer.
 /// Matches WASM getAccumulationContext: caller gets full encoded pair to decode and use as updated context.
 pub fn get_accumulation_context_encoded(
+    state: &PvmState,
     num_cores: i32,
     num_validators: i32,
     auth_queue_size: i32,
 ) -> Option<Vec<u8>> {
-    let g = STATE.lock().expect("pvm state lock");
-    let state = g.as_ref()?;
     if !state.has_accumulation_context {
         return None;
     }
     let mut regular = build_current_regular_implications(state)?;
     let exceptional = state.accumulation_implications_exceptional.clone()?;
     let yield_hash = state.yield_hash.clone();
-    drop(g);
 
     // imX (regular) gets current yield; imY (exceptional) keeps snapshot yield from CHECKPOINT (Gray Paper; match AS).
     regular.yield_hash = yield_hash;
@@ -437,33 +753,117 @@ pub fn get_accumulation_context_encoded(
 }
 
 /// Return export segments collected during refine invocation (EXPORT host). Empty if not in refine mode.
-pub fn get_export_segments() -> Vec<Vec<u8>> {
-    let g = STATE.lock().expect("pvm state lock");
-    let state = g.as_ref();
-    match state {
-        Some(s) if s.has_refine_context => s.refine_export_segments.clone(),
+pub fn get_export_segments(state: &PvmState) -> Vec<Vec<u8>> {
+    if state.has_refine_context {
+        state.refine_export_segments.clone()
+    } else {
+        vec![]
+    }
+}
+
+/// Gray Paper equation 831 (R function): on HALT the output is memory [registers[7], registers[7]+registers[8]).
+/// Empty when the range is empty or not readable.
+pub fn get_result_impl(state: &mut PvmState) -> Vec<u8> {
+    let offset = state.registers[7] as u32;
+    let length = state.registers[8] as u32;
+    if length == 0 {
+        return vec![];
+    }
+    let read_result = state.ram.read_octets(offset, length);
+    match read_result.data {
+        Some(data) if read_result.fault_address == 0 => data,
         _ => vec![],
     }
 }
 
-pub fn init_state(ram_type: i32) {
-    let mut g = STATE.lock().expect("pvm state lock");
-    let ram = match ram_type {
-        x if x == RAMType::SimpleRAM as i32 => RamEnum::Simple(SimpleRam::new()),
-        x if x == RAMType::MockRAM as i32 => RamEnum::Mock(MockRam::new()),
-        _ => RamEnum::Pvm(PvmRam::new()),
-    };
-    *g = Some(PvmState {
-        ram_type,
-        ram,
-        ..PvmState::default()
-    });
+/// Result of a complete accumulation invocation Ψ_A (run to completion, then collapse).
+pub struct AccumulateOutcome {
+    /// u = gas limit - max(gas', 0) (Gray Paper equation 834).
+    pub gas_consumed: u64,
+    pub result_code: u8,
+    /// R function output on HALT; empty on PANIC/FAULT/OOG.
+    pub output: Vec<u8>,
+    /// Collapsed yield: imY.yield on PANIC/FAULT/OOG, the output when it is a 32-byte hash, else imX.yield.
+    pub yield_hash: Option<Vec<u8>>,
+    /// Updated implications pair (imX', imY') as returned by get_accumulation_context_encoded.
+    pub encoded_context: Vec<u8>,
+    /// Collapsed implications: imX' on HALT, imY' on PANIC/FAULT/OOG, with the collapsed yield.
+    pub encoded_post_state: Vec<u8>,
+}
+
+/// Outcome when the service code could not be loaded (Gray Paper: c = ∅ or |c| > W_C).
+/// No gas is consumed and the post-state is the exceptional (initial) implications from the input context.
+pub fn accumulate_setup_failure(
+    encoded_context: &[u8],
+    num_cores: i32,
+    num_validators: i32,
+    auth_queue_size: i32,
+) -> AccumulateOutcome {
+    let encoded_post_state =
+        decode_implications_pair(encoded_context, num_cores, num_validators, auth_queue_size)
+            .map(|pair| {
+                encode_implications(
+                    &pair.value.exceptional,
+                    num_cores,
+                    num_validators,
+                    auth_queue_size,
+                )
+            })
+            .unwrap_or_default();
+    AccumulateOutcome {
+        gas_consumed: 0,
+        result_code: RESULT_CODE_PANIC,
+        output: vec![],
+        yield_hash: None,
+        encoded_context: encoded_context.to_vec(),
+        encoded_post_state,
+    }
 }
 
-pub fn reset_state() {
-    let mut g = STATE.lock().expect("pvm state lock");
-    if let Some(s) = g.as_mut() {
-        s.reset_program_state();
+/// Run a state prepared by setup_accumulate_from_preimage to completion and collapse the result
+/// (Gray Paper Ψ_A / C): HALT keeps the regular implications, PANIC/FAULT/OOG revert to the exceptional ones.
+pub fn run_accumulate_impl(
+    state: &mut PvmState,
+    gas_limit: u64,
+    num_cores: i32,
+    num_validators: i32,
+    auth_queue_size: i32,
+) -> AccumulateOutcome {
+    run_impl(state);
+
+    let gas_consumed = gas_limit.saturating_sub(state.gas_left);
+    let halted = state.status == Status::Halt;
+    let output = if halted {
+        get_result_impl(state)
+    } else {
+        vec![]
+    };
+    let encoded_context =
+        get_accumulation_context_encoded(state, num_cores, num_validators, auth_queue_size)
+            .unwrap_or_default();
+
+    let collapsed = if halted {
+        build_current_regular_implications(state).map(|mut regular| {
+            if output.len() == 32 {
+                regular.yield_hash = Some(output.clone());
+            }
+            regular
+        })
+    } else {
+        state.accumulation_implications_exceptional.clone()
+    };
+    let yield_hash = collapsed.as_ref().and_then(|im| im.yield_hash.clone());
+    let encoded_post_state = collapsed
+        .map(|im| encode_implications(&im, num_cores, num_validators, auth_queue_size))
+        .unwrap_or_default();
+
+    AccumulateOutcome {
+        gas_consumed,
+        result_code: state.result_code,
+        output,
+        yield_hash,
+        encoded_context,
+        encoded_post_state,
     }
 }
 
@@ -473,7 +873,7 @@ pub struct SetupAccumulateParams<'a> {
     pub args: &'a [u8],
     /// Encoded implications pair (regular × exceptional). Decoded to set accumulation_service_id and accumulation_accounts.
     pub encoded_context: &'a [u8],
-    pub gas_limit: u32,
+    pub gas_limit: u64,
     pub num_cores: u32,
     pub num_validators: u32,
     pub auth_queue_size: u32,
@@ -496,10 +896,10 @@ pub struct SetupAccumulateParams<'a> {
 }
 
 /// RefineContext implementation that appends export segments to state. Used during refine invocation.
-/// MACHINE/INVOKE/PEEK/POKE/PAGES/EXPUNGE are stubbed (no sub-machines in this implementation).
 struct RefineContextForState<'a> {
     segments: &'a mut Vec<Vec<u8>>,
     segment_offset: &'a mut i64,
+    machines: &'a mut BTreeMap<u64, GuestMachine>,
 }
 
 impl RefineContext for RefineContextForState<'_> {
@@ -512,14 +912,28 @@ impl RefineContext for RefineContextForState<'_> {
         self.segments.push(segment);
         Ok(*self.segment_offset)
     }
-    fn add_machine(&mut self, _program: &[u8], _initial_pc: u64) -> u64 {
-        0
+    /// Gray Paper Ω_M: n = min(N \ K(m)), the smallest free machine ID.
+    fn add_machine(&mut self, program: &[u8], initial_pc: u64) -> Option<u64> {
+        let machine = GuestMachine::new(program, initial_pc)?;
+        let machine_id = (0u64..)
+            .find(|id| !self.machines.contains_key(id))
+            .expect("machine id space exhausted");
+        self.machines.insert(machine_id, machine);
+        Some(machine_id)
     }
-    fn with_machine(&mut self, _machine_id: u64, _f: &mut dyn FnMut(&mut dyn crate::host_functions::refine::RefineMachine)) -> bool {
-        false
+    fn with_machine(&mut self, machine_id: u64, f: &mut dyn FnMut(&mut dyn RefineMachine)) -> bool {
+        match self.machines.get_mut(&machine_id) {
+            Some(machine) => {
+                f(machine);
+                true
+            }
+            None => false,
+        }
     }
-    fn remove_machine(&mut self, _machine_id: u64) -> Option<u64> {
-        None
+    fn remove_machine(&mut self, machine_id: u64) -> Option<u64> {
+        self.machines
+            .remove(&machine_id)
+            .map(|machine| machine.get_pc())
     }
 }
 
@@ -528,23 +942,46 @@ pub struct SetupRefineParams<'a> {
     pub program: &'a [u8],
     pub args: &'a [u8],
     pub refine_context_encoded: &'a [u8],
-    pub gas_limit: u32,
+    pub gas_limit: u64,
     pub config_max_refine_gas: u64,
+    /// Work package, segments and accounts for FETCH/HISTORICAL_LOOKUP. None leaves them unset (selectors return NONE).
+    pub inputs: Option<RefineInputs>,
 }
 
+/// Gray Paper Ψ_R host-call context beyond (m, e): p, i, ī, x̄, r, (p_x)_t, s and δ.
+#[derive(Default)]
+pub struct RefineInputs {
+    /// FETCH selector 7: encoded work package.
+    pub work_package_encoded: Vec<u8>,
+    /// FETCH selector 8: p.authconfig.
+    pub auth_config: Vec<u8>,
+    /// FETCH selector 9: p.authtoken.
+    pub auth_token: Vec<u8>,
+    /// FETCH selectors 11,12: encoded S(w) per work item.
+    pub work_item_summaries: Vec<Vec<u8>>,
+    /// FETCH selector 13: w.payload per work item.
+    pub work_item_payloads: Vec<Vec<u8>>,
+    pub work_item_index: u64,
+    pub import_segments: Vec<Vec<Vec<u8>>>,
+    pub extrinsics: Vec<Vec<Vec<u8>>>,
+    pub authorizer_trace: Vec<u8>,
+    /// Export segment offset (ς) returned by EXPORT.
+    pub export_segment_offset: i64,
+    pub lookup_anchor_timeslot: u64,
+    /// Service being refined (w.serviceindex).
+    pub service_id: u64,
+    /// Service accounts (δ) for HISTORICAL_LOOKUP.
+    pub accounts: HashMap<u64, CompleteServiceAccount>,
+}
 
-/// Setup state for refinement invocation. Tries two formats: (1) Preimage + Y-format program:
-/// decode_program_from_preimage → decode_blob(decoded.code), init memory from ro/rw. (2) Preimage + raw deblob:
-/// decode_service_code_from_preimage → decode_blob(code_blob), init memory with empty ro/rw. Initial PC=0 per
-/// pvm_invocations.tex eq. refinvocation: Ψ_M(𝐜, 0, …).
-pub fn setup_refine_from_preimage(params: SetupRefineParams<'_>) -> bool {
-    let mut g = STATE.lock().expect("pvm state lock");
-    let Some(state) = g.as_mut() else {
-        return false;
-    };
 
+/// Load code for a marshalling invocation at PC=0 (Ψ_M(𝐜, 0, g, 𝐚, …)) as used by refine and is-authorized.
+/// Tries two formats: (1) Preimage + Y-format program: decode_program_from_preimage → decode_blob(decoded.code),
+/// init memory from ro/rw. (2) Preimage + raw deblob: decode_service_code_from_preimage → decode_blob(code_blob),
+/// init memory with empty ro/rw. Returns false (PANIC) when neither decodes.
+fn load_program_at_zero(state: &mut PvmState, program: &[u8], args: &[u8], gas_limit: u64) -> bool {
     let (decoded_blob, ro_data, rw_data, stack_size, heap_zero_padding_size) =
-        if let Some(decoded) = decode_program_from_preimage(params.program) {
+        if let Some(decoded) = decode_program_from_preimage(program) {
             let Some(blob) = decode_blob(&decoded.code) else {
                 state.status = Status::Panic;
                 state.result_code = RESULT_CODE_PANIC;
@@ -557,7 +994,7 @@ pub fn setup_refine_from_preimage(params: SetupRefineParams<'_>) -> bool {
                 decoded.stack_size,
                 decoded.heap_zero_padding_size,
             )
-        } else if let Some(preimage_result) = decode_service_code_from_preimage(params.program) {
+        } else if let Some(preimage_result) = decode_service_code_from_preimage(program) {
             let Some(blob) = decode_blob(&preimage_result.value.code_blob) else {
                 state.status = Status::Panic;
                 state.result_code = RESULT_CODE_PANIC;
@@ -577,32 +1014,61 @@ pub fn setup_refine_from_preimage(params: SetupRefineParams<'_>) -> bool {
     extended_code[..code_len].copy_from_slice(&decoded_blob.code);
     let mut extended_bitmask = vec![1u8; ext_len + 25];
     extended_bitmask[..decoded_blob.bitmask.len().min(ext_len)].copy_from_slice(&decoded_blob.bitmask);
-    state.code = extended_code;
-    state.bitmask = extended_bitmask;
-    state.jump_table = decoded_blob.jump_table;
+    state.load_code(extended_code, extended_bitmask, decoded_blob.jump_table);
+    state.verify_loaded_code(code_len);
     state.ram.reset();
     state.ram.initialize_memory_layout(
-        params.args,
+        args,
         &ro_data,
         &rw_data,
         stack_size,
         heap_zero_padding_size,
     );
-    state.program_counter = 0;
-    state.gas_left = params.gas_limit;
+    state.program_counter = REFINE_ENTRY_PC;
+    state.gas_left = gas_limit;
     state.status = Status::Ok;
     state.result_code = RESULT_CODE_HALT;
     state.registers = [0u64; 13];
     state.registers[0] = u64::from(HALT_ADDRESS);
     state.registers[1] = u64::from(STACK_SEGMENT_END);
     state.registers[7] = u64::from(ARGS_SEGMENT_START);
-    state.registers[8] = params.args.len() as u64;
+    state.registers[8] = args.len() as u64;
     state.exit_arg = 0;
     state.host_call_id = 0;
+    true
+}
+
+/// Setup state for refinement invocation. Initial PC=0 per pvm_invocations.tex eq. refinvocation: Ψ_M(𝐜, 0, …).
+pub fn setup_refine_from_preimage(state: &mut PvmState, params: SetupRefineParams<'_>) -> bool {
+    if !load_program_at_zero(state, params.program, params.args, params.gas_limit) {
+        return false;
+    }
+    // Drop any previous Ψ_A/Ψ_I, so that without `inputs` no earlier FETCH or lookup data is reused.
+    state.clear_accumulation_context();
+    state.clear_refine_context();
+    state.has_is_authorized_context = false;
+    state.work_package_encoded = None;
+    state.auth_config = None;
+    state.auth_token = None;
+    state.work_item_summaries = None;
+    state.work_item_payloads = None;
     state.has_refine_context = true;
     state.refine_context_encoded = Some(params.refine_context_encoded.to_vec());
-    state.refine_export_segments.clear();
-    state.refine_segment_offset = 0;
+    if let Some(inputs) = params.inputs {
+        state.work_package_encoded = Some(inputs.work_package_encoded);
+        state.auth_config = Some(inputs.auth_config);
+        state.auth_token = Some(inputs.auth_token);
+        state.work_item_summaries = Some(inputs.work_item_summaries);
+        state.work_item_payloads = Some(inputs.work_item_payloads);
+        state.refine_work_item_index = Some(inputs.work_item_index);
+        state.refine_import_segments = Some(inputs.import_segments);
+        state.refine_extrinsics = Some(inputs.extrinsics);
+        state.refine_authorizer_trace = Some(inputs.authorizer_trace);
+        state.refine_segment_offset = inputs.export_segment_offset;
+        state.refine_lookup_timeslot = Some(inputs.lookup_anchor_timeslot);
+        state.accumulation_service_id = Some(inputs.service_id);
+        state.accumulation_accounts = Some(inputs.accounts);
+    }
     state.accumulation_fetch_config = Some(FetchSystemConstantsConfig {
         num_cores: 0,
         preimage_expunge_period: 0,
@@ -622,13 +1088,84 @@ pub fn setup_refine_from_preimage(params: SetupRefineParams<'_>) -> bool {
     true
 }
 
-/// Setup state for accumulation invocation from preimage blob and args (Gray Paper Y function).
-/// Decodes preimage, sets code/bitmask, initializes RAM, stores config/entropy/timeslot, sets PC=5 and gas. Returns true on success.
-pub fn setup_accumulate_from_preimage(params: SetupAccumulateParams<'_>) -> bool {
-    let mut g = STATE.lock().expect("pvm state lock");
-    let Some(state) = g.as_mut() else {
+/// Params for setup_is_authorized_from_preimage (from setup_is_authorized_invocation).
+pub struct SetupIsAuthorizedParams<'a> {
+    /// Authorizer code (histlookup of p.authcodehash).
+    pub program: &'a [u8],
+    pub core_index: u16,
+    /// FETCH selectors 7–13: p, p.authconfig, p.authtoken, p.context, S(w) per item, w.payload per item.
+    pub work_package_encoded: &'a [u8],
+    pub auth_config: &'a [u8],
+    pub auth_token: &'a [u8],
+    pub refine_context_encoded: &'a [u8],
+    pub work_item_summaries: Vec<Vec<u8>>,
+    pub work_item_payloads: Vec<Vec<u8>>,
+}
+
+/// Gray Paper eq. isauthinvocation: |c| > Cmaxauthcodesize → BIG.
+#[must_use]
+pub fn is_authorized_code_too_big(program: &[u8]) -> bool {
+    program.len() > MAX_AUTH_CODE_SIZE as usize
+}
+
+/// Setup state for Is-Authorized invocation Ψ_I: Ψ_M(c, 0, Cpackageauthgas, E_2(core), F, ∅).
+/// Returns false (PANIC) when the code is oversized or does not decode.
+pub fn setup_is_authorized_from_preimage(
+    state: &mut PvmState,
+    params: SetupIsAuthorizedParams<'_>,
+) -> bool {
+    if is_authorized_code_too_big(params.program) {
+        state.status = Status::Panic;
+        state.result_code = RESULT_CODE_PANIC;
+        return false;
+    }
+    let args = params.core_index.to_le_bytes();
+    if !load_program_at_zero(state, params.program, &args, u64::from(PACKAGE_AUTH_GAS)) {
         return false;
+    }
+    // invocation_kind() and host-call dispatch check refine first, so a previous Ψ_R/Ψ_A must not linger.
+    state.clear_refine_context();
+    state.clear_accumulation_context();
+    state.has_is_authorized_context = true;
+    state.work_package_encoded = Some(params.work_package_encoded.to_vec());
+    state.auth_config = Some(params.auth_config.to_vec());
+    state.auth_token = Some(params.auth_token.to_vec());
+    state.refine_context_encoded = Some(params.refine_context_encoded.to_vec());
+    state.work_item_summaries = Some(params.work_item_summaries);
+    state.work_item_payloads = Some(params.work_item_payloads);
+    true
+}
+
+/// Result of an Is-Authorized invocation Ψ_I run to completion.
+pub struct IsAuthorizedOutcome {
+    /// u = Cpackageauthgas - max(gas', 0).
+    pub gas_consumed: u64,
+    pub result_code: u8,
+    /// Authorizer trace on HALT; empty on PANIC/FAULT/OOG.
+    pub output: Vec<u8>,
+}
+
+/// Run a state prepared by setup_is_authorized_from_preimage to completion.
+pub fn run_is_authorized_impl(state: &mut PvmState) -> IsAuthorizedOutcome {
+    run_impl(state);
+    let output = if state.status == Status::Halt {
+        get_result_impl(state)
+    } else {
+        vec![]
     };
+    IsAuthorizedOutcome {
+        gas_consumed: u64::from(PACKAGE_AUTH_GAS).saturating_sub(state.gas_left),
+        result_code: state.result_code,
+        output,
+    }
+}
+
+/// Setup state for accumulation invocation from preimage blob and args (Gray Paper Y function).
+/// Decodes preimage, sets code/bitmask, initializes RAM, stores config/entropy/timeslot, sets PC=5 and gas. Returns true on success.
+pub fn setup_accumulate_from_preimage(
+    state: &mut PvmState,
+    params: SetupAccumulateParams<'_>,
+) -> bool {
     let Some(decoded) = decode_program_from_preimage(params.program) else {
         state.status = Status::Panic;
         state.result_code = RESULT_CODE_PANIC;
@@ -646,9 +1183,8 @@ pub fn setup_accumulate_from_preimage(params: SetupAccumulateParams<'_>) -> bool
     extended_code[..code_len].copy_from_slice(&decoded_blob.code);
     let mut extended_bitmask = vec![1u8; ext_len + 25];
     extended_bitmask[..decoded_blob.bitmask.len().min(ext_len)].copy_from_slice(&decoded_blob.bitmask);
-    state.code = extended_code;
-    state.bitmask = extended_bitmask;
-    state.jump_table = decoded_blob.jump_table;
+    state.load_code(extended_code, extended_bitmask, decoded_blob.jump_table);
+    state.verify_loaded_code(code_len);
     state.ram.reset();
     state.ram.initialize_memory_layout(
         params.args,
@@ -657,7 +1193,7 @@ pub fn setup_accumulate_from_preimage(params: SetupAccumulateParams<'_>) -> bool
         decoded.stack_size,
         decoded.heap_zero_padding_size,
     );
-    state.program_counter = 5;
+    state.program_counter = ACCUMULATE_ENTRY_PC;
     state.gas_left = params.gas_limit;
     state.status = Status::Ok;
     state.result_code = RESULT_CODE_HALT;
@@ -668,6 +1204,9 @@ pub fn setup_accumulate_from_preimage(params: SetupAccumulateParams<'_>) -> bool
     state.registers[8] = params.args.len() as u64;
     state.exit_arg = 0;
     state.host_call_id = 0;
+    state.clear_refine_context();
+    state.clear_accumulation_context();
+    state.has_is_authorized_context = false;
     state.has_accumulation_context = true;
     state.entropy_accumulator = if params.entropy_accumulator.len() == 32 {
         Some(params.entropy_accumulator.to_vec())
@@ -740,48 +1279,79 @@ pub fn setup_accumulate_from_preimage(params: SetupAccumulateParams<'_>) -> bool
     true
 }
 
-/// One step: fetch instruction at PC, execute, advance or halt. Returns true if execution should continue.
-pub fn next_step_impl(state: &mut PvmState) -> bool {
+/// Status for an instruction result code (unknown codes panic).
+pub(crate) fn status_from_result_code(result_code: u8) -> Status {
+    match result_code {
+        RESULT_CODE_HALT => Status::Halt,
+        RESULT_CODE_PANIC => Status::Panic,
+        RESULT_CODE_FAULT => Status::Fault,
+        RESULT_CODE_HOST => Status::Host,
+        RESULT_CODE_OOG => Status::Oog,
+        _ => Status::Panic,
+    }
+}
+
+/// Result of executing a single instruction with `execute_instruction`.
+pub enum StepOutcome {
+    /// Instruction completed; PC already advanced.
+    Continue,
+    /// ECALLI: `state.host_call_id` holds the ID, PC still points at the ECALLI; `next_pc` is the instruction after it.
+    Host { next_pc: u32 },
+    /// Execution stopped; `state.status` / `state.result_code` / `state.exit_arg` describe why.
+    Exit,
+}
+
+/// Execute the instruction at PC without dispatching host calls (Gray Paper single-step Ψ_1).
+/// Shared by the outer machine (`next_step_impl`) and nested refine machines (guest.rs).
+pub fn execute_instruction(state: &mut PvmState) -> StepOutcome {
     state.host_call_id = 0;
     if state.code.is_empty() {
         state.status = Status::Halt;
-        return false;
+        return StepOutcome::Exit;
     }
     let pc = state.program_counter;
     if pc as usize >= state.code.len() {
         state.status = Status::Halt;
         state.result_code = RESULT_CODE_HALT;
-        return false;
+        return StepOutcome::Exit;
     }
-    if state.gas_left == 0 {
+    let gas_charge = state.gas_charge(pc);
+    if state.gas_left < gas_charge {
         state.status = Status::Oog;
         state.result_code = RESULT_CODE_OOG;
-        return false;
+        return StepOutcome::Exit;
     }
 
     // Match TypeScript/AssemblyScript: always execute at current PC (use bitmask only for Fskip).
     // TS does not skip non-opcode slots; it runs every position and advances by instruction length.
-    let parser = PvmParser::new();
-    let fskip = parser.skip(pc as i32, &state.bitmask);
-    let instruction_length = 1 + fskip;
-    let opcode = state.code[pc as usize] as i32;
-    let operands_end = (pc as usize + instruction_length as usize).min(state.code.len());
-    let operands = &state.code[(pc as usize + 1)..operands_end];
-
-    let registry = get_registry();
-    let Some(handler) = registry.get_handler(opcode) else {
+    state.ensure_decoded();
+    let DecodedInstruction {
+        opcode,
+        next_pc,
+        operands,
+        ..
+    } = state.decoded[pc as usize];
+
+    let Some(handler) = instruction_registry().handler(opcode) else {
         state.status = Status::Panic;
         state.result_code = RESULT_CODE_PANIC;
-        return false;
+        return StepOutcome::Exit;
     };
 
-    state.last_opcode = opcode as u8;
-    state.gas_left = state.gas_left.saturating_sub(1);
+    state.last_opcode = opcode;
+    state.gas_left -= gas_charge;
 
     // Do not clear last memory op here so ECALLI (and other non-memory steps) retain the previous
     // instruction's load/store for trace; matches WASM trace (Store:[addr,value] at ECALLI).
     let pc_before = state.program_counter;
     let mut host_call_id_out = state.host_call_id;
+    let mut watched;
+    let ram: &mut dyn Ram = if state.debugger.has_watchpoints() {
+        watched = WatchedRam::new(&mut state.ram, &mut state.debugger);
+        &mut watched
+    } else {
+        &mut state.ram
+    };
     let mut context = InstructionContext {
         code: &state.code,
         bitmask: &state.bitmask,
@@ -789,9 +1359,8 @@ pub fn next_step_impl(state: &mut PvmState) -> bool {
         program_counter: state.program_counter,
         gas_remaining: state.gas_left,
         operands,
-        fskip,
         jump_table: &state.jump_table,
-        ram: &mut state.ram,
+        ram,
         host_call_id_out: Some(&mut host_call_id_out),
     };
     let result = handler.execute(&mut context);
@@ -808,251 +1377,316 @@ pub fn next_step_impl(state: &mut PvmState) -> bool {
     state.last_store_value = last_store_value;
 
     if result.result_code == RESULT_CODE_HOST as i32 {
-        // During accumulation invocation, only allow host IDs from AS pvm.ts handleAccumulationHostCall:
-        // general 0-5, log 100, accumulation 14-26. Others: deduct 10 gas, set r7=WHAT, advance PC, continue.
-        // During refine invocation, allow general 0-13 (GAS, FETCH, LOOKUP, READ, WRITE, INFO, HISTORICAL_LOOKUP, EXPORT, MACHINE, PEEK, POKE, PAGES, INVOKE, EXPUNGE) and LOG 100.
-        if state.has_refine_context {
-            let id = state.host_call_id as u64;
-            let allowed = (id <= 13) || (id == 100);
-            if !allowed {
-                const HOST_BASE_GAS: u32 = 10;
-                if state.gas_left < HOST_BASE_GAS {
-                    state.status = Status::Oog;
-                    state.result_code = RESULT_CODE_OOG;
-                    return false;
-                }
-                state.gas_left = state.gas_left.saturating_sub(HOST_BASE_GAS);
-                use crate::config::REG_WHAT;
-                state.registers[7] = REG_WHAT;
-                state.program_counter = pc + instruction_length as u32;
-                return true;
-            }
-        } else if state.has_accumulation_context {
-            let id = state.host_call_id as u64;
-            let allowed = (id <= 5) || (id == 100) || (id >= 14 && id <= 26);
-            if !allowed {
-                const HOST_BASE_GAS: u32 = 10;
-                if state.gas_left < HOST_BASE_GAS {
-                    state.status = Status::Oog;
-                    state.result_code = RESULT_CODE_OOG;
-                    return false;
-                }
-                state.gas_left = state.gas_left.saturating_sub(HOST_BASE_GAS);
-                use crate::config::REG_WHAT;
-                state.registers[7] = REG_WHAT;
-                state.program_counter = pc + instruction_length as u32;
-                return true;
+        return StepOutcome::Host { next_pc };
+    }
+
+    if result.result_code != InstructionResult::CONTINUE {
+        state.status = status_from_result_code(result.result_code as u8);
+        state.result_code = result.result_code as u8;
+        state.exit_arg = if result.has_fault_address {
+            result.fault_address
+        } else {
+            0
+        };
+        return StepOutcome::Exit;
+    }
+
+    if program_counter_after != pc_before {
+        state.program_counter = program_counter_after;
+    } else {
+        state.program_counter = next_pc;
+    }
+    StepOutcome::Continue
+}
+
+/// Run until execution stops, with the recompiler when enabled and available (it charges per instruction
+/// and does not see watchpoints), else step by step.
+pub fn run_impl(state: &mut PvmState) {
+    if state.use_recompiler
+        && recompiler::is_available()
+        && state.trace.is_none()
+        && state.profiler.is_none()
+        && !state.debugger.has_watchpoints()
+        && state.gas_charging == GasCharging::Instruction
+    {
+        recompiler::run(state);
+    } else {
+        while next_step_impl(state) {}
+    }
+}
+
+/// One step: fetch instruction at PC, execute, advance or halt. Returns true if execution should continue.
+/// Host calls are dispatched here (nested refine machines instead stop on them, see guest.rs).
+/// When tracing or profiling is enabled the step is recorded afterwards.
+pub fn next_step_impl(state: &mut PvmState) -> bool {
+    if state.trace.is_none() && state.profiler.is_none() {
+        return step_impl(state);
+    }
+    state.ensure_decoded();
+    let pc = state.program_counter;
+    let opcode = state.decoded.get(pc as usize).map(|d| d.opcode);
+    let gas_before = state.gas_left;
+    let instruction_gas = if opcode.is_some() {
+        state.gas_charge(pc)
+    } else {
+        0
+    };
+    let should_continue = step_impl(state);
+    if let Some(mut trace) = state.trace.take() {
+        trace.record(state, gas_before);
+        state.trace = Some(trace);
+    }
+    if let (Some(mut profiler), Some(opcode)) = (state.profiler.take(), opcode) {
+        profiler.record(pc, opcode, gas_before, instruction_gas, state);
+        state.profiler = Some(profiler);
+    }
+    should_continue
+}
+
+fn step_impl(state: &mut PvmState) -> bool {
+    let next_pc = match execute_instruction(state) {
+        StepOutcome::Continue => return true,
+        StepOutcome::Exit => return false,
+        StepOutcome::Host { next_pc } => next_pc,
+    };
+    if state.external_host_calls {
+        state.status = Status::Host;
+        state.result_code = RESULT_CODE_HOST;
+        state.host_resume_pc = Some(next_pc);
+        return false;
+    }
+    // Base gas from the registry's schedule (10 for every built-in, including LOG, to match jamtestnet traces),
+    // charged whether or not the call is allowed or handled.
+    let host_base_gas = state.host_functions.base_gas(state.host_call_id);
+    if state.gas_left < host_base_gas {
+        state.status = Status::Oog;
+        state.result_code = RESULT_CODE_OOG;
+        return false;
+    }
+    state.gas_left -= host_base_gas;
+    // Each invocation kind only allows its own host calls (see HostFunctionRegistry::with_defaults).
+    // Others: set r7=WHAT, advance PC, continue.
+    let handler = if state
+        .host_functions
+        .is_allowed(state.invocation_kind(), state.host_call_id)
+    {
+        state.host_functions.get(state.host_call_id).cloned()
+    } else {
+        None
+    };
+    if let Some(handler) = handler {
+        let registers_before = state.registers;
+        let mut ram = WatchedRam::new(&mut state.ram, &mut state.debugger);
+
+        let host_result = if state.has_refine_context {
+            let mut refine_ctx = RefineContextForState {
+                segments: &mut state.refine_export_segments,
+                segment_offset: &mut state.refine_segment_offset,
+                machines: &mut state.refine_machines,
+            };
+            let mut host_ctx = HostFunctionContext {
+                registers: &mut state.registers,
+                ram: &mut ram,
+                gas_remaining: &mut state.gas_left,
+                service_id: state.accumulation_service_id,
+                service_account: None,
+                accounts: state.accumulation_accounts.as_mut(),
+                manager_id: None,
+                registrar_id: None,
+                nextfreeid: None,
+                lookup_timeslot: state.refine_lookup_timeslot,
+                timeslot: state.timeslot,
+                expunge_period: state
+                    .accumulation_fetch_config
+                    .as_ref()
+                    .map(|c| c.preimage_expunge_period as u64),
+                refine_context: Some(&mut refine_ctx),
+                yield_hash: None,
+                provisions: None,
+                xfers: None,
+                delegator_id: None,
+                num_validators: None,
+                accumulation_state: None,
+                checkpoint_requested: None,
+                num_cores: None,
+                fetch_entropy_accumulator: state.entropy_accumulator.as_deref(),
+                fetch_authorizer_trace: state.refine_authorizer_trace.as_deref(),
+                fetch_extrinsics: state.refine_extrinsics.as_deref(),
+                fetch_import_segments: state.refine_import_segments.as_deref(),
+                fetch_work_item_index: state.refine_work_item_index,
+                fetch_accumulate_inputs: None,
+                fetch_work_package_encoded: state.work_package_encoded.as_deref(),
+                fetch_auth_config: state.auth_config.as_deref(),
+                fetch_auth_token: state.auth_token.as_deref(),
+                fetch_refine_context_encoded: state.refine_context_encoded.as_deref(),
+                fetch_work_item_summaries: state.work_item_summaries.as_deref(),
+                fetch_work_item_payloads: state.work_item_payloads.as_deref(),
+                log_messages: Some(&mut state.log_messages),
+                fetch_system_constants_config: state.accumulation_fetch_config.as_ref(),
+            };
+            handler.execute(&mut host_ctx)
+        } else {
+            let mut host_ctx = HostFunctionContext {
+                registers: &mut state.registers,
+                ram: &mut ram,
+                gas_remaining: &mut state.gas_left,
+                service_id: state.accumulation_service_id,
+                service_account: None,
+                accounts: state.accumulation_accounts.as_mut(),
+                manager_id: if state.has_accumulation_context {
+                    Some(state.accumulation_regular_state.manager as u64)
+                } else {
+                    None
+                },
+                registrar_id: if state.has_accumulation_context {
+                    Some(state.accumulation_regular_state.registrar as u64)
+                } else {
+                    None
+                },
+                nextfreeid: if state.has_accumulation_context {
+                    Some(&mut state.accumulation_nextfreeid)
+                } else {
+                    None
+                },
+                lookup_timeslot: None,
+                timeslot: state.timeslot,
+                expunge_period: state
+                    .accumulation_fetch_config
+                    .as_ref()
+                    .map(|c| c.preimage_expunge_period as u64),
+                refine_context: None,
+                yield_hash: if state.has_accumulation_context {
+                    Some(&mut state.yield_hash)
+                } else {
+                    None
+                },
+                provisions: if state.has_accumulation_context {
+                    Some(&mut state.accumulation_provisions)
+                } else {
+                    None
+                },
+                xfers: if state.has_accumulation_context {
+                    Some(&mut state.accumulation_pending_xfers)
+                } else {
+                    None
+                },
+                delegator_id: if state.has_accumulation_context {
+                    Some(state.accumulation_regular_state.delegator as u64)
+                } else {
+                    None
+                },
+                num_validators: if state.accumulation_num_validators > 0 {
+                    Some(state.accumulation_num_validators)
+                } else {
+                    None
+                },
+                accumulation_state: if state.has_accumulation_context {
+                    Some(&mut state.accumulation_regular_state)
+                } else {
+                    None
+                },
+                checkpoint_requested: if state.has_accumulation_context {
+                    Some(&mut state.checkpoint_requested)
+                } else {
+                    None
+                },
+                num_cores: if state.accumulation_num_cores > 0 {
+                    Some(state.accumulation_num_cores)
+                } else {
+                    None
+                },
+                fetch_entropy_accumulator: state.entropy_accumulator.as_deref(),
+                fetch_authorizer_trace: None,
+                fetch_extrinsics: None,
+                fetch_import_segments: None,
+                fetch_work_item_index: None,
+                fetch_accumulate_inputs: if state.accumulate_inputs_encoded.is_empty() {
+                    None
+                } else {
+                    Some(state.accumulate_inputs_encoded.as_slice())
+                },
+                fetch_work_package_encoded: state.work_package_encoded.as_deref(),
+                fetch_auth_config: state.auth_config.as_deref(),
+                fetch_auth_token: state.auth_token.as_deref(),
+                fetch_refine_context_encoded: state.refine_context_encoded.as_deref(),
+                fetch_work_item_summaries: state.work_item_summaries.as_deref(),
+                fetch_work_item_payloads: state.work_item_payloads.as_deref(),
+                log_messages: Some(&mut state.log_messages),
+                fetch_system_constants_config: state.accumulation_fetch_config.as_ref(),
+            };
+            handler.execute(&mut host_ctx)
+        };
+        let (bytes_read, bytes_written) = (ram.bytes_read, ram.bytes_written);
+        // Gray Paper line 752: imY' = imX. When CHECKPOINT (17) ran it set checkpoint_requested.
+        // Snapshot current regular into exceptional so panic/OOG reverts to this checkpoint.
+        if state.checkpoint_requested {
+            if let Some(snapshot) = build_current_regular_implications(state) {
+                state.accumulation_implications_exceptional = Some(snapshot);
             }
+            state.checkpoint_requested = false;
         }
-        if let Some(handler) = get_host_function(state.host_call_id) {
-            // All host functions (including LOG) use base 10 gas to match jamtestnet / expected traces.
-            const HOST_BASE_GAS: u32 = 10;
-            let host_base_gas = HOST_BASE_GAS;
-            if state.gas_left < host_base_gas {
+        if host_result.should_continue() {
+            let dynamic_gas = handler.dynamic_gas(&HostCallUsage {
+                before: &registers_before,
+                after: &state.registers,
+                bytes_read,
+                bytes_written,
+            });
+            if state.gas_left < dynamic_gas {
+                state.gas_left = 0;
                 state.status = Status::Oog;
                 state.result_code = RESULT_CODE_OOG;
                 return false;
             }
-            state.gas_left = state.gas_left.saturating_sub(host_base_gas);
-
-            let host_result = if state.has_refine_context {
-                let mut refine_ctx = RefineContextForState {
-                    segments: &mut state.refine_export_segments,
-                    segment_offset: &mut state.refine_segment_offset,
-                };
-                let mut host_ctx = HostFunctionContext {
-                    registers: &mut state.registers,
-                    ram: &mut state.ram,
-                    gas_remaining: &mut state.gas_left,
-                    service_id: state.accumulation_service_id,
-                    service_account: None,
-                    accounts: state.accumulation_accounts.as_mut(),
-                    manager_id: None,
-                    registrar_id: None,
-                    nextfreeid: None,
-                    lookup_timeslot: None,
-                    timeslot: state.timeslot,
-                    expunge_period: state
-                        .accumulation_fetch_config
-                        .as_ref()
-                        .map(|c| c.preimage_expunge_period as u64),
-                    refine_context: Some(&mut refine_ctx),
-                    yield_hash: None,
-                    provisions: None,
-                    xfers: None,
-                    delegator_id: None,
-                    num_validators: None,
-                    accumulation_state: None,
-                    checkpoint_requested: None,
-                    num_cores: None,
-                    fetch_entropy_accumulator: state.entropy_accumulator.as_deref(),
-                    fetch_authorizer_trace: None,
-                    fetch_export_segments: None,
-                    fetch_import_segments: None,
-                    fetch_work_item_index: None,
-                    fetch_accumulate_inputs: None,
-                    fetch_work_package_encoded: state.work_package_encoded.as_deref(),
-                    fetch_auth_config: state.auth_config.as_deref(),
-                    fetch_auth_token: state.auth_token.as_deref(),
-                    fetch_refine_context_encoded: state.refine_context_encoded.as_deref(),
-                    fetch_work_item_summaries: state.work_item_summaries.as_deref(),
-                    fetch_work_item_payloads: state.work_item_payloads.as_deref(),
-                    log_messages: Some(&mut state.log_messages),
-                    fetch_system_constants_config: state.accumulation_fetch_config.as_ref(),
-                };
-                handler.execute(&mut host_ctx)
-            } else {
-                let mut host_ctx = HostFunctionContext {
-                    registers: &mut state.registers,
-                    ram: &mut state.ram,
-                    gas_remaining: &mut state.gas_left,
-                    service_id: state.accumulation_service_id,
-                    service_account: None,
-                    accounts: state.accumulation_accounts.as_mut(),
-                    manager_id: if state.has_accumulation_context {
-                        Some(state.accumulation_regular_state.manager as u64)
-                    } else {
-                        None
-                    },
-                    registrar_id: if state.has_accumulation_context {
-                        Some(state.accumulation_regular_state.registrar as u64)
-                    } else {
-                        None
-                    },
-                    nextfreeid: if state.has_accumulation_context {
-                        Some(&mut state.accumulation_nextfreeid)
-                    } else {
-                        None
-                    },
-                    lookup_timeslot: None,
-                    timeslot: state.timeslot,
-                    expunge_period: state
-                        .accumulation_fetch_config
-                        .as_ref()
-                        .map(|c| c.preimage_expunge_period as u64),
-                    refine_context: None,
-                    yield_hash: if state.has_accumulation_context {
-                        Some(&mut state.yield_hash)
-                    } else {
-                        None
-                    },
-                    provisions: if state.has_accumulation_context {
-                        Some(&mut state.accumulation_provisions)
-                    } else {
-                        None
-                    },
-                    xfers: if state.has_accumulation_context {
-                        Some(&mut state.accumulation_pending_xfers)
-                    } else {
-                        None
-                    },
-                    delegator_id: if state.has_accumulation_context {
-                        Some(state.accumulation_regular_state.delegator as u64)
-                    } else {
-                        None
-                    },
-                    num_validators: if state.accumulation_num_validators > 0 {
-                        Some(state.accumulation_num_validators)
-                    } else {
-                        None
-                    },
-                    accumulation_state: if state.has_accumulation_context {
-                        Some(&mut state.accumulation_regular_state)
-                    } else {
-                        None
-                    },
-                    checkpoint_requested: if state.has_accumulation_context {
-                        Some(&mut state.checkpoint_requested)
-                    } else {
-                        None
-                    },
-                    num_cores: if state.accumulation_num_cores > 0 {
-                        Some(state.accumulation_num_cores)
-                    } else {
-                        None
-                    },
-                    fetch_entropy_accumulator: state.entropy_accumulator.as_deref(),
-                    fetch_authorizer_trace: None,
-                    fetch_export_segments: None,
-                    fetch_import_segments: None,
-                    fetch_work_item_index: None,
-                    fetch_accumulate_inputs: if state.accumulate_inputs_encoded.is_empty() {
-                        None
-                    } else {
-                        Some(state.accumulate_inputs_encoded.as_slice())
-                    },
-                    fetch_work_package_encoded: state.work_package_encoded.as_deref(),
-                    fetch_auth_config: state.auth_config.as_deref(),
-                    fetch_auth_token: state.auth_token.as_deref(),
-                    fetch_refine_context_encoded: state.refine_context_encoded.as_deref(),
-                    fetch_work_item_summaries: state.work_item_summaries.as_deref(),
-                    fetch_work_item_payloads: state.work_item_payloads.as_deref(),
-                    log_messages: Some(&mut state.log_messages),
-                    fetch_system_constants_config: state.accumulation_fetch_config.as_ref(),
-                };
-                handler.execute(&mut host_ctx)
-            };
-            // Gray Paper line 752: imY' = imX. When CHECKPOINT (17) ran it set checkpoint_requested.
-            // Snapshot current regular into exceptional so panic/OOG reverts to this checkpoint.
-            if state.checkpoint_requested {
-                if let Some(snapshot) = build_current_regular_implications(state) {
-                    state.accumulation_implications_exceptional = Some(snapshot);
-                }
-                state.checkpoint_requested = false;
-            }
-            if host_result.should_continue() {
-                state.program_counter = pc + instruction_length as u32;
-                return true;
-            }
-            state.status = match host_result.result_code {
-                x if x == RESULT_CODE_HALT => Status::Halt,
-                x if x == RESULT_CODE_PANIC => Status::Panic,
-                x if x == RESULT_CODE_FAULT => Status::Fault,
-                x if x == RESULT_CODE_OOG => Status::Oog,
-                _ => Status::Panic,
-            };
-            state.result_code = host_result.result_code;
-            return false;
+            state.gas_left -= dynamic_gas;
+            state.program_counter = next_pc;
+            return true;
         }
-        // Unknown host function (Gray Paper pvm_invocations.tex 206-210). Match AS: set r7 = WHAT, advance PC, continue.
-        use crate::config::REG_WHAT;
-        state.registers[7] = REG_WHAT;
-        state.program_counter = pc + instruction_length as u32;
-        return true;
-    }
-
-    if result.result_code != InstructionResult::CONTINUE {
-        state.status = match result.result_code as u8 {
+        state.status = match host_result.result_code {
             x if x == RESULT_CODE_HALT => Status::Halt,
             x if x == RESULT_CODE_PANIC => Status::Panic,
             x if x == RESULT_CODE_FAULT => Status::Fault,
-            x if x == RESULT_CODE_HOST => Status::Host,
             x if x == RESULT_CODE_OOG => Status::Oog,
             _ => Status::Panic,
         };
-        state.result_code = result.result_code as u8;
-        state.exit_arg = if result.has_fault_address {
-            result.fault_address
-        } else {
-            0
-        };
+        state.result_code = host_result.result_code;
         return false;
     }
+    // Disallowed or unknown host function (Gray Paper pvm_invocations.tex 206-210). Match AS: set r7 = WHAT,
+    // advance PC, continue.
+    state.registers[7] = REG_WHAT;
+    state.program_counter = next_pc;
+    true
+}
 
-    if program_counter_after != pc_before {
-        state.program_counter = program_counter_after;
-    } else {
-        state.program_counter = pc + instruction_length as u32;
+/// Resume after an ECALLI serviced by the embedder (external host-call mode): charge `gas_cost` and continue at
+/// the next instruction. Returns false if no host call is pending or gas runs out (OOG).
+pub fn resume_host_call_impl(state: &mut PvmState, gas_cost: u64) -> bool {
+    if state.status != Status::Host {
+        return false;
+    }
+    let Some(next_pc) = state.host_resume_pc.take() else {
+        return false;
+    };
+    if state.gas_left < gas_cost {
+        state.gas_left = 0;
+        state.status = Status::Oog;
+        state.result_code = RESULT_CODE_OOG;
+        return false;
+    }
+    state.gas_left -= gas_cost;
+    state.program_counter = next_pc;
+    state.status = Status::Ok;
+    state.result_code = RESULT_CODE_HALT;
+    if let Some(mut profiler) = state.profiler.take() {
+        profiler.record_resume(state.host_call_id, gas_cost, state);
+        state.profiler = Some(profiler);
     }
     true
 }
 
 /// Run blob: decode, parse, load code, reset, then step until halt.
-pub fn run_blob_impl(program: &[u8]) {
-    let mut g = STATE.lock().expect("pvm state lock");
-    let Some(state) = g.as_mut() else {
-        return;
-    };
+pub fn run_blob_impl(state: &mut PvmState, program: &[u8]) {
     let parser = PvmParser::new();
     let parse_result = parser.parse_program(program);
     if !parse_result.success {
@@ -1060,19 +1694,18 @@ pub fn run_blob_impl(program: &[u8]) {
         state.result_code = RESULT_CODE_PANIC;
         return;
     }
-    state.code = parse_result.extended_code;
-    state.bitmask = parse_result.bitmask;
-    state.jump_table = parse_result.jump_table;
+    state.load_code(
+        parse_result.extended_code,
+        parse_result.bitmask,
+        parse_result.jump_table,
+    );
+    state.verify_loaded_code(parse_result.code_length as usize);
     state.reset_program_state();
-    while next_step_impl(state) {}
+    run_impl(state);
 }
 
 /// Prepare blob: decode, parse, load code and reset; do not run.
-pub fn prepare_blob_impl(program: &[u8]) {
-    let mut g = STATE.lock().expect("pvm state lock");
-    let Some(state) = g.as_mut() else {
-        return;
-    };
+pub fn prepare_blob_impl(state: &mut PvmState, program: &[u8]) {
     let parser = PvmParser::new();
     let parse_result = parser.parse_program(program);
     if !parse_result.success {
@@ -1080,51 +1713,46 @@ pub fn prepare_blob_impl(program: &[u8]) {
         state.result_code = RESULT_CODE_PANIC;
         return;
     }
-    state.code = parse_result.extended_code;
-    state.bitmask = parse_result.bitmask;
-    state.jump_table = parse_result.jump_table;
+    state.load_code(
+        parse_result.extended_code,
+        parse_result.bitmask,
+        parse_result.jump_table,
+    );
+    state.verify_loaded_code(parse_result.code_length as usize);
     state.reset_program_state();
 }
 
-/// Initialize memory layout on the current state's RAM (Gray Paper 770–802). Use after prepareBlob when testing with PvmRam so heap/stack/args/ro exist.
+/// Initialize memory layout on the state's RAM (Gray Paper 770–802). Use after prepareBlob when testing with PvmRam so heap/stack/args/ro exist.
 pub fn init_memory_layout_impl(
+    state: &mut PvmState,
     argument_data: &[u8],
     read_only_data: &[u8],
     read_write_data: &[u8],
     stack_size: u32,
     heap_zero_padding_size: u32,
 ) {
-    let mut g = STATE.lock().expect("pvm state lock");
-    if let Some(state) = g.as_mut() {
-        state.ram.initialize_memory_layout(
-            argument_data,
-            read_only_data,
-            read_write_data,
-            stack_size,
-            heap_zero_padding_size,
-        );
-    }
+    state.ram.initialize_memory_layout(
+        argument_data,
+        read_only_data,
+        read_write_data,
+        stack_size,
+        heap_zero_padding_size,
+    );
 }
 
-/// Initialize a page in the current state's RAM (for test vectors). access_type: 0 = None, 1 = Read, 2 = Write.
-pub fn init_page_impl(address: u32, length: u32, access_type: u8) {
+/// Initialize a page in the state's RAM (for test vectors). access_type: 0 = None, 1 = Read, 2 = Write.
+pub fn init_page_impl(state: &mut PvmState, address: u32, length: u32, access_type: u8) {
     let access = match access_type {
         1 => MemoryAccessType::Read,
         2 => MemoryAccessType::Write,
         _ => MemoryAccessType::None,
     };
-    let mut g = STATE.lock().expect("pvm state lock");
-    if let Some(state) = g.as_mut() {
-        state.ram.init_page(address, length, access);
-    }
+    state.ram.init_page(address, length, access);
 }
 
-/// Write octets to current state's RAM at address (for test vectors initial memory).
+/// Write octets to the state's RAM at address (for test vectors initial memory).
 /// Uses write_octets_during_initialization so initial data can be written to any mapped page
 /// (including read-only), matching TypeScript test-vector-helper (writeOctetsDuringInitialization).
-pub fn set_memory_impl(address: u32, data: &[u8]) {
-    let mut g = STATE.lock().expect("pvm state lock");
-    if let Some(state) = g.as_mut() {
-        state.ram.write_octets_during_initialization(address, data);
-    }
+pub fn set_memory_impl(state: &mut PvmState, address: u32, data: &[u8]) {
+    state.ram.write_octets_during_initialization(address, data);
 }
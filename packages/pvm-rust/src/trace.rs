@@ -0,0 +1,227 @@
+//! Native execution trace in the `writeTraceDump` format (packages/pvm/src/panic-dump-util.ts), recorded after
+//! every `next_step_impl` so embedders do not need a NAPI round-trip per instruction:
+//!
+//! ```text
+//! Calling host function: <NAME> <ID> [gas used: <N>, gas remaining: <N>] [service: <ID>]
+//! <INSTRUCTION> <STEP> <PC> Gas: <GAS> Registers:[<R0>, …, <R12>] Load:[<ADDR>,<VALUE>] Store:[<ADDR>,<VALUE>]
+//! ```
+//!
+//! As in the TypeScript executors, PC, gas, registers and the memory op are the values after the step,
+//! and the host-call line precedes the instruction line of the step that made the call.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::state_wrapper::{instruction_registry, PvmState};
+
+/// One instruction line of a trace. Load/store are optional when parsing, for references that predate them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub name: Cow<'static, str>,
+    pub step: u64,
+    pub pc: u32,
+    pub gas: u64,
+    pub registers: [u64; 13],
+    pub load: Option<(u32, u64)>,
+    pub store: Option<(u32, u64)>,
+}
+
+impl TraceEntry {
+    /// The state after step `step`. The name comes from `last_opcode` (`InstructionHandler::name`).
+    #[must_use]
+    pub fn from_state(step: u64, state: &PvmState) -> Self {
+        Self {
+            name: Cow::Borrowed(
+                instruction_registry()
+                    .handler(state.last_opcode)
+                    .map_or("UNKNOWN", |handler| handler.name()),
+            ),
+            step,
+            pc: state.program_counter,
+            gas: state.gas_left,
+            registers: state.registers,
+            load: Some((state.last_load_address, state.last_load_value)),
+            store: Some((state.last_store_address, state.last_store_value)),
+        }
+    }
+
+    /// Parse an instruction line; None for host-call lines and anything else.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        let (head, rest) = line.trim().split_once(" Gas: ")?;
+        let mut head = head.split(' ');
+        let name = head.next()?.to_string();
+        let step = head.next()?.parse().ok()?;
+        let pc = head.next()?.parse().ok()?;
+        if head.next().is_some() {
+            return None;
+        }
+        let (gas, rest) = rest.split_once(" Registers:[")?;
+        let (registers, rest) = rest.split_once(']')?;
+        let registers: Vec<u64> = registers
+            .split(',')
+            .map(|r| r.trim().parse().ok())
+            .collect::<Option<_>>()?;
+        Some(Self {
+            name: Cow::Owned(name),
+            step,
+            pc,
+            gas: gas.parse().ok()?,
+            registers: registers.try_into().ok()?,
+            load: parse_memory_op(rest, "Load:[")?,
+            store: parse_memory_op(rest, "Store:[")?,
+        })
+    }
+}
+
+/// `Some(None)` when the field is absent, None when it is malformed.
+fn parse_memory_op(rest: &str, tag: &str) -> Option<Option<(u32, u64)>> {
+    let Some((_, op)) = rest.split_once(tag) else {
+        return Some(None);
+    };
+    let (address, value) = op.split_once(']')?.0.split_once(',')?;
+    Some(Some((
+        address.trim().parse().ok()?,
+        value.trim().parse().ok()?,
+    )))
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} Gas: {} Registers:[",
+            self.name, self.step, self.pc, self.gas
+        )?;
+        for (i, register) in self.registers.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{register}")?;
+        }
+        f.write_str("]")?;
+        if let Some((address, value)) = self.load {
+            write!(f, " Load:[{address},{value}]")?;
+        }
+        if let Some((address, value)) = self.store {
+            write!(f, " Store:[{address},{value}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where trace lines go: an in-memory buffer drained with `take`, or a file.
+enum TraceSink {
+    Buffer(String),
+    File(BufWriter<File>),
+}
+
+/// Per-instance trace recorder (see module docs for the line format).
+pub struct TraceWriter {
+    sink: TraceSink,
+    /// Steps recorded since the last program was loaded; the first step is 1.
+    step: u64,
+    line: String,
+}
+
+impl TraceWriter {
+    /// Record into memory; read with `take`.
+    #[must_use]
+    pub fn buffer() -> Self {
+        Self {
+            sink: TraceSink::Buffer(String::new()),
+            step: 0,
+            line: String::new(),
+        }
+    }
+
+    /// Record into `path`, truncating it.
+    pub fn file(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            sink: TraceSink::File(BufWriter::new(File::create(path)?)),
+            step: 0,
+            line: String::new(),
+        })
+    }
+
+    /// Restart step numbering (a new program was loaded).
+    pub fn restart(&mut self) {
+        self.step = 0;
+    }
+
+    /// Buffered trace text since the last call (empty for file sinks).
+    pub fn take(&mut self) -> String {
+        match &mut self.sink {
+            TraceSink::Buffer(text) => std::mem::take(text),
+            TraceSink::File(_) => String::new(),
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if let TraceSink::File(file) = &mut self.sink {
+            file.flush().ok();
+        }
+    }
+
+    /// Append the lines for the step that just ran; `gas_before` is the gas left before it.
+    pub fn record(&mut self, state: &PvmState, gas_before: u64) {
+        self.step += 1;
+        let line = &mut self.line;
+        line.clear();
+        if state.host_call_id != 0 {
+            let id = state.host_call_id;
+            let name = state.host_functions.get(id).map_or_else(
+                || format!("UNKNOWN_{id}"),
+                |handler| handler.name().to_uppercase(),
+            );
+            let _ = writeln!(
+                line,
+                "Calling host function: {name} {id} [gas used: {}, gas remaining: {}] [service: {}]",
+                gas_before.saturating_sub(state.gas_left),
+                state.gas_left,
+                state.accumulation_service_id.unwrap_or(0),
+            );
+        }
+        let _ = writeln!(line, "{}", TraceEntry::from_state(self.step, state));
+        match &mut self.sink {
+            TraceSink::Buffer(text) => text.push_str(line),
+            TraceSink::File(file) => {
+                file.write_all(line.as_bytes()).ok();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_wrapper::{next_step_impl, RAMType, Status};
+
+    #[test]
+    fn records_steps_in_trace_dump_format() {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        state.trace = Some(TraceWriter::buffer());
+        // LOAD_IMM r0, 7; ECALLI 99; TRAP
+        state.load_code(vec![51, 0, 7, 10, 99, 0], vec![1, 0, 0, 1, 0, 1], vec![]);
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        while next_step_impl(&mut state) {}
+
+        let text = state.trace.as_mut().unwrap().take();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4, "{text}");
+        assert_eq!(
+            lines[0],
+            "LOAD_IMM 1 3 Gas: 99 Registers:[7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] Load:[0,0] Store:[0,0]"
+        );
+        assert_eq!(
+            lines[1],
+            "Calling host function: UNKNOWN_99 99 [gas used: 11, gas remaining: 88] [service: 0]"
+        );
+        assert!(lines[2].starts_with("ECALLI 2 5 Gas: 88 "), "{text}");
+        assert!(lines[3].starts_with("TRAP 3 5 Gas: 87 "), "{text}");
+        assert!(state.trace.as_mut().unwrap().take().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Replay against a reference trace (jamtestnet or another implementation, `writeTraceDump` format) and stop at
+//! the first step whose opcode, PC, gas, registers or load/store differ. Host-call lines are skipped; their
+//! effects show up in the registers and gas of the ECALLI step.
+
+use crate::state_wrapper::{next_step_impl, PvmState};
+use crate::trace::TraceEntry;
+
+/// First step where execution disagrees with the reference.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    /// 1-based index of the instruction line in the reference.
+    pub step: u64,
+    /// The reference line for this step.
+    pub expected: TraceEntry,
+    /// None when the program stopped before the reference did.
+    pub actual: Option<TraceEntry>,
+    /// Differing fields: `opcode`, `pc`, `gas`, `r0`…`r12`, `load`, `store`, or `ended`.
+    pub fields: Vec<String>,
+}
+
+/// Outcome of `check_trace_impl`.
+#[derive(Clone, Debug)]
+pub enum TraceCheck {
+    /// Every reference step matched. `finished` is false when the program could still continue.
+    Matched {
+        steps: u64,
+        finished: bool,
+    },
+    Diverged(Box<Divergence>),
+}
+
+/// Fields of `actual` that differ from `expected`. Load/store are only compared when the reference records them.
+#[must_use]
+pub fn diff_entries(expected: &TraceEntry, actual: &TraceEntry) -> Vec<String> {
+    let mut fields = Vec::new();
+    if expected.name != actual.name {
+        fields.push("opcode".to_string());
+    }
+    if expected.pc != actual.pc {
+        fields.push("pc".to_string());
+    }
+    if expected.gas != actual.gas {
+        fields.push("gas".to_string());
+    }
+    for (i, (e, a)) in expected.registers.iter().zip(&actual.registers).enumerate() {
+        if e != a {
+            fields.push(format!("r{i}"));
+        }
+    }
+    if expected.load.is_some() && expected.load != actual.load {
+        fields.push("load".to_string());
+    }
+    if expected.store.is_some() && expected.store != actual.store {
+        fields.push("store".to_string());
+    }
+    fields
+}
+
+/// Step `state` once per instruction line of `reference`, comparing the state after each step.
+pub fn check_trace_impl(state: &mut PvmState, reference: &str) -> TraceCheck {
+    let mut running = true;
+    let mut steps = 0u64;
+    for expected in reference.lines().filter_map(TraceEntry::parse) {
+        steps += 1;
+        if !running {
+            return TraceCheck::Diverged(Box::new(Divergence {
+                step: steps,
+                expected,
+                actual: None,
+                fields: vec!["ended".to_string()],
+            }));
+        }
+        running = next_step_impl(state);
+        let actual = TraceEntry::from_state(expected.step, state);
+        let fields = diff_entries(&expected, &actual);
+        if !fields.is_empty() {
+            return TraceCheck::Diverged(Box::new(Divergence {
+                step: steps,
+                expected,
+                actual: Some(actual),
+                fields,
+            }));
+        }
+    }
+    TraceCheck::Matched {
+        steps,
+        finished: !running,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_wrapper::{RAMType, Status};
+    use crate::trace::TraceWriter;
+
+    fn program_state() -> PvmState {
+        let mut state = PvmState::new(RAMType::PvmRam as i32);
+        // LOAD_IMM r0, 7; ADD_IMM_64 r0, r0, 1; TRAP
+        state.load_code(
+            vec![51, 0, 7, 149, 0, 1, 0],
+            vec![1, 0, 0, 1, 0, 0, 1],
+            vec![],
+        );
+        state.status = Status::Ok;
+        state.gas_left = 100;
+        state
+    }
+
+    #[test]
+    fn matches_own_trace_and_reports_first_divergence() {
+        let mut traced = program_state();
+        traced.trace = Some(TraceWriter::buffer());
+        while next_step_impl(&mut traced) {}
+        let reference = traced.trace.as_mut().unwrap().take();
+
+        assert!(matches!(
+            check_trace_impl(&mut program_state(), &reference),
+            TraceCheck::Matched {
+                steps: 3,
+                finished: true
+            }
+        ));
+
+        let tampered = reference.replacen("Registers:[8,", "Registers:[9,", 1);
+        let TraceCheck::Diverged(divergence) = check_trace_impl(&mut program_state(), &tampered)
+        else {
+            panic!("expected divergence");
+        };
+        assert_eq!(divergence.step, 2);
+        assert_eq!(divergence.fields, ["r0"]);
+        assert_eq!(divergence.actual.unwrap().name, "ADD_IMM_64");
+
+        let longer = format!("{reference}{}", reference.lines().last().unwrap());
+        let TraceCheck::Diverged(divergence) = check_trace_impl(&mut program_state(), &longer)
+        else {
+            panic!("expected divergence");
+        };
+        assert_eq!(
+            (divergence.step, divergence.fields.as_slice()),
+            (4, &["ended".to_string()][..])
+        );
+    }
+}
@@ -158,14 +158,14 @@ impl ExecutionResult {
 /// Result of runProgram() (mirrors RunProgramResult).
 #[derive(Clone, Debug)]
 pub struct RunProgramResult {
-    pub gas_consumed: u32,
+    pub gas_consumed: u64,
     pub result: ExecutionResult,
 }
 
 /// Result of accumulateInvocation() (mirrors AccumulateInvocationResult).
 #[derive(Clone, Debug)]
 pub struct AccumulateInvocationResult {
-    pub gas_consumed: u32,
+    pub gas_consumed: u64,
     pub result: ExecutionResult,
     pub output: Vec<u8>,
 }
@@ -183,6 +183,21 @@ pub enum MemoryAccessType {
     Write = 2,
 }
 
+/// Changed bytes within one page: `old` and `new` have the same length and start at `offset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangedRange {
+    pub offset: u32,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// A dirty page whose contents differ from the snapshot it was compared with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageDiff {
+    pub page_index: u32,
+    pub ranges: Vec<ChangedRange>,
+}
+
 // ============================================================================
 // RAM trait (interface for memory backends)
 // ============================================================================
@@ -215,6 +230,43 @@ pub trait Ram: Send + Sync {
     fn last_store_address(&self) -> u32;
     fn last_store_value(&self) -> u64;
     fn clear_last_memory_op(&mut self);
+    /// Pages whose contents were written since the last `clear_dirty` or `reset`, ascending. Changing access
+    /// rights or allocating pages does not make them dirty.
+    fn dirty_pages(&self) -> Vec<u32>;
+    fn clear_dirty(&mut self);
+
+    /// Byte ranges of the dirty pages that differ from the same pages of `snapshot` (usually a fork or restored
+    /// snapshot taken at the last `clear_dirty`). Pages written back with their old contents are left out.
+    fn memory_diff(&self, snapshot: &dyn Ram) -> Vec<PageDiff> {
+        self.dirty_pages()
+            .into_iter()
+            .filter_map(|page_index| {
+                let (old, new) = (
+                    snapshot.get_page_dump(page_index),
+                    self.get_page_dump(page_index),
+                );
+                let len = old.len().min(new.len());
+                let mut ranges = Vec::new();
+                let mut i = 0;
+                while i < len {
+                    if old[i] == new[i] {
+                        i += 1;
+                        continue;
+                    }
+                    let start = i;
+                    while i < len && old[i] != new[i] {
+                        i += 1;
+                    }
+                    ranges.push(ChangedRange {
+                        offset: start as u32,
+                        old: old[start..i].to_vec(),
+                        new: new[start..i].to_vec(),
+                    });
+                }
+                (!ranges.is_empty()).then_some(PageDiff { page_index, ranges })
+            })
+            .collect()
+    }
 }
 
 // ============================================================================
@@ -228,15 +280,43 @@ pub struct InstructionContext<'a> {
     pub bitmask: &'a [u8],
     pub registers: &'a mut RegisterState,
     pub program_counter: u32,
-    pub gas_remaining: u32,
-    pub operands: &'a [u8],
-    pub fskip: i32,
+    pub gas_remaining: u64,
+    /// Operands of this instruction, parsed once at load time (`PvmParser::predecode`).
+    pub operands: DecodedOperands,
     pub jump_table: &'a [u32],
     pub ram: &'a mut dyn Ram,
     /// When Some, ECALLI writes the immediate (host function ID) here.
     pub host_call_id_out: Option<&'a mut u32>,
 }
 
+/// Registers, immediates and target of one instruction, parsed per its Gray Paper §A.5 format exactly as its
+/// handler reads them. Fields the format does not use are 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodedOperands {
+    pub register_a: u8,
+    pub register_b: u8,
+    pub register_d: u8,
+    /// immed_X (sign-extended, except for the unsigned load addresses and LOAD_IMM_64's 64-bit value).
+    pub immediate_x: i64,
+    pub immediate_y: i64,
+    /// Absolute branch or jump target (JUMP, LOAD_IMM_JUMP, BRANCH_*).
+    pub target_address: u32,
+}
+
+/// One pre-decoded code position (built once per program by `PvmParser::predecode`).
+/// The PVM may jump to any PC, so there is an entry for every code byte, not just opcode starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    /// Code byte at this PC (dispatch index).
+    pub opcode: u8,
+    /// Gray Paper Fskip(i): operand length in bytes (0–24).
+    pub fskip: u8,
+    /// PC of the following instruction (i + 1 + Fskip(i)); operands are code[i + 1..next_pc].
+    pub next_pc: u32,
+    /// code[i + 1..next_pc] parsed for the opcode at i.
+    pub operands: DecodedOperands,
+}
+
 // ============================================================================
 // VmOutput (runProgram result layout)
 // ============================================================================
@@ -0,0 +1,233 @@
+//! Static program verifier. Execution semantics are unchanged (the Gray Paper only panics when a bad
+//! instruction or jump is reached); this reports up front what would fail: unknown opcodes, instructions too
+//! short for their format, static branch targets and jump-table entries that are not basic-block starts.
+
+use std::fmt;
+
+use crate::cfg::basic_block_starts;
+use crate::disassembler::{decode_operands, instruction_format, InstructionFormat, Operand};
+use crate::parser::{ParseResult, PvmParser};
+use crate::state_wrapper::instruction_registry;
+
+/// Why a branch target or jump-table entry is not a valid destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetError {
+    /// Past the end of the code.
+    OutOfRange,
+    /// Inside another instruction's operands.
+    NotInstructionStart,
+    /// An instruction start that does not follow a terminating instruction.
+    NotBlockStart,
+}
+
+impl TargetError {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OutOfRange => "out of range",
+            Self::NotInstructionStart => "not an instruction start",
+            Self::NotBlockStart => "not a basic-block start",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// No handler is registered for the opcode.
+    UnknownOpcode { pc: u32, opcode: u8 },
+    /// Fewer operand octets than the instruction format needs (cut by the next opcode or the end of code).
+    TruncatedInstruction {
+        pc: u32,
+        opcode: u8,
+        length: u32,
+        expected: u32,
+    },
+    /// Static target of a jump, branch or LOAD_IMM_JUMP.
+    InvalidBranchTarget {
+        pc: u32,
+        target: u32,
+        error: TargetError,
+    },
+    /// Entry `index` of the jump table (`djump` address `2 * (index + 1)`).
+    InvalidJumpTableEntry {
+        index: u32,
+        target: u32,
+        error: TargetError,
+    },
+}
+
+impl Diagnostic {
+    /// Stable identifier for the diagnostic kind (used by the NAPI output).
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UnknownOpcode { .. } => "unknown_opcode",
+            Self::TruncatedInstruction { .. } => "truncated_instruction",
+            Self::InvalidBranchTarget { .. } => "invalid_branch_target",
+            Self::InvalidJumpTableEntry { .. } => "invalid_jump_table_entry",
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode { pc, opcode } => write!(f, "{pc}: unknown opcode {opcode}"),
+            Self::TruncatedInstruction {
+                pc,
+                opcode,
+                length,
+                expected,
+            } => write!(
+                f,
+                "{pc}: opcode {opcode} has {length} operand octets, needs at least {expected}"
+            ),
+            Self::InvalidBranchTarget { pc, target, error } => {
+                write!(f, "{pc}: branch target @{target} is {}", error.as_str())
+            }
+            Self::InvalidJumpTableEntry {
+                index,
+                target,
+                error,
+            } => write!(f, "jump table [{index}]: @{target} is {}", error.as_str()),
+        }
+    }
+}
+
+/// Minimum operand octets for an instruction to be fully encoded in its format.
+fn minimum_operands(format: InstructionFormat) -> u32 {
+    match format {
+        InstructionFormat::NoArguments
+        | InstructionFormat::OneImmediate
+        | InstructionFormat::OneOffset => 0,
+        InstructionFormat::OneRegisterOneExtendedImmediate => 9,
+        InstructionFormat::TwoRegistersTwoImmediates | InstructionFormat::ThreeRegisters => 2,
+        _ => 1,
+    }
+}
+
+/// Verify `code` with its opcode bitmask (both without the execution padding) and jump table.
+#[must_use]
+pub fn verify_code(code: &[u8], bitmask: &[u8], jump_table: &[u32]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let decoded = PvmParser::new().predecode(code, bitmask);
+    let mut registered = [false; 256];
+    for opcode in instruction_registry().registered_opcodes() {
+        registered[opcode as usize] = true;
+    }
+    let is_start = |pc: usize| bitmask.get(pc).copied().unwrap_or(0) == 1;
+    let block_start = basic_block_starts(bitmask, &decoded);
+    let check_target = |target: u32| -> Option<TargetError> {
+        let index = target as usize;
+        if index >= code.len() {
+            Some(TargetError::OutOfRange)
+        } else if !is_start(index) {
+            Some(TargetError::NotInstructionStart)
+        } else if !block_start[index] {
+            Some(TargetError::NotBlockStart)
+        } else {
+            None
+        }
+    };
+
+    for (pc, instruction) in decoded.iter().enumerate() {
+        if !is_start(pc) {
+            continue;
+        }
+        let (pc32, opcode) = (pc as u32, instruction.opcode);
+        if !registered[usize::from(opcode)] {
+            diagnostics.push(Diagnostic::UnknownOpcode { pc: pc32, opcode });
+            continue;
+        }
+        let Some(format) = instruction_format(opcode) else {
+            continue;
+        };
+        let end = (instruction.next_pc as usize).min(code.len());
+        let length = (end - pc - 1) as u32;
+        let expected = minimum_operands(format);
+        if length < expected {
+            diagnostics.push(Diagnostic::TruncatedInstruction {
+                pc: pc32,
+                opcode,
+                length,
+                expected,
+            });
+        }
+        let mut operands = code[pc + 1..end].to_vec();
+        operands.resize(usize::from(instruction.fskip), 0);
+        for operand in decode_operands(opcode, &operands, pc32) {
+            if let Operand::Target(target) = operand {
+                if let Some(error) = check_target(target) {
+                    diagnostics.push(Diagnostic::InvalidBranchTarget {
+                        pc: pc32,
+                        target,
+                        error,
+                    });
+                }
+            }
+        }
+    }
+
+    for (index, &target) in jump_table.iter().enumerate() {
+        if let Some(error) = check_target(target) {
+            diagnostics.push(Diagnostic::InvalidJumpTableEntry {
+                index: index as u32,
+                target,
+                error,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Verify a parsed program blob (ignores the execution padding after `code_length`).
+#[must_use]
+pub fn verify_program(parsed: &ParseResult) -> Vec<Diagnostic> {
+    let length = (parsed.code_length as usize).min(parsed.extended_code.len());
+    let bitmask_length = length.min(parsed.bitmask.len());
+    verify_code(
+        &parsed.extended_code[..length],
+        &parsed.bitmask[..bitmask_length],
+        &parsed.jump_table,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_bad_targets_opcodes_and_truncation() {
+        // 0: JUMP @3 (mid-instruction); 2: LOAD_IMM r0, 1; 5: FALLTHROUGH; 6: opcode 255; 7: BRANCH_EQ_IMM @6;
+        // 11: ADD_64 (truncated)
+        let code = vec![40, 3, 51, 0, 1, 1, 255, 81, 0x10, 0, 0xff, 200];
+        let bitmask = vec![1, 0, 1, 0, 0, 1, 1, 1, 0, 0, 0, 1];
+        let diagnostics = verify_code(&code, &bitmask, &[2, 6, 40]);
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::InvalidBranchTarget {
+                    pc: 0,
+                    target: 3,
+                    error: TargetError::NotInstructionStart
+                },
+                Diagnostic::UnknownOpcode { pc: 6, opcode: 255 },
+                Diagnostic::TruncatedInstruction {
+                    pc: 11,
+                    opcode: 200,
+                    length: 0,
+                    expected: 2
+                },
+                Diagnostic::InvalidJumpTableEntry {
+                    index: 2,
+                    target: 40,
+                    error: TargetError::OutOfRange
+                },
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "0: branch target @3 is not an instruction start"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+/**
+ * PVM Rust – recompiler vs interpreter.
+ * Runs every program test vector (RISC-V included) on both backends and expects identical results.
+ * Skipped when the recompiler is unavailable on this platform (non-x86-64).
+ */
+
+import { readdirSync, readFileSync } from 'node:fs'
+import { join } from 'node:path'
+import { describe, expect, test } from 'bun:test'
+import {
+  executeTestVectorRust,
+  getTestVectorsDir,
+  parseJsonSafe,
+  type PVMTestVector,
+} from './test-vector-helper-rust'
+
+let testVectorsDir: string
+let jsonFiles: string[] = []
+try {
+  testVectorsDir = getTestVectorsDir()
+  jsonFiles = readdirSync(testVectorsDir)
+    .filter((f) => f.endsWith('.json'))
+    .sort()
+} catch {
+  // submodules may not exist
+}
+
+const testVectors: PVMTestVector[] = []
+for (const file of jsonFiles) {
+  try {
+    const contents = readFileSync(join(testVectorsDir, file), 'utf-8')
+    const tv = parseJsonSafe(contents) as PVMTestVector
+    tv.name = tv.name ?? file.replace('.json', '')
+    testVectors.push(tv)
+  } catch {
+    continue
+  }
+}
+
+const native = require('@pbnjam/pvm-rust-native/native')
+const recompilerAvailable: boolean = native.setUseRecompiler(true)
+native.setUseRecompiler(false)
+
+describe.skipIf(!recompilerAvailable)('Rust PVM – recompiler matches interpreter', () => {
+  for (let i = 0; i < testVectors.length; i++) {
+    const testVector = testVectors[i]
+    test.serial(testVector.name ?? `vector-${i}`, async () => {
+      const interpreted = await executeTestVectorRust(testVector)
+      const recompiled = await executeTestVectorRust(testVector, { recompiler: true })
+
+      expect(Array.from(recompiled.registers)).toEqual(Array.from(interpreted.registers))
+      expect(recompiled.pc).toBe(interpreted.pc)
+      expect(recompiled.gas).toBe(interpreted.gas)
+      expect(recompiled.status).toBe(interpreted.status)
+      expect(recompiled.faultAddress).toBe(interpreted.faultAddress)
+      expect([...recompiled.memory.entries()]).toEqual([...interpreted.memory.entries()])
+      expect(recompiled.status).toBe(testVector['expected-status'])
+    })
+  }
+})
@@ -91,16 +91,18 @@ function loadNative(): {
   reset: () => void
   getRamTypeSimpleRam: () => number
   setRegisters: (registers: Buffer) => void
-  setGasLeft: (gas: number) => void
+  setGasLeft: (gas: bigint) => void
   setNextProgramCounter: (pc: number) => void
   initPage: (address: number, length: number, accessType: number) => void
   setMemory: (address: number, data: Buffer) => void
   prepareBlob: (program: Buffer) => void
   runBlob: (program: Buffer) => void
   nextStep: () => boolean
+  runProgram: () => { gasConsumed: bigint; resultCode: number }
+  setUseRecompiler: (enabled: boolean) => boolean
   getRegisters: () => Buffer
   getProgramCounter: () => number
-  getGasLeft: () => number
+  getGasLeft: () => bigint
   getStatus: () => number
   getExitArg: () => number
   getPageDump: (pageIndex: number) => Buffer
@@ -171,6 +173,8 @@ function getOpcodeName(opcode: number): string {
 export interface ExecuteTestVectorOptions {
   /** When true, run step-by-step and log each instruction (pc, opcode, name) to console. */
   trace?: boolean
+  /** When true, run with the x86-64 recompiler (runProgram) instead of stepping the interpreter. */
+  recompiler?: boolean
 }
 
 /**
@@ -211,7 +215,7 @@ export async function executeTestVectorRust(
     initialRegisterView.setBigUint64(i * 8, value, true)
   }
   native.setRegisters(Buffer.from(initialRegisters))
-  native.setGasLeft(Number(testVector['initial-gas']))
+  native.setGasLeft(BigInt(testVector['initial-gas']))
   native.setNextProgramCounter(Number(testVector['initial-pc']))
 
   if (testVector['initial-page-map']) {
@@ -271,6 +275,13 @@ export async function executeTestVectorRust(
       5: 'out-of-gas',
     }
     console.log(`  stopped pc=${finalPc} status=${statusNames[finalStatus] ?? finalStatus}`)
+  } else if (options.recompiler === true) {
+    native.setUseRecompiler(true)
+    try {
+      native.runProgram()
+    } finally {
+      native.setUseRecompiler(false)
+    }
   } else {
     while (native.nextStep()) {}
   }
@@ -324,7 +335,7 @@ export async function executeTestVectorRust(
       finalRegisters.byteLength,
     ),
     pc: finalPC,
-    gas: finalGas,
+    gas: Number(finalGas),
     status,
     faultAddress,
     memory: finalMemory,